@@ -0,0 +1,84 @@
+// The crate is a binary, so the modules the benchmarks need are compiled in directly.
+#![allow(dead_code)]
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ratatui::{backend::TestBackend, Terminal};
+use tui_textarea::TextArea;
+
+#[path = "../src/crash.rs"]
+mod crash;
+#[cfg(feature = "highlighting")]
+#[path = "../src/highlighting.rs"]
+mod highlighting;
+#[path = "../src/model.rs"]
+mod model;
+#[path = "../src/text_wrapping.rs"]
+mod text_wrapping;
+#[path = "../src/theme.rs"]
+mod theme;
+#[path = "../src/tmux.rs"]
+mod tmux;
+#[path = "../src/tui.rs"]
+mod tui;
+#[path = "../src/view.rs"]
+mod view;
+
+use model::Model;
+use text_wrapping::{truncate_ellipse, wrap_string};
+use view::view;
+
+const MEGABYTE: usize = 1024 * 1024;
+
+fn json_response(size: usize) -> String {
+    let mut body = "[\n".to_string();
+    let mut id = 0;
+    while body.len() < size {
+        body.push_str(&format!(
+            "  {{\n    \"id\": {},\n    \"name\": \"User {}\",\n    \"email\": \"user{}@example.com\",\n    \"active\": true\n  }},\n",
+            id, id, id
+        ));
+        id += 1;
+    }
+    body.push_str("  {}\n]");
+    body
+}
+
+fn text_wrapping(c: &mut Criterion) {
+    let mut group = c.benchmark_group("text_wrapping");
+    for size in [1024, 64 * 1024, MEGABYTE] {
+        let text = "lorem ipsum ".repeat(size / 12);
+        group.bench_with_input(BenchmarkId::new("wrap_string", size), &text, |b, text| {
+            b.iter(|| wrap_string(black_box(text), 80))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("truncate_ellipse", size),
+            &text,
+            |b, text| b.iter(|| truncate_ellipse(black_box(text), 80)),
+        );
+    }
+    group.finish();
+}
+
+fn output_rendering(c: &mut Criterion) {
+    let mut group = c.benchmark_group("output_rendering");
+    group.sample_size(10);
+    for megabytes in [1, 4, 16] {
+        let body = json_response(megabytes * MEGABYTE);
+        let id = format!("{}MB", megabytes);
+
+        group.bench_with_input(BenchmarkId::new("load", &id), &body, |b, body| {
+            b.iter(|| TextArea::from(black_box(body).lines()))
+        });
+
+        let mut model = Model::new("bench.http".to_string());
+        model.output_input = TextArea::from(body.lines());
+        let mut terminal = Terminal::new(TestBackend::new(200, 60)).unwrap();
+        group.bench_function(BenchmarkId::new("draw", &id), |b| {
+            b.iter(|| terminal.draw(|f| view(f, &mut model)).unwrap().area)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, text_wrapping, output_rendering);
+criterion_main!(benches);
@@ -0,0 +1,117 @@
+-- Sends the .http request under the cursor to a reqcoon started with --server, which sends it and
+-- shows the response. The request and the buffer's variables are only used for the session, so the
+-- file reqcoon has open is left as it was. Add this directory to your runtimepath and call
+-- require("reqcoon").setup().
+local M = {}
+
+M.config = {
+  -- Defaults to the socket --server listens on.
+  socket = nil,
+}
+
+local function socket_path()
+  if M.config.socket then
+    return M.config.socket
+  end
+  local directory = os.getenv("XDG_RUNTIME_DIR") or vim.loop.os_tmpdir()
+  return directory .. "/reqcoon.sock"
+end
+
+local function is_variable(line)
+  return line:match("^@[^=]+=") ~= nil
+end
+
+-- The file's variables followed by the lines between the ### separators around the cursor.
+local function current_block()
+  local lines = vim.api.nvim_buf_get_lines(0, 0, -1, false)
+  local cursor = vim.api.nvim_win_get_cursor(0)[1]
+
+  local first, last = 1, #lines
+  for i = cursor, 1, -1 do
+    if lines[i]:match("^###") then
+      first = i + 1
+      break
+    end
+  end
+  for i = cursor, #lines do
+    if lines[i]:match("^###") then
+      last = i - 1
+      break
+    end
+  end
+
+  local text = {}
+  for _, line in ipairs(lines) do
+    if is_variable(line) then
+      table.insert(text, line)
+    end
+  end
+  table.insert(text, "")
+  for i = first, last do
+    if not is_variable(lines[i]) then
+      table.insert(text, lines[i])
+    end
+  end
+  return table.concat(text, "\n")
+end
+
+local function report(reply)
+  if reply.ok then
+    vim.notify(string.format("reqcoon: %d %s (%d ms)", reply.status, reply.statusText, reply.time))
+  else
+    vim.notify("reqcoon: " .. reply.error, vim.log.levels.ERROR)
+  end
+end
+
+function M.run()
+  local path = socket_path()
+  local message = vim.json.encode({ command = "run", request = current_block() }) .. "\n"
+  local pipe = vim.loop.new_pipe(false)
+  local buffer = ""
+  local greeted = false
+
+  pipe:connect(path, function(connect_err)
+    if connect_err then
+      pipe:close()
+      vim.schedule(function()
+        vim.notify("reqcoon: unable to connect to " .. path .. ": " .. connect_err, vim.log.levels.ERROR)
+      end)
+      return
+    end
+
+    pipe:read_start(function(read_err, data)
+      if read_err or not data then
+        pipe:close()
+        return
+      end
+
+      buffer = buffer .. data
+      local newline = buffer:find("\n")
+      while newline do
+        local line = buffer:sub(1, newline - 1)
+        buffer = buffer:sub(newline + 1)
+        if not greeted then
+          -- The first line is the greeting, so reqcoon is ready for the request.
+          greeted = true
+          pipe:write(message)
+        else
+          pipe:close()
+          vim.schedule(function()
+            report(vim.json.decode(line))
+          end)
+          return
+        end
+        newline = buffer:find("\n")
+      end
+    end)
+  end)
+end
+
+function M.setup(opts)
+  M.config = vim.tbl_extend("force", M.config, opts or {})
+  vim.api.nvim_create_user_command("ReqcoonRun", M.run, {
+    desc = "Send the .http request under the cursor to reqcoon",
+  })
+end
+
+return M
@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use json::JsonValue;
+use regex::Regex;
+
+use crate::{
+    json_path,
+    snapshot::{self, Snapshots},
+};
+
+const OPERATORS: [&str; 9] = [
+    "exists", "==", "!=", "contains", "matches", "<", "<=", ">", ">=",
+];
+
+pub struct Response<'a> {
+    pub status: u16,
+    pub headers: &'a [(String, String)],
+    pub body: &'a str,
+    pub time: Duration,
+    pub snapshots: &'a Snapshots,
+}
+
+// An assertion is a subject, an operator and usually an expected value, like `status == 200`,
+// `$.items[0].id exists`, `body contains "ok"`, `header.Cache-Control matches max-age=\d+` or
+// `time < 500`, in milliseconds. `snapshot users` compares the response to one saved with
+// :snapshot instead. Failures are described in the error.
+pub fn check(assertion: &str, response: &Response) -> Result<(), String> {
+    let assertion = assertion.trim();
+    let (subject, rest) = assertion.split_once(' ').unwrap_or((assertion, ""));
+    if subject == "snapshot" {
+        return snapshot::check(rest, response.snapshots, response.status, response.body)
+            .map_err(|err| format!("{}: {}", assertion, err));
+    }
+    let (operator, expected) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+    let expected = unquote(expected.trim());
+    if !OPERATORS.contains(&operator) {
+        return Err(format!("{}: unknown operator \"{}\"", assertion, operator));
+    }
+
+    let actual = match subject {
+        "status" => Some(response.status.to_string()),
+        "body" => Some(response.body.to_string()),
+        "time" => Some(response.time.as_millis().to_string()),
+        name if name.starts_with("header.") => header(response, &name["header.".len()..]),
+        path if path.starts_with('$') => {
+            let body = json::parse(response.body)
+                .map_err(|err| format!("{}: response is not JSON: {}", assertion, err))?;
+            json_path::select(&body, path)
+                .map_err(|err| format!("{}: {}", assertion, err))?
+                .first()
+                .map(|value| text(value))
+        }
+        _ => return Err(format!("{}: unknown subject \"{}\"", assertion, subject)),
+    };
+
+    let passed = match (operator, &actual) {
+        ("exists", actual) => actual.is_some(),
+        (_, None) => false,
+        ("matches", Some(actual)) => Regex::new(expected)
+            .map_err(|err| format!("{}: invalid regex: {}", assertion, err))?
+            .is_match(actual),
+        (_, Some(actual)) => compare(operator, actual, expected).ok_or(format!(
+            "{}: {} and {} can't be compared as numbers",
+            assertion, actual, expected
+        ))?,
+    };
+
+    match actual {
+        _ if passed => Ok(()),
+        Some(actual) => Err(format!("{} (got {})", assertion, abbreviate(&actual))),
+        None => Err(format!("{} (no value)", assertion)),
+    }
+}
+
+fn compare(operator: &str, actual: &str, expected: &str) -> Option<bool> {
+    match operator {
+        "==" => Some(actual == expected),
+        "!=" => Some(actual != expected),
+        "contains" => Some(actual.contains(expected)),
+        _ => {
+            let actual: f64 = actual.parse().ok()?;
+            let expected: f64 = expected.parse().ok()?;
+            Some(match operator {
+                "<" => actual < expected,
+                "<=" => actual <= expected,
+                ">" => actual > expected,
+                _ => actual >= expected,
+            })
+        }
+    }
+}
+
+// Header names are case insensitive, and repeated headers are joined like they would be on one line.
+fn header(response: &Response, name: &str) -> Option<String> {
+    let values: Vec<&str> = response
+        .headers
+        .iter()
+        .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+        .collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.join(", "))
+    }
+}
+
+fn text(value: &JsonValue) -> String {
+    value.as_str().map_or(value.dump(), str::to_string)
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
+
+fn abbreviate(text: &str) -> String {
+    if text.chars().count() > 40 {
+        format!("{}…", text.chars().take(40).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::Snapshot;
+
+    const BODY: &str = r#"{"items": [{"id": 7, "name": "Ada"}], "total": 1}"#;
+
+    fn checked(assertion: &str) -> Result<(), String> {
+        checked_body(assertion, BODY)
+    }
+
+    fn checked_body(assertion: &str, body: &str) -> Result<(), String> {
+        let headers = [
+            ("Content-Type", "application/json"),
+            ("Set-Cookie", "a=1"),
+            ("set-cookie", "b=2"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let snapshots = vec![(
+            "users".to_string(),
+            Snapshot {
+                status: 200,
+                body: BODY.to_string(),
+            },
+        )];
+        check(
+            assertion,
+            &Response {
+                status: 200,
+                headers: &headers,
+                body,
+                time: Duration::from_millis(120),
+                snapshots: &snapshots,
+            },
+        )
+    }
+
+    #[test]
+    fn passing_assertions() {
+        for assertion in [
+            "status == 200",
+            "status != 404",
+            "status matches ^2\\d\\d$",
+            "status >= 200",
+            "$.items[0].id == 7",
+            "$.items[0].name == \"Ada\"",
+            "$.total exists",
+            "$.total <= 1",
+            "body contains \"Ada\"",
+            "header.content-type contains json",
+            "header.Set-Cookie == a=1, b=2",
+            "time < 500",
+            "time > 100",
+            "  status == 200  ",
+            "snapshot users",
+        ] {
+            assert_eq!(checked(assertion), Ok(()), "{}", assertion);
+        }
+    }
+
+    #[test]
+    fn failures_say_what_was_got() {
+        assert_eq!(
+            checked("status == 201"),
+            Err("status == 201 (got 200)".to_string())
+        );
+        assert_eq!(
+            checked("$.items[0].name != Ada"),
+            Err("$.items[0].name != Ada (got Ada)".to_string())
+        );
+        assert_eq!(
+            checked("$.missing == 1"),
+            Err("$.missing == 1 (no value)".to_string())
+        );
+        assert_eq!(
+            checked("header.X-Request-Id exists"),
+            Err("header.X-Request-Id exists (no value)".to_string())
+        );
+        assert_eq!(
+            checked("snapshot orders"),
+            Err("snapshot orders: no snapshot named orders".to_string())
+        );
+    }
+
+    #[test]
+    fn long_values_are_abbreviated() {
+        assert_eq!(
+            checked("body == {}"),
+            Err(format!("body == {{}} (got {}…)", &BODY[..40]))
+        );
+    }
+
+    #[test]
+    fn invalid_assertions() {
+        assert_eq!(
+            checked("status is 200"),
+            Err("status is 200: unknown operator \"is\"".to_string())
+        );
+        assert_eq!(
+            checked("cookie == a"),
+            Err("cookie == a: unknown subject \"cookie\"".to_string())
+        );
+        assert_eq!(
+            checked("$.total < many"),
+            Err("$.total < many: 1 and many can't be compared as numbers".to_string())
+        );
+        assert_eq!(
+            checked("$x == 1"),
+            Err("$x == 1: Invalid JSONPath: $x".to_string())
+        );
+        assert!(checked("status matches (")
+            .unwrap_err()
+            .starts_with("status matches (: invalid regex: "));
+        assert!(checked_body("$.total exists", "<total>1</total>")
+            .unwrap_err()
+            .starts_with("$.total exists: response is not JSON: "));
+    }
+}
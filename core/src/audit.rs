@@ -0,0 +1,165 @@
+use std::{error::Error, fs::OpenOptions, io::Write, path::Path};
+
+use crate::history::{self, Entry};
+
+// Headers whose values are credentials wherever they're sent, so they're never logged.
+pub const SENSITIVE_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+pub const REDACTED: &str = "[redacted]";
+
+// Logs a request and its response to the end of an audit log, with the values of secrets and
+// credential headers replaced. A log ending in .har is kept as a HAR file, and any other gets a
+// line of JSON per request, in the shape of a HAR entry.
+pub fn append(filename: &str, entry: &Entry, secrets: &[&str]) -> Result<(), Box<dyn Error>> {
+    let entry = sanitized(entry, secrets);
+    if filename.ends_with(".har") {
+        let mut entries = if Path::new(filename).exists() {
+            history::load(filename)?
+        } else {
+            vec![]
+        };
+        entries.push(entry);
+        history::save(filename, &entries)?;
+    } else {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(filename)?;
+        writeln!(file, "{}", json::stringify(entry.to_har()))?;
+    }
+    Ok(())
+}
+
+// The entry with the values of secrets and credential headers replaced, for anything written to disk.
+pub fn sanitized(entry: &Entry, secrets: &[&str]) -> Entry {
+    let hide = |text: &str| {
+        secrets
+            .iter()
+            .filter(|secret| !secret.is_empty())
+            .fold(text.to_string(), |text, secret| {
+                text.replace(secret, REDACTED)
+            })
+    };
+    let hide_headers = |headers: &[(String, String)]| {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if SENSITIVE_HEADERS.contains(&name.to_lowercase().as_str()) {
+                    REDACTED.to_string()
+                } else {
+                    hide(value)
+                };
+                (name.clone(), value)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    Entry {
+        url: hide(&entry.url),
+        request_headers: hide_headers(&entry.request_headers),
+        request_body: hide(&entry.request_body),
+        response_headers: hide_headers(&entry.response_headers),
+        response_body: hide(&entry.response_body),
+        ..entry.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    fn entry() -> Entry {
+        Entry {
+            method: "POST".to_string(),
+            url: "https://example.com/users?api_key=k3y".to_string(),
+            request_headers: vec![
+                ("Authorization".to_string(), "Bearer t0ken".to_string()),
+                ("Cookie".to_string(), "session=abc".to_string()),
+                ("X-Api-Key".to_string(), "k3y".to_string()),
+                ("Accept".to_string(), "application/json".to_string()),
+            ],
+            request_body: r#"{"password":"hunter2"}"#.to_string(),
+            status: 200,
+            response_headers: vec![("set-cookie".to_string(), "session=def".to_string())],
+            response_body: r#"{"token":"t0ken","name":"Ada"}"#.to_string(),
+            ..Entry::default()
+        }
+    }
+
+    #[test]
+    fn credential_headers_are_redacted() {
+        let entry = sanitized(&entry(), &[]);
+        assert_eq!(
+            entry.request_headers,
+            vec![
+                ("Authorization".to_string(), REDACTED.to_string()),
+                ("Cookie".to_string(), REDACTED.to_string()),
+                ("X-Api-Key".to_string(), "k3y".to_string()),
+                ("Accept".to_string(), "application/json".to_string()),
+            ]
+        );
+        assert_eq!(
+            entry.response_headers,
+            vec![("set-cookie".to_string(), REDACTED.to_string())]
+        );
+    }
+
+    #[test]
+    fn secret_values_are_replaced_everywhere() {
+        let entry = sanitized(&entry(), &["k3y", "hunter2", "t0ken", ""]);
+        assert_eq!(entry.url, "https://example.com/users?api_key=[redacted]");
+        assert_eq!(entry.request_headers[2].1, REDACTED);
+        assert_eq!(entry.request_body, r#"{"password":"[redacted]"}"#);
+        assert_eq!(
+            entry.response_body,
+            r#"{"token":"[redacted]","name":"Ada"}"#
+        );
+        assert_eq!(entry.method, "POST");
+        assert_eq!(entry.status, 200);
+    }
+
+    #[test]
+    fn append_lines_of_json() {
+        let filename = env::temp_dir()
+            .join(format!("reqcoon-audit-{}.log", process::id()))
+            .display()
+            .to_string();
+        append(&filename, &entry(), &["hunter2"]).unwrap();
+        append(&filename, &entry(), &["hunter2"]).unwrap();
+        let log = fs::read_to_string(&filename).unwrap();
+        fs::remove_file(&filename).unwrap();
+
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(!log.contains("hunter2") && !log.contains("Bearer t0ken"));
+        let logged = Entry::from_har(&json::parse(lines[0]).unwrap());
+        assert_eq!(logged, sanitized(&entry(), &["hunter2"]));
+    }
+
+    #[test]
+    fn append_to_a_har_file() {
+        let filename = env::temp_dir()
+            .join(format!("reqcoon-audit-{}.har", process::id()))
+            .display()
+            .to_string();
+        append(&filename, &entry(), &["t0ken"]).unwrap();
+        append(&filename, &entry(), &["t0ken"]).unwrap();
+        let logged = history::load(&filename);
+        fs::remove_file(&filename).unwrap();
+
+        let logged = logged.unwrap();
+        assert_eq!(logged.len(), 2);
+        assert_eq!(logged[1], sanitized(&entry(), &["t0ken"]));
+        assert_eq!(
+            logged[1].response_body,
+            r#"{"token":"[redacted]","name":"Ada"}"#
+        );
+    }
+}
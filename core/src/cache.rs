@@ -0,0 +1,54 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, Write},
+};
+
+use json::JsonValue;
+
+use crate::{history::Entry, save_file};
+
+// The last response to each request, so reopening the file shows it again. Requests are keyed the
+// way examples are, by name or position.
+pub type Responses = Vec<(String, Entry)>;
+
+pub fn set(responses: &mut Responses, key: &str, entry: Entry) {
+    match responses.iter_mut().find(|(existing, _)| existing == key) {
+        Some((_, existing)) => *existing = entry,
+        None => responses.push((key.to_string(), entry)),
+    }
+}
+
+pub fn get<'a>(responses: &'a Responses, key: &str) -> Option<&'a Entry> {
+    responses
+        .iter()
+        .find(|(existing, _)| existing == key)
+        .map(|(_, entry)| entry)
+}
+
+pub fn filename(request_filename: &str) -> String {
+    format!("{}.responses.json", request_filename)
+}
+
+// Each response is saved as a HAR entry, like the history.
+pub fn load(filename: &str) -> Result<Responses, Box<dyn Error>> {
+    let file = json::parse(&fs::read_to_string(filename)?)
+        .map_err(|err| format!("{} is not valid JSON: {}", filename, err))?;
+
+    Ok(save_file::contents(&file, "responses", filename)?
+        .entries()
+        .map(|(key, entry)| (key.to_string(), Entry::from_har(entry)))
+        .collect())
+}
+
+pub fn save(filename: &str, responses: &Responses) -> io::Result<()> {
+    let mut file_json = JsonValue::new_object();
+    for (key, entry) in responses {
+        file_json[key.as_str()] = entry.to_har();
+    }
+
+    let mut file = File::create(filename)?;
+    file.write_all(
+        json::stringify_pretty(save_file::versioned("responses", file_json), 2).as_bytes(),
+    )
+}
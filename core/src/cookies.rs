@@ -0,0 +1,151 @@
+use std::sync::{Arc, OnceLock, RwLock};
+
+use reqwest::{cookie::CookieStore, header::HeaderValue, Url};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    // Cookies set without a Domain attribute only go back to the host that set them.
+    pub host_only: bool,
+    pub secure: bool,
+}
+
+impl Cookie {
+    // A cookie to send to the URL's host, like one typed into the cookies popup.
+    pub fn for_host(url: &Url, name: &str, value: &str) -> Self {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: url.host_str().unwrap_or_default().to_lowercase(),
+            path: "/".to_string(),
+            host_only: true,
+            secure: false,
+        }
+    }
+
+    // Parses a Set-Cookie header, returning the cookie and whether it has already expired, which
+    // is how servers delete cookies.
+    fn parse(header: &str, url: &Url) -> Option<(Self, bool)> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let mut cookie = Cookie::for_host(url, name.trim(), value.trim().trim_matches('"'));
+        cookie.path = default_path(url);
+        let mut expired = false;
+
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    cookie.domain = value.trim_start_matches('.').to_lowercase();
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "max-age" => expired = value.parse::<i64>().is_ok_and(|max_age| max_age <= 0),
+                "secure" => cookie.secure = true,
+                _ => (),
+            }
+        }
+
+        Some((cookie, expired))
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            host == self.domain || host.ends_with(&format!(".{}", self.domain))
+        };
+        let path_matches = url.path().starts_with(&self.path);
+
+        domain_matches && path_matches && (!self.secure || url.scheme() == "https")
+    }
+
+    fn same(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+// The cookies servers have set this session. Every client shares it, so cookies from a login
+// request go out with the requests after it.
+#[derive(Debug, Default)]
+pub struct Jar {
+    cookies: RwLock<Vec<Cookie>>,
+}
+
+pub fn jar() -> Arc<Jar> {
+    static JAR: OnceLock<Arc<Jar>> = OnceLock::new();
+    JAR.get_or_init(Arc::default).clone()
+}
+
+impl Jar {
+    // The cookies that would be sent with a request to the URL.
+    pub fn matching(&self, url: &Url) -> Vec<Cookie> {
+        self.cookies
+            .read()
+            .map(|cookies| {
+                cookies
+                    .iter()
+                    .filter(|cookie| cookie.matches(url))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Swaps the cookies that would be sent to the URL for the given ones, so they can be edited
+    // or dropped.
+    pub fn replace_matching(&self, url: &Url, replacements: Vec<Cookie>) {
+        if let Ok(mut cookies) = self.cookies.write() {
+            cookies.retain(|cookie| !cookie.matches(url));
+            cookies.extend(replacements);
+        }
+    }
+}
+
+impl CookieStore for Jar {
+    fn set_cookies(&self, headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let Ok(mut cookies) = self.cookies.write() else {
+            return;
+        };
+        for header in headers {
+            let Some((cookie, expired)) = header
+                .to_str()
+                .ok()
+                .and_then(|header| Cookie::parse(header, url))
+            else {
+                continue;
+            };
+            cookies.retain(|existing| !existing.same(&cookie));
+            if !expired {
+                cookies.push(cookie);
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .matching(url)
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<String>>()
+            .join("; ");
+        if header.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&header).ok()
+        }
+    }
+}
+
+// The directory of the request path, per RFC 6265.
+fn default_path(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => url.path()[..index].to_string(),
+    }
+}
@@ -0,0 +1,414 @@
+use json::JsonValue;
+use reqwest::{Method, Url};
+
+use crate::request::{Auth, BodyFormat, Request};
+
+// Flags that don't change what is sent, so they're skipped.
+const IGNORED_FLAGS: [&str; 12] = [
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-L",
+    "--location",
+    "-v",
+    "--verbose",
+    "-i",
+    "--include",
+    "--compressed",
+    "--fail",
+];
+
+// Options that don't change what is sent, along with the value they take.
+const IGNORED_OPTIONS: [&str; 6] = [
+    "-o",
+    "--output",
+    "-w",
+    "--write-out",
+    "--connect-timeout",
+    "--retry",
+];
+
+// Parses a curl command, like one copied from a browser's developer tools, into a request.
+pub fn parse(command: &str) -> Result<Request, String> {
+    let mut words = split(command)?.into_iter();
+    if words.next().as_deref() != Some("curl") {
+        return Err("Expected a command starting with curl".to_string());
+    }
+
+    let mut request = Request::default();
+    let mut method = None;
+    let mut data: Vec<String> = vec![];
+    let mut form: Vec<(String, String)> = vec![];
+    let mut multipart: Vec<(String, String)> = vec![];
+    let mut get = false;
+
+    while let Some(word) = words.next() {
+        let (flag, attached) = split_flag(&word);
+        let mut value = || {
+            attached
+                .clone()
+                .or_else(|| words.next())
+                .ok_or(format!("Missing value for {}", flag))
+        };
+        match flag.as_str() {
+            "-X" | "--request" => {
+                let name = value()?;
+                method = Some(
+                    Method::from_bytes(name.to_uppercase().as_bytes())
+                        .map_err(|_| format!("Invalid method: {}", name))?,
+                );
+            }
+            "--url" => request.url = value()?,
+            "-H" | "--header" => {
+                let header = value()?;
+                let (name, value) = header
+                    .split_once(':')
+                    .ok_or(format!("Invalid header: {}", header))?;
+                request
+                    .headers
+                    .push((name.trim().to_string(), value.trim().to_string()));
+            }
+            "-A" | "--user-agent" => request.headers.push(("User-Agent".to_string(), value()?)),
+            "-e" | "--referer" => request.headers.push(("Referer".to_string(), value()?)),
+            "-b" | "--cookie" => request.headers.push(("Cookie".to_string(), value()?)),
+            "-u" | "--user" => {
+                let user = value()?;
+                request.auth = match user.split_once(':') {
+                    Some((username, password)) => Auth::Basic {
+                        username: username.to_string(),
+                        password: Some(password.to_string()),
+                    },
+                    None => Auth::Basic {
+                        username: user,
+                        password: None,
+                    },
+                };
+            }
+            "-d" | "--data" | "--data-raw" | "--data-binary" | "--data-ascii" => {
+                data.push(value()?)
+            }
+            "--json" => {
+                data.push(value()?);
+                request
+                    .headers
+                    .push(("Content-Type".to_string(), "application/json".to_string()));
+            }
+            "--data-urlencode" => {
+                let pair = value()?;
+                let (key, value) = pair.split_once('=').unwrap_or(("", &pair));
+                form.push((key.to_string(), value.to_string()));
+            }
+            "-F" | "--form" => {
+                let pair = value()?;
+                let (key, value) = pair
+                    .split_once('=')
+                    .ok_or(format!("Invalid form field: {}", pair))?;
+                multipart.push((key.to_string(), value.to_string()));
+            }
+            "-G" | "--get" => get = true,
+            "-k" | "--insecure" => request
+                .options
+                .push(("verify-tls".to_string(), "false".to_string())),
+            "-m" | "--max-time" => request
+                .options
+                .push(("timeout".to_string(), format!("{}s", value()?))),
+            "-x" | "--proxy" => request.options.push(("proxy".to_string(), value()?)),
+            _ if IGNORED_FLAGS.contains(&flag.as_str()) => (),
+            _ if IGNORED_OPTIONS.contains(&flag.as_str()) => {
+                value()?;
+            }
+            _ if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("Unsupported curl option: {}", flag))
+            }
+            _ => request.url = word,
+        }
+    }
+
+    if request.url.is_empty() {
+        return Err("The curl command has no URL".to_string());
+    }
+
+    // With -G the data goes in the query string instead of the body.
+    if get {
+        let query: Vec<String> = data
+            .drain(..)
+            .chain(
+                form.drain(..)
+                    .map(|(key, value)| format!("{}={}", key, value)),
+            )
+            .collect();
+        if !query.is_empty() {
+            let separator = if request.url.contains('?') { '&' } else { '?' };
+            request.url = format!("{}{}{}", request.url, separator, query.join("&"));
+        }
+    }
+
+    let has_body = !data.is_empty() || !form.is_empty() || !multipart.is_empty();
+    request.method = method.unwrap_or(if has_body { Method::POST } else { Method::GET });
+    if !form.is_empty() {
+        request.body_format = BodyFormat::UrlEncoded;
+        request.body.extend(form);
+    }
+    if !multipart.is_empty() {
+        request.body_format = BodyFormat::Multipart;
+        request.body.extend(multipart);
+    }
+    if !data.is_empty() {
+        let (format, pairs) = body(&data.join("&"))?;
+        request.body_format = format;
+        request.body.extend(pairs);
+    }
+
+    Ok(request)
+}
+
+// Bodies are edited as key-value pairs, so only JSON objects and form data can be imported.
+fn body(data: &str) -> Result<(BodyFormat, Vec<(String, String)>), String> {
+    if let Ok(JsonValue::Object(object)) = json::parse(data) {
+        let pairs = object
+            .iter()
+            .map(|(key, value)| {
+                let value = value.as_str().map_or(value.dump(), str::to_string);
+                (key.to_string(), value)
+            })
+            .collect();
+        return Ok((BodyFormat::Json, pairs));
+    }
+
+    if data.contains('=') && !data.trim_start().starts_with(['{', '[']) {
+        let url = Url::parse(&format!("http://localhost/?{}", data))
+            .map_err(|err| format!("Invalid form data: {}", err))?;
+        let pairs = url
+            .query_pairs()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        return Ok((BodyFormat::UrlEncoded, pairs));
+    }
+
+    Err("Only JSON object and form bodies can be imported".to_string())
+}
+
+// Long options can carry their value after an =, and short ones right after the letter, as in
+// --request=POST or -XPOST.
+fn split_flag(word: &str) -> (String, Option<String>) {
+    if let Some(option) = word.strip_prefix("--") {
+        return match option.split_once('=') {
+            Some((name, value)) => (format!("--{}", name), Some(value.to_string())),
+            None => (word.to_string(), None),
+        };
+    }
+    if word.starts_with('-') && word.len() > 2 && word.is_char_boundary(2) {
+        let (flag, value) = word.split_at(2);
+        if flag == "-X" || flag == "-H" || flag == "-d" || flag == "-u" {
+            return (flag.to_string(), Some(value.to_string()));
+        }
+        // Flags like -sSL combine several that are all skipped.
+        if value
+            .chars()
+            .all(|letter| IGNORED_FLAGS.contains(&format!("-{}", letter).as_str()))
+        {
+            return (flag.to_string(), None);
+        }
+    }
+    (word.to_string(), None)
+}
+
+// Splits a command into words the way a POSIX shell would, handling quotes, escapes and line
+// continuations.
+fn split(command: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(character) = chars.next() {
+        match character {
+            '\'' => {
+                let text = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(character) => text.push(character),
+                        None => return Err("Unterminated ' in the curl command".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let text = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(character @ ('"' | '\\' | '$' | '`')) => text.push(character),
+                            Some('\n') => (),
+                            Some(character) => {
+                                text.push('\\');
+                                text.push(character);
+                            }
+                            None => return Err("Unterminated \" in the curl command".to_string()),
+                        },
+                        Some(character) => text.push(character),
+                        None => return Err("Unterminated \" in the curl command".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') | Some('\r') => (),
+                Some(character) => word.get_or_insert_with(String::new).push(character),
+                None => (),
+            },
+            character if character.is_whitespace() => words.extend(word.take()),
+            character => word.get_or_insert_with(String::new).push(character),
+        }
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn urls_alone_are_gets() {
+        let request = parse("curl https://example.com/users").unwrap();
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(request.url, "https://example.com/users");
+        assert!(request.headers.is_empty());
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn commands_copied_from_devtools() {
+        let request = parse(
+            "curl 'https://example.com/users' \\\n  -H 'Accept: application/json' \\\n  -H \"Authorization: Bearer abc\" \\\n  --data-raw '{\"name\":\"Ada\",\"age\":36}' \\\n  --compressed",
+        )
+        .unwrap();
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.url, "https://example.com/users");
+        assert_eq!(
+            request.headers,
+            pairs(&[
+                ("Accept", "application/json"),
+                ("Authorization", "Bearer abc")
+            ])
+        );
+        assert_eq!(request.body_format, BodyFormat::Json);
+        assert_eq!(request.body, pairs(&[("name", "Ada"), ("age", "36")]));
+    }
+
+    #[test]
+    fn methods_can_be_attached_to_their_flag() {
+        assert_eq!(
+            parse("curl -XPUT https://x.io").unwrap().method,
+            Method::PUT
+        );
+        assert_eq!(
+            parse("curl --request=patch https://x.io").unwrap().method,
+            Method::PATCH
+        );
+        assert_eq!(
+            parse("curl --url https://x.io -X DELETE").unwrap().method,
+            Method::DELETE
+        );
+    }
+
+    #[test]
+    fn users_are_basic_auth() {
+        assert_eq!(
+            parse("curl -u ada:secret https://x.io").unwrap().auth,
+            Auth::Basic {
+                username: "ada".to_string(),
+                password: Some("secret".to_string())
+            }
+        );
+        assert_eq!(
+            parse("curl --user ada https://x.io").unwrap().auth,
+            Auth::Basic {
+                username: "ada".to_string(),
+                password: None
+            }
+        );
+    }
+
+    #[test]
+    fn form_bodies() {
+        let request = parse("curl https://x.io -d 'a=1&b=two+words'").unwrap();
+        assert_eq!(request.body_format, BodyFormat::UrlEncoded);
+        assert_eq!(request.body, pairs(&[("a", "1"), ("b", "two words")]));
+
+        let request = parse("curl https://x.io --data-urlencode 'q=hello world'").unwrap();
+        assert_eq!(request.body_format, BodyFormat::UrlEncoded);
+        assert_eq!(request.body, pairs(&[("q", "hello world")]));
+
+        let request = parse("curl https://x.io -F 'photo=@me.jpg' -F name=Ada").unwrap();
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.body_format, BodyFormat::Multipart);
+        assert_eq!(
+            request.body,
+            pairs(&[("photo", "@me.jpg"), ("name", "Ada")])
+        );
+    }
+
+    #[test]
+    fn get_puts_the_data_in_the_query() {
+        let request =
+            parse("curl -G https://x.io/search?lang=en -d q=rust --data-urlencode 'page=2'")
+                .unwrap();
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(request.url, "https://x.io/search?lang=en&q=rust&page=2");
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn options_and_skipped_flags() {
+        let request =
+            parse("curl -sSL -k -m 5 -x http://proxy:8080 -o out.json https://x.io").unwrap();
+        assert_eq!(request.url, "https://x.io");
+        assert_eq!(
+            request.options,
+            pairs(&[
+                ("verify-tls", "false"),
+                ("timeout", "5s"),
+                ("proxy", "http://proxy:8080")
+            ])
+        );
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        let request = parse(r#"curl "https://x.io/?a=\"b\"" -H X-Name:\ Ada"#).unwrap();
+        assert_eq!(request.url, r#"https://x.io/?a="b""#);
+        assert_eq!(request.headers, pairs(&[("X-Name", "Ada")]));
+    }
+
+    #[test]
+    fn invalid_commands() {
+        for (command, error) in [
+            ("wget https://x.io", "Expected a command starting with curl"),
+            ("curl -X GET", "The curl command has no URL"),
+            ("curl https://x.io -H", "Missing value for -H"),
+            ("curl https://x.io -H 'Accept'", "Invalid header: Accept"),
+            (
+                "curl --frobnicate https://x.io",
+                "Unsupported curl option: --frobnicate",
+            ),
+            ("curl 'https://x.io", "Unterminated ' in the curl command"),
+            ("curl \"https://x.io", "Unterminated \" in the curl command"),
+            (
+                "curl https://x.io -d '[1, 2]'",
+                "Only JSON object and form bodies can be imported",
+            ),
+        ] {
+            assert_eq!(parse(command), Err(error.to_string()), "{}", command);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+// Past this many cells in the comparison table, the changed lines are shown as all removed and
+// then all added rather than taking too long to match up.
+const CELL_LIMIT: usize = 4_000_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+impl Change {
+    // The line with a +, - or space in front, the way diffs are usually printed.
+    pub fn line(&self) -> String {
+        match self {
+            Change::Same(line) => format!("  {}", line),
+            Change::Added(line) => format!("+ {}", line),
+            Change::Removed(line) => format!("- {}", line),
+        }
+    }
+}
+
+// The changes that turn old into new a line at a time, keeping as many lines the same as possible.
+pub fn lines(old: &str, new: &str) -> Vec<Change> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Lines the texts start and end with are the same whatever is in between, and skipping them
+    // keeps the table small for the usual response that changed in a few places.
+    let prefix = old
+        .iter()
+        .zip(&new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_changed = &old[prefix..old.len() - suffix];
+    let new_changed = &new[prefix..new.len() - suffix];
+
+    let mut changes: Vec<Change> = old[..prefix]
+        .iter()
+        .map(|line| Change::Same(line.to_string()))
+        .collect();
+    changes.extend(changed(old_changed, new_changed));
+    changes.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Change::Same(line.to_string())),
+    );
+    changes
+}
+
+fn changed(old: &[&str], new: &[&str]) -> Vec<Change> {
+    if (old.len() + 1) * (new.len() + 1) > CELL_LIMIT {
+        return old
+            .iter()
+            .map(|line| Change::Removed(line.to_string()))
+            .chain(new.iter().map(|line| Change::Added(line.to_string())))
+            .collect();
+    }
+
+    // common[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let width = new.len() + 1;
+    let mut common = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i * width + j] = if old[i] == new[j] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(Change::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if common[(i + 1) * width + j] >= common[i * width + j + 1] {
+            changes.push(Change::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    changes.extend(
+        old[i..]
+            .iter()
+            .map(|line| Change::Removed(line.to_string())),
+    );
+    changes.extend(new[j..].iter().map(|line| Change::Added(line.to_string())));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same(line: &str) -> Change {
+        Change::Same(line.to_string())
+    }
+
+    fn added(line: &str) -> Change {
+        Change::Added(line.to_string())
+    }
+
+    fn removed(line: &str) -> Change {
+        Change::Removed(line.to_string())
+    }
+
+    #[test]
+    fn identical_texts_are_all_the_same() {
+        assert_eq!(lines("a\nb", "a\nb"), vec![same("a"), same("b")]);
+        assert_eq!(lines("", ""), vec![]);
+    }
+
+    #[test]
+    fn changed_lines_are_removed_then_added() {
+        assert_eq!(
+            lines("a\nb\nc", "a\nx\nc"),
+            vec![same("a"), removed("b"), added("x"), same("c")]
+        );
+    }
+
+    #[test]
+    fn lines_are_added_and_removed() {
+        assert_eq!(
+            lines("a\nc", "a\nb\nc"),
+            vec![same("a"), added("b"), same("c")]
+        );
+        assert_eq!(
+            lines("a\nb\nc", "a\nc"),
+            vec![same("a"), removed("b"), same("c")]
+        );
+        assert_eq!(lines("", "a\nb"), vec![added("a"), added("b")]);
+        assert_eq!(lines("a\nb", ""), vec![removed("a"), removed("b")]);
+    }
+
+    #[test]
+    fn as_many_lines_as_possible_stay_the_same() {
+        assert_eq!(
+            lines("a\nb\nc\nd", "b\nc\nd\na"),
+            vec![removed("a"), same("b"), same("c"), same("d"), added("a")]
+        );
+    }
+
+    #[test]
+    fn large_changes_are_not_matched_up() {
+        let text = |prefix: &str| {
+            (0..2000)
+                .map(|index| match index {
+                    1000 => "shared".to_string(),
+                    _ => format!("{} {}", prefix, index),
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+        let changes = lines(&text("old"), &text("new"));
+        assert_eq!(changes.len(), 4000);
+        assert_eq!(changes[1000], removed("shared"));
+        assert_eq!(changes[3000], added("shared"));
+        assert!(!changes
+            .iter()
+            .any(|change| matches!(change, Change::Same(_))));
+
+        // Below the limit the shared line is found.
+        let short = |prefix: &str| {
+            text(prefix)
+                .lines()
+                .skip(900)
+                .take(200)
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        assert!(lines(&short("old"), &short("new")).contains(&same("shared")));
+    }
+
+    #[test]
+    fn lines_are_marked() {
+        assert_eq!(same("a").line(), "  a");
+        assert_eq!(added("a").line(), "+ a");
+        assert_eq!(removed("a").line(), "- a");
+    }
+}
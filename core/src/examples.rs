@@ -0,0 +1,154 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, Write},
+};
+
+use json::JsonValue;
+
+use crate::{history::Entry, save_file};
+
+#[derive(Clone)]
+pub struct Example {
+    pub label: String,
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl Example {
+    pub fn from_entry(entry: &Entry) -> Self {
+        Example {
+            label: scenario(entry.status).map_or(entry.status_text.clone(), str::to_string),
+            status: entry.status,
+            content_type: entry
+                .response_headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                .map_or(String::default(), |(_, value)| value.clone()),
+            body: entry.response_body.clone(),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        format!("{} ({})", self.label, self.status)
+    }
+}
+
+// Examples are kept per request, keyed by the request's name or, for unnamed requests, its
+// position in the file.
+pub type Examples = Vec<(String, Vec<Example>)>;
+
+pub fn key(name: &str, index: usize) -> String {
+    if name.is_empty() {
+        format!("#{}", index + 1)
+    } else {
+        name.to_string()
+    }
+}
+
+// Adding an example replaces any other example for the same scenario.
+pub fn add(examples: &mut Examples, key: &str, example: Example) {
+    let index = match examples.iter().position(|(existing, _)| existing == key) {
+        Some(index) => index,
+        None => {
+            examples.push((key.to_string(), vec![]));
+            examples.len() - 1
+        }
+    };
+    let request_examples = &mut examples[index].1;
+    request_examples.retain(|existing| existing.label != example.label);
+    request_examples.push(example);
+}
+
+fn scenario(status: u16) -> Option<&'static str> {
+    match status {
+        200..=299 => Some("success"),
+        400 | 422 => Some("validation error"),
+        401 => Some("unauthorized"),
+        403 => Some("forbidden"),
+        404 => Some("not found"),
+        500..=599 => Some("server error"),
+        _ => None,
+    }
+}
+
+pub fn filename(request_filename: &str) -> String {
+    format!("{}.examples.json", request_filename)
+}
+
+pub fn load(filename: &str) -> Result<Examples, Box<dyn Error>> {
+    let file = json::parse(&fs::read_to_string(filename)?)
+        .map_err(|err| format!("{} is not valid JSON: {}", filename, err))?;
+
+    let examples = save_file::contents(&file, "examples", filename)?
+        .entries()
+        .map(|(key, examples)| {
+            if !examples.is_array() {
+                return Err(format!(
+                    "{}: the examples for {} aren't a list",
+                    filename, key
+                ));
+            }
+            let examples = examples
+                .members()
+                .enumerate()
+                .map(|(index, example)| {
+                    let invalid = |field: &str| {
+                        format!(
+                            "{}: example {} of {} has no {}",
+                            filename,
+                            index + 1,
+                            key,
+                            field
+                        )
+                    };
+                    Ok(Example {
+                        label: example["label"]
+                            .as_str()
+                            .ok_or_else(|| invalid("label"))?
+                            .to_string(),
+                        status: example["status"]
+                            .as_u16()
+                            .ok_or_else(|| invalid("status"))?,
+                        content_type: example["contentType"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        body: example["body"]
+                            .as_str()
+                            .ok_or_else(|| invalid("body"))?
+                            .to_string(),
+                    })
+                })
+                .collect::<Result<Vec<Example>, String>>()?;
+            Ok((key.to_string(), examples))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(examples)
+}
+
+pub fn save(filename: &str, examples: &Examples) -> io::Result<()> {
+    let mut file_json = JsonValue::new_object();
+    for (key, request_examples) in examples {
+        file_json[key.as_str()] = JsonValue::Array(
+            request_examples
+                .iter()
+                .map(|example| {
+                    let mut example_json = JsonValue::new_object();
+                    example_json["label"] = example.label.clone().into();
+                    example_json["status"] = example.status.into();
+                    example_json["contentType"] = example.content_type.clone().into();
+                    example_json["body"] = example.body.clone().into();
+                    example_json
+                })
+                .collect(),
+        );
+    }
+
+    let mut file = File::create(filename)?;
+    file.write_all(
+        json::stringify_pretty(save_file::versioned("examples", file_json), 2).as_bytes(),
+    )
+}
@@ -0,0 +1,255 @@
+use std::{error::Error, fmt};
+
+use crate::{
+    folder::Folder, request::Request, secrets::Secret, settings::Settings, snapshot::Snapshots,
+};
+
+// Settings that only apply in a named environment, like a staging proxy, by environment name.
+pub type Environments = Vec<(String, Vec<(String, String)>)>;
+
+// A file holds workspace settings and variables followed by one or more requests separated by
+// lines starting with ###, in the format used by the VS Code REST Client.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestFile {
+    // Settings from the user's config that the file's own settings override. They aren't saved in
+    // the file.
+    pub defaults: Vec<(String, String)>,
+    pub settings: Vec<(String, String)>,
+    pub environments: Environments,
+    // The environment chosen when reqcoon starts. It isn't saved in the file.
+    pub environment: Option<String>,
+    // The shared script and auth of the folders the file is in.
+    pub folder: Folder,
+    pub variables: Vec<(String, String)>,
+    // Variables whose values are kept outside the file.
+    pub secrets: Vec<Secret>,
+    pub requests: Vec<Request>,
+    // Responses saved next to the file, for snapshot assertions. They aren't saved in the file.
+    pub snapshots: Snapshots,
+}
+
+impl RequestFile {
+    pub fn parse(input: &str) -> Result<Self, Box<dyn Error>> {
+        let mut file = RequestFile::default();
+        let mut blocks = vec![];
+
+        let mut block = String::new();
+        let mut in_request = false;
+        for line in input.lines() {
+            if line.starts_with("###") {
+                blocks.push(std::mem::take(&mut block));
+                in_request = false;
+            } else if in_request {
+                block.push_str(line);
+                block.push('\n');
+            } else if let Some(setting) = line.strip_prefix("# @workspace ") {
+                let (key, value) = setting.split_once(' ').unwrap_or((setting, ""));
+                file.settings
+                    .push((key.trim().to_string(), value.trim().to_string()));
+            } else if let Some(setting) = line.strip_prefix("# @env ") {
+                let (environment, setting) =
+                    setting.trim().split_once(' ').unwrap_or((setting, ""));
+                let (key, value) = setting.trim().split_once(' ').unwrap_or((setting, ""));
+                let pair = (key.trim().to_string(), value.trim().to_string());
+                match file
+                    .environments
+                    .iter_mut()
+                    .find(|(existing, _)| existing == environment)
+                {
+                    Some((_, settings)) => settings.push(pair),
+                    None => file
+                        .environments
+                        .push((environment.to_string(), vec![pair])),
+                }
+            } else if let Some(declaration) = line.strip_prefix("# @secret ") {
+                file.secrets.push(Secret::parse(declaration)?);
+            } else if let Some((name, value)) =
+                line.strip_prefix('@').and_then(|line| line.split_once('='))
+            {
+                file.variables
+                    .push((name.trim().to_string(), value.trim().to_string()));
+            } else if line.starts_with("# @") {
+                block.push_str(line);
+                block.push('\n');
+            } else if !line.trim().is_empty() && !line.starts_with('#') && !line.starts_with("//") {
+                block.push_str(line);
+                block.push('\n');
+                in_request = true;
+            }
+        }
+        blocks.push(block);
+
+        for block in blocks.iter().map(|block| block.trim()) {
+            if !block.is_empty() {
+                let request = Request::parse(block).map_err(|err| {
+                    format!(
+                        "Unable to parse request {}: {}",
+                        file.requests.len() + 1,
+                        err
+                    )
+                })?;
+                file.requests.push(request);
+            }
+        }
+
+        Ok(file)
+    }
+
+    pub fn request_named(&self, name: &str) -> Option<&Request> {
+        self.requests.iter().find(|request| request.name == name)
+    }
+
+    // The user's defaults apply first, then workspace settings, then the environment's, and a
+    // request's own options override them all.
+    pub fn settings_for(&self, request: &Request) -> Result<Settings, String> {
+        Settings::default()
+            .overridden(pairs(&self.defaults))?
+            .overridden(pairs(&self.settings))?
+            .overridden(pairs(environment(
+                &self.environments,
+                self.environment.as_deref(),
+            )?))?
+            .overridden(pairs(&request.options))
+    }
+}
+
+pub fn environment<'a>(
+    environments: &'a Environments,
+    name: Option<&str>,
+) -> Result<&'a [(String, String)], String> {
+    let Some(name) = name else {
+        return Ok(&[]);
+    };
+    environments
+        .iter()
+        .find(|(existing, _)| existing == name)
+        .map(|(_, settings)| settings.as_slice())
+        .ok_or(format!("Unknown environment: {}", name))
+}
+
+impl fmt::Display for RequestFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (key, value) in &self.settings {
+            if !key.is_empty() && !value.is_empty() {
+                writeln!(f, "# @workspace {} {}", key, value)?;
+            }
+        }
+        for (environment, settings) in &self.environments {
+            for (key, value) in settings {
+                writeln!(f, "# @env {} {} {}", environment, key, value)?;
+            }
+        }
+        for secret in &self.secrets {
+            writeln!(f, "# @secret {}", secret)?;
+        }
+        // Secrets' values are only ever kept where their declaration says.
+        let variables: Vec<&(String, String)> = self
+            .variables
+            .iter()
+            .filter(|(name, _)| !self.secrets.iter().any(|secret| secret.name == *name))
+            .collect();
+        if !variables.is_empty() {
+            for (name, value) in variables {
+                writeln!(f, "@{} = {}", name, value)?;
+            }
+            writeln!(f)?;
+        }
+        write!(
+            f,
+            "{}",
+            self.requests
+                .iter()
+                .map(Request::to_string)
+                .collect::<Vec<String>>()
+                .join("\n\n###\n\n")
+        )
+    }
+}
+
+fn pairs(pairs: &[(String, String)]) -> impl Iterator<Item = (&str, &str)> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        request::BodyFormat,
+        secrets::{Secret, Source},
+    };
+
+    fn request(text: &str) -> Request {
+        Request::parse(text).unwrap()
+    }
+
+    #[test]
+    fn files_round_trip() {
+        let mut multipart = request("POST https://example.com/{{user}}/avatar");
+        multipart.name = "upload".to_string();
+        multipart.body_format = BodyFormat::Multipart;
+        multipart.body = vec![
+            ("caption".to_string(), "Me & my cat".to_string()),
+            ("avatar".to_string(), "@./cat photo.png".to_string()),
+        ];
+        let mut form = request("POST https://example.com/login");
+        form.body_format = BodyFormat::UrlEncoded;
+        form.body = vec![
+            ("user".to_string(), "{{user}}".to_string()),
+            ("password".to_string(), "{{password}}".to_string()),
+        ];
+        let file = RequestFile {
+            settings: vec![("timeout".to_string(), "5".to_string())],
+            environments: vec![(
+                "staging".to_string(),
+                vec![("proxy".to_string(), "http://proxy:3128".to_string())],
+            )],
+            variables: vec![("user".to_string(), "ada".to_string())],
+            secrets: vec![
+                Secret {
+                    name: "password".to_string(),
+                    source: Source::File,
+                },
+                Secret {
+                    name: "token".to_string(),
+                    source: Source::Env("API_TOKEN".to_string()),
+                },
+            ],
+            requests: vec![
+                request("# @name users\nGET https://example.com/users\nAccept: application/json"),
+                form,
+                multipart,
+                request("PUT https://example.com/users/1\n\n{\"name\":\"Ada\"}"),
+            ],
+            ..RequestFile::default()
+        };
+
+        assert_eq!(RequestFile::parse(&file.to_string()).unwrap(), file);
+    }
+
+    #[test]
+    fn secret_values_are_left_out() {
+        let file = RequestFile {
+            variables: vec![
+                ("user".to_string(), "ada".to_string()),
+                ("password".to_string(), "hunter2".to_string()),
+            ],
+            secrets: vec![Secret {
+                name: "password".to_string(),
+                source: Source::Keychain,
+            }],
+            requests: vec![request("GET https://example.com/")],
+            ..RequestFile::default()
+        };
+
+        let text = file.to_string();
+        assert!(!text.contains("hunter2"), "{}", text);
+        let parsed = RequestFile::parse(&text).unwrap();
+        assert_eq!(parsed.secrets, file.secrets);
+        assert_eq!(
+            parsed.variables,
+            vec![("user".to_string(), "ada".to_string())]
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    file::RequestFile,
+    request::{Auth, Request},
+};
+
+// A folder's _folder.http holds a request whose pre-request script and auth are shared by every
+// request in the folder and the folders under it. Its method and URL are ignored.
+pub const FILENAME: &str = "_folder.http";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Folder {
+    // The scripts of every enclosing folder, outermost first.
+    pub scripts: Vec<String>,
+    // The auth of the nearest folder that has some.
+    pub auth: Auth,
+    // The request file's own directory, which body files are read relative to.
+    pub directory: PathBuf,
+}
+
+impl Folder {
+    // Finds the folder files in the directories above a request file.
+    pub fn for_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = path.canonicalize().unwrap_or(path.to_path_buf());
+        let mut folder = Folder {
+            directory: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            ..Folder::default()
+        };
+        let directories: Vec<&Path> = path.ancestors().skip(1).collect();
+        for directory in directories.into_iter().rev() {
+            let folder_path = directory.join(FILENAME);
+            if folder_path == path || !folder_path.is_file() {
+                continue;
+            }
+
+            let file = RequestFile::parse(&fs::read_to_string(&folder_path)?)
+                .map_err(|err| format!("{}: {}", folder_path.display(), err))?;
+            let Some(request) = file.requests.first() else {
+                continue;
+            };
+            folder.scripts.extend(request.script.clone());
+            if request.auth != Auth::None {
+                folder.auth = request.auth.clone();
+            }
+        }
+
+        Ok(folder)
+    }
+
+    // The request as it is sent: the folder scripts run before its own, and the folder auth is
+    // used if it doesn't have any. Standalone requests are left alone, apart from finding their
+    // body file.
+    pub fn apply(&self, request: &Request) -> Request {
+        let mut request = request.clone();
+        if let Some(path) = request.body_file().map(Path::new) {
+            if path.is_relative() {
+                let path = self.directory.join(path).display().to_string();
+                request.body = vec![(format!("@{}", path), String::new())];
+            }
+        }
+        if request.standalone {
+            return request;
+        }
+
+        if request.auth == Auth::None {
+            request.auth = self.auth.clone();
+        }
+        let scripts: Vec<String> = self
+            .scripts
+            .iter()
+            .cloned()
+            .chain(request.script.take())
+            .collect();
+        if !scripts.is_empty() {
+            request.script = Some(scripts.join("\n"));
+        }
+
+        request
+    }
+}
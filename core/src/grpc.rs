@@ -0,0 +1,342 @@
+use std::{path::Path, str::FromStr};
+
+use bytes::{Buf, BufMut};
+use prost::Message as _;
+use prost_reflect::{DescriptorPool, DynamicMessage, Kind, MessageDescriptor, MethodDescriptor};
+use prost_types::FileDescriptorProto;
+use reqwest::Url;
+use tokio::runtime::{Builder, Runtime};
+use tonic::{
+    client::Grpc,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    codegen::http::uri::PathAndQuery,
+    metadata::{Ascii, MetadataKey, MetadataValue},
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Code, Status,
+};
+use tonic_reflection::pb::{
+    server_reflection_client::ServerReflectionClient, server_reflection_request::MessageRequest,
+    server_reflection_response::MessageResponse, ServerReflectionRequest,
+};
+
+use crate::{request::Request, settings::Settings};
+
+// Requests with this method are gRPC calls, like `GRPC localhost:50051/helloworld.Greeter/SayHello`,
+// with the request message as their JSON body. The server's reflection service describes its
+// methods, or the .proto file in the grpc-proto setting does for servers without one.
+pub const METHOD: &str = "GRPC";
+
+// Every method the server or .proto file has, like
+// `helloworld.Greeter/SayHello (HelloRequest) returns (HelloReply)`.
+pub fn methods(url: &str, settings: &Settings) -> Result<Vec<String>, String> {
+    let (endpoint, _) = target(url)?;
+    let pool = runtime()?.block_on(pool(&endpoint, settings))?;
+
+    Ok(pool
+        .services()
+        .filter(|service| !service.full_name().starts_with("grpc.reflection."))
+        .flat_map(|service| {
+            service
+                .methods()
+                .map(|method| {
+                    let stream = |streaming: bool| if streaming { "stream " } else { "" };
+                    format!(
+                        "{}/{} ({}{}) returns ({}{})",
+                        service.full_name(),
+                        method.name(),
+                        stream(method.is_client_streaming()),
+                        method.input().full_name(),
+                        stream(method.is_server_streaming()),
+                        method.output().full_name()
+                    )
+                })
+                .collect::<Vec<String>>()
+        })
+        .collect())
+}
+
+// The fields of the method's request message, as body rows to fill in.
+pub fn fields(url: &str, settings: &Settings) -> Result<Vec<String>, String> {
+    let (endpoint, path) = target(url)?;
+    let pool = runtime()?.block_on(pool(&endpoint, settings))?;
+
+    Ok(method(&pool, &path)?
+        .input()
+        .fields()
+        .map(|field| field.json_name().to_string())
+        .collect())
+}
+
+// Makes a unary call and returns the reply message as JSON.
+pub fn call(request: &Request, settings: &Settings) -> Result<String, String> {
+    let (endpoint, path) = target(&request.url)?;
+
+    runtime()?.block_on(async {
+        let pool = pool(&endpoint, settings).await?;
+        let method = method(&pool, &path)?;
+        if method.is_client_streaming() || method.is_server_streaming() {
+            return Err(format!(
+                "{} streams, and only unary calls are supported",
+                path
+            ));
+        }
+
+        let mut call = tonic::Request::new(message(&method.input(), request)?.encode_to_vec());
+        for (name, value) in metadata(request) {
+            let key = MetadataKey::<Ascii>::from_bytes(name.to_lowercase().as_bytes())
+                .map_err(|_| format!("Invalid metadata name: {}", name))?;
+            let value = MetadataValue::<Ascii>::from_str(&value)
+                .map_err(|_| format!("Invalid metadata value for {}", name))?;
+            call.metadata_mut().insert(key, value);
+        }
+
+        let mut client = Grpc::new(connect(&endpoint, settings).await?);
+        client
+            .ready()
+            .await
+            .map_err(|err| format!("Unable to connect to {}: {}", endpoint, err))?;
+        let path = PathAndQuery::from_str(&format!("/{}", path)).map_err(|err| err.to_string())?;
+        let reply = client
+            .unary(call, path, BytesCodec)
+            .await
+            .map_err(status_error)?;
+
+        let reply = DynamicMessage::decode(method.output(), reply.get_ref().as_slice())
+            .map_err(|err| format!("Unable to decode the reply: {}", err))?;
+        serde_json::to_string_pretty(&reply).map_err(|err| err.to_string())
+    })
+}
+
+// The URL with its path replaced by the service and method, like helloworld.Greeter/SayHello.
+pub fn url_for(url: &str, method: &str) -> String {
+    let start = url.find("://").map_or(0, |index| index + 3);
+    let end = url[start..]
+        .find('/')
+        .map_or(url.len(), |index| start + index);
+    format!("{}/{}", &url[..end], method.trim_matches('/'))
+}
+
+// The server to connect to and the service and method, from a URL like
+// http://localhost:50051/helloworld.Greeter/SayHello, where http:// is assumed without a scheme.
+fn target(url: &str) -> Result<(String, String), String> {
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    };
+    let url = Url::parse(&url).map_err(|err| format!("Invalid URL {}: {}", url, err))?;
+    let host = url.host_str().ok_or(format!("{} has no host", url))?;
+    let endpoint = match url.port() {
+        Some(port) => format!("{}://{}:{}", url.scheme(), host, port),
+        None => format!("{}://{}", url.scheme(), host),
+    };
+
+    Ok((endpoint, url.path().trim_matches('/').to_string()))
+}
+
+fn runtime() -> Result<Runtime, String> {
+    Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+async fn connect(endpoint: &str, settings: &Settings) -> Result<Channel, String> {
+    let mut channel = Endpoint::from_shared(endpoint.to_string()).map_err(|err| err.to_string())?;
+    if endpoint.starts_with("https://") {
+        channel = channel
+            .tls_config(ClientTlsConfig::new())
+            .map_err(|err| err.to_string())?;
+    }
+    if let Some(timeout) = settings.timeout {
+        channel = channel.timeout(timeout).connect_timeout(timeout);
+    }
+
+    channel
+        .connect()
+        .await
+        .map_err(|err| format!("Unable to connect to {}: {}", endpoint, err))
+}
+
+async fn pool(endpoint: &str, settings: &Settings) -> Result<DescriptorPool, String> {
+    match &settings.grpc_proto {
+        Some(path) => {
+            let include = Path::new(path).parent().unwrap_or(Path::new("."));
+            let files = protox::compile([path], [include])
+                .map_err(|err| format!("Unable to read {}: {}", path, err))?;
+            DescriptorPool::from_file_descriptor_set(files).map_err(|err| err.to_string())
+        }
+        None => reflected_pool(connect(endpoint, settings).await?).await,
+    }
+}
+
+// Asks the server for the files its services are defined in, and the files those import.
+async fn reflected_pool(channel: Channel) -> Result<DescriptorPool, String> {
+    let mut client = ServerReflectionClient::new(channel);
+    let MessageResponse::ListServicesResponse(services) =
+        reflect(&mut client, MessageRequest::ListServices(String::new())).await?
+    else {
+        return Err("The server's reflection service didn't list its services".to_string());
+    };
+
+    let mut files: Vec<FileDescriptorProto> = vec![];
+    let mut wanted: Vec<MessageRequest> = services
+        .service
+        .into_iter()
+        .filter(|service| !service.name.starts_with("grpc.reflection."))
+        .map(|service| MessageRequest::FileContainingSymbol(service.name))
+        .collect();
+    while let Some(request) = wanted.pop() {
+        let MessageResponse::FileDescriptorResponse(response) =
+            reflect(&mut client, request).await?
+        else {
+            return Err("The server's reflection service didn't send a file".to_string());
+        };
+        for bytes in response.file_descriptor_proto {
+            let file = FileDescriptorProto::decode(bytes.as_slice())
+                .map_err(|err| format!("The server sent an invalid file: {}", err))?;
+            if files.iter().any(|existing| existing.name == file.name) {
+                continue;
+            }
+            for dependency in &file.dependency {
+                if !files
+                    .iter()
+                    .any(|existing| existing.name.as_ref() == Some(dependency))
+                {
+                    wanted.push(MessageRequest::FileByFilename(dependency.clone()));
+                }
+            }
+            files.push(file);
+        }
+    }
+
+    let mut pool = DescriptorPool::new();
+    pool.add_file_descriptor_protos(files)
+        .map_err(|err| err.to_string())?;
+    Ok(pool)
+}
+
+async fn reflect(
+    client: &mut ServerReflectionClient<Channel>,
+    request: MessageRequest,
+) -> Result<MessageResponse, String> {
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(request),
+    };
+    let mut responses = client
+        .server_reflection_info(tokio_stream::iter([request]))
+        .await
+        .map_err(|status| match status.code() {
+            Code::Unimplemented => {
+                "The server has no reflection service. Set grpc-proto to its .proto file."
+                    .to_string()
+            }
+            _ => status_error(status),
+        })?
+        .into_inner();
+
+    match responses
+        .message()
+        .await
+        .map_err(status_error)?
+        .and_then(|response| response.message_response)
+    {
+        Some(MessageResponse::ErrorResponse(err)) => {
+            Err(format!("Reflection failed: {}", err.error_message))
+        }
+        Some(response) => Ok(response),
+        None => Err("The server's reflection service sent nothing back".to_string()),
+    }
+}
+
+fn method(pool: &DescriptorPool, path: &str) -> Result<MethodDescriptor, String> {
+    let (service, method) = path.split_once('/').ok_or(format!(
+        "The URL should end in /package.Service/Method, not /{}",
+        path
+    ))?;
+    pool.get_service_by_name(service)
+        .ok_or(format!("Unknown service: {}", service))?
+        .methods()
+        .find(|existing| existing.name() == method)
+        .ok_or(format!("Unknown method: {}", path))
+}
+
+// Body rows are text, so values for fields that aren't strings, like numbers, lists and nested
+// messages, are read as JSON.
+fn message(descriptor: &MessageDescriptor, request: &Request) -> Result<DynamicMessage, String> {
+    let mut object = serde_json::Map::new();
+    for (key, value) in request.non_empty_body() {
+        let field = descriptor
+            .get_field_by_json_name(key)
+            .or_else(|| descriptor.get_field_by_name(key))
+            .ok_or(format!("{} has no field {}", descriptor.full_name(), key))?;
+        let text = !field.is_list()
+            && !field.is_map()
+            && matches!(field.kind(), Kind::String | Kind::Bytes | Kind::Enum(_));
+        let value = if text {
+            serde_json::Value::String(value.clone())
+        } else {
+            serde_json::from_str(value)
+                .map_err(|err| format!("Invalid value for {}: {}", key, err))?
+        };
+        object.insert(key.clone(), value);
+    }
+
+    DynamicMessage::deserialize(descriptor.clone(), serde_json::Value::Object(object))
+        .map_err(|err| format!("Invalid {}: {}", descriptor.full_name(), err))
+}
+
+// Headers and auth are sent as metadata.
+fn metadata(request: &Request) -> Vec<(String, String)> {
+    let mut metadata: Vec<(String, String)> = request.non_empty_headers().cloned().collect();
+    if let Some(value) = request.auth.header_value() {
+        metadata.push(("authorization".to_string(), value));
+    }
+    metadata
+}
+
+fn status_error(status: Status) -> String {
+    if status.message().is_empty() {
+        format!("{:?}", status.code())
+    } else {
+        format!("{:?}: {}", status.code(), status.message())
+    }
+}
+
+// Messages are encoded and decoded with their descriptors, so the call itself only moves bytes.
+struct BytesCodec;
+
+impl Codec for BytesCodec {
+    type Encode = Vec<u8>;
+    type Decode = Vec<u8>;
+    type Encoder = BytesCodec;
+    type Decoder = BytesCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        BytesCodec
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        BytesCodec
+    }
+}
+
+impl Encoder for BytesCodec {
+    type Item = Vec<u8>;
+    type Error = Status;
+
+    fn encode(&mut self, item: Vec<u8>, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        dst.put_slice(&item);
+        Ok(())
+    }
+}
+
+impl Decoder for BytesCodec {
+    type Item = Vec<u8>;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Vec<u8>>, Status> {
+        Ok(Some(src.copy_to_bytes(src.remaining()).to_vec()))
+    }
+}
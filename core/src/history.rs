@@ -0,0 +1,414 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use json::JsonValue;
+use reqwest::{blocking::Request, header::HeaderMap, StatusCode, Version};
+
+pub const MAX_ENTRIES: usize = 100;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Entry {
+    pub started: String,
+    pub time: f64,
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: String,
+    pub status: u16,
+    pub status_text: String,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: String,
+}
+
+impl Entry {
+    pub fn from_request(request: &Request) -> Self {
+        Entry {
+            started: timestamp(SystemTime::now()),
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            request_headers: header_pairs(request.headers()),
+            request_body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map_or(String::default(), |bytes| {
+                    String::from_utf8_lossy(bytes).to_string()
+                }),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_response(
+        &mut self,
+        version: Version,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &str,
+        elapsed: Duration,
+    ) {
+        self.time = elapsed.as_secs_f64() * 1000.0;
+        self.http_version = format!("{:?}", version);
+        self.status = status.as_u16();
+        self.status_text = status.canonical_reason().unwrap_or_default().to_string();
+        self.response_headers = header_pairs(headers);
+        self.response_body = body.to_string();
+    }
+
+    pub fn summary(&self) -> String {
+        let status = if self.status == 0 {
+            "---".to_string()
+        } else {
+            self.status.to_string()
+        };
+        format!("{} {} {}", status, self.method, self.url)
+    }
+
+    // Builds the request in the same plain-text format that request files use, leaving out
+    // headers the client sets on its own.
+    pub fn request_text(&self) -> String {
+        let mut text = format!("{} {}", self.method, self.url);
+        for (name, value) in &self.request_headers {
+            if name.starts_with(':')
+                || value.is_empty()
+                || ["host", "content-length", "connection"].contains(&name.to_lowercase().as_str())
+            {
+                continue;
+            }
+            text.push_str(&format!("\n{}: {}", name, value));
+        }
+        if !self.request_body.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&self.request_body);
+        }
+        text
+    }
+
+    pub(crate) fn to_har(&self) -> JsonValue {
+        let mut entry = JsonValue::new_object();
+        entry["startedDateTime"] = self.started.clone().into();
+        entry["time"] = self.time.into();
+
+        let mut request = JsonValue::new_object();
+        request["method"] = self.method.clone().into();
+        request["url"] = self.url.clone().into();
+        request["httpVersion"] = self.http_version.clone().into();
+        request["cookies"] = JsonValue::new_array();
+        request["headers"] = har_pairs(&self.request_headers);
+        request["queryString"] = har_pairs(
+            &reqwest::Url::parse(&self.url)
+                .map(|url| {
+                    url.query_pairs()
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        );
+        if !self.request_body.is_empty() {
+            request["postData"]["mimeType"] = content_type(&self.request_headers).into();
+            request["postData"]["text"] = self.request_body.clone().into();
+        }
+        request["headersSize"] = (-1).into();
+        request["bodySize"] = self.request_body.len().into();
+        entry["request"] = request;
+
+        let mut response = JsonValue::new_object();
+        response["status"] = self.status.into();
+        response["statusText"] = self.status_text.clone().into();
+        response["httpVersion"] = self.http_version.clone().into();
+        response["cookies"] = JsonValue::new_array();
+        response["headers"] = har_pairs(&self.response_headers);
+        response["content"]["size"] = self.response_body.len().into();
+        response["content"]["mimeType"] = content_type(&self.response_headers).into();
+        response["content"]["text"] = self.response_body.clone().into();
+        response["redirectURL"] = "".into();
+        response["headersSize"] = (-1).into();
+        response["bodySize"] = self.response_body.len().into();
+        entry["response"] = response;
+
+        entry["cache"] = JsonValue::new_object();
+        entry["timings"]["send"] = 0.into();
+        entry["timings"]["wait"] = self.time.into();
+        entry["timings"]["receive"] = 0.into();
+
+        entry
+    }
+
+    pub(crate) fn from_har(entry: &JsonValue) -> Self {
+        let request = &entry["request"];
+        let response = &entry["response"];
+
+        Entry {
+            started: entry["startedDateTime"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            time: entry["time"].as_f64().unwrap_or_default(),
+            method: request["method"].as_str().unwrap_or("GET").to_string(),
+            url: request["url"].as_str().unwrap_or_default().to_string(),
+            http_version: request["httpVersion"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            request_headers: pairs_from_har(&request["headers"]),
+            request_body: request["postData"]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            status: response["status"].as_u16().unwrap_or_default(),
+            status_text: response["statusText"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            response_headers: pairs_from_har(&response["headers"]),
+            response_body: response["content"]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+}
+
+pub fn filename(request_filename: &str) -> String {
+    format!("{}.history.har", request_filename)
+}
+
+pub fn load(filename: &str) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let har = json::parse(&fs::read_to_string(filename)?)?;
+    if !har["log"]["entries"].is_array() {
+        return Err(format!("{} is not a HAR file", filename).into());
+    }
+
+    Ok(har["log"]["entries"]
+        .members()
+        .map(Entry::from_har)
+        .collect())
+}
+
+pub fn save(filename: &str, entries: &[Entry]) -> io::Result<()> {
+    let mut har = JsonValue::new_object();
+    har["log"]["version"] = "1.2".into();
+    har["log"]["creator"]["name"] = env!("CARGO_PKG_NAME").into();
+    har["log"]["creator"]["version"] = env!("CARGO_PKG_VERSION").into();
+    har["log"]["entries"] = JsonValue::Array(entries.iter().map(Entry::to_har).collect());
+
+    let mut file = File::create(filename)?;
+    file.write_all(json::stringify_pretty(har, 2).as_bytes())
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+fn content_type(headers: &[(String, String)]) -> String {
+    headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map_or(String::default(), |(_, value)| value.clone())
+}
+
+fn har_pairs(pairs: &[(String, String)]) -> JsonValue {
+    JsonValue::Array(
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                let mut pair = JsonValue::new_object();
+                pair["name"] = name.clone().into();
+                pair["value"] = value.clone().into();
+                pair
+            })
+            .collect(),
+    )
+}
+
+fn pairs_from_har(pairs: &JsonValue) -> Vec<(String, String)> {
+    pairs
+        .members()
+        .map(|pair| {
+            (
+                pair["name"].as_str().unwrap_or_default().to_string(),
+                pair["value"].as_str().unwrap_or_default().to_string(),
+            )
+        })
+        .collect()
+}
+
+// Formats a time as an ISO 8601 UTC timestamp, which is what HAR expects.
+pub(crate) fn timestamp(time: SystemTime) -> String {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = duration.as_secs();
+
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        duration.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn entry() -> Entry {
+        Entry {
+            started: "2024-02-29T12:34:56.789Z".to_string(),
+            time: 123.5,
+            method: "POST".to_string(),
+            url: "https://example.com/users?page=2".to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            request_headers: pairs(&[
+                ("host", "example.com"),
+                ("content-type", "application/json"),
+                ("content-length", "14"),
+                ("x-trace", "1"),
+            ]),
+            request_body: r#"{"name":"Ada"}"#.to_string(),
+            status: 201,
+            status_text: "Created".to_string(),
+            response_headers: pairs(&[("Content-Type", "application/json")]),
+            response_body: r#"{"id":7}"#.to_string(),
+        }
+    }
+
+    #[test]
+    fn har_round_trip() {
+        let har = entry().to_har();
+        assert_eq!(har["request"]["postData"]["mimeType"], "application/json");
+        assert_eq!(
+            har["request"]["queryString"].dump(),
+            r#"[{"name":"page","value":"2"}]"#
+        );
+        assert_eq!(har["response"]["content"]["size"], 8);
+        assert_eq!(Entry::from_har(&har), entry());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let filename = env::temp_dir()
+            .join(format!("reqcoon-history-{}.har", process::id()))
+            .display()
+            .to_string();
+        let entries = vec![entry(), Entry::default()];
+        save(&filename, &entries).unwrap();
+        let loaded = load(&filename);
+        fs::remove_file(&filename).unwrap();
+
+        assert_eq!(loaded.unwrap(), entries);
+    }
+
+    #[test]
+    fn load_needs_a_har_file() {
+        let filename = env::temp_dir()
+            .join(format!("reqcoon-not-har-{}.har", process::id()))
+            .display()
+            .to_string();
+        fs::write(&filename, r#"{"entries": []}"#).unwrap();
+        let loaded = load(&filename);
+        fs::remove_file(&filename).unwrap();
+
+        assert_eq!(
+            loaded.unwrap_err().to_string(),
+            format!("{} is not a HAR file", filename)
+        );
+    }
+
+    // HAR files from browsers leave out what they don't have.
+    #[test]
+    fn from_har_fills_in_missing_fields() {
+        let har = json::parse(
+            r#"{"request": {"url": "https://example.com/"}, "response": {"status": 204}}"#,
+        )
+        .unwrap();
+        let entry = Entry::from_har(&har);
+        assert_eq!(entry.method, "GET");
+        assert_eq!(entry.url, "https://example.com/");
+        assert_eq!(entry.status, 204);
+        assert!(entry.request_headers.is_empty());
+        assert!(entry.response_body.is_empty());
+    }
+
+    #[test]
+    fn request_text_leaves_out_client_headers() {
+        let mut entry = entry();
+        entry
+            .request_headers
+            .push((":authority".to_string(), "example.com".to_string()));
+        entry
+            .request_headers
+            .push(("x-empty".to_string(), String::new()));
+        assert_eq!(
+            entry.request_text(),
+            "POST https://example.com/users?page=2\ncontent-type: application/json\nx-trace: 1\n\n{\"name\":\"Ada\"}"
+        );
+    }
+
+    #[test]
+    fn summaries() {
+        assert_eq!(
+            entry().summary(),
+            "201 POST https://example.com/users?page=2"
+        );
+        let unanswered = Entry {
+            status: 0,
+            ..entry()
+        };
+        assert_eq!(
+            unanswered.summary(),
+            "--- POST https://example.com/users?page=2"
+        );
+    }
+
+    #[test]
+    fn timestamps_are_utc() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            timestamp(UNIX_EPOCH + Duration::from_millis(1_709_210_096_789)),
+            "2024-02-29T12:34:56.789Z"
+        );
+        assert_eq!(
+            timestamp(UNIX_EPOCH + Duration::from_secs(978_307_199)),
+            "2000-12-31T23:59:59.000Z"
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use json::JsonValue;
+
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+}
+
+// Supports the subset of JSONPath that picks values out of a response: $, .key, ['key'], [0],
+// [-1] and the * wildcard.
+pub fn select<'a>(value: &'a JsonValue, path: &str) -> Result<Vec<&'a JsonValue>, String> {
+    let mut selected = vec![value];
+    for segment in parse(path)? {
+        selected = selected
+            .into_iter()
+            .flat_map(|value| children(value, &segment))
+            .collect();
+    }
+    Ok(selected)
+}
+
+// Takes the values a path selects out of the JSON they're in, like $.items[*].id from every item.
+pub fn remove(value: &mut JsonValue, path: &str) -> Result<(), String> {
+    if let Some((last, parents)) = parse(path)?.split_last() {
+        remove_from(value, parents, last);
+    }
+    Ok(())
+}
+
+fn remove_from(value: &mut JsonValue, parents: &[Segment], last: &Segment) {
+    if let Some((segment, rest)) = parents.split_first() {
+        for child in children_mut(value, segment) {
+            remove_from(child, rest, last);
+        }
+        return;
+    }
+
+    match last {
+        Segment::Key(key) => {
+            value.remove(key);
+        }
+        Segment::Index(index) => {
+            if let Some(index) = position(value, *index) {
+                value.array_remove(index);
+            }
+        }
+        Segment::Wildcard => value.clear(),
+    }
+}
+
+fn children<'a>(value: &'a JsonValue, segment: &Segment) -> Vec<&'a JsonValue> {
+    match segment {
+        Segment::Key(key) if value.has_key(key) => vec![&value[key.as_str()]],
+        Segment::Index(index) => match position(value, *index) {
+            Some(index) => vec![&value[index]],
+            None => vec![],
+        },
+        Segment::Wildcard if value.is_array() => value.members().collect(),
+        Segment::Wildcard => value.entries().map(|(_, value)| value).collect(),
+        _ => vec![],
+    }
+}
+
+fn children_mut<'a>(value: &'a mut JsonValue, segment: &Segment) -> Vec<&'a mut JsonValue> {
+    match segment {
+        Segment::Key(key) if value.has_key(key) => vec![&mut value[key.as_str()]],
+        Segment::Index(index) => match position(value, *index) {
+            Some(index) => vec![&mut value[index]],
+            None => vec![],
+        },
+        Segment::Wildcard if value.is_array() => value.members_mut().collect(),
+        Segment::Wildcard => value.entries_mut().map(|(_, value)| value).collect(),
+        _ => vec![],
+    }
+}
+
+// Negative indexes count back from the end of an array.
+fn position(value: &JsonValue, index: i64) -> Option<usize> {
+    if !value.is_array() {
+        return None;
+    }
+    let index = if index < 0 {
+        value.len() as i64 + index
+    } else {
+        index
+    };
+    (index >= 0 && (index as usize) < value.len()).then_some(index as usize)
+}
+
+fn parse(path: &str) -> Result<Vec<Segment>, String> {
+    let invalid = || format!("Invalid JSONPath: {}", path);
+    let mut rest = path.trim().strip_prefix('$').ok_or_else(invalid)?;
+    let mut segments = vec![];
+
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            let key = &after_dot[..end];
+            segments.push(match key {
+                "" => return Err(invalid()),
+                "*" => Segment::Wildcard,
+                _ => Segment::Key(key.to_string()),
+            });
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket.find(']').ok_or_else(invalid)?;
+            let inner = after_bracket[..end].trim();
+            segments.push(if inner == "*" {
+                Segment::Wildcard
+            } else if let Some(key) = quoted(inner) {
+                Segment::Key(key.to_string())
+            } else {
+                Segment::Index(inner.parse().map_err(|_| invalid())?)
+            });
+            rest = &after_bracket[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    Ok(segments)
+}
+
+fn quoted(text: &str) -> Option<&str> {
+    text.strip_prefix('\'')
+        .and_then(|text| text.strip_suffix('\''))
+        .or_else(|| text.strip_prefix('"')?.strip_suffix('"'))
+}
+
+enum Container {
+    // The key of the member being read, once it has been.
+    Object(Option<String>),
+    Array(usize),
+}
+
+// The path of the value at a character offset into JSON text, like $.items[3].id, so it can be
+// pasted into a capture or an assertion. An offset on a key gives the path of its value.
+pub fn path_at(text: &str, offset: usize) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut stack = vec![];
+    let mut path = None;
+    // Whether the next string in an object is a key rather than a value.
+    let mut expecting_key = false;
+
+    let mut index = 0;
+    while index < chars.len() && index <= offset {
+        match chars[index] {
+            '{' | '[' => {
+                path = Some(render(&stack));
+                expecting_key = chars[index] == '{';
+                stack.push(if expecting_key {
+                    Container::Object(None)
+                } else {
+                    Container::Array(0)
+                });
+                index += 1;
+            }
+            '}' | ']' => {
+                stack.pop();
+                expecting_key = false;
+                path = Some(render(&stack));
+                index += 1;
+            }
+            ',' => {
+                match stack.last_mut() {
+                    Some(Container::Object(key)) => {
+                        *key = None;
+                        expecting_key = true;
+                    }
+                    Some(Container::Array(array_index)) => *array_index += 1,
+                    None => (),
+                }
+                index += 1;
+            }
+            ':' => {
+                expecting_key = false;
+                index += 1;
+            }
+            '"' => {
+                let end = string_end(&chars, index);
+                if expecting_key {
+                    if let Some(Container::Object(key)) = stack.last_mut() {
+                        let text: String = chars[index..end].iter().collect();
+                        *key = Some(
+                            json::parse(&text)
+                                .ok()
+                                .and_then(|key| key.as_str().map(str::to_string))
+                                .unwrap_or_else(|| text.trim_matches('"').to_string()),
+                        );
+                    }
+                }
+                path = Some(render(&stack));
+                index = end;
+            }
+            character if character.is_whitespace() => index += 1,
+            _ => {
+                path = Some(render(&stack));
+                while index < chars.len()
+                    && !matches!(chars[index], ',' | '}' | ']')
+                    && !chars[index].is_whitespace()
+                {
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    path
+}
+
+// The index just past the closing quote of the string starting at start.
+fn string_end(chars: &[char], start: usize) -> usize {
+    let mut index = start + 1;
+    while index < chars.len() {
+        match chars[index] {
+            '\\' => index += 2,
+            '"' => return index + 1,
+            _ => index += 1,
+        }
+    }
+    chars.len()
+}
+
+fn render(stack: &[Container]) -> String {
+    let mut path = "$".to_string();
+    for container in stack {
+        match container {
+            Container::Object(Some(key)) if is_identifier(key) => {
+                path.push('.');
+                path.push_str(key);
+            }
+            Container::Object(Some(key)) if key.contains('\'') => {
+                path.push_str(&format!("[\"{}\"]", key))
+            }
+            Container::Object(Some(key)) => path.push_str(&format!("['{}']", key)),
+            Container::Object(None) => (),
+            Container::Array(index) => path.push_str(&format!("[{}]", index)),
+        }
+    }
+    path
+}
+
+fn is_identifier(key: &str) -> bool {
+    key.chars()
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && key
+            .chars()
+            .all(|character| character.is_alphanumeric() || character == '_' || character == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USERS: &str =
+        r#"{"items": [{"id": 1, "name": "Ada"}, {"id": 2}], "a.b": 3, "it's": 4, "total": 2}"#;
+
+    fn selected(path: &str) -> Result<Vec<String>, String> {
+        let value = json::parse(USERS).unwrap();
+        select(&value, path).map(|values| values.iter().map(|value| value.dump()).collect())
+    }
+
+    fn removed(path: &str) -> Result<String, String> {
+        let mut value = json::parse(USERS).unwrap();
+        remove(&mut value, path).map(|()| value.dump())
+    }
+
+    #[test]
+    fn select_keys_and_indexes() {
+        assert_eq!(selected("$"), Ok(vec![json::parse(USERS).unwrap().dump()]));
+        assert_eq!(selected("$.total"), Ok(vec!["2".to_string()]));
+        assert_eq!(
+            selected("$.items[0].name"),
+            Ok(vec![r#""Ada""#.to_string()])
+        );
+        assert_eq!(selected("$.items[-1].id"), Ok(vec!["2".to_string()]));
+        assert_eq!(
+            selected(" $.items[ 1 ] "),
+            Ok(vec![r#"{"id":2}"#.to_string()])
+        );
+    }
+
+    #[test]
+    fn select_bracketed_keys() {
+        assert_eq!(selected("$['a.b']"), Ok(vec!["3".to_string()]));
+        assert_eq!(selected(r#"$["a.b"]"#), Ok(vec!["3".to_string()]));
+        assert_eq!(selected(r#"$["it's"]"#), Ok(vec!["4".to_string()]));
+        assert_eq!(selected("$['items'][0]['id']"), Ok(vec!["1".to_string()]));
+    }
+
+    #[test]
+    fn select_wildcards() {
+        assert_eq!(
+            selected("$.items[*].id"),
+            Ok(vec!["1".to_string(), "2".to_string()])
+        );
+        assert_eq!(
+            selected("$.items.*.id"),
+            Ok(vec!["1".to_string(), "2".to_string()])
+        );
+        assert_eq!(
+            selected("$.items[0].*"),
+            Ok(vec!["1".to_string(), r#""Ada""#.to_string()])
+        );
+    }
+
+    #[test]
+    fn select_nothing_for_paths_that_miss() {
+        assert_eq!(selected("$.missing"), Ok(vec![]));
+        assert_eq!(selected("$.items[2]"), Ok(vec![]));
+        assert_eq!(selected("$.items[-3]"), Ok(vec![]));
+        assert_eq!(selected("$.total[0]"), Ok(vec![]));
+        assert_eq!(selected("$.items.id"), Ok(vec![]));
+    }
+
+    #[test]
+    fn invalid_paths_are_errors() {
+        for path in [
+            "",
+            "items",
+            "$.",
+            "$..id",
+            "$.items[0",
+            "$.items[one]",
+            "$ items",
+        ] {
+            assert_eq!(
+                selected(path),
+                Err(format!("Invalid JSONPath: {}", path)),
+                "{}",
+                path
+            );
+        }
+        assert_eq!(
+            removed("$.items["),
+            Err("Invalid JSONPath: $.items[".to_string())
+        );
+    }
+
+    #[test]
+    fn remove_takes_out_what_the_path_selects() {
+        assert_eq!(
+            removed("$.items[*].id"),
+            Ok(r#"{"items":[{"name":"Ada"},{}],"a.b":3,"it's":4,"total":2}"#.to_string())
+        );
+        assert_eq!(
+            removed("$.items[-1]"),
+            Ok(r#"{"items":[{"id":1,"name":"Ada"}],"a.b":3,"it's":4,"total":2}"#.to_string())
+        );
+        assert_eq!(
+            removed("$['a.b']"),
+            Ok(r#"{"items":[{"id":1,"name":"Ada"},{"id":2}],"it's":4,"total":2}"#.to_string())
+        );
+        assert_eq!(
+            removed("$.items[*]"),
+            Ok(r#"{"items":[],"a.b":3,"it's":4,"total":2}"#.to_string())
+        );
+        assert_eq!(
+            removed("$.missing.id"),
+            Ok(json::parse(USERS).unwrap().dump())
+        );
+    }
+
+    #[test]
+    fn path_at_an_offset() {
+        let text = r#"{"items": [{"id": 1}, {"id": 2}], "a b": true, "it's": null}"#;
+        let at = |needle: &str, nth: usize| {
+            let offset = text.match_indices(needle).nth(nth).unwrap().0;
+            path_at(text, offset)
+        };
+        assert_eq!(path_at(text, 0), Some("$".to_string()));
+        assert_eq!(at("[", 0), Some("$.items".to_string()));
+        assert_eq!(at("1", 0), Some("$.items[0].id".to_string()));
+        assert_eq!(at(r#""id""#, 1), Some("$.items[1].id".to_string()));
+        assert_eq!(at("2", 0), Some("$.items[1].id".to_string()));
+        assert_eq!(at("true", 0), Some("$['a b']".to_string()));
+        assert_eq!(at("null", 0), Some(r#"$["it's"]"#.to_string()));
+        assert_eq!(path_at("", 0), None);
+    }
+}
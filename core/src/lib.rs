@@ -0,0 +1,31 @@
+pub mod assertion;
+pub mod audit;
+pub mod cache;
+pub mod cookies;
+pub mod curl;
+pub mod diff;
+pub mod examples;
+pub mod file;
+pub mod folder;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod history;
+pub mod json_path;
+pub mod load;
+pub mod markup;
+pub mod mock;
+pub mod oauth;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+pub mod query;
+pub mod request;
+pub mod response;
+pub mod runner;
+pub mod save_file;
+pub mod schema;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod secrets;
+pub mod settings;
+pub mod snapshot;
+pub mod variables;
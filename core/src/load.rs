@@ -0,0 +1,153 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use reqwest::blocking::RequestBuilder;
+
+// What sending a request many times found, for a quick check without reaching for hey or wrk.
+pub struct Report {
+    pub requests: usize,
+    pub concurrency: usize,
+    pub elapsed: Duration,
+    // How long each request that got a response took, fastest first.
+    pub latencies: Vec<Duration>,
+    // How many responses came back with each status code.
+    pub statuses: Vec<(u16, usize)>,
+    // How many requests failed without a response, by why.
+    pub errors: Vec<(String, usize)>,
+}
+
+// Sends the request the given number of times, with that many in flight at once.
+pub fn run(
+    request_builder: &RequestBuilder,
+    requests: usize,
+    concurrency: usize,
+) -> Result<Report, String> {
+    let queue = (0..requests)
+        .map(|_| {
+            request_builder
+                .try_clone()
+                .ok_or("The request's body can only be sent once".to_string())
+        })
+        .collect::<Result<Vec<RequestBuilder>, String>>()?;
+    let queue = Arc::new(Mutex::new(queue));
+    let results = Arc::new(Mutex::new(vec![]));
+
+    let started = Instant::now();
+    let workers: Vec<_> = (0..concurrency.clamp(1, requests.max(1)))
+        .map(|_| {
+            let queue = queue.clone();
+            let results = results.clone();
+            thread::spawn(move || loop {
+                let Some(request_builder) = queue.lock().unwrap().pop() else {
+                    break;
+                };
+                let sent = Instant::now();
+                let result = request_builder
+                    .send()
+                    .map(|response| response.status().as_u16())
+                    .map_err(|err| {
+                        if err.is_timeout() {
+                            "Timed out"
+                        } else if err.is_connect() {
+                            "Unable to connect"
+                        } else {
+                            "Request failed"
+                        }
+                    });
+                results.lock().unwrap().push((result, sent.elapsed()));
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker
+            .join()
+            .map_err(|_| "A load test worker stopped".to_string())?;
+    }
+    let elapsed = started.elapsed();
+
+    let mut report = Report {
+        requests,
+        concurrency,
+        elapsed,
+        latencies: vec![],
+        statuses: vec![],
+        errors: vec![],
+    };
+    for (result, latency) in results.lock().unwrap().iter() {
+        match result {
+            Ok(status) => {
+                report.latencies.push(*latency);
+                count(&mut report.statuses, *status);
+            }
+            Err(err) => count(&mut report.errors, err.to_string()),
+        }
+    }
+    report.latencies.sort();
+    report.statuses.sort();
+    Ok(report)
+}
+
+fn count<T: PartialEq>(counts: &mut Vec<(T, usize)>, value: T) {
+    match counts.iter_mut().find(|(existing, _)| *existing == value) {
+        Some((_, count)) => *count += 1,
+        None => counts.push((value, 1)),
+    }
+}
+
+impl Report {
+    // The latency that the given percent of responses came back within.
+    pub fn percentile(&self, percent: usize) -> Option<Duration> {
+        let rank = (self.latencies.len() * percent).div_ceil(100);
+        self.latencies.get(rank.saturating_sub(1)).copied()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Sent {} requests, {} at a time, in {:.2?}: {:.1} requests/s",
+            self.requests,
+            self.concurrency,
+            self.elapsed,
+            self.requests as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+        )?;
+
+        if !self.latencies.is_empty() {
+            writeln!(f, "\nLatency")?;
+            let rows = [
+                ("min", self.latencies.first().copied()),
+                ("p50", self.percentile(50)),
+                ("p90", self.percentile(90)),
+                ("p95", self.percentile(95)),
+                ("p99", self.percentile(99)),
+                ("max", self.latencies.last().copied()),
+            ];
+            for (label, latency) in rows {
+                if let Some(latency) = latency {
+                    writeln!(f, "  {:<4} {:.1?}", label, latency)?;
+                }
+            }
+        }
+
+        if !self.statuses.is_empty() {
+            writeln!(f, "\nStatus codes")?;
+            for (status, count) in &self.statuses {
+                writeln!(f, "  {:<4} {}", status, count)?;
+            }
+        }
+
+        if !self.errors.is_empty() {
+            writeln!(f, "\nErrors")?;
+            for (err, count) in &self.errors {
+                writeln!(f, "  {} {}", err, count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
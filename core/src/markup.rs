@@ -0,0 +1,294 @@
+// HTML elements that never have a closing tag.
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+// HTML elements whose content is kept exactly as it came, since whitespace matters in them or
+// they hold code rather than markup.
+const RAW_ELEMENTS: [&str; 4] = ["pre", "script", "style", "textarea"];
+
+enum Token<'a> {
+    Open(&'a str, String),
+    Close(&'a str, String),
+    // Self-closing and void elements, comments, CDATA sections, declarations and instructions.
+    Single(&'a str),
+    Text(&'a str),
+    Raw(&'a str),
+}
+
+// Whether a body of the content type, or one that starts like the text, is XML or HTML, and if so
+// whether it's HTML.
+pub fn kind(content_type: Option<&str>, text: &str) -> Option<bool> {
+    match content_type {
+        Some(content_type) => {
+            let mime_type = content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase();
+            if mime_type.ends_with("html") {
+                Some(true)
+            } else if mime_type.ends_with("xml") {
+                Some(false)
+            } else {
+                None
+            }
+        }
+        None => {
+            let start = text.trim_start().to_lowercase();
+            if start.starts_with("<!doctype html") || start.starts_with("<html") {
+                Some(true)
+            } else if start.starts_with('<') {
+                Some(false)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// Indents XML and HTML a tag per line, for bodies that come back as one long line. Elements that
+// only hold text stay on one line, like <name>Ada</name>.
+pub fn pretty(text: &str, html: bool) -> String {
+    let tokens = tokenize(text.trim(), html);
+    let mut lines: Vec<String> = vec![];
+    let mut depth = 0;
+    let mut index = 0;
+    while index < tokens.len() {
+        let indent = "  ".repeat(depth);
+        match &tokens[index] {
+            Token::Open(tag, name) => {
+                let inline = match (tokens.get(index + 1), tokens.get(index + 2)) {
+                    (Some(Token::Close(close, closed)), _) if closed == name => {
+                        Some((format!("{}{}", tag, close), 2))
+                    }
+                    (Some(Token::Text(text)), Some(Token::Close(close, closed)))
+                        if closed == name && !text.contains('\n') =>
+                    {
+                        Some((format!("{}{}{}", tag, text.trim(), close), 3))
+                    }
+                    (Some(Token::Raw(text)), Some(Token::Close(close, closed)))
+                        if closed == name && !text.contains('\n') =>
+                    {
+                        Some((format!("{}{}{}", tag, text, close), 3))
+                    }
+                    _ => None,
+                };
+                match inline {
+                    Some((line, consumed)) => {
+                        lines.push(format!("{}{}", indent, line));
+                        index += consumed;
+                        continue;
+                    }
+                    None => {
+                        lines.push(format!("{}{}", indent, tag));
+                        depth += 1;
+                    }
+                }
+            }
+            Token::Close(tag, _) => {
+                depth = depth.saturating_sub(1);
+                lines.push(format!("{}{}", "  ".repeat(depth), tag));
+            }
+            Token::Single(tag) => lines.push(format!("{}{}", indent, tag)),
+            Token::Text(text) => {
+                for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                    lines.push(format!("{}{}", indent, line));
+                }
+            }
+            Token::Raw(text) => lines.extend(text.trim_matches('\n').lines().map(str::to_string)),
+        }
+        index += 1;
+    }
+    lines.join("\n")
+}
+
+fn tokenize(text: &str, html: bool) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            tokens.push(Token::Text(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+
+        let Some(end) = tag_end(rest) else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        let tag = &rest[..end];
+        rest = &rest[end..];
+        if tag.starts_with("<!") || tag.starts_with("<?") || tag.ends_with("/>") {
+            tokens.push(Token::Single(tag));
+            continue;
+        }
+
+        let name = tag
+            .trim_start_matches(['<', '/'])
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or_default();
+        let name = if html {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        };
+        if tag.starts_with("</") {
+            tokens.push(Token::Close(tag, name));
+        } else if html && VOID_ELEMENTS.contains(&name.as_str()) {
+            tokens.push(Token::Single(tag));
+        } else if html && RAW_ELEMENTS.contains(&name.as_str()) {
+            let close = format!("</{}", name);
+            let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+            tokens.push(Token::Open(tag, name));
+            if !rest[..end].trim().is_empty() {
+                tokens.push(Token::Raw(&rest[..end]));
+            }
+            rest = &rest[end..];
+        } else {
+            tokens.push(Token::Open(tag, name));
+        }
+    }
+    tokens
+}
+
+// Where the tag at the start of the text ends, past quoted attribute values that contain >.
+fn tag_end(text: &str) -> Option<usize> {
+    for (start, end) in [("<!--", "-->"), ("<![CDATA[", "]]>")] {
+        if text.starts_with(start) {
+            return text.find(end).map(|index| index + end.len());
+        }
+    }
+
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(index + 1),
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> String {
+        lines.join("\n")
+    }
+
+    #[test]
+    fn kind_from_the_content_type() {
+        assert_eq!(kind(Some("text/html; charset=utf-8"), ""), Some(true));
+        assert_eq!(kind(Some("application/xml"), ""), Some(false));
+        assert_eq!(kind(Some("application/atom+xml"), ""), Some(false));
+        assert_eq!(kind(Some("application/json"), "<a/>"), None);
+    }
+
+    #[test]
+    fn kind_from_the_text() {
+        assert_eq!(kind(None, "<!DOCTYPE html><html></html>"), Some(true));
+        assert_eq!(kind(None, "  <html lang=\"en\">"), Some(true));
+        assert_eq!(kind(None, "<?xml version=\"1.0\"?><a/>"), Some(false));
+        assert_eq!(kind(None, "{\"a\": 1}"), None);
+    }
+
+    #[test]
+    fn xml_is_indented_a_tag_per_line() {
+        assert_eq!(
+            pretty(
+                "<users><user id=\"1\"><name>Ada</name><tags/></user><empty></empty></users>",
+                false
+            ),
+            lines(&[
+                "<users>",
+                "  <user id=\"1\">",
+                "    <name>Ada</name>",
+                "    <tags/>",
+                "  </user>",
+                "  <empty></empty>",
+                "</users>",
+            ])
+        );
+    }
+
+    #[test]
+    fn declarations_comments_and_quoted_attributes() {
+        assert_eq!(
+            pretty(
+                "<?xml version=\"1.0\"?><!-- a > b --><a title=\"x > y\"><![CDATA[<b>]]></a>",
+                false
+            ),
+            lines(&[
+                "<?xml version=\"1.0\"?>",
+                "<!-- a > b -->",
+                "<a title=\"x > y\">",
+                "  <![CDATA[<b>]]>",
+                "</a>",
+            ])
+        );
+    }
+
+    #[test]
+    fn text_over_several_lines_is_indented() {
+        assert_eq!(
+            pretty("<a>\n  one\n  two\n</a>", false),
+            lines(&["<a>", "  one", "  two", "</a>"])
+        );
+    }
+
+    #[test]
+    fn only_html_names_ignore_case() {
+        assert_eq!(pretty("<A>x</a>", false), lines(&["<A>", "  x", "</a>"]));
+        assert_eq!(pretty("<A>x</a>", true), "<A>x</a>");
+    }
+
+    #[test]
+    fn html_void_elements_have_no_closing_tag() {
+        assert_eq!(
+            pretty("<p>Hi<br>there<img src=\"a.png\"></p>", true),
+            lines(&[
+                "<p>",
+                "  Hi",
+                "  <br>",
+                "  there",
+                "  <img src=\"a.png\">",
+                "</p>"
+            ])
+        );
+    }
+
+    #[test]
+    fn html_raw_elements_are_kept_as_they_came() {
+        assert_eq!(
+            pretty(
+                "<div><pre>  keep\n  this</pre><script>if (a < b) {}</script></div>",
+                true
+            ),
+            lines(&[
+                "<div>",
+                "  <pre>",
+                "  keep",
+                "  this",
+                "  </pre>",
+                "  <script>if (a < b) {}</script>",
+                "</div>",
+            ])
+        );
+    }
+
+    #[test]
+    fn unterminated_tags_are_kept_as_text() {
+        assert_eq!(
+            pretty("<a>text<b", false),
+            lines(&["<a>", "  text", "  <b"])
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::history::{self, Entry};
+
+// How long the listener waits between checks for a connection or for being stopped.
+const POLL_RATE: Duration = Duration::from_millis(50);
+
+// How long a client has to send its request before it's dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Bodies past this are cut off rather than read into memory.
+const MAX_BODY: usize = 10 * 1024 * 1024;
+
+// A canned response for requests to a path, like GET /users. Routes without a method answer every
+// method, and the path * answers every path.
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub method: Option<String>,
+    pub path: String,
+    pub status: u16,
+    pub body: String,
+}
+
+impl Route {
+    // Takes a route like "GET /users" or "/users" and a response like `201 {"id": 1}`. The status
+    // can be left off for a 200.
+    pub fn parse(route: &str, response: &str) -> Result<Route, String> {
+        let route = route.trim();
+        let (method, path) = match route.split_once(' ') {
+            Some((method, path)) => (Some(method.to_ascii_uppercase()), path.trim()),
+            None => (None, route),
+        };
+        if path != "*" && !path.starts_with('/') {
+            return Err(format!("Routes are a path like /users, not {}", route));
+        }
+
+        let response = response.trim();
+        let (status, body) = match response.split_once(char::is_whitespace) {
+            Some((status, body)) => (status, body.trim_start()),
+            None => (response, ""),
+        };
+        let (status, body) = match status.parse::<u16>() {
+            Ok(status) if (100..=599).contains(&status) => (status, body),
+            _ => (200, response),
+        };
+
+        Ok(Route {
+            method,
+            path: path.to_string(),
+            status,
+            body: body.to_string(),
+        })
+    }
+
+    fn matches(&self, method: &str, path: &str) -> bool {
+        self.method
+            .as_deref()
+            .map_or(true, |route_method| route_method == method)
+            && (self.path == "*" || self.path == path)
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self.body.trim_start().chars().next() {
+            Some('{' | '[') => "application/json",
+            Some('<') => "text/html; charset=utf-8",
+            _ => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+// A request that came in, and the status it was answered with.
+#[derive(Clone, Debug)]
+pub struct Received {
+    pub method: String,
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub status: u16,
+    pub time: SystemTime,
+}
+
+impl Received {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    pub fn summary(&self) -> String {
+        format!("{} {} → {}", self.method, self.target, self.status)
+    }
+
+    // The request as it came in, with a JSON body indented.
+    pub fn text(&self) -> String {
+        let mut text = format!("{} {} HTTP/1.1", self.method, self.target);
+        for (name, value) in &self.headers {
+            text.push_str(&format!("\n{}: {}", name, value));
+        }
+        if !self.body.is_empty() {
+            text.push_str("\n\n");
+            match json::parse(&self.body) {
+                Ok(body) => text.push_str(&body.pretty(2)),
+                Err(_) => text.push_str(&self.body),
+            }
+        }
+        text
+    }
+
+    // A history entry for the request, as if it had been sent to the server on the port.
+    pub fn to_entry(&self, port: u16) -> Entry {
+        Entry {
+            started: history::timestamp(self.time),
+            method: self.method.clone(),
+            url: format!("http://127.0.0.1:{}{}", port, self.target),
+            http_version: "HTTP/1.1".to_string(),
+            request_headers: self.headers.clone(),
+            request_body: self.body.clone(),
+            status: self.status,
+            status_text: reason(self.status).to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+// A listener answering requests on a port from its routes, on a thread of its own until it's
+// dropped. What it received arrives on the receiver.
+pub struct Server {
+    pub port: u16,
+    pub received: Receiver<Received>,
+    routes: Arc<Mutex<Vec<Route>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Server {
+    // Routes can be changed while the server is running, and apply from the next request.
+    pub fn set_routes(&self, routes: Vec<Route>) {
+        *self.routes.lock().unwrap() = routes;
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+// Listens on the port on localhost, or on any free port for 0. Requests no route matches get a
+// 404.
+pub fn start(port: u16, routes: Vec<Route>) -> io::Result<Server> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+    let port = listener.local_addr()?.port();
+
+    let (sender, receiver) = mpsc::channel();
+    let routes = Arc::new(Mutex::new(routes));
+    let stopped = Arc::new(AtomicBool::new(false));
+    let (serving_routes, serving_stopped) = (routes.clone(), stopped.clone());
+    thread::spawn(move || {
+        while !serving_stopped.load(Ordering::Relaxed) {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_RATE);
+                    continue;
+                }
+                Err(_) => break,
+            };
+            let routes = serving_routes.lock().unwrap().clone();
+            let Ok(received) = answer(stream, &routes) else {
+                continue;
+            };
+            if sender.send(received).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(Server {
+        port,
+        received: receiver,
+        routes,
+        stopped,
+    })
+}
+
+fn answer(mut stream: TcpStream, routes: &[Route]) -> io::Result<Received> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut received = read_request(&stream)?;
+
+    let route = routes
+        .iter()
+        .find(|route| route.matches(&received.method, received.path()));
+    let (status, content_type, body) = match route {
+        Some(route) => (route.status, route.content_type(), route.body.as_str()),
+        None => (404, "text/plain; charset=utf-8", "No mock route matches"),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        content_type,
+        body.len(),
+        body
+    )?;
+
+    received.status = status;
+    Ok(received)
+}
+
+// Answers every request with an empty 200, for seeing what webhooks send.
+pub fn catch(port: u16) -> io::Result<Server> {
+    let route = Route {
+        method: None,
+        path: "*".to_string(),
+        status: 200,
+        body: String::new(),
+    };
+    start(port, vec![route])
+}
+
+// Reads the request line, the headers and as much of the body as Content-Length says there is.
+pub fn read_request(stream: &TcpStream) -> io::Result<Received> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Not an HTTP request",
+        ));
+    };
+
+    let mut headers = vec![];
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0)
+        .min(MAX_BODY);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Received {
+        method: method.to_string(),
+        target: target.to_string(),
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+        status: 0,
+        time: SystemTime::now(),
+    })
+}
+
+fn reason(status: u16) -> &'static str {
+    reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default()
+}
@@ -0,0 +1,399 @@
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    net::TcpListener,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use json::JsonValue;
+use reqwest::{blocking::Client, header::ACCEPT, Url};
+use sha2::{Digest, Sha256};
+
+use crate::save_file;
+
+// Tokens are installed as a session variable, so requests use them as {{oauth_token}}.
+pub const TOKEN_VARIABLE: &str = "oauth_token";
+
+const DEFAULT_REDIRECT_URI: &str = "http://127.0.0.1:8976/callback";
+
+// How long before a token expires to refresh it.
+pub const REFRESH_AHEAD: Duration = Duration::from_secs(60);
+
+// How long to wait for the browser to come back with an authorization code.
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub auth_url: Option<String>,
+    pub device_url: Option<String>,
+    pub token_url: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<SystemTime>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: Option<String>,
+    pub interval: Duration,
+    pub expires_at: Instant,
+}
+
+// An authorization code request the user is finishing in their browser, which redirects back to
+// the listener with the code.
+pub struct CodeAuthorization {
+    pub url: String,
+    redirect_uri: String,
+    listener: TcpListener,
+    verifier: String,
+    state: String,
+}
+
+// Saved tokens by environment, with "" for the file's own settings.
+pub type Tokens = Vec<(String, Token)>;
+
+impl Config {
+    fn client_id(&self) -> Result<&str, String> {
+        required(&self.client_id, "oauth-client-id")
+    }
+
+    fn token_url(&self) -> Result<&str, String> {
+        required(&self.token_url, "oauth-token-url")
+    }
+
+    fn redirect_uri(&self) -> &str {
+        self.redirect_uri.as_deref().unwrap_or(DEFAULT_REDIRECT_URI)
+    }
+
+    // The client ID, and the secret for confidential clients.
+    fn client_form(&self) -> Result<Vec<(&str, &str)>, String> {
+        let mut form = vec![("client_id", self.client_id()?)];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+        Ok(form)
+    }
+}
+
+impl Token {
+    // How long the token has left, or None if the provider didn't say when it expires.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.expires_at.map(|expires_at| {
+            expires_at
+                .duration_since(SystemTime::now())
+                .unwrap_or_default()
+        })
+    }
+}
+
+// Starts the device authorization grant from RFC 8628. The user opens the verification URL and
+// enters the code somewhere else while reqcoon waits for the token.
+pub fn authorize_device(client: &Client, config: &Config) -> Result<DeviceAuthorization, String> {
+    let device_url = required(&config.device_url, "oauth-device-url")?;
+    let mut form = config.client_form()?;
+    if let Some(scope) = &config.scope {
+        form.push(("scope", scope.as_str()));
+    }
+
+    let started = Instant::now();
+    let body = send_form(client, device_url, &form)?;
+    if let Some(err) = error(&body) {
+        return Err(err);
+    }
+
+    Ok(DeviceAuthorization {
+        device_code: field(&body, "device_code")?,
+        user_code: field(&body, "user_code")?,
+        // Some providers still use the draft name verification_url.
+        verification_uri: field(&body, "verification_uri")
+            .or_else(|_| field(&body, "verification_url"))?,
+        verification_uri_complete: body["verification_uri_complete"]
+            .as_str()
+            .map(str::to_string),
+        interval: Duration::from_secs(body["interval"].as_u64().unwrap_or(5)),
+        expires_at: started + Duration::from_secs(body["expires_in"].as_u64().unwrap_or(600)),
+    })
+}
+
+// Polls the token endpoint at the interval the server asks for until the user approves or denies
+// the request, or the code expires. This blocks, so it's meant to run on its own thread.
+pub fn wait_for_device_token(
+    client: &Client,
+    config: &Config,
+    authorization: &DeviceAuthorization,
+) -> Result<Token, String> {
+    let token_url = config.token_url()?;
+    let mut form = config.client_form()?;
+    form.push(("grant_type", "urn:ietf:params:oauth:grant-type:device_code"));
+    form.push(("device_code", authorization.device_code.as_str()));
+
+    let mut interval = authorization.interval;
+    while Instant::now() + interval < authorization.expires_at {
+        thread::sleep(interval);
+        let body = send_form(client, token_url, &form)?;
+        match body["error"].as_str() {
+            None => return token(&body, None),
+            Some("authorization_pending") => (),
+            Some("slow_down") => interval += Duration::from_secs(5),
+            Some(_) => return Err(error(&body).unwrap_or_default()),
+        }
+    }
+
+    Err("The device code expired before it was approved".to_string())
+}
+
+// Starts the authorization code grant with PKCE from RFC 7636, listening on the redirect URI for
+// the browser to come back.
+pub fn authorize_code(config: &Config) -> Result<CodeAuthorization, String> {
+    let auth_url = required(&config.auth_url, "oauth-auth-url")?;
+    let redirect_uri = config.redirect_uri();
+    let redirect = Url::parse(redirect_uri)
+        .map_err(|err| format!("Invalid oauth-redirect-uri {}: {}", redirect_uri, err))?;
+    let port = redirect.port_or_known_default().unwrap_or(80);
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|err| format!("Unable to listen on port {}: {}", port, err))?;
+
+    let verifier = random_string();
+    let state = random_string();
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+    let mut url = Url::parse(auth_url)
+        .map_err(|err| format!("Invalid oauth-auth-url {}: {}", auth_url, err))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", config.client_id()?)
+        .append_pair("redirect_uri", redirect_uri)
+        .append_pair("code_challenge", &challenge)
+        .append_pair("code_challenge_method", "S256")
+        .append_pair("state", &state);
+    if let Some(scope) = &config.scope {
+        url.query_pairs_mut().append_pair("scope", scope);
+    }
+
+    Ok(CodeAuthorization {
+        url: url.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        listener,
+        verifier,
+        state,
+    })
+}
+
+// Waits for the browser to be redirected back with the code, then exchanges it for a token. This
+// blocks, so it's meant to run on its own thread.
+pub fn wait_for_code_token(
+    client: &Client,
+    config: &Config,
+    authorization: CodeAuthorization,
+) -> Result<Token, String> {
+    let code = receive_code(&authorization).map_err(|err| err.to_string())??;
+
+    let mut form = config.client_form()?;
+    form.push(("grant_type", "authorization_code"));
+    form.push(("code", code.as_str()));
+    form.push(("redirect_uri", authorization.redirect_uri.as_str()));
+    form.push(("code_verifier", authorization.verifier.as_str()));
+    let body = send_form(client, config.token_url()?, &form)?;
+    match error(&body) {
+        Some(err) => Err(err),
+        None => token(&body, None),
+    }
+}
+
+// Answers requests to the listener until one arrives at the redirect path with a code or an error.
+fn receive_code(authorization: &CodeAuthorization) -> io::Result<Result<String, String>> {
+    let redirect_path = Url::parse(&authorization.redirect_uri)
+        .map(|url| url.path().to_string())
+        .unwrap_or_default();
+    let started = Instant::now();
+    authorization.listener.set_nonblocking(true)?;
+
+    while started.elapsed() < CALLBACK_TIMEOUT {
+        let mut stream = match authorization.listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        stream.set_nonblocking(false)?;
+
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+        let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+        let Ok(url) = Url::parse(&format!("http://localhost{}", target)) else {
+            continue;
+        };
+        if url.path() != redirect_path {
+            write!(
+                stream,
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"
+            )?;
+            continue;
+        }
+
+        let query = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        let result = if let Some(err) = query("error") {
+            Err(match query("error_description") {
+                Some(description) => format!("{}: {}", err, description),
+                None => err,
+            })
+        } else if query("state").as_deref() != Some(authorization.state.as_str()) {
+            Err("The authorization response had the wrong state".to_string())
+        } else {
+            query("code").ok_or("The authorization response had no code".to_string())
+        };
+
+        let page = match &result {
+            Ok(_) => "Signed in. You can close this tab and go back to reqcoon.".to_string(),
+            Err(err) => format!("Unable to sign in: {}", err),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            page.len(),
+            page
+        )?;
+        return Ok(result);
+    }
+
+    Ok(Err("Timed out waiting for the browser".to_string()))
+}
+
+// Trades a refresh token for a new access token. Providers that don't rotate refresh tokens don't
+// send a new one, so the old one is kept.
+pub fn refresh(client: &Client, config: &Config, refresh_token: &str) -> Result<Token, String> {
+    let mut form = config.client_form()?;
+    form.push(("grant_type", "refresh_token"));
+    form.push(("refresh_token", refresh_token));
+    let body = send_form(client, config.token_url()?, &form)?;
+    match error(&body) {
+        Some(err) => Err(format!("Unable to refresh the token: {}", err)),
+        None => token(&body, Some(refresh_token)),
+    }
+}
+
+fn token(body: &JsonValue, refresh_token: Option<&str>) -> Result<Token, String> {
+    Ok(Token {
+        access_token: field(body, "access_token")?,
+        refresh_token: body["refresh_token"]
+            .as_str()
+            .or(refresh_token)
+            .map(str::to_string),
+        expires_at: body["expires_in"]
+            .as_u64()
+            .map(|secs| SystemTime::now() + Duration::from_secs(secs)),
+    })
+}
+
+// Token endpoints report errors in a JSON body, usually with a 400, so the status isn't checked.
+fn send_form(client: &Client, url: &str, form: &[(&str, &str)]) -> Result<JsonValue, String> {
+    let text = client
+        .post(url)
+        .header(ACCEPT, "application/json")
+        .form(form)
+        .send()
+        .and_then(|response| response.text())
+        .map_err(|err| format!("{}: {}", url, err))?;
+    json::parse(&text).map_err(|err| format!("Invalid response from {}: {}", url, err))
+}
+
+fn error(body: &JsonValue) -> Option<String> {
+    let error = body["error"].as_str()?;
+    Some(match body["error_description"].as_str() {
+        Some(description) => format!("{}: {}", error, description),
+        None => error.to_string(),
+    })
+}
+
+fn field(body: &JsonValue, name: &str) -> Result<String, String> {
+    body[name]
+        .as_str()
+        .map(str::to_string)
+        .ok_or(format!("Missing {} in the response", name))
+}
+
+fn required<'a>(value: &'a Option<String>, key: &str) -> Result<&'a str, String> {
+    value.as_deref().ok_or(format!("Set {} to use OAuth2", key))
+}
+
+fn random_string() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+pub fn filename(request_filename: &str) -> String {
+    format!("{}.tokens.json", request_filename)
+}
+
+pub fn load(filename: &str) -> Result<Tokens, Box<dyn Error>> {
+    let file = json::parse(&fs::read_to_string(filename)?)
+        .map_err(|err| format!("{} is not valid JSON: {}", filename, err))?;
+
+    let tokens = save_file::contents(&file, "tokens", filename)?
+        .entries()
+        .map(|(environment, token)| {
+            let access_token = token["accessToken"].as_str().ok_or(format!(
+                "{}: the token for {} has no accessToken",
+                filename, environment
+            ))?;
+            Ok((
+                environment.to_string(),
+                Token {
+                    access_token: access_token.to_string(),
+                    refresh_token: token["refreshToken"].as_str().map(str::to_string),
+                    expires_at: token["expiresAt"]
+                        .as_u64()
+                        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+                },
+            ))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(tokens)
+}
+
+pub fn save(filename: &str, tokens: &Tokens) -> io::Result<()> {
+    let mut file_json = JsonValue::new_object();
+    for (environment, token) in tokens {
+        let mut token_json = JsonValue::new_object();
+        token_json["accessToken"] = token.access_token.clone().into();
+        if let Some(refresh_token) = &token.refresh_token {
+            token_json["refreshToken"] = refresh_token.clone().into();
+        }
+        if let Some(expires_at) = token.expires_at {
+            token_json["expiresAt"] = expires_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs())
+                .into();
+        }
+        file_json[environment.as_str()] = token_json;
+    }
+
+    // Only the user can read the file on systems with permissions, as with the secrets file.
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(filename)?;
+    file.write_all(json::stringify_pretty(save_file::versioned("tokens", file_json), 2).as_bytes())
+}
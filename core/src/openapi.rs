@@ -0,0 +1,575 @@
+use std::error::Error;
+
+use json::JsonValue;
+use reqwest::{Method, Url};
+
+use crate::request::{self, Auth, BodyFormat, Request};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+pub fn import(spec: &str) -> Result<Vec<Request>, Box<dyn Error>> {
+    let spec = json::parse(spec)?;
+    let server = match spec["servers"][0]["url"].as_str() {
+        Some(url) if !url.is_empty() => url.trim_end_matches('/').to_string(),
+        _ => "http://localhost".to_string(),
+    };
+
+    let mut requests = vec![];
+    for (path, path_item) in spec["paths"].entries() {
+        for (method, operation) in path_item.entries() {
+            if !METHODS.contains(&method) {
+                continue;
+            }
+
+            let name = match operation["operationId"].as_str() {
+                Some(operation_id) => operation_id.to_string(),
+                None => format!("{}{}", method, path.replace(['/', '{', '}'], "-"))
+                    .trim_end_matches('-')
+                    .to_string(),
+            };
+            let mut request = Request {
+                name,
+                description: operation["description"]
+                    .as_str()
+                    .or(operation["summary"].as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                method: Method::from_bytes(method.to_uppercase().as_bytes())?,
+                ..Request::default()
+            };
+
+            let parameters: Vec<&JsonValue> = path_item["parameters"]
+                .members()
+                .chain(operation["parameters"].members())
+                .map(|parameter| resolve(&spec, parameter))
+                .collect();
+            let query: Vec<String> = parameters
+                .iter()
+                .filter(|parameter| parameter["in"] == "query")
+                .filter(|parameter| parameter["required"] == true || has_example(parameter))
+                .map(|parameter| {
+                    format!(
+                        "{}={}",
+                        parameter["name"].as_str().unwrap_or_default(),
+                        example(&spec, parameter)
+                    )
+                })
+                .collect();
+            request.url = if query.is_empty() {
+                format!("{}{}", server, path)
+            } else {
+                format!("{}{}?{}", server, path, query.join("&"))
+            };
+
+            let headers = parameters
+                .iter()
+                .filter(|parameter| parameter["in"] == "header")
+                .map(|parameter| {
+                    pair(
+                        parameter["name"].as_str().unwrap_or_default(),
+                        &example(&spec, parameter),
+                    )
+                });
+            let content = &operation["requestBody"]["content"]["application/json"];
+            request.headers = if content.is_null() {
+                headers.collect()
+            } else {
+                std::iter::once(pair("Content-Type", "application/json"))
+                    .chain(headers)
+                    .collect()
+            };
+            request.body = example_body(&spec, content);
+            request.path_params = parameters
+                .iter()
+                .filter(|parameter| parameter["in"] == "path" && has_example(parameter))
+                .map(|parameter| {
+                    pair(
+                        parameter["name"].as_str().unwrap_or_default(),
+                        &example(&spec, parameter),
+                    )
+                })
+                .collect();
+
+            let security = if operation["security"].is_null() {
+                &spec["security"]
+            } else {
+                &operation["security"]
+            };
+            for requirement in security.members() {
+                for (scheme_name, _) in requirement.entries() {
+                    let scheme = &spec["components"]["securitySchemes"][scheme_name];
+                    if scheme["type"] == "http" && scheme["scheme"] == "basic" {
+                        request.auth = Auth::Basic {
+                            username: String::default(),
+                            password: None,
+                        };
+                    } else if scheme["type"] == "http" && scheme["scheme"] == "bearer" {
+                        request.auth = Auth::Bearer(String::default());
+                    }
+                }
+            }
+
+            requests.push(request);
+        }
+    }
+
+    Ok(requests)
+}
+
+// A file name for each imported request, from its name with anything that isn't safe in a file name
+// replaced. Requests whose names come out the same are numbered, without regard to case for file
+// systems that ignore it.
+pub fn filenames(requests: &[Request]) -> Vec<String> {
+    let mut filenames: Vec<String> = vec![];
+    for request in requests {
+        let safe: String = request
+            .name
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+        // A leading dot would hide the file, or make it .. or the like.
+        let safe = match safe.trim_start_matches('.') {
+            "" => "request",
+            safe => safe,
+        };
+        let mut filename = format!("{}.http", safe);
+        let mut copy = 1;
+        while filenames
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(&filename))
+        {
+            copy += 1;
+            filename = format!("{}-{}.http", safe, copy);
+        }
+        filenames.push(filename);
+    }
+    filenames
+}
+
+fn pair(key: &str, value: &str) -> (String, String) {
+    (key.to_string(), value.to_string())
+}
+
+fn resolve<'a>(spec: &'a JsonValue, value: &'a JsonValue) -> &'a JsonValue {
+    match value["$ref"]
+        .as_str()
+        .and_then(|pointer| pointer.strip_prefix("#/"))
+    {
+        Some(pointer) => pointer
+            .split('/')
+            .fold(spec, |value, segment| &value[segment]),
+        None => value,
+    }
+}
+
+fn has_example(parameter: &JsonValue) -> bool {
+    !parameter["example"].is_null() || !parameter["schema"]["example"].is_null()
+}
+
+fn example(spec: &JsonValue, value: &JsonValue) -> String {
+    let schema = resolve(spec, &value["schema"]);
+    [
+        &value["example"],
+        &schema["example"],
+        &schema["default"],
+        &schema["enum"][0],
+    ]
+    .into_iter()
+    .find(|example| !example.is_null())
+    .map_or(String::default(), |example| match example.as_str() {
+        Some(example) => example.to_string(),
+        None => example.dump(),
+    })
+}
+
+fn example_body(spec: &JsonValue, content: &JsonValue) -> Vec<(String, String)> {
+    let example = resolve(spec, &content["example"]);
+    if example.is_object() {
+        return example
+            .entries()
+            .map(|(key, value)| pair(key, &value.as_str().map_or(value.dump(), str::to_string)))
+            .collect();
+    }
+
+    let schema = resolve(spec, &content["schema"]);
+    schema["properties"]
+        .entries()
+        .map(|(key, property)| {
+            let mut property_value = JsonValue::new_object();
+            property_value["schema"] = property.clone();
+            pair(key, &example(spec, &property_value))
+        })
+        .collect()
+}
+
+// Requests should already have their variables substituted so their URLs parse.
+pub fn export(title: &str, requests: &[Request]) -> Result<JsonValue, Box<dyn Error>> {
+    let mut spec = JsonValue::new_object();
+    spec["openapi"] = "3.0.3".into();
+    spec["info"]["title"] = title.into();
+    spec["info"]["version"] = "0.1.0".into();
+    spec["servers"] = JsonValue::new_array();
+
+    for request in requests {
+        let url = Url::parse(&request.url)
+            .map_err(|err| format!("Invalid URL for {}: {}", request.url, err))?;
+        // Placeholders like :id are written the way OpenAPI writes them, as {id}.
+        let path = url
+            .path()
+            .replace("%7B", "{")
+            .replace("%7D", "}")
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) if !name.is_empty() => format!("{{{}}}", name),
+                _ => segment.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("/");
+
+        let server = server(&url);
+        if !spec["servers"]
+            .members()
+            .any(|existing| *existing == server)
+        {
+            spec["servers"].push(server)?;
+        }
+        spec["paths"][path.as_str()][request.method.as_str().to_lowercase().as_str()] =
+            operation(request, &url, &path);
+
+        match request.auth {
+            Auth::None => (),
+            Auth::Basic { .. } => {
+                spec["components"]["securitySchemes"]["basicAuth"] = security_scheme("basic")
+            }
+            Auth::Bearer(_) => {
+                spec["components"]["securitySchemes"]["bearerAuth"] = security_scheme("bearer")
+            }
+        }
+    }
+
+    Ok(spec)
+}
+
+fn server(url: &Url) -> JsonValue {
+    let mut server = JsonValue::new_object();
+    server["url"] = url.origin().ascii_serialization().into();
+    server
+}
+
+fn operation(request: &Request, url: &Url, path: &str) -> JsonValue {
+    let mut operation = JsonValue::new_object();
+    if !request.name.is_empty() {
+        operation["operationId"] = request.name.clone().into();
+    }
+    if !request.description.is_empty() {
+        operation["description"] = request.description.clone().into();
+    }
+
+    let mut parameters = vec![];
+    for name in path
+        .split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+    {
+        let mut parameter = parameter(name, "path", None);
+        parameter["required"] = true.into();
+        parameters.push(parameter);
+    }
+    for (name, value) in url.query_pairs() {
+        parameters.push(parameter(&name, "query", Some(&*value)));
+    }
+    for (name, value) in request.headers.iter().filter(|(name, _)| !name.is_empty()) {
+        if !name.eq_ignore_ascii_case("content-type") && !name.eq_ignore_ascii_case("accept") {
+            parameters.push(parameter(name, "header", Some(value.as_str())));
+        }
+    }
+    if !parameters.is_empty() {
+        operation["parameters"] = JsonValue::Array(parameters);
+    }
+
+    let body: Vec<&(String, String)> = request
+        .body
+        .iter()
+        .filter(|(key, _)| !key.is_empty())
+        .collect();
+    if !body.is_empty() {
+        let content_type = match request.body_format {
+            BodyFormat::Json => "application/json",
+            BodyFormat::UrlEncoded => "application/x-www-form-urlencoded",
+            BodyFormat::Multipart => "multipart/form-data",
+        };
+        let mut schema = JsonValue::new_object();
+        schema["type"] = "object".into();
+        let mut example = JsonValue::new_object();
+        for (name, value) in body {
+            schema["properties"][name.as_str()]["type"] = "string".into();
+            example[name.as_str()] = value.as_str().into();
+        }
+        operation["requestBody"]["content"][content_type]["schema"] = schema;
+        operation["requestBody"]["content"][content_type]["example"] = example;
+    } else if let Some(body) = request.sent_raw_body() {
+        let content_type = request::raw_content_type(body);
+        operation["requestBody"]["content"][content_type]["example"] =
+            json::parse(body).unwrap_or_else(|_| body.into());
+    }
+
+    let security_name = match request.auth {
+        Auth::None => None,
+        Auth::Basic { .. } => Some("basicAuth"),
+        Auth::Bearer(_) => Some("bearerAuth"),
+    };
+    if let Some(security_name) = security_name {
+        let mut requirement = JsonValue::new_object();
+        requirement[security_name] = JsonValue::new_array();
+        operation["security"] = JsonValue::Array(vec![requirement]);
+    }
+
+    operation["responses"]["default"]["description"] = "Response".into();
+
+    operation
+}
+
+fn parameter(name: &str, location: &str, example: Option<&str>) -> JsonValue {
+    let mut parameter = JsonValue::new_object();
+    parameter["name"] = name.into();
+    parameter["in"] = location.into();
+    parameter["schema"]["type"] = "string".into();
+    if let Some(example) = example {
+        parameter["example"] = example.into();
+    }
+    parameter
+}
+
+fn security_scheme(scheme: &str) -> JsonValue {
+    let mut security_scheme = JsonValue::new_object();
+    security_scheme["type"] = "http".into();
+    security_scheme["scheme"] = scheme.into();
+    security_scheme
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(key, value)| pair(key, value)).collect()
+    }
+
+    const SPEC: &str = r##"{
+  "openapi": "3.0.3",
+  "servers": [{"url": "https://api.example.com/v1/"}],
+  "security": [{"bearerAuth": []}],
+  "components": {
+    "securitySchemes": {
+      "bearerAuth": {"type": "http", "scheme": "bearer"},
+      "basicAuth": {"type": "http", "scheme": "basic"}
+    },
+    "parameters": {
+      "Limit": {"name": "limit", "in": "query", "required": true, "schema": {"default": 20}}
+    },
+    "schemas": {
+      "User": {
+        "type": "object",
+        "properties": {
+          "name": {"type": "string", "example": "Ada"},
+          "role": {"enum": ["admin", "user"]}
+        }
+      }
+    }
+  },
+  "paths": {
+    "/users": {
+      "get": {
+        "operationId": "listUsers",
+        "summary": "List users",
+        "parameters": [
+          {"$ref": "#/components/parameters/Limit"},
+          {"name": "cursor", "in": "query"},
+          {"name": "X-Trace", "in": "header", "example": "1"}
+        ]
+      },
+      "post": {
+        "description": "Create a user",
+        "security": [{"basicAuth": []}],
+        "requestBody": {
+          "content": {"application/json": {"schema": {"$ref": "#/components/schemas/User"}}}
+        }
+      }
+    },
+    "/users/{id}": {
+      "summary": "One user",
+      "parameters": [{"name": "id", "in": "path", "required": true, "example": 42}],
+      "patch": {
+        "security": [],
+        "requestBody": {
+          "content": {"application/json": {"example": {"name": "Ada", "age": 36}}}
+        }
+      }
+    }
+  }
+}"##;
+
+    #[test]
+    fn import_operations() {
+        let requests = import(SPEC).unwrap();
+        assert_eq!(requests.len(), 3);
+
+        let list = &requests[0];
+        assert_eq!(list.name, "listUsers");
+        assert_eq!(list.description, "List users");
+        assert_eq!(list.method, Method::GET);
+        assert_eq!(list.url, "https://api.example.com/v1/users?limit=20");
+        assert_eq!(list.headers, pairs(&[("X-Trace", "1")]));
+        assert!(list.body.is_empty());
+        assert_eq!(list.auth, Auth::Bearer(String::new()));
+
+        let create = &requests[1];
+        assert_eq!(create.name, "post-users");
+        assert_eq!(create.description, "Create a user");
+        assert_eq!(create.method, Method::POST);
+        assert_eq!(create.url, "https://api.example.com/v1/users");
+        assert_eq!(
+            create.headers,
+            pairs(&[("Content-Type", "application/json")])
+        );
+        assert_eq!(create.body, pairs(&[("name", "Ada"), ("role", "admin")]));
+        assert_eq!(
+            create.auth,
+            Auth::Basic {
+                username: String::new(),
+                password: None
+            }
+        );
+
+        let update = &requests[2];
+        assert_eq!(update.name, "patch-users--id");
+        assert_eq!(update.method, Method::PATCH);
+        assert_eq!(update.url, "https://api.example.com/v1/users/{id}");
+        assert_eq!(update.path_params, pairs(&[("id", "42")]));
+        assert_eq!(update.body, pairs(&[("name", "Ada"), ("age", "36")]));
+        assert_eq!(update.auth, Auth::None);
+    }
+
+    #[test]
+    fn filenames_are_safe_and_distinct() {
+        let requests: Vec<Request> = ["../../etc/passwd", "get user", "Get-User", "get_user", ""]
+            .iter()
+            .map(|name| Request {
+                name: name.to_string(),
+                ..Request::default()
+            })
+            .collect();
+        assert_eq!(
+            filenames(&requests),
+            vec![
+                "_.._etc_passwd.http",
+                "get_user.http",
+                "Get-User.http",
+                "get_user-2.http",
+                "request.http"
+            ]
+        );
+    }
+
+    #[test]
+    fn import_without_servers() {
+        let requests = import(r#"{"paths": {"/health": {"get": {}}}}"#).unwrap();
+        assert_eq!(requests[0].url, "http://localhost/health");
+        assert_eq!(requests[0].name, "get-health");
+    }
+
+    #[test]
+    fn import_needs_json() {
+        assert!(import("openapi: 3.0.3").is_err());
+    }
+
+    #[test]
+    fn export_operations() {
+        let requests = [
+            Request {
+                name: "get-user".to_string(),
+                description: "Fetch a user".to_string(),
+                url: "https://api.example.com/users/:id?fields=name".to_string(),
+                headers: pairs(&[("Accept", "application/json"), ("X-Trace", "1")]),
+                auth: Auth::Bearer("abc".to_string()),
+                ..Request::default()
+            },
+            Request {
+                method: Method::POST,
+                url: "https://api.example.com/users".to_string(),
+                body: pairs(&[("name", "Ada")]),
+                auth: Auth::Basic {
+                    username: "ada".to_string(),
+                    password: None,
+                },
+                ..Request::default()
+            },
+            Request {
+                method: Method::PUT,
+                url: "https://notes.example.com:8443/notes/{id}".to_string(),
+                raw_body: Some("<note/>".to_string()),
+                ..Request::default()
+            },
+        ];
+        let spec = export("Users", &requests).unwrap();
+
+        assert_eq!(spec["openapi"], "3.0.3");
+        assert_eq!(spec["info"]["title"], "Users");
+        assert_eq!(
+            spec["servers"].dump(),
+            r#"[{"url":"https://api.example.com"},{"url":"https://notes.example.com:8443"}]"#
+        );
+
+        let get = &spec["paths"]["/users/{id}"]["get"];
+        assert_eq!(get["operationId"], "get-user");
+        assert_eq!(get["description"], "Fetch a user");
+        assert_eq!(
+            get["parameters"].dump(),
+            concat!(
+                r#"[{"name":"id","in":"path","schema":{"type":"string"},"required":true},"#,
+                r#"{"name":"fields","in":"query","schema":{"type":"string"},"example":"name"},"#,
+                r#"{"name":"X-Trace","in":"header","schema":{"type":"string"},"example":"1"}]"#
+            )
+        );
+        assert_eq!(get["security"].dump(), r#"[{"bearerAuth":[]}]"#);
+        assert!(get["requestBody"].is_null());
+
+        let post = &spec["paths"]["/users"]["post"];
+        assert_eq!(
+            post["requestBody"]["content"]["application/json"].dump(),
+            r#"{"schema":{"type":"object","properties":{"name":{"type":"string"}}},"example":{"name":"Ada"}}"#
+        );
+        assert_eq!(post["security"].dump(), r#"[{"basicAuth":[]}]"#);
+
+        let put = &spec["paths"]["/notes/{id}"]["put"];
+        assert_eq!(
+            put["requestBody"]["content"]["application/xml"]["example"],
+            "<note/>"
+        );
+        assert!(put["security"].is_null());
+
+        assert_eq!(
+            spec["components"]["securitySchemes"].dump(),
+            concat!(
+                r#"{"bearerAuth":{"type":"http","scheme":"bearer"},"#,
+                r#""basicAuth":{"type":"http","scheme":"basic"}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn export_needs_urls_that_parse() {
+        let requests = [Request {
+            url: "{{host}}/users".to_string(),
+            ..Request::default()
+        }];
+        assert!(export("Users", &requests)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid URL for {{host}}/users: "));
+    }
+}
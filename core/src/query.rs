@@ -0,0 +1,60 @@
+use crate::json_path;
+
+// Picks values out of a response body for the Output query bar. Queries starting with $ are
+// JSONPath, ones starting with / or ( are XPath over XML, and anything else is a CSS selector over
+// HTML.
+pub fn select(body: &str, query: &str) -> Result<Vec<String>, String> {
+    let query = query.trim();
+    if query.starts_with('$') {
+        let body = json::parse(body).map_err(|err| format!("Response is not JSON: {}", err))?;
+        Ok(json_path::select(&body, query)?
+            .into_iter()
+            .map(|value| value.as_str().map_or(value.pretty(2), str::to_string))
+            .collect())
+    } else if query.starts_with(['/', '(']) {
+        xpath(body, query)
+    } else {
+        css(body, query)
+    }
+}
+
+#[cfg(feature = "selectors")]
+fn xpath(body: &str, query: &str) -> Result<Vec<String>, String> {
+    use sxd_xpath::Value;
+
+    let package =
+        sxd_document::parser::parse(body).map_err(|err| format!("Response is not XML: {}", err))?;
+    let document = package.as_document();
+    let value = sxd_xpath::evaluate_xpath(&document, query)
+        .map_err(|err| format!("Invalid XPath: {}", err))?;
+
+    Ok(match value {
+        Value::Nodeset(nodes) => nodes
+            .document_order()
+            .iter()
+            .map(|node| node.string_value())
+            .collect(),
+        value => vec![value.string()],
+    })
+}
+
+#[cfg(not(feature = "selectors"))]
+fn xpath(_body: &str, _query: &str) -> Result<Vec<String>, String> {
+    Err("XPath queries need reqcoon built with the selectors feature".to_string())
+}
+
+#[cfg(feature = "selectors")]
+fn css(body: &str, query: &str) -> Result<Vec<String>, String> {
+    let selector =
+        scraper::Selector::parse(query).map_err(|err| format!("Invalid selector: {}", err))?;
+
+    Ok(scraper::Html::parse_document(body)
+        .select(&selector)
+        .map(|element| element.html())
+        .collect())
+}
+
+#[cfg(not(feature = "selectors"))]
+fn css(_body: &str, _query: &str) -> Result<Vec<String>, String> {
+    Err("CSS selector queries need reqcoon built with the selectors feature".to_string())
+}
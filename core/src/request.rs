@@ -0,0 +1,959 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    error::Error,
+    fmt, fs,
+    hash::{Hash, Hasher},
+    path::Path,
+};
+
+use enum_iterator::Sequence;
+use http_auth_basic::Credentials;
+use json::JsonValue;
+use log::error;
+use pest::Parser;
+use pest_derive::Parser;
+use regex::RegexBuilder;
+use reqwest::{blocking::RequestBuilder, header::CONTENT_TYPE, Method, Url};
+
+use crate::{
+    json_path, schema,
+    settings::{self, Settings},
+    variables::substitute,
+};
+
+#[derive(Parser)]
+#[grammar = "http.pest"]
+struct RequestParser;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Sequence)]
+pub enum BodyFormat {
+    #[default]
+    Json,
+    UrlEncoded,
+    Multipart,
+}
+
+impl fmt::Display for BodyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BodyFormat::Json => write!(f, "JSON"),
+            BodyFormat::UrlEncoded => write!(f, "Form"),
+            BodyFormat::Multipart => write!(f, "Multipart"),
+        }
+    }
+}
+
+impl BodyFormat {
+    // How the format is named in a `# @body-format` directive.
+    fn directive(&self) -> &'static str {
+        match self {
+            BodyFormat::Json => "json",
+            BodyFormat::UrlEncoded => "form",
+            BodyFormat::Multipart => "multipart",
+        }
+    }
+
+    fn from_directive(name: &str) -> Option<Self> {
+        enum_iterator::all::<BodyFormat>().find(|format| format.directive() == name.trim())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Auth {
+    #[default]
+    None,
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    Bearer(String),
+}
+
+impl Auth {
+    pub fn header_value(&self) -> Option<String> {
+        match self {
+            Auth::None => None,
+            Auth::Basic { username, password } => Some(
+                Credentials {
+                    user_id: username.clone(),
+                    password: password.clone().unwrap_or_default(),
+                }
+                .as_http_header(),
+            ),
+            Auth::Bearer(token) => Some(format!("Bearer {}", token)),
+        }
+    }
+
+    pub(crate) fn from_header(value: &str) -> Option<Self> {
+        let re = RegexBuilder::new(r"(basic|bearer) (.*)")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        let captures = re.captures(value)?;
+        match captures[1].to_lowercase().as_str() {
+            "basic" => match Credentials::from_header(value.to_string()) {
+                Ok(credentials) => Some(Auth::Basic {
+                    username: credentials.user_id,
+                    password: Some(credentials.password).filter(|password| !password.is_empty()),
+                }),
+                Err(_) => {
+                    // The error can quote the credentials, which shouldn't end up in the log.
+                    error!("Unable to read Basic credentials from the Authorization header");
+                    None
+                }
+            },
+            "bearer" => Some(Auth::Bearer(captures[2].to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Request {
+    pub name: String,
+    // Notes for whoever uses the request next, a line per `# @description` line.
+    pub description: String,
+    pub method: Method,
+    pub url: String,
+    pub auth: Auth,
+    pub options: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<(String, String)>,
+    pub body_format: BodyFormat,
+    // A body the rows can't hold, like an array, nested JSON, XML or plain text, sent and written
+    // back as it was read. Rows take its place once there are any.
+    pub raw_body: Option<String>,
+    pub script: Option<String>,
+    // Variables to set from the response, each with the JSONPath of its value.
+    pub captures: Vec<(String, String)>,
+    pub assertions: Vec<String>,
+    // Opts out of the enclosing folders' shared script and auth.
+    pub standalone: bool,
+    // Values for the placeholders in the URL's path, like id in /users/:id or /users/{id}.
+    pub path_params: Vec<(String, String)>,
+    // Rows switched off in the editor. They aren't sent, but are kept so they can be switched back
+    // on.
+    pub disabled_headers: Vec<(String, String)>,
+    pub disabled_body: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn parse(input: &str) -> Result<Self, Box<dyn Error>> {
+        let mut request = Request::default();
+
+        let pairs = RequestParser::parse(Rule::request, input)?;
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::directives => {
+                    for directive in pair.into_inner() {
+                        let mut name = "";
+                        let mut value = "";
+                        for inner_rule in directive.into_inner() {
+                            match inner_rule.as_rule() {
+                                Rule::directive_name => name = inner_rule.as_str(),
+                                Rule::directive_value => value = inner_rule.as_str(),
+                                _ => (),
+                            }
+                        }
+                        if name == "name" {
+                            request.name = value.to_string();
+                        } else if name == "description" {
+                            if !request.description.is_empty() {
+                                request.description.push('\n');
+                            }
+                            request.description.push_str(value);
+                        } else if name == "capture" {
+                            let (variable, path) = value
+                                .split_once('=')
+                                .ok_or(format!("Invalid capture: {}", value))?;
+                            request
+                                .captures
+                                .push((variable.trim().to_string(), path.trim().to_string()));
+                        } else if name == "assert" {
+                            request.assertions.push(value.to_string());
+                        } else if name == "standalone" {
+                            request.standalone = true;
+                        } else if name == "body-format" {
+                            request.body_format = BodyFormat::from_directive(value)
+                                .ok_or(format!("Invalid body-format: {}", value))?;
+                        } else if ["disabled-header", "disabled-body", "path-param"].contains(&name)
+                        {
+                            let (key, value) = value
+                                .split_once(':')
+                                .ok_or(format!("Invalid {}: {}", name, value))?;
+                            let pair = (key.trim().to_string(), value.trim().to_string());
+                            match name {
+                                "disabled-header" => request.disabled_headers.push(pair),
+                                "disabled-body" => request.disabled_body.push(pair),
+                                _ => request.path_params.push(pair),
+                            }
+                        } else if settings::KEYS.contains(&name) {
+                            request.options.push((name.to_string(), value.to_string()));
+                        }
+                    }
+                }
+                Rule::script_body => request.script = Some(pair.as_str().trim().to_string()),
+                Rule::method => request.method = Method::from_bytes(pair.as_str().as_bytes())?,
+                Rule::uri => request.url = pair.as_str().to_string(),
+                Rule::headers => {
+                    for header in pair.into_inner() {
+                        let mut key = "";
+                        let mut value = "";
+                        for inner_rule in header.into_inner() {
+                            match inner_rule.as_rule() {
+                                Rule::header_name => key = inner_rule.as_str(),
+                                Rule::header_value => value = inner_rule.as_str(),
+                                _ => (),
+                            }
+                        }
+                        request.headers.push((key.to_string(), value.to_string()));
+                    }
+                }
+                // A body of `< path` is read from the file when the request is sent.
+                Rule::body if pair.as_str().starts_with("< ") => {
+                    let path = pair.as_str()[2..].trim();
+                    request.body.push((format!("@{}", path), String::new()));
+                }
+                // A form body is written encoded, the way a URL-encoded one is sent.
+                Rule::body if request.body_format != BodyFormat::Json => {
+                    request.body = form_rows(pair.as_str().trim());
+                }
+                // Only a JSON object of strings can be edited as rows.
+                Rule::body => {
+                    let text = pair.as_str().trim_end();
+                    match body_rows(text) {
+                        Some(rows) => request.body = rows,
+                        None if !text.trim().is_empty() => {
+                            request.raw_body = Some(text.to_string())
+                        }
+                        None => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // An Authorization header we understand is edited as auth rather than as a header.
+        if let Some(index) = request
+            .headers
+            .iter()
+            .position(|(name, _)| name == "Authorization")
+        {
+            if let Some(auth) = Auth::from_header(&request.headers[index].1) {
+                request.auth = auth;
+                request.headers.remove(index);
+            }
+        }
+
+        Ok(request)
+    }
+
+    pub fn request_line(&self) -> String {
+        format!("{} {}", self.method, self.url)
+    }
+
+    // Everything after the request line: the headers, then the body after a blank line.
+    pub fn message(&self) -> String {
+        let mut output = String::new();
+        if let Some(auth) = self.auth.header_value() {
+            output.push_str("\nAuthorization: ");
+            output.push_str(&auth);
+        }
+        for (name, value) in self.non_empty_headers() {
+            output.push_str(&format!("\n{}: {}", name, value));
+        }
+        let body = self.body_string();
+        if !body.is_empty() {
+            output.push_str("\n\n");
+            output.push_str(&body);
+        }
+        output
+    }
+
+    pub fn raw(&self) -> String {
+        format!("{} HTTP/1.1{}", self.request_line(), self.message())
+    }
+
+    // A curl command that sends the same request, to share it with people who don't use reqcoon.
+    pub fn curl(&self) -> String {
+        let quote = |text: &str| format!("'{}'", text.replace('\'', "'\\''"));
+        let mut command = format!("curl -X {} {}", self.method, quote(&self.url));
+        if let Some(value) = self.auth.header_value() {
+            command.push_str(&format!(
+                " -H {}",
+                quote(&format!("Authorization: {}", value))
+            ));
+        }
+        for (name, value) in self.non_empty_headers() {
+            command.push_str(&format!(" -H {}", quote(&format!("{}: {}", name, value))));
+        }
+        if let Some(path) = self.body_file() {
+            command.push_str(&format!(" --data-binary {}", quote(&format!("@{}", path))));
+            return command;
+        }
+        if let Some(body) = self.sent_raw_body() {
+            if !self.has_header("Content-Type") && !self.switched_off("Content-Type") {
+                command.push_str(&format!(
+                    " -H {}",
+                    quote(&format!("Content-Type: {}", raw_content_type(body)))
+                ));
+            }
+            command.push_str(&format!(" --data-binary {}", quote(body)));
+            return command;
+        }
+        match self.body_format {
+            BodyFormat::Json if self.non_empty_body().next().is_some() => {
+                if !self.has_header("Content-Type") && !self.switched_off("Content-Type") {
+                    command.push_str(" -H 'Content-Type: application/json'");
+                }
+                command.push_str(&format!(" --data {}", quote(&self.body_string())));
+            }
+            BodyFormat::Json => (),
+            BodyFormat::UrlEncoded => {
+                for (key, value) in self.non_empty_body() {
+                    command.push_str(&format!(
+                        " --data-urlencode {}",
+                        quote(&format!("{}={}", key, value))
+                    ));
+                }
+            }
+            BodyFormat::Multipart => {
+                for (key, value) in self.non_empty_body() {
+                    command.push_str(&format!(" -F {}", quote(&format!("{}={}", key, value))));
+                }
+            }
+        }
+        command
+    }
+
+    pub fn body_string(&self) -> String {
+        if let Some(path) = self.body_file() {
+            return format!("< {}", path);
+        }
+        if let Some(body) = self.sent_raw_body() {
+            return body.to_string();
+        }
+        match self.body_format {
+            BodyFormat::Json if self.non_empty_body().next().is_some() => JsonValue::Object(
+                self.non_empty_body()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            )
+            .dump(),
+            BodyFormat::Json => String::default(),
+            BodyFormat::UrlEncoded | BodyFormat::Multipart => self.form_string(),
+        }
+    }
+
+    // A body with a single row keyed `@path` and no value is the contents of the file at path.
+    pub fn body_file(&self) -> Option<&str> {
+        let mut rows = self.non_empty_body();
+        match (rows.next(), rows.next()) {
+            (Some((key, value)), None) if value.is_empty() => key.strip_prefix('@'),
+            _ => None,
+        }
+    }
+
+    // The raw body, unless there are rows to send instead.
+    pub fn sent_raw_body(&self) -> Option<&str> {
+        self.raw_body
+            .as_deref()
+            .filter(|_| self.non_empty_body().next().is_none())
+    }
+
+    pub fn substituted(&self, variables: &[(String, String)]) -> Self {
+        let pairs = |pairs: &[(String, String)]| {
+            pairs
+                .iter()
+                .map(|(key, value)| (substitute(key, variables), substitute(value, variables)))
+                .collect()
+        };
+
+        Request {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            method: self.method.clone(),
+            url: fill_path(&substitute(&self.url, variables), &pairs(&self.path_params)),
+            auth: match &self.auth {
+                Auth::None => Auth::None,
+                Auth::Basic { username, password } => Auth::Basic {
+                    username: substitute(username, variables),
+                    password: password
+                        .as_ref()
+                        .map(|password| substitute(password, variables)),
+                },
+                Auth::Bearer(token) => Auth::Bearer(substitute(token, variables)),
+            },
+            options: self.options.clone(),
+            headers: pairs(&self.headers),
+            body: pairs(&self.body),
+            body_format: self.body_format,
+            raw_body: self
+                .raw_body
+                .as_ref()
+                .map(|body| substitute(body, variables)),
+            script: self.script.clone(),
+            captures: self.captures.clone(),
+            assertions: self.assertions.clone(),
+            standalone: self.standalone,
+            path_params: pairs(&self.path_params),
+            disabled_headers: self.disabled_headers.clone(),
+            disabled_body: self.disabled_body.clone(),
+        }
+    }
+
+    // Runs the pre-request script, if there is one, on a copy of the request that is about to be
+    // sent. Variables the script sets are added to the captured ones.
+    #[cfg_attr(not(feature = "scripting"), allow(unused_mut, unused_variables))]
+    pub fn prepare(
+        &self,
+        variables: &[(String, String)],
+        captured: &mut Vec<(String, String)>,
+    ) -> Result<Self, String> {
+        let mut request = self.clone();
+        match &self.script {
+            #[cfg(feature = "scripting")]
+            Some(script) => crate::script::run(script, &mut request, variables, captured)?,
+            #[cfg(not(feature = "scripting"))]
+            Some(_) => {
+                return Err(
+                    "Pre-request scripts need reqcoon built with the scripting feature".to_string(),
+                )
+            }
+            None => (),
+        }
+
+        Ok(request)
+    }
+
+    pub fn builder(
+        &self,
+        settings: &Settings,
+        variables: &[(String, String)],
+    ) -> Result<RequestBuilder, String> {
+        let request = self.substituted(variables);
+        // Switched off headers leave out the workspace's default headers of the same name.
+        let mut settings = settings.clone();
+        settings
+            .default_headers
+            .retain(|(name, _)| !request.switched_off(name));
+        let client = settings
+            .client()
+            .map_err(|err| format!("Invalid settings: {}", err))?;
+        let url = parse_url(&request.url)?;
+        let mut request_builder = client.request(request.method.clone(), url);
+        #[cfg(feature = "http3")]
+        if settings.http_version == Some(reqwest::Version::HTTP_3) {
+            request_builder = request_builder.version(reqwest::Version::HTTP_3);
+        }
+
+        request_builder = match &request.auth {
+            Auth::None => request_builder,
+            Auth::Basic { username, password } => {
+                request_builder.basic_auth(username, password.as_ref())
+            }
+            Auth::Bearer(token) => request_builder.bearer_auth(token),
+        };
+        request_builder = request
+            .non_empty_headers()
+            .fold(request_builder, |builder, (name, value)| {
+                builder.header(name, value)
+            });
+        let body = match request.body_chunks() {
+            Some((content_type, chunks)) => {
+                let mut body = vec![];
+                for chunk in chunks {
+                    match chunk {
+                        Chunk::Text(text) => body.extend(text.into_bytes()),
+                        Chunk::File(path) => body.extend(fs::read(path).map_err(|err| {
+                            format!("Unable to read the body from {}: {}", path, err)
+                        })?),
+                    }
+                }
+                Some((content_type, body))
+            }
+            None => None,
+        };
+        if let (Some(schema), Some((_, body))) = (&settings.schema, &body) {
+            schema::check(schema, body)?;
+        }
+        if let Some((content_type, body)) = body {
+            if !request.has_header("Content-Type") && !request.switched_off("Content-Type") {
+                request_builder = request_builder.header(CONTENT_TYPE, content_type);
+            }
+            request_builder = request_builder.body(body);
+        }
+
+        Ok(request_builder)
+    }
+
+    // Evaluates the captures against a JSON response body, returning the variables to set.
+    pub fn capture(&self, body: &str) -> Result<Vec<(String, String)>, String> {
+        if self.captures.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let body = json::parse(body).map_err(|err| format!("Response is not JSON: {}", err))?;
+        self.captures
+            .iter()
+            .map(|(variable, path)| {
+                let value = json_path::select(&body, path)?
+                    .into_iter()
+                    .next()
+                    .ok_or(format!("Nothing in the response matches {}", path))?;
+                let value = value.as_str().map_or(value.dump(), str::to_string);
+                Ok((variable.clone(), value))
+            })
+            .collect()
+    }
+
+    // Headers that are sent without being among the request's own: the workspace's default
+    // headers, the body's Content-Type, and the Host and Content-Length the HTTP client adds. A
+    // header of the same name overrides any of them, and a switched off one leaves out any but
+    // Host and Content-Length, which the client always sends.
+    pub fn auto_headers(&self, settings: &Settings) -> Vec<(String, String)> {
+        let mut headers: Vec<(String, String)> = settings
+            .default_headers
+            .iter()
+            .filter(|(name, _)| !self.switched_off(name))
+            .cloned()
+            .collect();
+        if let Some((content_type, chunks)) = self.body_chunks() {
+            let length: usize = chunks
+                .iter()
+                .map(|chunk| match chunk {
+                    Chunk::Text(text) => text.len(),
+                    Chunk::File(path) => {
+                        fs::metadata(path).map_or(0, |metadata| metadata.len() as usize)
+                    }
+                })
+                .sum();
+            if !self.switched_off("Content-Type") {
+                headers.push(("Content-Type".to_string(), content_type));
+            }
+            headers.push(("Content-Length".to_string(), length.to_string()));
+        }
+        if let Ok(url) = parse_url(&self.url) {
+            let host = url.host_str().unwrap_or_default();
+            headers.push((
+                "Host".to_string(),
+                match url.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.to_string(),
+                },
+            ));
+        }
+        headers.retain(|(name, _)| !self.has_header(name));
+        headers
+    }
+
+    fn has_header(&self, name: &str) -> bool {
+        self.non_empty_headers()
+            .any(|(header, _)| header.eq_ignore_ascii_case(name))
+    }
+
+    fn switched_off(&self, name: &str) -> bool {
+        self.disabled_headers
+            .iter()
+            .any(|(header, _)| header.eq_ignore_ascii_case(name))
+    }
+
+    // The fields of a form body as they're sent, like name=Ada+Lovelace&age=36.
+    pub fn form_string(&self) -> String {
+        let mut url = Url::parse("http://localhost/").unwrap();
+        url.query_pairs_mut().extend_pairs(self.non_empty_body());
+        url.query().unwrap_or_default().to_string()
+    }
+
+    // What a form body sends, to show under the Body table: the encoded string of a URL-encoded
+    // form, or the parts of a multipart one with the files they upload.
+    pub fn form_preview(&self) -> Option<String> {
+        self.non_empty_body().next()?;
+        if self.body_file().is_some() {
+            return None;
+        }
+        match self.body_format {
+            BodyFormat::Json => None,
+            BodyFormat::UrlEncoded => Some(self.form_string()),
+            BodyFormat::Multipart => Some(
+                self.non_empty_body()
+                    .map(|(key, value)| match value.strip_prefix('@') {
+                        Some(path) => format!("{} ({}, {})", key, path, file_content_type(path)),
+                        None => key.clone(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ),
+        }
+    }
+
+    // The body's content type and what it's made of, or None when it has no fields. Files are
+    // only read when the request is sent, so the headers can be shown without reading them.
+    fn body_chunks(&self) -> Option<(String, Vec<Chunk<'_>>)> {
+        if let Some(path) = self.body_file() {
+            return Some((file_content_type(path).to_string(), vec![Chunk::File(path)]));
+        }
+        if let Some(body) = self.sent_raw_body() {
+            return Some((
+                raw_content_type(body).to_string(),
+                vec![Chunk::Text(body.to_string())],
+            ));
+        }
+        self.non_empty_body().next()?;
+        match self.body_format {
+            BodyFormat::Json => Some((
+                "application/json".to_string(),
+                vec![Chunk::Text(self.body_string())],
+            )),
+            BodyFormat::UrlEncoded => Some((
+                "application/x-www-form-urlencoded".to_string(),
+                vec![Chunk::Text(self.form_string())],
+            )),
+            // Each row is a part, and one with a value of `@path` uploads the file at path, as
+            // with curl -F.
+            BodyFormat::Multipart => {
+                let boundary = self.boundary();
+                let mut chunks = vec![];
+                for (key, value) in self.non_empty_body() {
+                    let disposition = format!(
+                        "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+                        boundary,
+                        key.replace('"', "%22")
+                    );
+                    match value.strip_prefix('@') {
+                        Some(path) => {
+                            let filename = Path::new(path)
+                                .file_name()
+                                .map_or(path.into(), |name| name.to_string_lossy())
+                                .replace('"', "%22");
+                            chunks.push(Chunk::Text(format!(
+                                "{}; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                                disposition,
+                                filename,
+                                file_content_type(path)
+                            )));
+                            chunks.push(Chunk::File(path));
+                            chunks.push(Chunk::Text("\r\n".to_string()));
+                        }
+                        None => chunks
+                            .push(Chunk::Text(format!("{}\r\n\r\n{}\r\n", disposition, value))),
+                    }
+                }
+                chunks.push(Chunk::Text(format!("--{}--\r\n", boundary)));
+                Some((
+                    format!("multipart/form-data; boundary={}", boundary),
+                    chunks,
+                ))
+            }
+        }
+    }
+
+    // The boundary between multipart parts comes from the fields, so the same body is always sent
+    // the same way.
+    fn boundary(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        for row in self.non_empty_body() {
+            row.hash(&mut hasher);
+        }
+        format!("reqcoon-{:016x}", hasher.finish())
+    }
+
+    pub(crate) fn non_empty_headers(&self) -> impl Iterator<Item = &(String, String)> {
+        self.headers.iter().filter(|(name, _)| !name.is_empty())
+    }
+
+    pub(crate) fn non_empty_body(&self) -> impl Iterator<Item = &(String, String)> {
+        self.body.iter().filter(|(key, _)| !key.is_empty())
+    }
+}
+
+enum Chunk<'a> {
+    Text(String),
+    File(&'a str),
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.name.is_empty() {
+            writeln!(f, "# @name {}", self.name)?;
+        }
+        for line in self.description.lines() {
+            if line.is_empty() {
+                writeln!(f, "# @description")?;
+            } else {
+                writeln!(f, "# @description {}", line)?;
+            }
+        }
+        for (key, value) in &self.options {
+            if !key.is_empty() && !value.is_empty() {
+                writeln!(f, "# @{} {}", key, value)?;
+            }
+        }
+        for (variable, path) in &self.captures {
+            writeln!(f, "# @capture {} = {}", variable, path)?;
+        }
+        for assertion in &self.assertions {
+            writeln!(f, "# @assert {}", assertion)?;
+        }
+        if self.standalone {
+            writeln!(f, "# @standalone")?;
+        }
+        // A raw body or a file is written as it is, so only form rows need their format.
+        if self.body_format != BodyFormat::Json
+            && self.body_file().is_none()
+            && self.sent_raw_body().is_none()
+        {
+            writeln!(f, "# @body-format {}", self.body_format.directive())?;
+        }
+        for (name, value) in &self.path_params {
+            writeln!(f, "# @path-param {}: {}", name, value)?;
+        }
+        for (name, value) in &self.disabled_headers {
+            writeln!(f, "# @disabled-header {}: {}", name, value)?;
+        }
+        for (key, value) in &self.disabled_body {
+            writeln!(f, "# @disabled-body {}: {}", key, value)?;
+        }
+        if let Some(script) = &self.script {
+            writeln!(f, "< {{%\n{}\n%}}", script)?;
+        }
+        write!(f, "{}{}", self.request_line(), self.message())
+    }
+}
+
+// The names of the placeholders in the URL's path, in the order they first appear. A placeholder is
+// a whole segment, like :id or {id}.
+pub fn path_placeholders(url: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    let (start, end) = path_range(url);
+    for name in url[start..end].split('/').filter_map(placeholder) {
+        if !names.iter().any(|existing| existing == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+// The URL with each placeholder that has a value replaced by it.
+fn fill_path(url: &str, values: &[(String, String)]) -> String {
+    let (start, end) = path_range(url);
+    let path = url[start..end]
+        .split('/')
+        .map(|segment| {
+            placeholder(segment)
+                .and_then(|name| {
+                    values
+                        .iter()
+                        .find(|(key, value)| key == name && !value.is_empty())
+                })
+                .map_or(segment, |(_, value)| value.as_str())
+        })
+        .collect::<Vec<&str>>()
+        .join("/");
+    format!("{}{}{}", &url[..start], path, &url[end..])
+}
+
+// Where the path is in the URL: after the scheme and host, or from the first / of a URL that starts
+// with a variable like {{base}}, up to the query or fragment.
+fn path_range(url: &str) -> (usize, usize) {
+    let host = url.find("://").map_or(0, |index| index + 3);
+    let start = url[host..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |index| host + index);
+    let end = url[start..]
+        .find(['?', '#'])
+        .map_or(url.len(), |index| start + index);
+    (start, end)
+}
+
+fn placeholder(segment: &str) -> Option<&str> {
+    let name = segment
+        .strip_prefix(':')
+        .or_else(|| segment.strip_prefix('{')?.strip_suffix('}'))?;
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    valid.then_some(name)
+}
+
+// The rows of a body that's a JSON object of strings, or None for any other body.
+fn body_rows(text: &str) -> Option<Vec<(String, String)>> {
+    let object = json::parse(text).ok().filter(JsonValue::is_object)?;
+    object
+        .entries()
+        .map(|(key, value)| Some((key.to_string(), value.as_str()?.to_string())))
+        .collect()
+}
+
+// The rows of a form body written the way it's sent, like name=Ada+Lovelace&age=36.
+pub fn form_rows(text: &str) -> Vec<(String, String)> {
+    let mut url = Url::parse("http://localhost/").unwrap();
+    url.set_query(Some(text));
+    url.query_pairs()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect()
+}
+
+// Raw bodies are described by what they look like, since there's no file extension to go by.
+pub(crate) fn raw_content_type(body: &str) -> &'static str {
+    if json::parse(body).is_ok() {
+        "application/json"
+    } else if body.trim_start().starts_with('<') {
+        "application/xml"
+    } else {
+        "text/plain"
+    }
+}
+
+// Files sent as the body are described by their extension.
+fn file_content_type(path: &str) -> &'static str {
+    match Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("html" | "htm") => "text/html",
+        Some("csv") => "text/csv",
+        Some("txt") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+// Parses a URL the way the client will send it, so a bad one is reported before it's sent.
+pub fn parse_url(url: &str) -> Result<Url, String> {
+    if url.trim().is_empty() {
+        return Err("Invalid URL: the URL is empty".to_string());
+    }
+    let url = Url::parse(url.trim()).map_err(|err| format!("Invalid URL: {}", err))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Invalid URL: unsupported scheme {}", url.scheme()));
+    }
+    if url.host_str().map_or(true, str::is_empty) {
+        return Err("Invalid URL: missing host".to_string());
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_body(body: &str) -> String {
+        format!("POST https://example.com/users\nX-Trace: 1\n\n{}", body)
+    }
+
+    #[test]
+    fn objects_of_strings_are_rows() {
+        let request = Request::parse(&with_body(r#"{"name": "Ada", "role": "admin"}"#)).unwrap();
+        assert_eq!(
+            request.body,
+            vec![
+                ("name".to_string(), "Ada".to_string()),
+                ("role".to_string(), "admin".to_string())
+            ]
+        );
+        assert_eq!(request.raw_body, None);
+        assert_eq!(request.body_string(), r#"{"name":"Ada","role":"admin"}"#);
+    }
+
+    #[test]
+    fn other_bodies_are_kept_as_written() {
+        for body in [
+            r#"{"count": 3}"#,
+            "{\n  \"user\": {\n    \"name\": \"Ada\"\n  }\n}",
+            "[1, 2, 3]",
+            "<user name=\"Ada\"/>",
+            "name is Ada",
+        ] {
+            let text = with_body(body);
+            let request = Request::parse(&text).unwrap();
+            assert!(request.body.is_empty(), "{}", body);
+            assert_eq!(request.raw_body.as_deref(), Some(body));
+            assert_eq!(request.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn raw_bodies_are_sent_with_the_type_they_look_like() {
+        for (body, content_type) in [
+            ("[1, 2, 3]", "application/json"),
+            ("<user/>", "application/xml"),
+            ("name is Ada", "text/plain"),
+        ] {
+            let curl = Request::parse(&with_body(body)).unwrap().curl();
+            assert!(
+                curl.contains(&format!("Content-Type: {}", content_type)),
+                "{}",
+                curl
+            );
+            assert!(
+                curl.ends_with(&format!("--data-binary '{}'", body)),
+                "{}",
+                curl
+            );
+        }
+    }
+
+    #[test]
+    fn rows_replace_a_raw_body() {
+        let mut request = Request::parse(&with_body("<user/>")).unwrap();
+        request.body.push(("name".to_string(), "Ada".to_string()));
+        assert_eq!(request.sent_raw_body(), None);
+        assert_eq!(request.body_string(), r#"{"name":"Ada"}"#);
+    }
+
+    #[test]
+    fn raw_bodies_are_substituted() {
+        let request = Request::parse(&with_body("<user name=\"{{name}}\"/>")).unwrap();
+        let variables = [("name".to_string(), "Ada".to_string())];
+        assert_eq!(
+            request.substituted(&variables).raw_body.as_deref(),
+            Some("<user name=\"Ada\"/>")
+        );
+    }
+
+    #[test]
+    fn form_bodies_keep_their_format() {
+        let text =
+            "# @body-format form\nPOST https://example.com/users\n\nname=Ada+Lovelace&note=a%26b";
+        let request = Request::parse(text).unwrap();
+        assert_eq!(request.body_format, BodyFormat::UrlEncoded);
+        assert_eq!(
+            request.body,
+            vec![
+                ("name".to_string(), "Ada Lovelace".to_string()),
+                ("note".to_string(), "a&b".to_string())
+            ]
+        );
+        assert_eq!(request.to_string(), text);
+    }
+
+    #[test]
+    fn multipart_bodies_keep_their_files() {
+        let mut request = Request::parse("POST https://example.com/upload").unwrap();
+        request.body_format = BodyFormat::Multipart;
+        request.body = vec![
+            ("caption".to_string(), "Ada's cat".to_string()),
+            ("photo".to_string(), "@photos/cat.png".to_string()),
+        ];
+
+        let parsed = Request::parse(&request.to_string()).unwrap();
+        assert_eq!(parsed, request);
+        assert_eq!(
+            parsed.form_preview().as_deref(),
+            Some("caption, photo (photos/cat.png, application/octet-stream)")
+        );
+    }
+
+    #[test]
+    fn unknown_body_formats_are_refused() {
+        assert!(Request::parse("# @body-format yaml\nPOST https://example.com/users").is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    env, fs,
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    str,
+};
+
+pub const DISPLAY_LIMIT: usize = 1024 * 1024;
+
+pub struct Body {
+    pub text: String,
+    pub binary: bool,
+    pub truncated: bool,
+    pub size: u64,
+    pub path: PathBuf,
+}
+
+// Streams the body to a temporary file so large or binary responses never have to fit in memory,
+// keeping only the first DISPLAY_LIMIT bytes around for the Output panel. Binary bodies are shown
+// as a hex dump.
+pub fn spool(reader: &mut impl Read, content_type: Option<&str>) -> io::Result<Body> {
+    let path = env::temp_dir().join(format!("reqcoon-response-{}", process::id()));
+    let mut file = File::create(&path)?;
+
+    let mut preview = Vec::new();
+    let mut size = 0;
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        let take = read.min(DISPLAY_LIMIT - preview.len());
+        preview.extend_from_slice(&buffer[..take]);
+        file.write_all(&buffer[..read])?;
+        size += read as u64;
+    }
+
+    let truncated = size > preview.len() as u64;
+    let text = match str::from_utf8(&preview) {
+        _ if content_type.is_some_and(binary_content_type) || preview.contains(&0) => None,
+        Ok(text) => Some(text.to_string()),
+        // The preview can end partway through a character when it has been cut short.
+        Err(err) if err.error_len().is_none() => {
+            Some(String::from_utf8_lossy(&preview[..err.valid_up_to()]).to_string())
+        }
+        Err(_) => None,
+    };
+
+    Ok(Body {
+        binary: text.is_none(),
+        text: text.unwrap_or_else(|| hex_dump(&preview)),
+        truncated,
+        size,
+        path,
+    })
+}
+
+fn binary_content_type(content_type: &str) -> bool {
+    let mime_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    ["image/", "audio/", "video/", "font/"]
+        .iter()
+        .any(|prefix| mime_type.starts_with(prefix) && !mime_type.ends_with("+xml"))
+        || [
+            "application/octet-stream",
+            "application/pdf",
+            "application/zip",
+            "application/gzip",
+            "application/x-tar",
+            "application/wasm",
+            "application/protobuf",
+            "application/x-protobuf",
+            "application/grpc",
+            "application/msgpack",
+            "application/cbor",
+        ]
+        .contains(&mime_type.as_str())
+}
+
+pub fn hex_dump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let hex = (0..16)
+                .map(|column| {
+                    let byte = chunk
+                        .get(column)
+                        .map_or("  ".to_string(), |byte| format!("{:02x}", byte));
+                    if column == 8 {
+                        format!(" {}", byte)
+                    } else {
+                        byte
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(" ");
+            let ascii = chunk
+                .iter()
+                .map(|byte| {
+                    if byte.is_ascii_graphic() || *byte == b' ' {
+                        *byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+
+            format!("{:08x}  {}  |{}|", index * 16, hex, ascii)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Pipes a spooled body through a viewer command, like pdftotext - - or chafa -, and returns what
+// it prints for the Output panel.
+pub fn view(body_path: &Path, command: &str) -> Result<String, String> {
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+
+    let body = File::open(body_path).map_err(|err| err.to_string())?;
+    let output = shell
+        .arg(command)
+        .stdin(body)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|err| format!("Unable to run {}: {}", command, err))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = match stderr.lines().next() {
+            Some(line) => line.to_string(),
+            None => output.status.to_string(),
+        };
+        return Err(format!("{} failed: {}", command, reason));
+    }
+
+    let shown = &output.stdout[..output.stdout.len().min(DISPLAY_LIMIT)];
+    Ok(String::from_utf8_lossy(shown).to_string())
+}
+
+// Copies a spooled body into the current directory, named after the last segment of the URL and
+// never overwriting an existing file.
+pub fn save(body_path: &Path, url: &str) -> io::Result<PathBuf> {
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty() && !name.contains(':'))
+        .unwrap_or("response");
+
+    let mut path = PathBuf::from(name);
+    let mut copy = 1;
+    while path.exists() {
+        path = PathBuf::from(format!("{}.{}", name, copy));
+        copy += 1;
+    }
+
+    fs::copy(body_path, &path)?;
+    Ok(path)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    assertion::{self, Response},
+    file::RequestFile,
+    folder,
+    request::{Auth, Request},
+};
+
+pub struct Outcome {
+    pub name: String,
+    pub status: Option<u16>,
+    pub time: Duration,
+    // Assertions that didn't hold, or the error that kept the request from being sent.
+    pub failures: Vec<String>,
+    // Set when the request got a 401 and was sent again with a refreshed token.
+    pub retried: bool,
+    // How many times the request was sent, counting retries from the retries setting.
+    pub attempts: usize,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.status.is_some() && self.failures.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        let status = self
+            .status
+            .map_or("---".to_string(), |status| status.to_string());
+        let mut notes = vec![];
+        if self.retried {
+            notes.push("retried after 401".to_string());
+        }
+        if self.attempts > 1 {
+            notes.push(format!("{} attempts", self.attempts));
+        }
+        format!(
+            "{} {} {:>6} ms  {}{}",
+            if self.passed() { "✓" } else { "✗" },
+            status,
+            self.time.as_millis(),
+            self.name,
+            if notes.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", notes.join(", "))
+            }
+        )
+    }
+}
+
+// Runs every request in the file in order. Values captured by earlier requests are available to
+// later ones, and to later files when running a folder.
+pub fn run(file: &RequestFile, captured: &mut Vec<(String, String)>) -> Vec<Outcome> {
+    file.requests
+        .iter()
+        .map(|request| run_request(file, request, captured))
+        .collect()
+}
+
+fn run_request(
+    file: &RequestFile,
+    request: &Request,
+    captured: &mut Vec<(String, String)>,
+) -> Outcome {
+    let mut outcome = Outcome {
+        name: if request.name.is_empty() {
+            request.request_line()
+        } else {
+            request.name.clone()
+        },
+        status: None,
+        time: Duration::ZERO,
+        failures: vec![],
+        retried: false,
+        attempts: 0,
+    };
+
+    let started = Instant::now();
+    // Latency assertions are about the request as it was last sent, not the retry as a whole.
+    let mut attempt_started = started;
+    let mut result = send(file, request, captured, &mut outcome.attempts);
+    if let (Ok((401, _, _)), Some(refresh_with)) = (&result, refresh_request(file, request)) {
+        match refresh(file, &refresh_with, captured) {
+            Ok(()) => {
+                outcome.retried = true;
+                attempt_started = Instant::now();
+                result = send(file, request, captured, &mut outcome.attempts);
+            }
+            Err(err) => outcome.failures.push(err),
+        }
+    }
+    outcome.time = started.elapsed();
+    let latency = attempt_started.elapsed();
+    let (status, headers, body) = match result {
+        Ok(response) => response,
+        Err(err) => {
+            outcome.failures.push(err);
+            return outcome;
+        }
+    };
+    outcome.status = Some(status);
+
+    if (200..300).contains(&status) {
+        match request.capture(&body) {
+            Ok(values) => keep(captured, values),
+            Err(err) => outcome.failures.push(format!("Unable to capture: {}", err)),
+        }
+    }
+
+    // Without assertions, a request passes unless the server returns an error status.
+    if request.assertions.is_empty() && status >= 400 {
+        outcome
+            .failures
+            .push(format!("status {} is an error", status));
+    }
+    let response = Response {
+        status,
+        headers: &headers,
+        body: &body,
+        time: latency,
+        snapshots: &file.snapshots,
+    };
+    for assertion in &request.assertions {
+        if let Err(failure) = assertion::check(assertion, &response) {
+            outcome.failures.push(failure);
+        }
+    }
+
+    outcome
+}
+
+// The request to refresh the token with, if the request uses bearer auth and has one configured.
+pub fn refresh_request(file: &RequestFile, request: &Request) -> Option<String> {
+    if !matches!(request.auth, Auth::Bearer(_)) {
+        return None;
+    }
+    file.settings_for(request)
+        .ok()
+        .and_then(|settings| settings.refresh)
+}
+
+// Sends the named request and keeps what it captures, which is expected to include the token the
+// rejected request's auth refers to.
+pub fn refresh(
+    file: &RequestFile,
+    name: &str,
+    captured: &mut Vec<(String, String)>,
+) -> Result<(), String> {
+    let request = file.request_named(name).ok_or(format!(
+        "Unable to refresh the token: no request named {}",
+        name
+    ))?;
+    let (status, _, body) = send(file, request, captured, &mut 0)
+        .map_err(|err| format!("Unable to refresh the token: {}", err))?;
+    if !(200..300).contains(&status) {
+        return Err(format!(
+            "Unable to refresh the token: {} returned {}",
+            name, status
+        ));
+    }
+    let values = request
+        .capture(&body)
+        .map_err(|err| format!("Unable to refresh the token: {}", err))?;
+    keep(captured, values);
+
+    Ok(())
+}
+
+fn keep(captured: &mut Vec<(String, String)>, values: Vec<(String, String)>) {
+    for (name, value) in values {
+        match captured.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, existing)) => *existing = value,
+            None => captured.push((name, value)),
+        }
+    }
+}
+
+fn send(
+    file: &RequestFile,
+    request: &Request,
+    captured: &mut Vec<(String, String)>,
+    attempts: &mut usize,
+) -> Result<(u16, Vec<(String, String)>, String), String> {
+    let settings = file.settings_for(request)?;
+    let request = file
+        .folder
+        .apply(request)
+        .prepare(&file.variables, captured)?;
+    let all_variables: Vec<(String, String)> =
+        captured.iter().chain(&file.variables).cloned().collect();
+    let (response, sent) = settings.send(request.builder(&settings, &all_variables)?);
+    *attempts += sent;
+    let response = response.map_err(|err| err.to_string())?;
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect();
+    let body = response
+        .text()
+        .map_err(|err| format!("Error reading body: {}", err))?;
+
+    Ok((status, headers, body))
+}
+
+// A folder is run as every .http file under it, in path order.
+pub fn collect(path: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+
+    let mut files = vec![];
+    for entry in entries {
+        if entry.is_dir() {
+            files.extend(collect(&entry)?);
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "http")
+            && !entry.ends_with(folder::FILENAME)
+        {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,128 @@
+use json::JsonValue;
+
+// Bumped whenever the layout of a .json file saved next to a request file changes, so older files
+// can be migrated and newer ones aren't misread.
+pub const VERSION: u32 = 1;
+
+// Wraps what a file holds with the version it was saved in.
+pub fn versioned(key: &str, contents: JsonValue) -> JsonValue {
+    let mut file = JsonValue::new_object();
+    file["version"] = VERSION.into();
+    file[key] = contents;
+    file
+}
+
+// What a versioned file holds, migrated to the current layout. Files from before versioning are
+// version 0.
+pub fn contents(file: &JsonValue, key: &str, filename: &str) -> Result<JsonValue, String> {
+    let version = file["version"].as_u32().unwrap_or(0);
+    if version > VERSION {
+        return Err(format!(
+            "{} was saved by a newer reqcoon (version {}, this one reads {})",
+            filename, version, VERSION
+        ));
+    }
+
+    let mut file =
+        (version..VERSION).fold(file.clone(), |file, version| migrate(version, key, file));
+    let contents = file[key].take();
+    if contents.is_object() {
+        Ok(contents)
+    } else {
+        Err(format!("{} has no {}", filename, key))
+    }
+}
+
+// Brings a file saved in a version up to the next one.
+fn migrate(version: u32, key: &str, file: JsonValue) -> JsonValue {
+    match version {
+        // Version 0 held the contents at the top level.
+        0 => {
+            let mut migrated = JsonValue::new_object();
+            migrated["version"] = 1.into();
+            migrated[key] = file;
+            migrated
+        }
+        _ => file,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::examples;
+
+    #[test]
+    fn versioned_files_hold_their_contents_under_the_key() {
+        let mut contents = JsonValue::new_object();
+        contents["token"] = "abc123".into();
+        let file = versioned("secrets", contents.clone());
+        assert_eq!(file["version"], VERSION);
+        assert_eq!(
+            contents,
+            super::contents(&file, "secrets", "test.json").unwrap()
+        );
+    }
+
+    #[test]
+    fn unversioned_files_are_migrated() {
+        let file = json::parse(r#"{"token": "abc123"}"#).unwrap();
+        let contents = contents(&file, "secrets", "test.json").unwrap();
+        assert_eq!(contents["token"], "abc123");
+    }
+
+    #[test]
+    fn newer_files_are_rejected() {
+        let file = json::parse(r#"{"version": 99, "secrets": {}}"#).unwrap();
+        assert_eq!(
+            contents(&file, "secrets", "test.json"),
+            Err(
+                "test.json was saved by a newer reqcoon (version 99, this one reads 1)".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn files_without_the_contents_are_rejected() {
+        let file = json::parse(r#"{"version": 1, "tokens": {}}"#).unwrap();
+        assert_eq!(
+            contents(&file, "secrets", "test.json"),
+            Err("test.json has no secrets".to_string())
+        );
+    }
+
+    // Examples files were saved without a version before there was one.
+    #[test]
+    fn examples_saved_before_versioning_load() {
+        let filename = env::temp_dir()
+            .join(format!("reqcoon-examples-{}.json", process::id()))
+            .display()
+            .to_string();
+        fs::write(
+            &filename,
+            r#"{
+  "list-users": [
+    {
+      "label": "success",
+      "status": 200,
+      "contentType": "application/json",
+      "body": "[]"
+    }
+  ]
+}"#,
+        )
+        .unwrap();
+        let loaded = examples::load(&filename);
+        fs::remove_file(&filename).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.len(), 1);
+        let (key, examples) = &loaded[0];
+        assert_eq!(key, "list-users");
+        assert_eq!(examples[0].label, "success");
+        assert_eq!(examples[0].status, 200);
+        assert_eq!(examples[0].body, "[]");
+    }
+}
@@ -0,0 +1,569 @@
+use std::fs;
+
+use json::JsonValue;
+use regex::Regex;
+
+// How many violations are listed before the rest are only counted, so they fit in the message bar.
+const LISTED: usize = 3;
+
+// Checks a JSON body against the schema setting, which is either a schema written inline or the
+// path of a file holding one.
+pub fn check(schema: &str, body: &[u8]) -> Result<(), String> {
+    let schema = if schema.trim_start().starts_with('{') {
+        json::parse(schema).map_err(|err| format!("Invalid schema: {}", err))?
+    } else {
+        let text = fs::read_to_string(schema)
+            .map_err(|err| format!("Unable to read the schema {}: {}", schema, err))?;
+        json::parse(&text).map_err(|err| format!("{} is not valid JSON: {}", schema, err))?
+    };
+    let body = std::str::from_utf8(body)
+        .ok()
+        .and_then(|body| json::parse(body).ok())
+        .ok_or("The body isn't JSON, so it can't be checked against the schema")?;
+
+    let violations = violations(&schema, &body);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let mut listed = violations[..violations.len().min(LISTED)].join("; ");
+    if violations.len() > LISTED {
+        listed.push_str(&format!(" and {} more", violations.len() - LISTED));
+    }
+    Err(format!("The body doesn't match the schema: {}", listed))
+}
+
+// Everything about the value the schema doesn't allow, each starting with the JSONPath of the part
+// at fault. The keywords that describe a payload's shape are supported: type, enum, const,
+// properties, required, additionalProperties, items, the length, size and range limits, pattern,
+// allOf, anyOf, oneOf, not, and $ref to a definition in the same schema.
+pub fn violations(schema: &JsonValue, value: &JsonValue) -> Vec<String> {
+    let mut violations = vec![];
+    validate(schema, schema, value, "$", &[], &mut violations);
+    violations
+}
+
+// refs holds the $refs followed since the last step into the value, so a schema that refers back
+// to itself without one, like {"$ref": "#"}, is reported instead of followed forever.
+fn validate(
+    root: &JsonValue,
+    schema: &JsonValue,
+    value: &JsonValue,
+    path: &str,
+    refs: &[String],
+    violations: &mut Vec<String>,
+) {
+    match schema.as_bool() {
+        Some(true) => return,
+        Some(false) => {
+            violations.push(format!("{} isn't allowed", path));
+            return;
+        }
+        None => (),
+    }
+
+    if let Some(reference) = schema["$ref"].as_str() {
+        if refs.iter().any(|followed| followed == reference) {
+            violations.push(format!(
+                "{} refers to {}, which refers back to itself",
+                path, reference
+            ));
+            return;
+        }
+        match resolve(root, reference) {
+            Some(schema) => {
+                let mut refs = refs.to_vec();
+                refs.push(reference.to_string());
+                validate(root, schema, value, path, &refs, violations);
+            }
+            None => violations.push(format!(
+                "{} refers to {}, which isn't in the schema",
+                path, reference
+            )),
+        }
+        return;
+    }
+
+    let types: Vec<&str> = match &schema["type"] {
+        JsonValue::Array(types) => types.iter().filter_map(JsonValue::as_str).collect(),
+        other => other.as_str().into_iter().collect(),
+    };
+    if !types.is_empty() && !types.iter().any(|expected| is_type(value, expected)) {
+        violations.push(format!(
+            "{} should be {}, not {}",
+            path,
+            types.join(" or "),
+            type_of(value)
+        ));
+        return;
+    }
+    if schema["enum"].is_array() && !schema["enum"].members().any(|allowed| allowed == value) {
+        let allowed: Vec<String> = schema["enum"].members().map(JsonValue::dump).collect();
+        violations.push(format!("{} should be one of {}", path, allowed.join(", ")));
+    }
+    if schema.has_key("const") && schema["const"] != *value {
+        violations.push(format!("{} should be {}", path, schema["const"].dump()));
+    }
+
+    match value {
+        JsonValue::Object(object) => {
+            for required in schema["required"].members().filter_map(JsonValue::as_str) {
+                if object.get(required).is_none() {
+                    violations.push(format!("{} is missing {}", path, required));
+                }
+            }
+            for (key, member) in object.iter() {
+                let member_path = member_path(path, key);
+                let property = &schema["properties"][key];
+                if !property.is_null() {
+                    validate(root, property, member, &member_path, &[], violations);
+                } else if !schema["additionalProperties"].is_null() {
+                    let additional = &schema["additionalProperties"];
+                    if additional.as_bool() == Some(false) {
+                        violations.push(format!("{} isn't an allowed property", member_path));
+                    } else {
+                        validate(root, additional, member, &member_path, &[], violations);
+                    }
+                }
+            }
+            limit(
+                schema,
+                "minProperties",
+                "maxProperties",
+                object.len(),
+                "properties",
+                violations,
+                path,
+            );
+        }
+        JsonValue::Array(items) => {
+            if !schema["items"].is_null() {
+                for (index, item) in items.iter().enumerate() {
+                    let item_path = format!("{}[{}]", path, index);
+                    validate(root, &schema["items"], item, &item_path, &[], violations);
+                }
+            }
+            if schema["uniqueItems"].as_bool() == Some(true)
+                && items
+                    .iter()
+                    .enumerate()
+                    .any(|(index, item)| items[..index].contains(item))
+            {
+                violations.push(format!("{} has duplicate items", path));
+            }
+            limit(
+                schema,
+                "minItems",
+                "maxItems",
+                items.len(),
+                "items",
+                violations,
+                path,
+            );
+        }
+        _ => (),
+    }
+
+    if let Some(text) = value.as_str() {
+        limit(
+            schema,
+            "minLength",
+            "maxLength",
+            text.chars().count(),
+            "characters",
+            violations,
+            path,
+        );
+        if let Some(pattern) = schema["pattern"].as_str() {
+            match Regex::new(pattern) {
+                Ok(regex) if !regex.is_match(text) => {
+                    violations.push(format!("{} should match {}", path, pattern))
+                }
+                Ok(_) => (),
+                Err(_) => violations.push(format!("{} has an invalid pattern: {}", path, pattern)),
+            }
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        let bound = |keyword: &str| schema[keyword].as_f64();
+        let broken = [
+            (
+                bound("minimum").filter(|minimum| number < *minimum),
+                "at least",
+            ),
+            (
+                bound("maximum").filter(|maximum| number > *maximum),
+                "at most",
+            ),
+            (
+                bound("exclusiveMinimum").filter(|minimum| number <= *minimum),
+                "more than",
+            ),
+            (
+                bound("exclusiveMaximum").filter(|maximum| number >= *maximum),
+                "less than",
+            ),
+            (
+                bound("multipleOf")
+                    .filter(|divisor| *divisor > 0.0 && (number / divisor).fract() != 0.0),
+                "a multiple of",
+            ),
+        ];
+        for (bound, relation) in broken {
+            if let Some(bound) = bound {
+                violations.push(format!("{} should be {} {}", path, relation, bound));
+            }
+        }
+    }
+
+    let passes = |schema: &JsonValue| {
+        let mut violations = vec![];
+        validate(root, schema, value, path, refs, &mut violations);
+        violations.is_empty()
+    };
+    for schema in schema["allOf"].members() {
+        validate(root, schema, value, path, refs, violations);
+    }
+    if schema["anyOf"].is_array() && !schema["anyOf"].members().any(passes) {
+        violations.push(format!("{} doesn't match any of the allowed schemas", path));
+    }
+    if schema["oneOf"].is_array()
+        && schema["oneOf"]
+            .members()
+            .filter(|&schema| passes(schema))
+            .count()
+            != 1
+    {
+        violations.push(format!(
+            "{} should match exactly one of the allowed schemas",
+            path
+        ));
+    }
+    if !schema["not"].is_null() && passes(&schema["not"]) {
+        violations.push(format!("{} matches a schema it shouldn't", path));
+    }
+}
+
+// A $ref like #/$defs/user or #/definitions/user, as a JSON Pointer into the same schema.
+fn resolve<'a>(root: &'a JsonValue, reference: &str) -> Option<&'a JsonValue> {
+    let pointer = reference.strip_prefix('#')?;
+    let mut schema = root;
+    for token in pointer.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        schema = match schema {
+            JsonValue::Array(items) => items.get(token.parse::<usize>().ok()?)?,
+            _ if schema.has_key(&token) => &schema[token.as_str()],
+            _ => return None,
+        };
+    }
+    Some(schema)
+}
+
+fn limit(
+    schema: &JsonValue,
+    min: &str,
+    max: &str,
+    count: usize,
+    unit: &str,
+    violations: &mut Vec<String>,
+    path: &str,
+) {
+    if let Some(min) = schema[min].as_usize().filter(|min| count < *min) {
+        violations.push(format!("{} should have at least {} {}", path, min, unit));
+    }
+    if let Some(max) = schema[max].as_usize().filter(|max| count > *max) {
+        violations.push(format!("{} should have at most {} {}", path, max, unit));
+    }
+}
+
+fn is_type(value: &JsonValue, expected: &str) -> bool {
+    match expected {
+        "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_of(value) == other,
+    }
+}
+
+fn type_of(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Boolean(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::Short(_) | JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+fn member_path(path: &str, key: &str) -> String {
+    let identifier = key
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    if identifier {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}['{}']", path, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_value(schema: &str, value: &str) -> Vec<String> {
+        violations(&json::parse(schema).unwrap(), &json::parse(value).unwrap())
+    }
+
+    #[test]
+    fn type_and_type_lists() {
+        assert_eq!(
+            check_value(r#"{"type": "string"}"#, "1"),
+            ["$ should be string, not number"]
+        );
+        assert!(check_value(r#"{"type": ["string", "null"]}"#, "null").is_empty());
+        assert_eq!(check_value("false", "1"), ["$ isn't allowed"]);
+        assert!(check_value("true", "1").is_empty());
+    }
+
+    #[test]
+    fn integer_is_a_number_without_a_fraction() {
+        assert!(check_value(r#"{"type": "integer"}"#, "2").is_empty());
+        assert_eq!(
+            check_value(r#"{"type": "integer"}"#, "1.5"),
+            ["$ should be integer, not number"]
+        );
+        assert!(check_value(r#"{"type": "number"}"#, "1.5").is_empty());
+        assert!(check_value(r#"{"type": "number"}"#, "2").is_empty());
+    }
+
+    #[test]
+    fn enum_and_const() {
+        assert_eq!(
+            check_value(r#"{"enum": ["a", "b"]}"#, r#""c""#),
+            [r#"$ should be one of "a", "b""#]
+        );
+        assert!(check_value(r#"{"enum": ["a", "b"]}"#, r#""b""#).is_empty());
+        assert_eq!(check_value(r#"{"const": 3}"#, "4"), ["$ should be 3"]);
+    }
+
+    #[test]
+    fn properties_and_required() {
+        assert_eq!(
+            check_value(
+                r#"{"properties": {"age": {"type": "integer"}}, "required": ["name"]}"#,
+                r#"{"age": "x"}"#
+            ),
+            ["$ is missing name", "$.age should be integer, not string"]
+        );
+        assert_eq!(
+            check_value(
+                r#"{"properties": {"the key": {"type": "string"}}}"#,
+                r#"{"the key": 1}"#
+            ),
+            ["$['the key'] should be string, not number"]
+        );
+    }
+
+    #[test]
+    fn additional_properties() {
+        assert_eq!(
+            check_value(
+                r#"{"properties": {"a": true}, "additionalProperties": false}"#,
+                r#"{"a": 1, "b": 2}"#
+            ),
+            ["$.b isn't an allowed property"]
+        );
+        assert_eq!(
+            check_value(
+                r#"{"additionalProperties": {"type": "number"}}"#,
+                r#"{"x": "y"}"#
+            ),
+            ["$.x should be number, not string"]
+        );
+        assert!(check_value(r#"{"properties": {"a": true}}"#, r#"{"b": 2}"#).is_empty());
+    }
+
+    #[test]
+    fn items_and_array_limits() {
+        assert_eq!(
+            check_value(r#"{"items": {"type": "string"}}"#, r#"["a", 1]"#),
+            ["$[1] should be string, not number"]
+        );
+        assert_eq!(
+            check_value(r#"{"uniqueItems": true}"#, "[1, 1]"),
+            ["$ has duplicate items"]
+        );
+        assert_eq!(
+            check_value(r#"{"minItems": 2, "maxItems": 3}"#, "[1]"),
+            ["$ should have at least 2 items"]
+        );
+        assert_eq!(
+            check_value(r#"{"maxItems": 1}"#, "[1, 2]"),
+            ["$ should have at most 1 items"]
+        );
+    }
+
+    #[test]
+    fn property_counts() {
+        assert_eq!(
+            check_value(r#"{"minProperties": 1}"#, "{}"),
+            ["$ should have at least 1 properties"]
+        );
+        assert_eq!(
+            check_value(r#"{"maxProperties": 1}"#, r#"{"a": 1, "b": 2}"#),
+            ["$ should have at most 1 properties"]
+        );
+    }
+
+    #[test]
+    fn string_length_and_pattern() {
+        assert_eq!(
+            check_value(r#"{"maxLength": 3}"#, r#""abcd""#),
+            ["$ should have at most 3 characters"]
+        );
+        assert_eq!(
+            check_value(r#"{"minLength": 2}"#, r#""é""#),
+            ["$ should have at least 2 characters"]
+        );
+        assert_eq!(
+            check_value(r#"{"pattern": "^\\d+$"}"#, r#""12a""#),
+            [r"$ should match ^\d+$"]
+        );
+        assert!(check_value(r#"{"pattern": "^\\d+$"}"#, r#""12""#).is_empty());
+        assert_eq!(
+            check_value(r#"{"pattern": "("}"#, r#""a""#),
+            ["$ has an invalid pattern: ("]
+        );
+    }
+
+    #[test]
+    fn number_bounds() {
+        assert_eq!(
+            check_value(r#"{"minimum": 5}"#, "3"),
+            ["$ should be at least 5"]
+        );
+        assert_eq!(
+            check_value(r#"{"maximum": 5}"#, "6"),
+            ["$ should be at most 5"]
+        );
+        assert_eq!(
+            check_value(r#"{"exclusiveMinimum": 5}"#, "5"),
+            ["$ should be more than 5"]
+        );
+        assert_eq!(
+            check_value(r#"{"exclusiveMaximum": 10}"#, "10"),
+            ["$ should be less than 10"]
+        );
+        assert_eq!(
+            check_value(r#"{"multipleOf": 3}"#, "7"),
+            ["$ should be a multiple of 3"]
+        );
+        assert!(check_value(r#"{"minimum": 5, "multipleOf": 3}"#, "6").is_empty());
+    }
+
+    #[test]
+    fn combinators() {
+        assert_eq!(
+            check_value(r#"{"allOf": [{"type": "number"}, {"minimum": 2}]}"#, "1"),
+            ["$ should be at least 2"]
+        );
+        assert_eq!(
+            check_value(r#"{"anyOf": [{"type": "string"}, {"type": "null"}]}"#, "1"),
+            ["$ doesn't match any of the allowed schemas"]
+        );
+        assert!(check_value(
+            r#"{"anyOf": [{"type": "string"}, {"type": "null"}]}"#,
+            "null"
+        )
+        .is_empty());
+        assert_eq!(
+            check_value(r#"{"not": {"type": "null"}}"#, "null"),
+            ["$ matches a schema it shouldn't"]
+        );
+    }
+
+    #[test]
+    fn one_of_fails_when_more_than_one_matches() {
+        let schema = r#"{"oneOf": [{"type": "number"}, {"minimum": 0}]}"#;
+        assert_eq!(
+            check_value(schema, "5"),
+            ["$ should match exactly one of the allowed schemas"]
+        );
+        assert!(check_value(schema, "-1").is_empty());
+        assert_eq!(
+            check_value(r#"{"oneOf": [{"type": "string"}, {"type": "null"}]}"#, "1"),
+            ["$ should match exactly one of the allowed schemas"]
+        );
+    }
+
+    #[test]
+    fn refs_unescape_their_pointer() {
+        let schema = r##"{
+            "$defs": {"a/b": {"type": "string"}, "c~d": {"type": "number"}},
+            "properties": {
+                "x": {"$ref": "#/$defs/a~1b"},
+                "y": {"$ref": "#/$defs/c~0d"},
+                "z": {"$ref": "#/$defs/missing"}
+            }
+        }"##;
+        assert_eq!(
+            check_value(schema, r#"{"x": 1, "y": "z", "z": 1}"#),
+            [
+                "$.x should be string, not number",
+                "$.y should be number, not string",
+                "$.z refers to #/$defs/missing, which isn't in the schema",
+            ]
+        );
+    }
+
+    #[test]
+    fn refs_that_loop_are_reported() {
+        assert_eq!(
+            check_value(r##"{"$ref": "#"}"##, "1"),
+            ["$ refers to #, which refers back to itself"]
+        );
+        let mutual = r##"{
+            "$defs": {"a": {"$ref": "#/$defs/b"}, "b": {"$ref": "#/$defs/a"}},
+            "$ref": "#/$defs/a"
+        }"##;
+        assert_eq!(
+            check_value(mutual, "1"),
+            ["$ refers to #/$defs/a, which refers back to itself"]
+        );
+        assert_eq!(
+            check_value(r##"{"anyOf": [{"$ref": "#"}, {"type": "string"}]}"##, "1"),
+            ["$ doesn't match any of the allowed schemas"]
+        );
+    }
+
+    #[test]
+    fn refs_can_recurse_through_the_value() {
+        let tree = r##"{"type": "object", "properties": {"child": {"$ref": "#"}}}"##;
+        assert!(check_value(tree, r#"{"child": {"child": {}}}"#).is_empty());
+        assert_eq!(
+            check_value(tree, r#"{"child": {"child": 1}}"#),
+            ["$.child.child should be object, not number"]
+        );
+    }
+
+    #[test]
+    fn check_lists_the_first_violations() {
+        assert_eq!(
+            check(r#"{"required": ["a", "b", "c", "d"]}"#, b"{}"),
+            Err(
+                "The body doesn't match the schema: $ is missing a; $ is missing b; \
+                 $ is missing c and 1 more"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(r#"{"type": "object"}"#, b"not json"),
+            Err("The body isn't JSON, so it can't be checked against the schema".to_string())
+        );
+        assert_eq!(check(r#"{"type": "object"}"#, b"{}"), Ok(()));
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use rhai::{Dynamic, Engine, Map, Scope};
+use sha2::{Digest, Sha256};
+
+use crate::request::Request;
+
+// Scripts see the file's variables, shadowed by the values captured this session, and the outgoing
+// request as the `variables` and `request` maps. What they set in `variables` is kept with the
+// captured values for later requests rather than saved to the file, while their changes to
+// `request` only apply to what gets sent.
+pub fn run(
+    script: &str,
+    request: &mut Request,
+    variables: &[(String, String)],
+    captured: &mut Vec<(String, String)>,
+) -> Result<(), String> {
+    let mut engine = Engine::new();
+    engine
+        .register_fn("timestamp", timestamp)
+        .register_fn("timestamp_ms", timestamp_ms)
+        .register_fn("nonce", nonce)
+        .register_fn("sha256", sha256)
+        .register_fn("hmac_sha256", hmac_sha256)
+        .register_fn("base64", base64_encode);
+
+    let mut visible = variables.to_vec();
+    for (name, value) in captured.iter() {
+        set(&mut visible, name, value);
+    }
+    let mut scope = Scope::new();
+    scope.push("variables", to_map(&visible));
+    scope.push("request", request_map(request));
+
+    engine
+        .run_with_scope(&mut scope, script)
+        .map_err(|err| format!("Script error: {}", err))?;
+
+    if let Some(map) = scope.get_value::<Map>("variables") {
+        for (name, value) in from_map(map) {
+            if !visible.contains(&(name.clone(), value.clone())) {
+                set(captured, &name, &value);
+            }
+        }
+    }
+    if let Some(map) = scope.get_value::<Map>("request") {
+        apply_request_map(map, request)?;
+    }
+
+    Ok(())
+}
+
+fn set(pairs: &mut Vec<(String, String)>, name: &str, value: &str) {
+    match pairs.iter_mut().find(|(existing, _)| existing == name) {
+        Some((_, existing)) => *existing = value.to_string(),
+        None => pairs.push((name.to_string(), value.to_string())),
+    }
+}
+
+fn request_map(request: &Request) -> Map {
+    let mut map = Map::new();
+    map.insert("method".into(), request.method.as_str().into());
+    map.insert("url".into(), request.url.as_str().into());
+    map.insert(
+        "headers".into(),
+        Dynamic::from_map(to_map(&request.headers)),
+    );
+    map.insert("body".into(), Dynamic::from_map(to_map(&request.body)));
+    map
+}
+
+fn apply_request_map(map: Map, request: &mut Request) -> Result<(), String> {
+    if let Some(method) = map.get("method") {
+        request.method = Method::from_bytes(method.to_string().to_uppercase().as_bytes())
+            .map_err(|err| format!("Script set an invalid method: {}", err))?;
+    }
+    if let Some(url) = map.get("url") {
+        request.url = url.to_string();
+    }
+    if let Some(headers) = map
+        .get("headers")
+        .and_then(|headers| headers.clone().try_cast())
+    {
+        request.headers = from_map(headers);
+    }
+    if let Some(body) = map.get("body").and_then(|body| body.clone().try_cast()) {
+        request.body = from_map(body);
+    }
+
+    Ok(())
+}
+
+fn to_map(pairs: &[(String, String)]) -> Map {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.as_str().into(), value.as_str().into()))
+        .collect()
+}
+
+fn from_map(map: Map) -> Vec<(String, String)> {
+    map.into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+fn timestamp_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as i64)
+}
+
+fn nonce() -> String {
+    hex(&rand::random::<[u8; 16]>())
+}
+
+fn sha256(text: &str) -> String {
+    hex(&Sha256::digest(text.as_bytes()))
+}
+
+fn hmac_sha256(key: &str, message: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(message.as_bytes());
+    hex(&mac.finalize().into_bytes())
+}
+
+fn base64_encode(text: &str) -> String {
+    base64::engine::general_purpose::STANDARD.encode(text)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
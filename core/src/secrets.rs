@@ -0,0 +1,190 @@
+use std::{
+    env,
+    error::Error,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+};
+
+use json::JsonValue;
+
+use crate::save_file;
+
+// Where a secret variable's value is kept, so it never has to be written in the request file. The
+// secrets file is plain JSON readable only by the user; it isn't encrypted.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    // The secrets file saved next to the request file, which is left out of version control.
+    File,
+    Env(String),
+    Keychain,
+}
+
+// A variable declared with `# @secret name`, `# @secret name env:VARIABLE` or
+// `# @secret name keychain`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Secret {
+    pub name: String,
+    pub source: Source,
+}
+
+// The values of secrets kept in the secrets file, by name.
+pub type Values = Vec<(String, String)>;
+
+impl Secret {
+    pub fn parse(declaration: &str) -> Result<Self, String> {
+        let mut words = declaration.split_whitespace();
+        let name = words.next().ok_or("A secret needs a name")?.to_string();
+        let source = match words.next() {
+            None => Source::File,
+            Some("keychain") => Source::Keychain,
+            Some(word) => match word.strip_prefix("env:") {
+                Some(variable) if !variable.is_empty() => Source::Env(variable.to_string()),
+                _ => {
+                    return Err(format!(
+                        "Unknown place to keep the secret {}: {}",
+                        name, word
+                    ))
+                }
+            },
+        };
+
+        Ok(Secret { name, source })
+    }
+
+    // The secret's value, or None when it hasn't been set, in which case references to it are
+    // left as they are.
+    pub fn value(&self, stored: &Values, request_filename: &str) -> Option<String> {
+        match &self.source {
+            Source::File => stored
+                .iter()
+                .find(|(name, _)| *name == self.name)
+                .map(|(_, value)| value.clone()),
+            Source::Env(variable) => env::var(variable).ok(),
+            Source::Keychain => keychain::get(request_filename, &self.name),
+        }
+    }
+
+    pub fn store(
+        &self,
+        value: &str,
+        stored: &mut Values,
+        request_filename: &str,
+    ) -> Result<(), String> {
+        match &self.source {
+            Source::File => {
+                match stored.iter_mut().find(|(name, _)| *name == self.name) {
+                    Some((_, existing)) => *existing = value.to_string(),
+                    None => stored.push((self.name.clone(), value.to_string())),
+                }
+                Ok(())
+            }
+            Source::Env(variable) => Err(format!(
+                "{} comes from ${}, which has to be set outside reqcoon",
+                self.name, variable
+            )),
+            Source::Keychain => keychain::set(request_filename, &self.name, value),
+        }
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.source {
+            Source::File => write!(f, "{}", self.name),
+            Source::Env(variable) => write!(f, "{} env:{}", self.name, variable),
+            Source::Keychain => write!(f, "{} keychain", self.name),
+        }
+    }
+}
+
+// The value of every secret that has one.
+pub fn resolve(secrets: &[Secret], stored: &Values, request_filename: &str) -> Values {
+    secrets
+        .iter()
+        .filter_map(|secret| {
+            let value = secret.value(stored, request_filename)?;
+            Some((secret.name.clone(), value))
+        })
+        .collect()
+}
+
+pub fn filename(request_filename: &str) -> String {
+    format!("{}.secrets.json", request_filename)
+}
+
+pub fn load(filename: &str) -> Result<Values, Box<dyn Error>> {
+    let file = json::parse(&fs::read_to_string(filename)?)
+        .map_err(|err| format!("{} is not valid JSON: {}", filename, err))?;
+
+    let values = save_file::contents(&file, "secrets", filename)?
+        .entries()
+        .map(|(name, value)| {
+            let value = value
+                .as_str()
+                .ok_or(format!("{}: the secret {} isn't a string", filename, name))?;
+            Ok((name.to_string(), value.to_string()))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(values)
+}
+
+pub fn save(filename: &str, values: &Values) -> io::Result<()> {
+    let mut file_json = JsonValue::new_object();
+    for (name, value) in values {
+        file_json[name.as_str()] = value.clone().into();
+    }
+
+    // Only the user can read the file on systems with permissions.
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(filename)?;
+    file.write_all(json::stringify_pretty(save_file::versioned("secrets", file_json), 2).as_bytes())
+}
+
+// Keychain entries are named after the request file and the secret, so files with a secret of the
+// same name don't share it.
+#[cfg(feature = "keychain")]
+mod keychain {
+    use std::path::Path;
+
+    use keyring::Entry;
+
+    fn entry(request_filename: &str, name: &str) -> keyring::Result<Entry> {
+        let path = Path::new(request_filename)
+            .canonicalize()
+            .map_or(request_filename.to_string(), |path| {
+                path.display().to_string()
+            });
+        Entry::new("reqcoon", &format!("{}:{}", path, name))
+    }
+
+    pub fn get(request_filename: &str, name: &str) -> Option<String> {
+        entry(request_filename, name)
+            .and_then(|entry| entry.get_password())
+            .ok()
+    }
+
+    pub fn set(request_filename: &str, name: &str, value: &str) -> Result<(), String> {
+        entry(request_filename, name)
+            .and_then(|entry| entry.set_password(value))
+            .map_err(|err| format!("Unable to save {} to the keychain: {}", name, err))
+    }
+}
+
+#[cfg(not(feature = "keychain"))]
+mod keychain {
+    pub fn get(_request_filename: &str, _name: &str) -> Option<String> {
+        None
+    }
+
+    pub fn set(_request_filename: &str, name: &str, _value: &str) -> Result<(), String> {
+        Err(format!(
+            "{} is kept in the keychain, but reqcoon was built without the keychain feature",
+            name
+        ))
+    }
+}
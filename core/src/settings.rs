@@ -0,0 +1,410 @@
+use std::{error::Error, fs, path::Path, thread, time::Duration};
+
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    header::{HeaderMap, HeaderName, HeaderValue},
+    redirect::Policy,
+    tls, Certificate, Identity, NoProxy, Proxy, Version,
+};
+
+use crate::{cookies, oauth};
+
+pub const KEYS: [&str; 25] = [
+    "timeout",
+    "http-version",
+    "verify-tls",
+    "ca-cert",
+    "min-tls",
+    "client-cert",
+    "client-key",
+    "client-cert-password",
+    "proxy",
+    "proxy-auth",
+    "no-proxy",
+    "redirects",
+    "retries",
+    "retry-backoff",
+    "retry-on",
+    "refresh",
+    "oauth-client-id",
+    "oauth-client-secret",
+    "oauth-auth-url",
+    "oauth-device-url",
+    "oauth-token-url",
+    "oauth-redirect-uri",
+    "oauth-scope",
+    "schema",
+    "audit-log",
+];
+
+#[derive(Clone)]
+pub struct Settings {
+    pub timeout: Option<Duration>,
+    pub http_version: Option<Version>,
+    pub verify_tls: bool,
+    // A PEM file of extra CA certificates to trust, for servers with self-signed certificates.
+    pub ca_cert: Option<String>,
+    pub min_tls_version: Option<tls::Version>,
+    // A certificate for mutual TLS, either a PEM certificate with its PEM key or a PKCS#12 archive.
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    pub client_cert_password: Option<String>,
+    pub proxy: Option<String>,
+    pub proxy_auth: Option<(String, String)>,
+    // Comma separated hosts, domains and IP ranges to connect to directly, as in NO_PROXY.
+    pub no_proxy: Option<String>,
+    pub redirect_limit: Option<usize>,
+    // How many more times to send a request whose first attempt is worth retrying, waiting
+    // retry_backoff before the first retry and twice as long before each one after it.
+    pub retries: usize,
+    pub retry_backoff: Duration,
+    // What's worth retrying: status codes like 503, classes of them like 5xx, and connect and
+    // timeout for requests that got no response.
+    pub retry_on: Vec<String>,
+    // The request to send for a fresh bearer token when a request gets a 401.
+    pub refresh: Option<String>,
+    pub oauth: oauth::Config,
+    pub default_headers: Vec<(String, String)>,
+    // Content types, like application/pdf or image/*, and the commands that show them as text.
+    pub viewers: Vec<(String, String)>,
+    // A .proto file describing the services of gRPC servers without reflection.
+    pub grpc_proto: Option<String>,
+    // A JSON Schema for the body, inline or in a file, that it's checked against before sending.
+    pub schema: Option<String>,
+    // A file every request sent and its response are logged to, with secrets left out.
+    pub audit_log: Option<String>,
+}
+
+const DEFAULT_RETRY_ON: [&str; 3] = ["5xx", "connect", "timeout"];
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            timeout: None,
+            http_version: None,
+            verify_tls: true,
+            ca_cert: None,
+            min_tls_version: None,
+            client_cert: None,
+            client_key: None,
+            client_cert_password: None,
+            proxy: None,
+            proxy_auth: None,
+            no_proxy: None,
+            redirect_limit: None,
+            retries: 0,
+            retry_backoff: Duration::from_secs(1),
+            retry_on: DEFAULT_RETRY_ON.iter().map(|on| on.to_string()).collect(),
+            refresh: None,
+            oauth: oauth::Config::default(),
+            default_headers: vec![],
+            viewers: vec![],
+            grpc_proto: None,
+            schema: None,
+            audit_log: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn overridden<'a>(
+        mut self,
+        rows: impl Iterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, String> {
+        for (key, value) in rows {
+            if key.is_empty() || value.is_empty() {
+                continue;
+            }
+
+            match key {
+                "timeout" => {
+                    self.timeout =
+                        Some(parse_duration(value).ok_or(format!("Invalid timeout: {}", value))?)
+                }
+                "http-version" => self.http_version = Some(parse_http_version(value)?),
+                "verify-tls" => {
+                    self.verify_tls =
+                        parse_bool(value).ok_or(format!("Invalid verify-tls: {}", value))?
+                }
+                "ca-cert" => self.ca_cert = Some(value.to_string()),
+                "min-tls" => {
+                    self.min_tls_version = Some(
+                        parse_tls_version(value).ok_or(format!("Invalid min-tls: {}", value))?,
+                    )
+                }
+                "client-cert" => self.client_cert = Some(value.to_string()),
+                "client-key" => self.client_key = Some(value.to_string()),
+                "client-cert-password" => self.client_cert_password = Some(value.to_string()),
+                "proxy" => self.proxy = Some(value.to_string()),
+                "proxy-auth" => {
+                    let (username, password) = value
+                        .split_once(':')
+                        .ok_or(format!("Invalid proxy-auth: {}", value))?;
+                    self.proxy_auth = Some((username.to_string(), password.to_string()));
+                }
+                "no-proxy" => self.no_proxy = Some(value.to_string()),
+                "redirects" => {
+                    self.redirect_limit = match value {
+                        "none" => Some(0),
+                        _ => Some(
+                            value
+                                .parse()
+                                .map_err(|_| format!("Invalid redirects: {}", value))?,
+                        ),
+                    }
+                }
+                "retries" => {
+                    self.retries = value
+                        .parse()
+                        .map_err(|_| format!("Invalid retries: {}", value))?
+                }
+                "retry-backoff" => {
+                    self.retry_backoff =
+                        parse_duration(value).ok_or(format!("Invalid retry-backoff: {}", value))?
+                }
+                "retry-on" => {
+                    self.retry_on = value
+                        .split(',')
+                        .map(|on| on.trim().to_lowercase())
+                        .filter(|on| !on.is_empty())
+                        .collect();
+                    if let Some(on) = self.retry_on.iter().find(|on| !valid_retry_on(on)) {
+                        return Err(format!("Invalid retry-on: {}", on));
+                    }
+                }
+                "refresh" => self.refresh = Some(value.to_string()),
+                "oauth-client-id" => self.oauth.client_id = Some(value.to_string()),
+                "oauth-client-secret" => self.oauth.client_secret = Some(value.to_string()),
+                "oauth-auth-url" => self.oauth.auth_url = Some(value.to_string()),
+                "oauth-device-url" => self.oauth.device_url = Some(value.to_string()),
+                "oauth-token-url" => self.oauth.token_url = Some(value.to_string()),
+                "oauth-redirect-uri" => self.oauth.redirect_uri = Some(value.to_string()),
+                "oauth-scope" => self.oauth.scope = Some(value.to_string()),
+                "header" => {
+                    let (name, header_value) = value
+                        .split_once(':')
+                        .ok_or(format!("Invalid header: {}", value))?;
+                    // A later layer's header, like an environment's, replaces one of the same name.
+                    let name = name.trim();
+                    self.default_headers
+                        .retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+                    self.default_headers
+                        .push((name.to_string(), header_value.trim().to_string()));
+                }
+                "viewer" => {
+                    let (content_type, command) = value
+                        .split_once(char::is_whitespace)
+                        .ok_or(format!("Invalid viewer: {}", value))?;
+                    self.viewers
+                        .push((content_type.to_lowercase(), command.trim().to_string()));
+                }
+                "grpc-proto" => self.grpc_proto = Some(value.to_string()),
+                "schema" => self.schema = Some(value.to_string()),
+                "audit-log" => self.audit_log = Some(value.to_string()),
+                _ => return Err(format!("Unknown setting: {}", key)),
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn client(&self) -> Result<Client, Box<dyn Error>> {
+        let mut builder = Client::builder()
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .cookie_provider(cookies::jar());
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        // HTTP/3 is asked for on the request instead, see Request::builder.
+        builder = match self.http_version {
+            Some(Version::HTTP_11) => builder.http1_only(),
+            Some(Version::HTTP_2) => builder.http2_prior_knowledge(),
+            _ => builder,
+        };
+        if let Some(path) = &self.ca_cert {
+            let pem = fs::read_to_string(path)
+                .map_err(|err| format!("Unable to read ca-cert {}: {}", path, err))?;
+            for certificate in pem_certificates(&pem) {
+                builder =
+                    builder.add_root_certificate(Certificate::from_pem(certificate.as_bytes())?);
+            }
+        }
+        if let Some(version) = self.min_tls_version {
+            builder = builder.min_tls_version(version);
+        }
+        if let Some(path) = &self.client_cert {
+            builder = builder.identity(self.identity(path)?);
+        }
+        builder = match self.proxy.as_deref() {
+            None => builder,
+            Some("off") | Some("none") => builder.no_proxy(),
+            // http://, https:// and socks5:// proxies are all supported.
+            Some(url) => {
+                let mut proxy = Proxy::all(url)?;
+                if let Some((username, password)) = &self.proxy_auth {
+                    proxy = proxy.basic_auth(username, password);
+                }
+                if let Some(no_proxy) = &self.no_proxy {
+                    proxy = proxy.no_proxy(NoProxy::from_string(no_proxy));
+                }
+                builder.proxy(proxy)
+            }
+        };
+        builder = match self.redirect_limit {
+            None => builder,
+            Some(0) => builder.redirect(Policy::none()),
+            Some(limit) => builder.redirect(Policy::limited(limit)),
+        };
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.default_headers {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        Ok(builder.default_headers(headers).build()?)
+    }
+
+    // Whether an attempt at sending a request should be followed by another.
+    pub fn retryable(&self, result: &reqwest::Result<Response>) -> bool {
+        match result {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                self.retry_on.iter().any(|on| match on.strip_suffix("xx") {
+                    Some(class) => class == (status / 100).to_string(),
+                    None => *on == status.to_string(),
+                })
+            }
+            Err(err) => self.retry_on.iter().any(|on| {
+                (on == "connect" && err.is_connect()) || (on == "timeout" && err.is_timeout())
+            }),
+        }
+    }
+
+    // How long to wait before the given retry, counting from 1.
+    pub fn backoff(&self, retry: usize) -> Duration {
+        self.retry_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1) as u32))
+    }
+
+    // Sends the request, retrying it as the retry settings say. Returns the last attempt and how
+    // many attempts there were.
+    pub fn send(&self, mut request_builder: RequestBuilder) -> (reqwest::Result<Response>, usize) {
+        let mut attempts = 1;
+        loop {
+            let retry = request_builder
+                .try_clone()
+                .filter(|_| attempts <= self.retries);
+            let result = request_builder.send();
+            match retry {
+                Some(retry) if self.retryable(&result) => {
+                    thread::sleep(self.backoff(attempts));
+                    request_builder = retry;
+                    attempts += 1;
+                }
+                _ => return (result, attempts),
+            }
+        }
+    }
+
+    // The command to show a response of the content type with. Later viewers win, so an
+    // environment can replace a workspace viewer.
+    pub fn viewer(&self, content_type: &str) -> Option<&str> {
+        let mime_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        self.viewers
+            .iter()
+            .rev()
+            .find(|(pattern, _)| match pattern.strip_suffix("/*") {
+                Some(prefix) => mime_type.starts_with(&format!("{}/", prefix)),
+                None => *pattern == mime_type,
+            })
+            .map(|(_, command)| command.as_str())
+    }
+
+    fn identity(&self, path: &str) -> Result<Identity, Box<dyn Error>> {
+        let read =
+            |path: &str| fs::read(path).map_err(|err| format!("Unable to read {}: {}", path, err));
+        let pkcs12 = Path::new(path).extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("p12") || extension.eq_ignore_ascii_case("pfx")
+        });
+
+        if pkcs12 {
+            let password = self.client_cert_password.as_deref().unwrap_or_default();
+            Ok(Identity::from_pkcs12_der(&read(path)?, password)?)
+        } else {
+            let key = self
+                .client_key
+                .as_deref()
+                .ok_or("Set client-key to the PEM key for client-cert")?;
+            Ok(Identity::from_pkcs8_pem(&read(path)?, &read(key)?)?)
+        }
+    }
+}
+
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    if let Some(millis) = value.strip_suffix("ms") {
+        millis.trim().parse().ok().map(Duration::from_millis)
+    } else {
+        value
+            .strip_suffix('s')
+            .unwrap_or(value)
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(Duration::from_secs_f64)
+    }
+}
+
+fn valid_retry_on(on: &str) -> bool {
+    match on.strip_suffix("xx") {
+        Some(class) => matches!(class, "1" | "2" | "3" | "4" | "5"),
+        None => on == "connect" || on == "timeout" || on.parse::<u16>().is_ok(),
+    }
+}
+
+fn parse_http_version(value: &str) -> Result<Version, String> {
+    match value.to_uppercase().trim_start_matches("HTTP/") {
+        "1.1" => Ok(Version::HTTP_11),
+        "2" | "2.0" => Ok(Version::HTTP_2),
+        "3" | "3.0" if cfg!(feature = "http3") => Ok(Version::HTTP_3),
+        "3" | "3.0" => Err("HTTP/3 needs reqcoon built with the http3 feature".to_string()),
+        _ => Err(format!("Invalid http-version: {}", value)),
+    }
+}
+
+fn parse_tls_version(value: &str) -> Option<tls::Version> {
+    match value.trim_start_matches("TLS").trim() {
+        "1.0" => Some(tls::Version::TLS_1_0),
+        "1.1" => Some(tls::Version::TLS_1_1),
+        "1.2" => Some(tls::Version::TLS_1_2),
+        "1.3" => Some(tls::Version::TLS_1_3),
+        _ => None,
+    }
+}
+
+// A CA bundle holds any number of certificates, which have to be added one at a time.
+fn pem_certificates(pem: &str) -> Vec<String> {
+    const END: &str = "-----END CERTIFICATE-----";
+    pem.split_inclusive(END)
+        .filter(|block| block.contains("-----BEGIN CERTIFICATE-----"))
+        .map(|block| block.trim().to_string())
+        .collect()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, Write},
+};
+
+use json::JsonValue;
+
+use crate::{
+    diff::{self, Change},
+    json_path, save_file,
+};
+
+// How many of the lines that changed a failed snapshot assertion lists.
+const SHOWN_CHANGES: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub status: u16,
+    pub body: String,
+}
+
+// Responses saved by name with :snapshot, for later responses to be checked against with
+// `@assert snapshot <name>`.
+pub type Snapshots = Vec<(String, Snapshot)>;
+
+// Saving a snapshot under a name that's taken replaces the old one.
+pub fn set(snapshots: &mut Snapshots, name: &str, snapshot: Snapshot) {
+    match snapshots.iter_mut().find(|(existing, _)| existing == name) {
+        Some((_, existing)) => *existing = snapshot,
+        None => snapshots.push((name.to_string(), snapshot)),
+    }
+}
+
+// Takes the snapshot's name and JSONPaths to leave out of the comparison, for values that change
+// every time like timestamps and IDs, as in `users ignore $.generated_at $.items[*].id`. JSON
+// bodies are compared indented, so only changes to the values fail.
+pub fn check(
+    assertion: &str,
+    snapshots: &Snapshots,
+    status: u16,
+    body: &str,
+) -> Result<(), String> {
+    let (name, ignored) = match assertion.split_once(" ignore ") {
+        Some((name, paths)) => (name.trim(), paths.split_whitespace().collect()),
+        None => (assertion.trim(), vec![]),
+    };
+    let snapshot = snapshots
+        .iter()
+        .find(|(existing, _)| existing == name)
+        .map(|(_, snapshot)| snapshot)
+        .ok_or(format!("no snapshot named {}", name))?;
+    if snapshot.status != status {
+        return Err(format!("got status {}, not {}", status, snapshot.status));
+    }
+
+    let changes: Vec<String> = diff::lines(
+        &comparable(&snapshot.body, &ignored)?,
+        &comparable(body, &ignored)?,
+    )
+    .iter()
+    .filter(|change| !matches!(change, Change::Same(_)))
+    .map(|change| change.line().trim().to_string())
+    .collect();
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let mut shown = changes[..changes.len().min(SHOWN_CHANGES)].join(", ");
+    if changes.len() > SHOWN_CHANGES {
+        shown.push_str(", …");
+    }
+    Err(format!("{} lines changed: {}", changes.len(), shown))
+}
+
+fn comparable(body: &str, ignored: &[&str]) -> Result<String, String> {
+    let Ok(mut value) = json::parse(body) else {
+        return Ok(body.to_string());
+    };
+    for path in ignored {
+        json_path::remove(&mut value, path)?;
+    }
+    Ok(value.pretty(2))
+}
+
+pub fn filename(request_filename: &str) -> String {
+    format!("{}.snapshots.json", request_filename)
+}
+
+pub fn load(filename: &str) -> Result<Snapshots, Box<dyn Error>> {
+    let file = json::parse(&fs::read_to_string(filename)?)
+        .map_err(|err| format!("{} is not valid JSON: {}", filename, err))?;
+
+    let snapshots = save_file::contents(&file, "snapshots", filename)?
+        .entries()
+        .map(|(name, snapshot)| {
+            let invalid = |field: &str| format!("{}: snapshot {} has no {}", filename, name, field);
+            Ok((
+                name.to_string(),
+                Snapshot {
+                    status: snapshot["status"]
+                        .as_u16()
+                        .ok_or_else(|| invalid("status"))?,
+                    body: snapshot["body"]
+                        .as_str()
+                        .ok_or_else(|| invalid("body"))?
+                        .to_string(),
+                },
+            ))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(snapshots)
+}
+
+pub fn save(filename: &str, snapshots: &Snapshots) -> io::Result<()> {
+    let mut file_json = JsonValue::new_object();
+    for (name, snapshot) in snapshots {
+        let mut snapshot_json = JsonValue::new_object();
+        snapshot_json["status"] = snapshot.status.into();
+        snapshot_json["body"] = snapshot.body.clone().into();
+        file_json[name.as_str()] = snapshot_json;
+    }
+
+    let mut file = File::create(filename)?;
+    file.write_all(
+        json::stringify_pretty(save_file::versioned("snapshots", file_json), 2).as_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn saved(body: &str) -> Snapshots {
+        vec![(
+            "users".to_string(),
+            Snapshot {
+                status: 200,
+                body: body.to_string(),
+            },
+        )]
+    }
+
+    #[test]
+    fn set_replaces_a_snapshot_of_the_same_name() {
+        let mut snapshots = saved("[]");
+        set(
+            &mut snapshots,
+            "users",
+            Snapshot {
+                status: 201,
+                body: "[1]".to_string(),
+            },
+        );
+        set(
+            &mut snapshots,
+            "empty",
+            Snapshot {
+                status: 204,
+                body: String::new(),
+            },
+        );
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].1.status, 201);
+        assert_eq!(snapshots[1].0, "empty");
+    }
+
+    #[test]
+    fn json_is_compared_by_value() {
+        let snapshots = saved(r#"{"id":1,"tags":["a"]}"#);
+        assert_eq!(
+            check(
+                "users",
+                &snapshots,
+                200,
+                "{\n  \"id\": 1,\n  \"tags\": [ \"a\" ]\n}"
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn ignored_paths_are_left_out() {
+        let snapshots =
+            saved(r#"{"generated_at": "2024-01-01", "items": [{"id": "a1", "name": "Ada"}]}"#);
+        let body = r#"{"generated_at": "2025-06-30", "items": [{"id": "b7", "name": "Ada"}]}"#;
+        assert!(check("users", &snapshots, 200, body).is_err());
+        assert_eq!(
+            check(
+                "users ignore $.generated_at $.items[*].id",
+                &snapshots,
+                200,
+                body
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check("users ignore $.generated_at", &snapshots, 200, body),
+            Err(r#"2 lines changed: -       "id": "a1",, +       "id": "b7","#.to_string())
+        );
+    }
+
+    #[test]
+    fn only_the_first_changes_are_listed() {
+        let snapshots = saved("a\nb\nc\nd");
+        assert_eq!(
+            check("users", &snapshots, 200, "a\nx\ny\nz"),
+            Err("6 lines changed: - b, - c, - d, …".to_string())
+        );
+    }
+
+    #[test]
+    fn failures() {
+        let snapshots = saved(r#"{"id": 1}"#);
+        assert_eq!(
+            check("orders", &snapshots, 200, "{}"),
+            Err("no snapshot named orders".to_string())
+        );
+        assert_eq!(
+            check("users", &snapshots, 500, r#"{"id": 1}"#),
+            Err("got status 500, not 200".to_string())
+        );
+        assert_eq!(
+            check("users ignore id", &snapshots, 200, r#"{"id": 1}"#),
+            Err("Invalid JSONPath: id".to_string())
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let filename = env::temp_dir()
+            .join(format!("reqcoon-snapshots-{}.json", process::id()))
+            .display()
+            .to_string();
+        let snapshots = saved("{\"id\": 1}\n");
+        save(&filename, &snapshots).unwrap();
+        let loaded = load(&filename);
+        fs::remove_file(&filename).unwrap();
+
+        assert_eq!(loaded.unwrap(), snapshots);
+    }
+}
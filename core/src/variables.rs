@@ -0,0 +1,52 @@
+use std::{env, fs, io, path::Path};
+
+use regex::{Captures, Regex};
+
+// References like {{env:HOME}} are read from the process environment when the request is sent.
+pub fn substitute(text: &str, variables: &[(String, String)]) -> String {
+    let re = Regex::new(r"\{\{\s*([^{}\s]+)\s*\}\}").unwrap();
+    let mut output = text.to_string();
+    // Variables can refer to each other, so expand until nothing changes.
+    for _ in 0..10 {
+        let expanded = re
+            .replace_all(&output, |captures: &Captures| {
+                let value = match captures[1].strip_prefix("env:") {
+                    Some(name) => env::var(name).ok(),
+                    None => variables
+                        .iter()
+                        .find(|(name, _)| *name == captures[1])
+                        .map(|(_, value)| value.clone()),
+                };
+                value.unwrap_or(captures[0].to_string())
+            })
+            .to_string();
+        if expanded == output {
+            break;
+        }
+        output = expanded;
+    }
+    output
+}
+
+// Sets the process environment from a file of NAME=value lines, like a .env file, for {{env:NAME}}
+// references. Variables that are already set keep their values, so the shell can override the
+// file.
+pub fn load_env_file(path: &Path) -> io::Result<()> {
+    for line in fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, value)) = line.split_once('=').filter(|_| !line.starts_with('#')) else {
+            continue;
+        };
+        let value = value.trim();
+        let value = ['"', '\'']
+            .iter()
+            .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+            .unwrap_or(value);
+        if env::var_os(name.trim()).is_none() {
+            env::set_var(name.trim(), value);
+        }
+    }
+
+    Ok(())
+}
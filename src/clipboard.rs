@@ -0,0 +1,42 @@
+use std::{sync::mpsc::Sender, thread, time::Duration};
+
+use clippers::{Clipboard, ClipperData};
+use reqwest::Url;
+
+use crate::events::Event;
+
+// Watches the system clipboard and passes on anything copied that looks like a request, so it can
+// be offered for import. What's on the clipboard when reqcoon starts isn't offered.
+pub fn watch(sender: Sender<Event>) {
+    thread::spawn(move || {
+        let mut clipboard = Clipboard::get();
+        let mut last = read(&mut clipboard);
+        loop {
+            thread::sleep(Duration::from_secs(1));
+            let text = read(&mut clipboard);
+            if text == last {
+                continue;
+            }
+            last = text;
+            if let Some(text) = last.as_ref().filter(|text| importable(text)) {
+                if sender.send(Event::Clipboard(text.clone())).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+fn read(clipboard: &mut Clipboard) -> Option<String> {
+    match clipboard.read() {
+        Some(ClipperData::Text(text)) => Some(text.to_string()),
+        _ => None,
+    }
+}
+
+fn importable(text: &str) -> bool {
+    let text = text.trim();
+    text.starts_with("curl ")
+        || (!text.contains(char::is_whitespace)
+            && Url::parse(text).is_ok_and(|url| matches!(url.scheme(), "http" | "https")))
+}
@@ -0,0 +1,249 @@
+use std::{env, error::Error, fs, path::PathBuf, time::Duration};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use reqcoon_core::{file::Environments, settings::Settings};
+use reqwest::Method;
+use toml::{Table, Value};
+
+use crate::theme::Theme;
+
+// The user's preferences from ~/.config/reqcoon/config.toml, applied under every request file.
+#[derive(Default)]
+pub struct Config {
+    // The method new requests start with.
+    pub method: Option<Method>,
+    // Workspace settings every request file starts from, which the file's own settings override.
+    pub settings: Vec<(String, String)>,
+    // Settings applied over the defaults while an environment of the same name is active.
+    pub environments: Environments,
+    // Keys that act as other keys in normal and visual mode, or as no key at all.
+    pub keys: Vec<(KeyEvent, Option<KeyEvent>)>,
+    // Whether requests open in raw HTTP editing.
+    pub raw: bool,
+    // Whether curl commands and URLs copied in other programs are offered for import.
+    pub watch_clipboard: bool,
+    // How often edits are saved, where zero is never. Without one they're saved every
+    // AUTOSAVE_INTERVAL.
+    pub autosave: Option<Duration>,
+    // How often :poll sends the request when it isn't given a number of seconds.
+    pub poll: Option<Duration>,
+    pub theme: Theme,
+}
+
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+impl Config {
+    pub fn remap(&self, key: KeyEvent) -> Option<KeyEvent> {
+        match self.keys.iter().find(|(from, _)| same_key(from, &key)) {
+            Some((_, to)) => *to,
+            None => Some(key),
+        }
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .map(|directory| directory.join("reqcoon").join("config.toml"))
+}
+
+// Where request files live when reqcoon is started without --filename.
+pub fn workspace() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .map(|directory| directory.join("reqcoon"))
+}
+
+pub fn load() -> Result<Config, Box<dyn Error>> {
+    let Some(path) = path().filter(|path| path.exists()) else {
+        return Ok(Config::default());
+    };
+    let table: Table = fs::read_to_string(&path)?
+        .parse()
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    parse(&table).map_err(|err| format!("{}: {}", path.display(), err).into())
+}
+
+fn parse(table: &Table) -> Result<Config, String> {
+    let mut config = Config::default();
+
+    for (section, value) in table {
+        let entries = value
+            .as_table()
+            .ok_or(format!("{} should be a [{}] table", section, section))?;
+        match section.as_str() {
+            "defaults" => parse_defaults(&mut config, entries)?,
+            "environments" => {
+                for (name, settings) in entries {
+                    let settings = settings.as_table().ok_or(format!(
+                        "environments.{} should be an [environments.{}] table",
+                        name, name
+                    ))?;
+                    config.environments.push((
+                        name.clone(),
+                        parse_settings(&format!("environments.{}", name), settings)?,
+                    ));
+                }
+            }
+            "editor" => {
+                for (key, value) in entries {
+                    match key.as_str() {
+                        "raw" => {
+                            config.raw = value
+                                .as_bool()
+                                .ok_or("editor.raw should be true or false")?
+                        }
+                        "watch-clipboard" => {
+                            config.watch_clipboard = value
+                                .as_bool()
+                                .ok_or("editor.watch-clipboard should be true or false")?
+                        }
+                        "autosave" => {
+                            let seconds = value
+                                .as_integer()
+                                .and_then(|seconds| u64::try_from(seconds).ok())
+                                .ok_or("editor.autosave should be a number of seconds")?;
+                            config.autosave = Some(Duration::from_secs(seconds));
+                        }
+                        "poll" => {
+                            let seconds = value
+                                .as_integer()
+                                .and_then(|seconds| u64::try_from(seconds).ok())
+                                .filter(|seconds| *seconds > 0)
+                                .ok_or("editor.poll should be a number of seconds above zero")?;
+                            config.poll = Some(Duration::from_secs(seconds));
+                        }
+                        _ => return Err(format!("Unknown config key: editor.{}", key)),
+                    }
+                }
+            }
+            "theme" => config.theme = Theme::parse(entries)?,
+            "keys" => {
+                for (from, to) in entries {
+                    let to = to
+                        .as_str()
+                        .ok_or(format!("keys.{} should be a key like \"ctrl-h\"", from))?;
+                    config.keys.push((
+                        parse_key(from)?,
+                        if to == "none" {
+                            None
+                        } else {
+                            Some(parse_key(to)?)
+                        },
+                    ));
+                }
+            }
+            _ => return Err(format!("Unknown config section: [{}]", section)),
+        }
+    }
+
+    Ok(config)
+}
+
+fn parse_defaults(config: &mut Config, entries: &Table) -> Result<(), String> {
+    let mut entries = entries.clone();
+    if let Some(value) = entries.remove("method") {
+        let method = value.as_str().ok_or("defaults.method should be a string")?;
+        config.method = Some(
+            Method::from_bytes(method.to_uppercase().as_bytes())
+                .map_err(|_| format!("Invalid defaults.method: {}", method))?,
+        );
+    }
+    config.settings = parse_settings("defaults", &entries)?;
+
+    Ok(())
+}
+
+// Settings like timeout = 30, with a headers table of names and values that become header
+// settings.
+fn parse_settings(section: &str, entries: &Table) -> Result<Vec<(String, String)>, String> {
+    let mut settings = vec![];
+    for (key, value) in entries {
+        match key.as_str() {
+            "headers" => {
+                let headers = value.as_table().ok_or(format!(
+                    "{}.headers should be a table of names and values",
+                    section
+                ))?;
+                for (name, value) in headers {
+                    settings.push(("header".to_string(), format!("{}: {}", name, text(value))));
+                }
+            }
+            _ => settings.push((key.clone(), text(value))),
+        }
+    }
+
+    // Checked up front so a typo is reported when reqcoon starts rather than on every request.
+    Settings::default()
+        .overridden(
+            settings
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        )
+        .map_err(|err| format!("[{}]: {}", section, err))?;
+
+    Ok(settings)
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().map_or(value.to_string(), str::to_string)
+}
+
+// Keys are written like "ctrl-h", "alt-shift-j", "esc" or "H".
+fn parse_key(spec: &str) -> Result<KeyEvent, String> {
+    let invalid = || format!("Invalid key: {}", spec);
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = spec.trim();
+    while let Some((modifier, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(invalid()),
+        };
+        rest = key;
+    }
+
+    let code = match rest.to_lowercase().as_str() {
+        "esc" => KeyCode::Esc,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        _ if rest.chars().count() == 1 => KeyCode::Char(rest.chars().next().unwrap_or_default()),
+        key => KeyCode::F(
+            key.strip_prefix('f')
+                .and_then(|number| number.parse().ok())
+                .ok_or_else(invalid)?,
+        ),
+    };
+
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+// Terminals report shifted characters like H with or without the shift modifier.
+fn same_key(configured: &KeyEvent, pressed: &KeyEvent) -> bool {
+    let modifiers = |key: &KeyEvent| match key.code {
+        KeyCode::Char(_) => key.modifiers.difference(KeyModifiers::SHIFT),
+        _ => key.modifiers,
+    };
+    configured.code == pressed.code && modifiers(configured) == modifiers(pressed)
+}
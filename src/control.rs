@@ -0,0 +1,182 @@
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+use json::JsonValue;
+use reqcoon_core::file::RequestFile;
+
+use crate::{events::Event, model::Model};
+
+// Bump this whenever a command or reply changes in a way existing clients would notice.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+pub enum Command {
+    Load(String),
+    Run(String),
+    Select(String),
+    Set(String, String),
+    Send,
+}
+
+// Where --server listens, so editor plugins can find reqcoon without being told.
+pub fn default_path() -> String {
+    let directory = env::var_os("XDG_RUNTIME_DIR").map_or(env::temp_dir(), Into::into);
+    directory.join("reqcoon.sock").to_string_lossy().to_string()
+}
+
+// Each connection sends one JSON object per line and gets one back per line, after a greeting
+// with the protocol version. Commands are handed to the main loop, which owns the model.
+pub fn listen(path: &str, sender: Sender<Event>) -> io::Result<()> {
+    // A socket left behind by a reqcoon that's gone is replaced, but one that still answers
+    // belongs to another instance, and anything that isn't a socket isn't ours to remove.
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and isn't a socket", path),
+            ));
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("Another reqcoon is listening on {}", path),
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let _ = serve(stream, sender);
+            });
+        }
+    });
+
+    Ok(())
+}
+
+fn serve(stream: UnixStream, sender: Sender<Event>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut greeting = JsonValue::new_object();
+    greeting["reqcoon"] = env!("CARGO_PKG_VERSION").into();
+    greeting["protocol"] = PROTOCOL_VERSION.into();
+    writeln!(writer, "{}", greeting.dump())?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match parse(&line) {
+            Ok(command) => {
+                let (reply_sender, reply_receiver) = mpsc::channel();
+                if sender.send(Event::Command(command, reply_sender)).is_err() {
+                    break;
+                }
+                match reply_receiver.recv() {
+                    Ok(reply) => reply,
+                    Err(_) => break,
+                }
+            }
+            Err(err) => error(&err),
+        };
+        writeln!(writer, "{}", reply.dump())?;
+    }
+
+    Ok(())
+}
+
+fn parse(line: &str) -> Result<Command, String> {
+    let message = json::parse(line).map_err(|err| format!("Invalid JSON: {}", err))?;
+    let field = |name: &str| {
+        message[name]
+            .as_str()
+            .map(str::to_string)
+            .ok_or(format!("Missing \"{}\"", name))
+    };
+
+    match message["command"].as_str() {
+        Some("load") => Ok(Command::Load(field("request")?)),
+        Some("run") => Ok(Command::Run(field("request")?)),
+        Some("select") => Ok(Command::Select(field("name")?)),
+        Some("set") => Ok(Command::Set(field("name")?, field("value")?)),
+        Some("send") => Ok(Command::Send),
+        Some(command) => Err(format!("Unknown command: {}", command)),
+        None => Err("Missing \"command\"".to_string()),
+    }
+}
+
+pub fn handle(model: &mut Model, command: Command) -> JsonValue {
+    let result = match command {
+        Command::Load(request) => model.load_request_text(&request),
+        Command::Select(name) => model.select_request_named(&name),
+        Command::Set(name, value) => {
+            model.set_variable(&name, &value);
+            Ok(())
+        }
+        Command::Run(text) => match run(model, &text) {
+            Ok(()) => return send(model),
+            Err(err) => Err(err),
+        },
+        Command::Send => return send(model),
+    };
+
+    match result {
+        Ok(()) => ok(),
+        Err(err) => error(&err),
+    }
+}
+
+// Runs a block of .http text as an editor would send it: the file's variable lines followed by the
+// request under the cursor.
+fn run(model: &mut Model, text: &str) -> Result<(), String> {
+    let file = RequestFile::parse(text).map_err(|err| err.to_string())?;
+    let request = file.requests.first().ok_or("No request found")?;
+    for (name, value) in &file.variables {
+        model.set_variable(name, value);
+    }
+    model.load_scratch_request(request)
+}
+
+fn send(model: &mut Model) -> JsonValue {
+    if !model.submit_request() {
+        return error(model.message());
+    }
+
+    let body = model.output_input.lines().join("\n");
+    match model.history.last() {
+        Some(entry) if entry.status != 0 => {
+            let mut reply = ok();
+            reply["status"] = entry.status.into();
+            reply["statusText"] = entry.status_text.clone().into();
+            reply["time"] = entry.time.into();
+            reply["body"] = body.into();
+            reply
+        }
+        _ => error(&body),
+    }
+}
+
+fn ok() -> JsonValue {
+    let mut reply = JsonValue::new_object();
+    reply["ok"] = true.into();
+    reply
+}
+
+fn error(message: &str) -> JsonValue {
+    let mut reply = JsonValue::new_object();
+    reply["ok"] = false.into();
+    reply["error"] = message.into();
+    reply
+}
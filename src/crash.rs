@@ -0,0 +1,48 @@
+use std::{
+    backtrace::Backtrace,
+    env,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+static SNAPSHOT: Mutex<String> = Mutex::new(String::new());
+
+pub fn update_snapshot(snapshot: String) {
+    if let Ok(mut current) = SNAPSHOT.lock() {
+        *current = snapshot;
+    }
+}
+
+pub fn write_report(panic_message: &str) -> io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = env::temp_dir().join(format!("reqcoon-crash-{}.txt", timestamp));
+
+    // The panic may have happened while the snapshot was being updated, so don't wait on it.
+    let snapshot = match SNAPSHOT.try_lock() {
+        Ok(snapshot) => snapshot.clone(),
+        Err(_) => "<unavailable>".to_string(),
+    };
+
+    // The report is masked, but only the user can read it all the same.
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path)?;
+    writeln!(
+        file,
+        "reqcoon {} crashed\n\n{}\n\nState:\n{}\n\nBacktrace:\n{}",
+        env!("CARGO_PKG_VERSION"),
+        panic_message,
+        snapshot,
+        Backtrace::force_capture()
+    )?;
+
+    Ok(path)
+}
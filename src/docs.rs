@@ -0,0 +1,432 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use reqcoon_core::{
+    audit::{REDACTED, SENSITIVE_HEADERS},
+    examples::{self, Example},
+    request::{Auth, Request},
+};
+
+use crate::model::{BodyFormat, Model};
+
+#[derive(Clone, ValueEnum)]
+pub enum DocsFormat {
+    Markdown,
+    Html,
+}
+
+// Every request in the file, with its saved examples. Credential headers and the values of secrets
+// are left out, since docs are made to be shared.
+pub fn export(model: &Model, format: DocsFormat) -> String {
+    let secrets = model.secret_texts();
+    let hide = |text: &str| {
+        secrets
+            .iter()
+            .filter(|secret| !secret.is_empty())
+            .fold(text.to_string(), |text, secret| {
+                text.replace(secret.as_str(), REDACTED)
+            })
+    };
+    let pages: Vec<Page> = model
+        .requests()
+        .iter()
+        .enumerate()
+        .map(|(index, request)| Page::new(model, index, request, &hide))
+        .collect();
+
+    match format {
+        DocsFormat::Markdown => markdown(&name(model), &pages),
+        DocsFormat::Html => html(&name(model), &pages),
+    }
+}
+
+// What the docs show of one request, already redacted.
+struct Page {
+    name: String,
+    method: String,
+    url: String,
+    description: String,
+    auth: Option<&'static str>,
+    headers: Vec<(String, String)>,
+    // The language of the example body, for Markdown code blocks, and the body.
+    body: Option<(&'static str, String)>,
+    form: Vec<(String, String)>,
+    examples: Vec<Example>,
+}
+
+impl Page {
+    fn new(model: &Model, index: usize, request: &Request, hide: &impl Fn(&str) -> String) -> Self {
+        let key = examples::key(&request.name, index);
+        let examples = model
+            .examples
+            .iter()
+            .find(|(existing, _)| *existing == key)
+            .map_or(vec![], |(_, examples)| {
+                examples
+                    .iter()
+                    .map(|example| Example {
+                        body: hide(&example.body),
+                        ..example.clone()
+                    })
+                    .collect()
+            });
+
+        Page {
+            name: request.name.clone(),
+            method: request.method.to_string(),
+            url: hide(&request.url),
+            description: hide(&request.description),
+            auth: match request.auth {
+                Auth::None => None,
+                Auth::Basic { .. } => Some("Basic"),
+                Auth::Bearer(_) => Some("Bearer"),
+            },
+            headers: request
+                .headers
+                .iter()
+                .filter(|(name, _)| !name.is_empty())
+                .map(|(name, value)| {
+                    let value = if SENSITIVE_HEADERS.contains(&name.to_lowercase().as_str()) {
+                        REDACTED.to_string()
+                    } else {
+                        hide(value)
+                    };
+                    (name.clone(), value)
+                })
+                .collect(),
+            body: example_body(request).map(|(language, body)| (language, hide(&body))),
+            form: match request.body_format {
+                BodyFormat::Json => vec![],
+                BodyFormat::UrlEncoded | BodyFormat::Multipart => request
+                    .body
+                    .iter()
+                    .filter(|(name, _)| !name.is_empty())
+                    .map(|(name, value)| (name.clone(), hide(value)))
+                    .collect(),
+            },
+            examples,
+        }
+    }
+
+    fn request_line(&self) -> String {
+        format!("{} {}", self.method, self.url)
+    }
+}
+
+fn name(model: &Model) -> String {
+    Path::new(&model.filename)
+        .file_stem()
+        .map_or(model.filename.clone(), |stem| {
+            stem.to_string_lossy().to_string()
+        })
+}
+
+fn example_body(request: &Request) -> Option<(&'static str, String)> {
+    if request.body_format != BodyFormat::Json || request.body_file().is_some() {
+        return None;
+    }
+    let body = request.body_string();
+    if body.trim().is_empty() {
+        return None;
+    }
+    match json::parse(&body) {
+        Ok(json) if json.is_empty() => None,
+        Ok(json) => Some(("json", json::stringify_pretty(json, 2))),
+        // Raw bodies, like XML or plain text, are shown as they're sent.
+        Err(_) => Some(("", body)),
+    }
+}
+
+fn language(content_type: &str) -> &'static str {
+    if content_type.contains("json") {
+        "json"
+    } else if content_type.contains("html") {
+        "html"
+    } else if content_type.contains("xml") {
+        "xml"
+    } else {
+        ""
+    }
+}
+
+fn markdown(name: &str, pages: &[Page]) -> String {
+    let mut output = format!("# {}\n", name);
+    for page in pages {
+        let request_line = inline_code(&page.request_line());
+        if page.name.is_empty() {
+            output.push_str(&format!("\n## {}\n\n", request_line));
+        } else {
+            output.push_str(&format!("\n## {}\n\n{}\n\n", page.name, request_line));
+        }
+
+        // The notes are often markdown already, so they go in as they're written.
+        if !page.description.is_empty() {
+            output.push_str(&format!("{}\n\n", page.description));
+        }
+
+        if let Some(auth) = page.auth {
+            output.push_str(&format!("**Authentication:** {}\n\n", auth));
+        }
+
+        if !page.headers.is_empty() {
+            output.push_str("### Headers\n\n");
+            output.push_str(&markdown_table(["Name", "Value"], &page.headers));
+        }
+
+        if let Some((language, body)) = &page.body {
+            output.push_str("### Example body\n\n");
+            output.push_str(&code_block(language, body));
+        }
+        if !page.form.is_empty() {
+            output.push_str("### Form fields\n\n");
+            output.push_str(&markdown_table(["Name", "Example"], &page.form));
+        }
+
+        if !page.examples.is_empty() {
+            output.push_str("### Example responses\n\n");
+            for example in &page.examples {
+                output.push_str(&format!("#### {}\n\n", example.summary()));
+                if !example.body.is_empty() {
+                    output.push_str(&code_block(language(&example.content_type), &example.body));
+                }
+            }
+        }
+    }
+
+    output.trim_end().to_string() + "\n"
+}
+
+fn markdown_table(header: [&str; 2], rows: &[(String, String)]) -> String {
+    let mut output = format!(
+        "| {} | {} |\n| {} | {} |\n",
+        header[0],
+        header[1],
+        "-".repeat(header[0].len()),
+        "-".repeat(header[1].len())
+    );
+    for (name, value) in rows {
+        output.push_str(&format!("| {} | {} |\n", cell(name), cell(value)));
+    }
+    output.push('\n');
+    output
+}
+
+// Pipes would end the cell and newlines the row, so they're escaped.
+fn cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+// Backticks in code are fenced with a longer run of them.
+fn longest_backticks(text: &str) -> usize {
+    text.split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default()
+}
+
+fn inline_code(text: &str) -> String {
+    let fence = "`".repeat(longest_backticks(text) + 1);
+    let padding = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{fence}{padding}{text}{padding}{fence}")
+}
+
+fn code_block(language: &str, text: &str) -> String {
+    let fence = "`".repeat((longest_backticks(text) + 1).max(3));
+    format!("{fence}{language}\n{}\n{fence}\n\n", text.trim_end())
+}
+
+fn html(name: &str, pages: &[Page]) -> String {
+    let mut output = format!(
+        "<!DOCTYPE html>\n<html>\n<head><title>{name}</title></head>\n<body>\n<h1>{name}</h1>\n",
+        name = escape(name)
+    );
+    for page in pages {
+        let request_line = format!("<code>{}</code>", escape(&page.request_line()));
+        if page.name.is_empty() {
+            output.push_str(&format!("<h2>{}</h2>\n", request_line));
+        } else {
+            output.push_str(&format!(
+                "<h2>{}</h2>\n<p>{}</p>\n",
+                escape(&page.name),
+                request_line
+            ));
+        }
+
+        for paragraph in page
+            .description
+            .split("\n\n")
+            .filter(|paragraph| !paragraph.trim().is_empty())
+        {
+            output.push_str(&format!("<p>{}</p>\n", escape(paragraph.trim())));
+        }
+
+        if let Some(auth) = page.auth {
+            output.push_str(&format!(
+                "<p><strong>Authentication:</strong> {}</p>\n",
+                auth
+            ));
+        }
+
+        if !page.headers.is_empty() {
+            output.push_str("<h3>Headers</h3>\n");
+            output.push_str(&html_table(["Name", "Value"], &page.headers));
+        }
+
+        if let Some((_, body)) = &page.body {
+            output.push_str(&format!(
+                "<h3>Example body</h3>\n<pre><code>{}</code></pre>\n",
+                escape(body)
+            ));
+        }
+        if !page.form.is_empty() {
+            output.push_str("<h3>Form fields</h3>\n");
+            output.push_str(&html_table(["Name", "Example"], &page.form));
+        }
+
+        if !page.examples.is_empty() {
+            output.push_str("<h3>Example responses</h3>\n");
+            for example in &page.examples {
+                output.push_str(&format!("<h4>{}</h4>\n", escape(&example.summary())));
+                if !example.body.is_empty() {
+                    output.push_str(&format!(
+                        "<pre><code>{}</code></pre>\n",
+                        escape(&example.body)
+                    ));
+                }
+            }
+        }
+    }
+
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+fn html_table(header: [&str; 2], rows: &[(String, String)]) -> String {
+    let mut output = format!(
+        "<table>\n<tr><th>{}</th><th>{}</th></tr>\n",
+        header[0], header[1]
+    );
+    for (name, value) in rows {
+        output.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            escape(name),
+            escape(value)
+        ));
+    }
+    output.push_str("</table>\n");
+    output
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    const FILE: &str = r#"# @secret token
+@base = https://example.com
+
+# @name login
+# @description Signs in.
+POST {{base}}/login
+Proxy-Authorization: Basic YWRhOnQwa2Vu
+X-Note: a|b
+
+{"user": "ada", "token": "t0ken"}
+
+###
+
+GET {{base}}/users
+Cookie: session=abc
+"#;
+
+    fn model() -> Model {
+        let filename = env::temp_dir()
+            .join(format!("reqcoon-docs-{}.http", process::id()))
+            .display()
+            .to_string();
+        fs::write(&filename, FILE).unwrap();
+        let model = Model::from_file(filename.clone());
+        fs::remove_file(&filename).unwrap();
+
+        let mut model = model.unwrap();
+        model
+            .load_secrets(vec![("token".to_string(), "t0ken".to_string())])
+            .unwrap();
+        model.examples = vec![(
+            "login".to_string(),
+            vec![Example {
+                label: "Success".to_string(),
+                status: 200,
+                content_type: "application/json".to_string(),
+                body: r#"{"token": "t0ken"}"#.to_string(),
+            }],
+        )];
+        model
+    }
+
+    #[test]
+    fn markdown_covers_every_request() {
+        let docs = export(&model(), DocsFormat::Markdown);
+        assert!(
+            docs.contains("## login\n\n`POST {{base}}/login`\n\nSigns in.\n"),
+            "{}",
+            docs
+        );
+        assert!(docs.contains("## `GET {{base}}/users`\n"), "{}", docs);
+        assert!(
+            docs.contains("#### Success (200)\n\n```json\n{\"token\": \"[redacted]\"}\n```"),
+            "{}",
+            docs
+        );
+    }
+
+    #[test]
+    fn credentials_are_redacted() {
+        for format in [DocsFormat::Markdown, DocsFormat::Html] {
+            let docs = export(&model(), format);
+            assert!(!docs.contains("t0ken"), "{}", docs);
+            assert!(!docs.contains("YWRhOnQwa2Vu"), "{}", docs);
+            assert!(!docs.contains("session=abc"), "{}", docs);
+        }
+    }
+
+    #[test]
+    fn markdown_cells_are_escaped() {
+        let docs = export(&model(), DocsFormat::Markdown);
+        assert!(docs.contains("| X-Note | a\\|b |\n"), "{}", docs);
+        assert_eq!(cell("one\ntwo|three"), "one<br>two\\|three");
+    }
+
+    #[test]
+    fn code_fences_are_longer_than_the_code() {
+        assert_eq!(inline_code("GET /a`b"), "``GET /a`b``");
+        assert_eq!(code_block("", "```\nx\n```"), "````\n```\nx\n```\n````\n\n");
+    }
+
+    #[test]
+    fn html_covers_every_request() {
+        let docs = export(&model(), DocsFormat::Html);
+        assert!(docs.contains("<h2>login</h2>"), "{}", docs);
+        assert!(
+            docs.contains("<h2><code>GET {{base}}/users</code></h2>"),
+            "{}",
+            docs
+        );
+        assert!(docs.contains("<h4>Success (200)</h4>"), "{}", docs);
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvError, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crossterm::event::{self, Event as TerminalEvent, KeyEvent, KeyEventKind};
+#[cfg(unix)]
+use json::JsonValue;
+
+#[cfg(unix)]
+use crate::control::Command;
+
+// How often timers like token refreshes are checked, and the screen redrawn without any input.
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+// How long the input thread waits for the terminal before checking whether it has been paused.
+const POLL_RATE: Duration = Duration::from_millis(50);
+
+// What the main loop wakes up for. Threads working in the background send these with a sender from
+// Events::sender, so the loop is never stuck waiting on any one of them.
+pub enum Event {
+    Key(KeyEvent),
+    Resize,
+    Tick,
+    Clipboard(String),
+    #[cfg(unix)]
+    Command(Command, Sender<JsonValue>),
+    Failed(io::Error),
+}
+
+pub struct Events {
+    sender: Sender<Event>,
+    receiver: Receiver<Event>,
+    paused: Arc<AtomicBool>,
+    // Each pause sends the input thread a sender to answer on once it has stopped reading.
+    pauses: Sender<Sender<()>>,
+}
+
+impl Events {
+    // Starts reading the terminal and ticking, each on a thread of its own.
+    pub fn start() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let paused = Arc::new(AtomicBool::new(false));
+        let (pauses, pause_requests) = mpsc::channel::<Sender<()>>();
+
+        let input = sender.clone();
+        let input_paused = paused.clone();
+        thread::spawn(move || loop {
+            // Pauses are only answered between polls, so nothing is read once they have been.
+            while let Ok(acknowledge) = pause_requests.try_recv() {
+                let _ = acknowledge.send(());
+            }
+            if input_paused.load(Ordering::Relaxed) {
+                thread::sleep(POLL_RATE);
+                continue;
+            }
+            let event = match event::poll(POLL_RATE)
+                .and_then(|ready| ready.then(event::read).transpose())
+            {
+                Ok(Some(TerminalEvent::Key(key))) if key.kind == KeyEventKind::Press => {
+                    Event::Key(key)
+                }
+                Ok(Some(TerminalEvent::Resize(_, _))) => Event::Resize,
+                Ok(_) => continue,
+                Err(err) => {
+                    let _ = input.send(Event::Failed(err));
+                    break;
+                }
+            };
+            if input.send(event).is_err() {
+                break;
+            }
+        });
+
+        let tick = sender.clone();
+        thread::spawn(move || loop {
+            thread::sleep(TICK_RATE);
+            if tick.send(Event::Tick).is_err() {
+                break;
+            }
+        });
+
+        Events {
+            sender,
+            receiver,
+            paused,
+            pauses,
+        }
+    }
+
+    pub fn sender(&self) -> Sender<Event> {
+        self.sender.clone()
+    }
+
+    pub fn next(&self) -> Result<Event, RecvError> {
+        self.receiver.recv()
+    }
+
+    // An event that arrived while the last one was being handled, if there is one.
+    pub fn pending(&self) -> Option<Event> {
+        self.receiver.try_recv().ok()
+    }
+
+    // Leaves the terminal's input alone while another program, like the shell reqcoon was
+    // suspended to, is reading it. Returns once the input thread has stopped reading, so no key
+    // meant for the other program is taken from it.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+        let (acknowledge, acknowledged) = mpsc::channel();
+        // An input thread that has ended isn't reading, so there's nothing to wait for.
+        if self.pauses.send(acknowledge).is_ok() {
+            let _ = acknowledged.recv();
+        }
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+}
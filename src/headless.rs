@@ -0,0 +1,127 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::Path,
+    process::ExitCode,
+};
+
+use reqcoon_core::{
+    file::{self, RequestFile},
+    folder::Folder,
+    runner,
+    secrets::{self, Values},
+    snapshot,
+};
+
+use crate::{config::Config, model::Model};
+
+pub fn send(
+    filename: String,
+    request: Option<String>,
+    environment: Option<String>,
+    config: &Config,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let mut model = Model::from_file(filename.clone())?;
+    model.load_secrets(stored_secrets(&filename)?)?;
+    model.environment = environment;
+    model.set_defaults(config.settings.clone(), config.environments.clone());
+    if let Some(name) = request {
+        model.select_request_named(&name)?;
+    }
+
+    let request_builder = model.request_builder()?;
+    let (response, _) = model.settings()?.send(request_builder);
+    let mut response = response?;
+    let status = response.status();
+
+    println!("{:?} {}", response.version(), status);
+    for (name, value) in response.headers() {
+        println!("{}: {}", name, value.to_str().unwrap_or("<binary>"));
+    }
+    println!();
+
+    // Stream the body so large downloads can be piped to a file without being held in memory.
+    let mut stdout = io::stdout().lock();
+    io::copy(&mut response, &mut stdout)?;
+    writeln!(stdout)?;
+
+    Ok(ExitCode::from(match status.as_u16() / 100 {
+        2 => 0,
+        3 => 3,
+        4 => 4,
+        5 => 5,
+        _ => 1,
+    }))
+}
+
+pub fn run(
+    path: &str,
+    environment: Option<String>,
+    config: &Config,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let filenames = runner::collect(Path::new(path))?;
+    let mut captured = vec![];
+    let mut passed = 0;
+    let mut failed = 0;
+
+    for filename in filenames {
+        let mut file = RequestFile::parse(&fs::read_to_string(&filename)?)
+            .map_err(|err| format!("{}: {}", filename.display(), err))?;
+        file.environment = environment.clone();
+        let request_filename = filename.display().to_string();
+        for (name, value) in secrets::resolve(
+            &file.secrets,
+            &stored_secrets(&request_filename)?,
+            &request_filename,
+        ) {
+            file.variables.retain(|(existing, _)| *existing != name);
+            file.variables.push((name, value));
+        }
+        file.defaults = config.settings.clone();
+        if let Ok(settings) = file::environment(&config.environments, environment.as_deref()) {
+            file.defaults.extend(settings.iter().cloned());
+            if let Some(name) = environment.as_ref().filter(|name| {
+                !file
+                    .environments
+                    .iter()
+                    .any(|(existing, _)| existing == *name)
+            }) {
+                file.environments.push((name.clone(), vec![]));
+            }
+        }
+        file.folder = Folder::for_file(&filename)?;
+        let snapshots_filename = snapshot::filename(&request_filename);
+        if Path::new(&snapshots_filename).exists() {
+            file.snapshots = snapshot::load(&snapshots_filename)?;
+        }
+        println!("{}", filename.display());
+        for outcome in runner::run(&file, &mut captured) {
+            println!("  {}", outcome.summary());
+            for failure in &outcome.failures {
+                println!("      {}", failure);
+            }
+            if outcome.passed() {
+                passed += 1;
+            } else {
+                failed += 1;
+            }
+        }
+    }
+    println!("\n{} passed, {} failed", passed, failed);
+
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn stored_secrets(filename: &str) -> Result<Values, Box<dyn Error>> {
+    let secrets_filename = secrets::filename(filename);
+    if Path::new(&secrets_filename).exists() {
+        secrets::load(&secrets_filename)
+    } else {
+        Ok(vec![])
+    }
+}
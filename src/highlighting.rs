@@ -0,0 +1,140 @@
+use std::sync::OnceLock;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Style, Stylize},
+};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+
+use crate::theme;
+
+// Parsing starts from the top of the body so strings and comments that begin above the screen are
+// colored right, which gets slow on very long bodies.
+const LINE_LIMIT: usize = 10_000;
+
+// Minified responses can be a single line of megabytes, which is as slow to parse.
+const LENGTH_LIMIT: usize = 1_000_000;
+
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+
+fn syntaxes() -> &'static SyntaxSet {
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+// The syntax for a body with the given Content-Type, like application/json or
+// application/atom+xml. Without one, JSON and markup are recognized by how the body starts.
+pub fn syntax(content_type: Option<&str>, text: &str) -> Option<&'static SyntaxReference> {
+    let extension = match content_type {
+        Some(content_type) => {
+            let mime_type = content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase();
+            match mime_type.rsplit(['/', '+']).next().unwrap_or_default() {
+                "javascript" | "x-javascript" | "ecmascript" => "js".to_string(),
+                "x-yaml" => "yaml".to_string(),
+                "markdown" => "md".to_string(),
+                "plain" | "octet-stream" => return None,
+                subtype => subtype.to_string(),
+            }
+        }
+        None => match text.trim_start().chars().next() {
+            Some('{' | '[') => "json".to_string(),
+            Some('<') => "xml".to_string(),
+            _ => return None,
+        },
+    };
+    syntaxes().find_syntax_by_extension(&extension)
+}
+
+// Colors the lines shown in area with the UI theme, where the top of area shows line first and
+// its left edge shows column left.
+pub fn highlight(
+    buf: &mut Buffer,
+    area: Rect,
+    lines: &[String],
+    syntax: &SyntaxReference,
+    first: usize,
+    left: usize,
+) {
+    let last = (first + area.height as usize).min(lines.len());
+    if last > LINE_LIMIT || lines[..last].iter().map(String::len).sum::<usize>() > LENGTH_LIMIT {
+        return;
+    }
+
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    for (index, line) in lines[..last].iter().enumerate() {
+        let Ok(ops) = state.parse_line(&format!("{}\n", line), syntaxes()) else {
+            return;
+        };
+        let mut ops = ops.into_iter().peekable();
+        let mut style = scope_style(&stack);
+        for (column, (byte, _)) in line.char_indices().enumerate() {
+            while let Some((_, op)) = ops.next_if(|(position, _)| *position <= byte) {
+                let _ = stack.apply(&op);
+                style = scope_style(&stack);
+            }
+            let shown = index >= first && column >= left && column - left < area.width as usize;
+            if let Some(style) = style.filter(|_| shown) {
+                let x = area.left() + (column - left) as u16;
+                let y = area.top() + (index - first) as u16;
+                buf.get_mut(x, y).set_style(style);
+            }
+        }
+        for (_, op) in ops {
+            let _ = stack.apply(&op);
+        }
+    }
+}
+
+// Syntaxes name their scopes differently, so only the broad kinds are told apart, in the colors
+// JSON is drawn with.
+fn scope_style(stack: &ScopeStack) -> Option<Style> {
+    let theme = theme::current();
+    let names: Vec<String> = stack
+        .as_slice()
+        .iter()
+        .rev()
+        .map(|scope| scope.build_string())
+        .collect();
+
+    let key = [
+        "meta.mapping.key",
+        "entity.name.tag",
+        "entity.other.attribute-name",
+        "support.type.property-name",
+    ];
+    if names
+        .iter()
+        .any(|name| key.iter().any(|prefix| name.starts_with(prefix)))
+    {
+        return Some(Style::default().fg(theme.json_key));
+    }
+
+    names.iter().find_map(|name| {
+        let style = Style::default();
+        if name.starts_with("comment") {
+            Some(style.dim())
+        } else if name.starts_with("string") {
+            Some(style.fg(theme.json_string))
+        } else if name.starts_with("constant.numeric") {
+            Some(style.fg(theme.json_number))
+        } else if [
+            "constant.language",
+            "constant.character",
+            "keyword",
+            "storage",
+        ]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        {
+            Some(style.fg(theme.json_literal))
+        } else {
+            None
+        }
+    })
+}
@@ -1,24 +1,108 @@
-use std::{error::Error, time::Duration};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
 use clap::Parser;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use log::LevelFilter;
+use ratatui::{backend::Backend, Terminal};
+use reqcoon_core::{cache, examples, history, oauth, runner, secrets, snapshot, variables};
+#[cfg(feature = "openapi")]
+use reqcoon_core::{file::RequestFile, openapi};
 
+mod clipboard;
+mod config;
+#[cfg(unix)]
+mod control;
+mod crash;
+#[cfg(feature = "docs")]
+mod docs;
+mod events;
+mod headless;
+#[cfg(feature = "highlighting")]
+mod highlighting;
 mod model;
 mod text_wrapping;
+mod theme;
 mod tmux;
 mod tui;
 mod view;
+#[cfg(feature = "docs")]
+use crate::docs::DocsFormat;
 use crate::{
-    model::{Mode, Model, Panel},
+    config::Config,
+    events::{Event, Events},
+    model::{InputType, Mode, Model, Panel, Selection},
     view::view,
 };
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    /// The request file, or a directory of them to pick from. Defaults to the workspace in the
+    /// user's data directory
     #[arg(short, long)]
-    filename: String,
+    filename: Option<String>,
+
+    /// Print documentation for the request instead of starting the TUI
+    #[cfg(feature = "docs")]
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    export_docs: Option<DocsFormat>,
+
+    /// Print an OpenAPI 3 spec for the request instead of starting the TUI
+    #[cfg(feature = "openapi")]
+    #[arg(long)]
+    export_openapi: bool,
+
+    /// Write a request file for each operation in the OpenAPI 3 JSON SPEC into the directory given
+    /// by --filename
+    #[cfg(feature = "openapi")]
+    #[arg(long, value_name = "SPEC")]
+    import_openapi: Option<String>,
+
+    /// Add the entries of a HAR file to the request history
+    #[cfg(feature = "har")]
+    #[arg(long, value_name = "FILE")]
+    import_har: Option<String>,
+
+    /// Write the request history to a HAR file
+    #[cfg(feature = "har")]
+    #[arg(long, value_name = "FILE")]
+    export_har: Option<String>,
+
+    /// Listen for JSON commands from other tools on the Unix socket at PATH
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH")]
+    control: Option<String>,
+
+    /// Listen for editor plugins on the default control socket
+    #[cfg(unix)]
+    #[arg(long, conflicts_with = "control")]
+    server: bool,
+
+    /// Apply the settings of environment NAME from the request file
+    #[arg(long, value_name = "NAME")]
+    env: Option<String>,
+
+    /// Draw over the current screen instead of switching to the alternate screen
+    #[arg(long)]
+    no_altscreen: bool,
+
+    /// Send the request and print the response instead of starting the TUI
+    #[arg(long)]
+    send: bool,
+
+    /// Only send if the request is named NAME
+    #[arg(long, value_name = "NAME", requires = "send")]
+    request: Option<String>,
+
+    /// Run every request in the file, or in every request file under the directory given by
+    /// --filename, and print a summary instead of starting the TUI
+    #[arg(long, conflicts_with = "send")]
+    run: bool,
 }
 
 #[derive(PartialEq)]
@@ -28,7 +112,7 @@ enum Message {
     Insert,
     LeaveInsert,
     Normal,
-    Visual,
+    Visual(Selection),
     LeaveVisual,
 
     // Navigation
@@ -36,6 +120,24 @@ enum Message {
     SelectPanelDown,
     SelectPanelUp,
     SelectPanelRight,
+    ToggleSettings,
+    ToggleHistory,
+    ToggleRunner,
+    ToggleFiles,
+    ToggleCookies,
+    ToggleMock,
+    ToggleQuery,
+    RunQuery,
+    ToggleMenu,
+    NextMenuItem,
+    PreviousMenuItem,
+    ChooseMenuItem,
+    ToggleCommand,
+    CompleteCommand,
+    RunCommand,
+    CompleteUrl,
+    NextRequest,
+    PreviousRequest,
 
     // Method input
     NextMethod,
@@ -43,6 +145,7 @@ enum Message {
 
     // Input
     Copy,
+    Cut,
     InsertInput(KeyEvent),
     NormalInput(KeyEvent),
 
@@ -53,121 +156,779 @@ enum Message {
     PreviousInputField,
     NextInputFormat,
     PreviousInputFormat,
+    ToggleRawInput,
+    DeleteRow,
+    MoveRowDown,
+    MoveRowUp,
+    ToggleRowDisabled,
+    ToggleRevealSecrets,
+
+    // History
+    NextHistoryEntry,
+    PreviousHistoryEntry,
+    ReplayHistoryEntry,
+    DiffHistoryEntry,
+
+    // Runner
+    RunCollection,
+    NextOutcome,
+    PreviousOutcome,
+    OpenOutcome,
+
+    // Files
+    NextFile,
+    PreviousFile,
+    OpenFile,
+
+    // OAuth2
+    Authorize,
+    CloseAuthorization,
 
     // Submission
     SubmitRequest,
+    SaveResponse,
+    CopyAsCurl,
+    CopyJsonPath,
+    TogglePreview,
+    ToggleDescription,
+    AddAutoHeaders,
+    DiffResponse,
+    ToggleFormat,
+    OpenOutput,
+    EditBody,
+    FormatBody,
+    CloseDiff,
+    ToggleMessages,
+    ImportClipboard,
+
+    // Macros
+    Record,
+    Replay,
+    ChooseRegister(KeyEvent),
+    ConfirmQuit(KeyEvent),
+    Repeat,
 
+    // Examples
+    SaveResponseExample,
+    SaveHistoryExample,
+    NextExample,
+    PreviousExample,
+
+    Suspend,
     Quit,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> Result<ExitCode, Box<dyn Error>> {
     simple_logging::log_to_file("debug.log", LevelFilter::Info)?;
 
     let args = Args::parse();
+    // Loaded before any threads start, since they could be reading the environment.
+    if Path::new(".env").exists() {
+        variables::load_env_file(Path::new(".env"))
+            .map_err(|err| format!("Unable to load .env: {}", err))?;
+    }
+    let config = config::load().map_err(|err| format!("Unable to load config: {}", err))?;
+    let filename = args
+        .filename
+        .clone()
+        .or_else(|| config::workspace().map(|path| path.display().to_string()))
+        .ok_or("Pass --filename, there's no home directory for the default workspace")?;
+
+    #[cfg(feature = "docs")]
+    if let Some(format) = args.export_docs {
+        // Opened like the TUI does, for the saved examples and the secrets to leave out.
+        let model = open(filename, &config, None)?;
+        print!("{}", docs::export(&model, format));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    #[cfg(feature = "openapi")]
+    if args.export_openapi {
+        let model = Model::from_file(filename)?;
+        let requests: Vec<_> = model
+            .requests()
+            .iter()
+            // Placeholders stay in the paths, where the spec describes them as parameters.
+            .map(|request| {
+                let mut request = request.clone();
+                request.path_params.clear();
+                request.substituted(&model.variables)
+            })
+            .collect();
+        println!(
+            "{}",
+            json::stringify_pretty(openapi::export(&model.filename, &requests)?, 2)
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    #[cfg(feature = "openapi")]
+    if let Some(spec) = args.import_openapi {
+        let requests = openapi::import(&fs::read_to_string(&spec)?)?;
+        let paths: Vec<_> = openapi::filenames(&requests)
+            .iter()
+            .map(|name| Path::new(&filename).join(name))
+            .collect();
+        // Nothing is written if any of the files is already there, rather than overwriting it.
+        if let Some(path) = paths.iter().find(|path| path.exists()) {
+            return Err(format!(
+                "{} already exists, import into an empty directory",
+                path.display()
+            )
+            .into());
+        }
+        fs::create_dir_all(&filename)?;
+        for (request, path) in requests.into_iter().zip(paths) {
+            let file = RequestFile {
+                requests: vec![request],
+                ..RequestFile::default()
+            };
+            fs::write(&path, file.to_string())?;
+            println!("{}", path.display());
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    #[cfg(feature = "har")]
+    if let Some(har_filename) = args.import_har {
+        let history_filename = history::filename(&filename);
+        let mut entries = history::load(&history_filename).unwrap_or_default();
+        let imported = history::load(&har_filename)?;
+        println!("Imported {} entries", imported.len());
+        entries.extend(imported);
+        history::save(&history_filename, &entries)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    #[cfg(feature = "har")]
+    if let Some(har_filename) = args.export_har {
+        let entries = history::load(&history::filename(&filename))?;
+        history::save(&har_filename, &entries)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if args.send {
+        return headless::send(filename, args.request, args.env, &config);
+    }
+
+    if args.run {
+        return headless::run(&filename, args.env, &config);
+    }
+
+    // A directory opens its first request file and offers the others to pick from.
+    if args.filename.is_none() {
+        fs::create_dir_all(&filename)?;
+    }
+    let workspace = Path::new(&filename)
+        .is_dir()
+        .then(|| PathBuf::from(&filename));
+    let filename = match &workspace {
+        Some(directory) => runner::collect(directory)?
+            .first()
+            .cloned()
+            .unwrap_or_else(|| directory.join("requests.http"))
+            .display()
+            .to_string(),
+        None => filename,
+    };
+    let mut model = open(filename, &config, args.env)?;
+    model.workspace = workspace;
+    if model.workspace.is_some() {
+        model.toggle_files();
+    }
+
+    theme::set(config.theme.clone());
 
     // setup terminal
     tui::install_panic_hook();
-    let mut terminal = tui::init_terminal();
-    let mut model = Model::from_file(args.filename.clone()).unwrap_or(Model::new(args.filename));
+    let mut terminal = tui::init_terminal(!args.no_altscreen)?;
+    #[cfg(unix)]
+    let control_path = args
+        .control
+        .clone()
+        .or_else(|| args.server.then(control::default_path));
+    let events = Events::start();
+    #[cfg(unix)]
+    if let Some(path) = &control_path {
+        control::listen(path, events.sender()).map_err(|err| {
+            tui::restore_terminal();
+            err
+        })?;
+    }
+    if config.watch_clipboard {
+        clipboard::watch(events.sender());
+    }
+
+    // The terminal is restored before any error the loop ends with is printed.
+    let result = run(&mut model, &mut terminal, &events, &config);
+    tui::restore_terminal();
+    let closed = close(&mut model);
+    #[cfg(unix)]
+    if let Some(path) = &control_path {
+        let _ = fs::remove_file(path);
+    }
+    result?;
+    closed?;
+
+    Ok(ExitCode::SUCCESS)
+}
 
+fn run<B: Backend>(
+    model: &mut Model,
+    terminal: &mut Terminal<B>,
+    events: &Events,
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
     while model.exit == false {
         match model.current_mode {
-            Mode::Normal | Mode::Visual => tui::set_cursor_block(),
+            Mode::Normal | Mode::Visual(_) => tui::set_cursor_block(),
             Mode::Insert => tui::set_cursor_bar(),
         };
 
-        terminal.draw(|f| view(f, &mut model))?;
+        crash::update_snapshot(model.crash_snapshot());
+        terminal.draw(|f| view(f, model))?;
 
-        let mut current_message = handle_event(&mut model);
+        // Whatever arrived during a slow update is handled before drawing again, so keys typed
+        // ahead aren't drawn one at a time.
+        handle_event(model, config, events.next()?)?;
+        while !model.exit && !model.suspend {
+            let Some(event) = events.pending() else {
+                break;
+            };
+            handle_event(model, config, event)?;
+        }
+
+        if let Some(filename) = model.open_file.take() {
+            switch_file(model, filename, config);
+        }
+
+        if model.suspend {
+            model.suspend = false;
+            events.pause();
+            tui::suspend(terminal)?;
+            events.resume();
+        }
 
-        while current_message.is_some() {
-            current_message = update(&mut model, current_message.unwrap());
+        if let Some((program, path)) = model.external.take() {
+            events.pause();
+            let result = tui::run_external(terminal, &program, &path)?;
+            events.resume();
+            model.finish_external(&program, &path, result);
         }
     }
 
-    tui::restore_terminal();
-    model.to_file()?;
+    Ok(())
+}
+
+// A file that exists but can't be read is reported rather than replaced with a blank request when
+// reqcoon exits.
+fn open(
+    filename: String,
+    config: &Config,
+    environment: Option<String>,
+) -> Result<Model, Box<dyn Error>> {
+    let mut model = if Path::new(&filename).exists() {
+        Model::from_file(filename.clone())
+            .map_err(|err| format!("Unable to load {}: {}", filename, err))?
+    } else {
+        let mut model = Model::new(filename);
+        if let Some(method) = &config.method {
+            model.current_method = method.clone();
+        }
+        model
+    };
+    model.set_defaults(config.settings.clone(), config.environments.clone());
+    model.poll_interval = config.poll.unwrap_or(config::POLL_INTERVAL);
+    if config.raw {
+        model.toggle_raw_input();
+    }
+
+    model.environment = environment;
+    let mut problems = vec![];
+    model.load_tokens(load_side_file(
+        &oauth::filename(&model.filename),
+        oauth::load,
+        &mut problems,
+    ));
+    model.history = load_side_file(
+        &history::filename(&model.filename),
+        history::load,
+        &mut problems,
+    );
+    model.examples = load_side_file(
+        &examples::filename(&model.filename),
+        examples::load,
+        &mut problems,
+    );
+    model.cached_responses = load_side_file(
+        &cache::filename(&model.filename),
+        cache::load,
+        &mut problems,
+    );
+    model.snapshots = load_side_file(
+        &snapshot::filename(&model.filename),
+        snapshot::load,
+        &mut problems,
+    );
+    model.show_cached_response();
+    let stored = load_side_file(
+        &secrets::filename(&model.filename),
+        secrets::load,
+        &mut problems,
+    );
+    if let Err(err) = model.load_secrets(stored) {
+        problems.push(err);
+    }
+    if Path::new(&model.filename).exists() {
+        model.mark_saved();
+    }
+    if !problems.is_empty() {
+        model.error(problems.join(". "));
+    }
+
+    Ok(model)
+}
+
+// Saves the request file and everything kept next to it.
+fn close(model: &mut Model) -> Result<(), Box<dyn Error>> {
+    if model.raw_mode {
+        model.toggle_raw_input();
+    }
+    if model.discard_edits {
+        model.restore_committed()?;
+    } else {
+        model.to_file()?;
+    }
+    let redact = model.redactor();
+    if !model.history.is_empty() {
+        let history: Vec<_> = model.history.iter().map(&redact).collect();
+        history::save(&history::filename(&model.filename), &history)?;
+    }
+    if !model.examples.is_empty() {
+        examples::save(&examples::filename(&model.filename), &model.examples)?;
+    }
+    if !model.cached_responses.is_empty() {
+        let responses: Vec<_> = model
+            .cached_responses
+            .iter()
+            .map(|(key, entry)| (key.clone(), redact(entry)))
+            .collect();
+        cache::save(&cache::filename(&model.filename), &responses)?;
+    }
+    if !model.snapshots.is_empty() {
+        snapshot::save(&snapshot::filename(&model.filename), &model.snapshots)?;
+    }
+    if !model.oauth_tokens.is_empty() {
+        oauth::save(&oauth::filename(&model.filename), &model.oauth_tokens)?;
+    }
+    if !model.secret_values.is_empty() {
+        secrets::save(&secrets::filename(&model.filename), &model.secret_values)?;
+    }
+    if let Some(body) = &model.response_body {
+        let _ = fs::remove_file(&body.path);
+    }
 
     Ok(())
 }
 
-fn handle_event(model: &mut Model) -> Option<Message> {
-    if event::poll(Duration::from_millis(250)).expect("Unable to poll events") {
-        if let Ok(Event::Key(key)) = event::read() {
-            if key.kind == KeyEventKind::Press {
-                match model.current_mode {
-                    Mode::Normal => handle_normal_key(key, model),
-                    Mode::Insert => handle_insert_key(key),
-                    Mode::Visual => handle_visual_key(key),
+// Opens another request file in place of the current one, which is saved first. The environment
+// and workspace carry over, and a file that can't be opened, or a current one that can't be saved,
+// leaves the current one open. Returns whether the file was switched.
+fn switch_file(model: &mut Model, filename: String, config: &Config) -> bool {
+    let mut opened = match open(filename, config, model.environment.clone()) {
+        Ok(opened) => opened,
+        Err(err) => {
+            model.error(err.to_string());
+            return false;
+        }
+    };
+    if let Err(err) = close(model) {
+        model.error(format!(
+            "Unable to save {}, so it's still open: {}",
+            model.filename, err
+        ));
+        return false;
+    }
+    opened.workspace = model.workspace.take();
+    // Macros and a recording carry over, so one can be replayed across files.
+    opened.macros = std::mem::take(&mut model.macros);
+    opened.recording = model.recording.take();
+    opened.replaying = std::mem::take(&mut model.replaying);
+    if opened.messages.is_empty() {
+        opened.info(format!("Opened {}", opened.filename));
+    }
+    // So is the log of messages, with the new file's after the old one's.
+    opened
+        .messages
+        .splice(0..0, std::mem::take(&mut model.messages));
+    *model = opened;
+
+    true
+}
+
+// Loads a file saved next to the request file. One that can't be read is moved aside, so it isn't
+// overwritten on exit and can be fixed by hand.
+fn load_side_file<T: Default>(
+    filename: &str,
+    load: fn(&str) -> Result<T, Box<dyn Error>>,
+    problems: &mut Vec<String>,
+) -> T {
+    if !Path::new(filename).exists() {
+        return T::default();
+    }
+
+    load(filename).unwrap_or_else(|err| {
+        let backup = format!("{}.bak", filename);
+        problems.push(match fs::rename(filename, &backup) {
+            Ok(()) => format!(
+                "Unable to load {}, moved it to {}: {}",
+                filename, backup, err
+            ),
+            Err(_) => format!("Unable to load {}: {}", filename, err),
+        });
+        T::default()
+    })
+}
+
+fn handle_event(model: &mut Model, config: &Config, event: Event) -> Result<(), Box<dyn Error>> {
+    match event {
+        Event::Key(key) => {
+            model.record_key(key);
+            let mut current_message = handle_key(model, config, key);
+            while let Some(message) = current_message {
+                current_message = update(model, message);
+            }
+            model.finish_command();
+            // A macro's keys are handled as if they were typed.
+            if let Some((register, keys)) = model.pending_macro.take() {
+                model.replaying.push(register);
+                for key in keys {
+                    if model.exit {
+                        break;
+                    }
+                    handle_event(model, config, Event::Key(key))?;
+                    // The rest of the macro works on a file it opened.
+                    if let Some(filename) = model.open_file.take() {
+                        if !switch_file(model, filename, config) {
+                            break;
+                        }
+                    }
                 }
-            } else {
-                None
+                model.replaying.pop();
             }
-        } else {
-            None
         }
-    } else {
-        None
+        // The next draw fits the new size.
+        Event::Resize => (),
+        Event::Tick => {
+            model.ticks = model.ticks.wrapping_add(1);
+            model.check_authorization();
+            model.refresh_expiring_token();
+            model.check_watched_body();
+            model.check_polling();
+            model.check_load_test();
+            model.check_mock_server();
+            model.check_catcher();
+            let interval = config.autosave.unwrap_or(config::AUTOSAVE_INTERVAL);
+            if !interval.is_zero() {
+                model.autosave(interval);
+            }
+        }
+        Event::Clipboard(text) => model.offer_clipboard(text),
+        #[cfg(unix)]
+        Event::Command(command, reply) => {
+            let _ = reply.send(control::handle(model, command));
+        }
+        Event::Failed(err) => return Err(format!("Unable to read events: {}", err).into()),
     }
+
+    Ok(())
 }
 
-fn handle_normal_key(key: KeyEvent, model: &Model) -> Option<Message> {
-    let panel_specific_handler = match model.current_panel {
-        Panel::Method => handle_normal_method_key,
-        Panel::Url => handle_normal_url_key,
-        Panel::Input => handle_normal_input_key,
-        Panel::Output => handle_normal_output_key,
-    };
+fn handle_key(model: &mut Model, config: &Config, key: KeyEvent) -> Option<Message> {
+    // The key after quitting asks to be confirmed answers it, in any mode.
+    if model.confirming_quit {
+        return Some(Message::ConfirmQuit(key));
+    }
+    match model.current_mode {
+        // The key after q or @ names a register, whatever it's bound to.
+        Mode::Normal if model.awaiting_register() => Some(Message::ChooseRegister(key)),
+        // So is the character after f or t.
+        Mode::Normal if model.awaiting_char() => Some(Message::NormalInput(key)),
+        Mode::Normal => handle_normal_key(config.remap(key)?, model),
+        Mode::Insert => handle_insert_key(key, model),
+        Mode::Visual(_) => handle_visual_key(config.remap(key)?, model),
+    }
+}
+
+// The keys of one focusable element, or of everything around it.
+type Layer = fn(KeyEvent) -> Option<Message>;
+
+// The layers for what has focus, in order of precedence: the element under the cursor comes
+// before the pane it's in, which comes before the keys that work from every pane. Popups only
+// get their own keys, so nothing opens underneath them.
+fn normal_layers(model: &Model) -> Vec<Layer> {
+    match model.current_panel {
+        Panel::Method => vec![
+            handle_normal_frame_key,
+            handle_normal_method_key,
+            handle_normal_editing_key,
+            handle_normal_app_key,
+        ],
+        Panel::Url => vec![
+            handle_normal_frame_key,
+            handle_normal_url_key,
+            handle_normal_editing_key,
+            handle_normal_app_key,
+        ],
+        Panel::Input if model.raw_mode => vec![
+            handle_normal_frame_key,
+            handle_normal_raw_input_key,
+            handle_normal_multiline_key,
+            handle_normal_editing_key,
+            handle_normal_app_key,
+        ],
+        Panel::Input if model.current_input_type == InputType::Auth => vec![
+            handle_normal_frame_key,
+            handle_normal_table_row_key,
+            handle_normal_auth_key,
+            handle_normal_input_key,
+            handle_normal_editing_key,
+            handle_normal_app_key,
+        ],
+        Panel::Input if model.editing_movable_row() => vec![
+            handle_normal_frame_key,
+            handle_normal_table_row_key,
+            handle_normal_movable_row_key,
+            handle_normal_input_key,
+            handle_normal_editing_key,
+            handle_normal_app_key,
+        ],
+        Panel::Input => vec![
+            handle_normal_frame_key,
+            handle_normal_table_row_key,
+            handle_normal_input_key,
+            handle_normal_editing_key,
+            handle_normal_app_key,
+        ],
+        Panel::Output => vec![
+            handle_normal_frame_key,
+            handle_normal_output_key,
+            handle_normal_multiline_key,
+            handle_normal_editing_key,
+            handle_normal_app_key,
+        ],
+        Panel::Settings => vec![
+            handle_normal_frame_key,
+            handle_normal_settings_key,
+            handle_normal_table_row_key,
+            handle_normal_editing_key,
+        ],
+        Panel::Cookies => vec![
+            handle_normal_frame_key,
+            handle_normal_cookies_key,
+            handle_normal_table_row_key,
+            handle_normal_editing_key,
+        ],
+        Panel::Mock => vec![
+            handle_normal_frame_key,
+            handle_normal_mock_key,
+            handle_normal_table_row_key,
+            handle_normal_editing_key,
+        ],
+        Panel::Query => vec![
+            handle_normal_frame_key,
+            handle_normal_query_key,
+            handle_normal_editing_key,
+        ],
+        Panel::Command => vec![
+            handle_normal_frame_key,
+            handle_normal_command_key,
+            handle_normal_editing_key,
+        ],
+        Panel::History => vec![handle_normal_frame_key, handle_normal_history_key],
+        Panel::Runner => vec![handle_normal_frame_key, handle_normal_runner_key],
+        Panel::Files => vec![handle_normal_frame_key, handle_normal_files_key],
+        Panel::Authorize => vec![handle_normal_frame_key, handle_normal_authorize_key],
+        Panel::Menu => vec![handle_normal_frame_key, handle_normal_menu_key],
+        Panel::Preview => vec![
+            handle_normal_frame_key,
+            handle_normal_preview_key,
+            handle_normal_visual_key,
+        ],
+        Panel::Description => vec![
+            handle_normal_frame_key,
+            handle_normal_description_key,
+            handle_normal_editing_key,
+        ],
+        Panel::Diff => vec![
+            handle_normal_frame_key,
+            handle_normal_diff_key,
+            handle_normal_visual_key,
+        ],
+        Panel::Messages => vec![
+            handle_normal_frame_key,
+            handle_normal_messages_key,
+            handle_normal_visual_key,
+        ],
+    }
+}
 
-    globally_pre_handle_normal_key(key)
-        .or_else(|| panel_specific_handler(key))
-        .or_else(|| globally_post_handle_normal_key(key))
+fn handle_normal_key(key: KeyEvent, model: &Model) -> Option<Message> {
+    normal_layers(model)
+        .iter()
+        .find_map(|layer| layer(key))
+        .or(Some(Message::NormalInput(key)))
 }
 
-fn handle_insert_key(key: KeyEvent) -> Option<Message> {
+fn handle_insert_key(key: KeyEvent, model: &Model) -> Option<Message> {
     match key {
         KeyEvent {
             code: KeyCode::Char('c'),
             modifiers: KeyModifiers::CONTROL,
             ..
         } => Some(Message::Quit),
+        KeyEvent {
+            code: KeyCode::Char('z'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        } => Some(Message::Suspend),
+        KeyEvent {
+            code: KeyCode::Esc, ..
+        } if model.current_panel == Panel::Command => Some(Message::ToggleCommand),
         KeyEvent {
             code: KeyCode::Esc, ..
         } => Some(Message::LeaveInsert),
         KeyEvent {
             code: KeyCode::Enter,
             ..
+        } if model.current_panel == Panel::Query => Some(Message::RunQuery),
+        KeyEvent {
+            code: KeyCode::Enter,
+            ..
+        } if model.current_panel == Panel::Command => Some(Message::RunCommand),
+        KeyEvent {
+            code: KeyCode::Tab, ..
+        } if model.current_panel == Panel::Command => Some(Message::CompleteCommand),
+        KeyEvent {
+            code: KeyCode::Tab, ..
+        } if model.current_panel == Panel::Url => Some(Message::CompleteUrl),
+        KeyEvent {
+            code: KeyCode::Enter,
+            modifiers: KeyModifiers::CONTROL,
+            ..
         } => Some(Message::SubmitRequest),
+        KeyEvent {
+            code: KeyCode::Enter,
+            ..
+        } if !model.editing_lines() => Some(Message::SubmitRequest),
         _ => Some(Message::InsertInput(key)),
     }
 }
 
-fn handle_visual_key(key: KeyEvent) -> Option<Message> {
+fn handle_visual_key(key: KeyEvent, model: &Model) -> Option<Message> {
     match key {
         KeyEvent {
             code: KeyCode::Esc, ..
         } => Some(Message::LeaveVisual),
+        // The other kinds of selection switch to that shape from the same start.
+        KeyEvent {
+            code: KeyCode::Char('v'),
+            modifiers: KeyModifiers::NONE,
+            ..
+        } => Some(Message::Visual(Selection::Char)),
+        KeyEvent {
+            code: KeyCode::Char('V'),
+            ..
+        } if model.multiline_input() => Some(Message::Visual(Selection::Line)),
+        KeyEvent {
+            code: KeyCode::Char('v'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        } if model.multiline_input() => Some(Message::Visual(Selection::Block)),
         KeyEvent {
             code: KeyCode::Char('y'),
             ..
         } => Some(Message::Copy),
+        KeyEvent {
+            code: KeyCode::Char('d'),
+            ..
+        } => Some(Message::Cut),
         _ => Some(Message::NormalInput(key)),
     }
 }
 
-fn globally_pre_handle_normal_key(key: KeyEvent) -> Option<Message> {
+// Suspending, moving between panes and macros work whatever has focus. Moving is a no-op in
+// popups.
+fn handle_normal_frame_key(key: KeyEvent) -> Option<Message> {
     match key.modifiers {
         KeyModifiers::CONTROL => match key.code {
+            KeyCode::Char('z') => Some(Message::Suspend),
             KeyCode::Char('h') => Some(Message::SelectPanelLeft),
             KeyCode::Char('j') => Some(Message::SelectPanelDown),
             KeyCode::Char('k') => Some(Message::SelectPanelUp),
             KeyCode::Char('l') => Some(Message::SelectPanelRight),
             _ => None,
         },
-        KeyModifiers::NONE => match key.code {
+        KeyModifiers::NONE | KeyModifiers::SHIFT => match key.code {
+            KeyCode::Char('q') => Some(Message::Record),
+            KeyCode::Char('@') => Some(Message::Replay),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Keys for anything with text in it.
+fn handle_normal_editing_key(key: KeyEvent) -> Option<Message> {
+    match key.modifiers {
+        KeyModifiers::NONE | KeyModifiers::SHIFT => match key.code {
             KeyCode::Char('a') => Some(Message::Append),
             KeyCode::Char('i') => Some(Message::Insert),
-            KeyCode::Char('v') => Some(Message::Visual),
+            KeyCode::Char('v') => Some(Message::Visual(Selection::Char)),
+            KeyCode::Char('.') => Some(Message::Repeat),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Line and block selections, for the panes with more than one line.
+fn handle_normal_multiline_key(key: KeyEvent) -> Option<Message> {
+    match (key.modifiers, key.code) {
+        (_, KeyCode::Char('V')) => Some(Message::Visual(Selection::Line)),
+        (KeyModifiers::CONTROL, KeyCode::Char('v')) => Some(Message::Visual(Selection::Block)),
+        _ => None,
+    }
+}
+
+// The preview is read-only, but its text can still be selected and copied.
+fn handle_normal_visual_key(key: KeyEvent) -> Option<Message> {
+    match (key.modifiers, key.code) {
+        (KeyModifiers::NONE, KeyCode::Char('v')) => Some(Message::Visual(Selection::Char)),
+        _ => None,
+    }
+}
+
+// Keys that work from every pane, unless the pane uses them for something else.
+fn handle_normal_app_key(key: KeyEvent) -> Option<Message> {
+    if key.code == KeyCode::Enter {
+        return Some(Message::SubmitRequest);
+    }
+    match key.modifiers {
+        KeyModifiers::NONE | KeyModifiers::SHIFT => match key.code {
+            KeyCode::Char('R') => Some(Message::RunCollection),
+            KeyCode::Char('S') => Some(Message::ToggleSettings),
+            KeyCode::Char('H') => Some(Message::ToggleHistory),
+            KeyCode::Char('N') => Some(Message::ToggleDescription),
+            KeyCode::Char('B') => Some(Message::ToggleFiles),
+            KeyCode::Char('C') => Some(Message::ToggleCookies),
+            KeyCode::Char('O') => Some(Message::Authorize),
+            KeyCode::Char(']') => Some(Message::NextRequest),
+            KeyCode::Char('[') => Some(Message::PreviousRequest),
+            KeyCode::Char('m') | KeyCode::Char(' ') => Some(Message::ToggleMenu),
+            KeyCode::Char('Y') => Some(Message::CopyAsCurl),
+            KeyCode::Char('I') => Some(Message::ImportClipboard),
+            KeyCode::Char(':') => Some(Message::ToggleCommand),
             _ => None,
         },
         _ => None,
@@ -182,8 +943,11 @@ fn handle_normal_method_key(key: KeyEvent) -> Option<Message> {
     }
 }
 
-fn handle_normal_url_key(_key: KeyEvent) -> Option<Message> {
-    None
+fn handle_normal_url_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Char('P') => Some(Message::TogglePreview),
+        _ => None,
+    }
 }
 
 fn handle_normal_input_key(key: KeyEvent) -> Option<Message> {
@@ -196,24 +960,184 @@ fn handle_normal_input_key(key: KeyEvent) -> Option<Message> {
         KeyCode::Left if key.modifiers == KeyModifiers::SHIFT | KeyModifiers::CONTROL => {
             Some(Message::PreviousInputFormat)
         }
+        KeyCode::Char('R') => Some(Message::ToggleRawInput),
+        KeyCode::Char('E') => Some(Message::EditBody),
+        _ => None,
+    }
+}
+
+// Keys for a row of key-value fields, in the Input pane and the table popups.
+fn handle_normal_table_row_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
         KeyCode::Tab => Some(Message::NextInputField),
         KeyCode::BackTab => Some(Message::PreviousInputField),
+        KeyCode::Char('D') => Some(Message::DeleteRow),
         _ => None,
     }
 }
 
-fn handle_normal_output_key(_key: KeyEvent) -> Option<Message> {
-    None
+// Keys for a header or body row, which can be reordered and switched off.
+fn handle_normal_movable_row_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Char('J') => Some(Message::MoveRowDown),
+        KeyCode::Char('K') => Some(Message::MoveRowUp),
+        KeyCode::Char('#') | KeyCode::Char(' ') => Some(Message::ToggleRowDisabled),
+        KeyCode::Char('A') => Some(Message::AddAutoHeaders),
+        _ => None,
+    }
 }
 
-fn globally_post_handle_normal_key(key: KeyEvent) -> Option<Message> {
+fn handle_normal_auth_key(key: KeyEvent) -> Option<Message> {
     match key.code {
-        KeyCode::Enter => Some(Message::SubmitRequest),
-        _ => Some(Message::NormalInput(key)),
+        KeyCode::Char('*') => Some(Message::ToggleRevealSecrets),
+        _ => None,
+    }
+}
+
+fn handle_normal_raw_input_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Char('R') => Some(Message::ToggleRawInput),
+        KeyCode::Char('E') => Some(Message::EditBody),
+        KeyCode::Char('=') => Some(Message::FormatBody),
+        _ => None,
+    }
+}
+
+fn handle_normal_output_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Char('W') => Some(Message::SaveResponse),
+        KeyCode::Char('E') => Some(Message::SaveResponseExample),
+        KeyCode::Char('}') => Some(Message::NextExample),
+        KeyCode::Char('{') => Some(Message::PreviousExample),
+        KeyCode::Char('/') => Some(Message::ToggleQuery),
+        KeyCode::Char('P') => Some(Message::CopyJsonPath),
+        KeyCode::Char('D') => Some(Message::DiffResponse),
+        KeyCode::Char('=') => Some(Message::ToggleFormat),
+        KeyCode::Char('o') => Some(Message::OpenOutput),
+        _ => None,
+    }
+}
+
+fn handle_normal_settings_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::ToggleSettings),
+        _ => None,
+    }
+}
+
+fn handle_normal_cookies_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::ToggleCookies),
+        _ => None,
+    }
+}
+
+fn handle_normal_mock_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::ToggleMock),
+        _ => None,
+    }
+}
+
+fn handle_normal_query_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::ToggleQuery),
+        KeyCode::Enter => Some(Message::RunQuery),
+        _ => None,
+    }
+}
+
+fn handle_normal_preview_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('P') => Some(Message::TogglePreview),
+        _ => None,
+    }
+}
+
+fn handle_normal_description_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('N') => Some(Message::ToggleDescription),
+        _ => None,
+    }
+}
+
+fn handle_normal_diff_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('D') => Some(Message::CloseDiff),
+        _ => None,
+    }
+}
+
+fn handle_normal_messages_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::ToggleMessages),
+        _ => None,
+    }
+}
+
+fn handle_normal_menu_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('m') | KeyCode::Char(' ') => Some(Message::ToggleMenu),
+        KeyCode::Char('j') | KeyCode::Down => Some(Message::NextMenuItem),
+        KeyCode::Char('k') | KeyCode::Up => Some(Message::PreviousMenuItem),
+        KeyCode::Enter => Some(Message::ChooseMenuItem),
+        _ => None,
+    }
+}
+
+fn handle_normal_command_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::ToggleCommand),
+        KeyCode::Enter => Some(Message::RunCommand),
+        KeyCode::Tab => Some(Message::CompleteCommand),
+        _ => None,
+    }
+}
+
+fn handle_normal_history_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::ToggleHistory),
+        KeyCode::Char('j') | KeyCode::Down => Some(Message::NextHistoryEntry),
+        KeyCode::Char('k') | KeyCode::Up => Some(Message::PreviousHistoryEntry),
+        KeyCode::Enter => Some(Message::ReplayHistoryEntry),
+        KeyCode::Char('e') => Some(Message::SaveHistoryExample),
+        KeyCode::Char('D') => Some(Message::DiffHistoryEntry),
+        _ => None,
+    }
+}
+
+fn handle_normal_runner_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::ToggleRunner),
+        KeyCode::Char('j') | KeyCode::Down => Some(Message::NextOutcome),
+        KeyCode::Char('k') | KeyCode::Up => Some(Message::PreviousOutcome),
+        KeyCode::Enter => Some(Message::OpenOutcome),
+        _ => None,
+    }
+}
+
+fn handle_normal_files_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::ToggleFiles),
+        KeyCode::Char('j') | KeyCode::Down => Some(Message::NextFile),
+        KeyCode::Char('k') | KeyCode::Up => Some(Message::PreviousFile),
+        KeyCode::Enter => Some(Message::OpenFile),
+        _ => None,
+    }
+}
+
+fn handle_normal_authorize_key(key: KeyEvent) -> Option<Message> {
+    match key.code {
+        KeyCode::Esc => Some(Message::CloseAuthorization),
+        _ => None,
     }
 }
 
 fn update(model: &mut Model, msg: Message) -> Option<Message> {
+    // A count is only for the motion or . typed right after it.
+    if !matches!(msg, Message::NormalInput(_) | Message::Repeat) {
+        model.clear_count();
+    }
     match msg {
         Message::Append => model.append(),
         Message::Insert => model.insert(),
@@ -222,7 +1146,7 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             return Some(Message::Normal);
         }
         Message::Normal => model.normal(),
-        Message::Visual => model.visual(),
+        Message::Visual(shape) => model.visual(shape),
         Message::LeaveVisual => {
             model.leave_visual();
             return Some(Message::Normal);
@@ -231,12 +1155,37 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
         Message::SelectPanelDown => model.select_panel_down(),
         Message::SelectPanelUp => model.select_panel_up(),
         Message::SelectPanelRight => model.select_panel_right(),
+        Message::ToggleSettings => model.toggle_settings(),
+        Message::ToggleHistory => model.toggle_history(),
+        Message::ToggleRunner => model.toggle_runner(),
+        Message::ToggleFiles => model.toggle_files(),
+        Message::ToggleCookies => model.toggle_cookies(),
+        Message::ToggleMock => model.toggle_mock(),
+        Message::ToggleQuery => model.toggle_query(),
+        Message::RunQuery => model.run_query(),
+        Message::ToggleMenu => model.toggle_menu(),
+        Message::NextMenuItem => model.next_menu_item(),
+        Message::PreviousMenuItem => model.previous_menu_item(),
+        Message::ToggleCommand => model.toggle_command(),
+        Message::CompleteCommand => model.complete_command(),
+        Message::CompleteUrl => model.complete_url(),
+        Message::RunCommand => model.run_command(),
+        Message::ChooseMenuItem => {
+            let key = model.choose_menu_item()?;
+            return handle_normal_key(key, model);
+        }
+        Message::NextRequest => model.next_request(),
+        Message::PreviousRequest => model.previous_request(),
         Message::NextMethod => model.next_method(),
         Message::PreviousMethod => model.previous_method(),
         Message::Copy => {
             model.copy();
             return Some(Message::Normal);
         }
+        Message::Cut => {
+            model.cut();
+            return Some(Message::Normal);
+        }
         Message::InsertInput(key_event) => model.handle_insert_input(key_event),
         Message::NormalInput(key_event) => model.handle_normal_input(key_event),
         Message::NextInputType => model.next_input_type(),
@@ -245,8 +1194,59 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
         Message::PreviousInputField => model.previous_input_field(),
         Message::NextInputFormat => model.next_input_format(),
         Message::PreviousInputFormat => model.previous_input_format(),
-        Message::SubmitRequest => model.submit_request(),
-        Message::Quit => model.exit = true,
+        Message::ToggleRawInput => model.toggle_raw_input(),
+        Message::DeleteRow => model.delete_row(),
+        Message::MoveRowDown => model.move_row_down(),
+        Message::MoveRowUp => model.move_row_up(),
+        Message::ToggleRowDisabled => model.toggle_row_disabled(),
+        Message::ToggleRevealSecrets => model.toggle_reveal_secrets(),
+        Message::NextHistoryEntry => model.next_history_entry(),
+        Message::PreviousHistoryEntry => model.previous_history_entry(),
+        Message::ReplayHistoryEntry => model.replay_history_entry(),
+        Message::DiffHistoryEntry => model.diff_history_entry(),
+        Message::RunCollection => model.run_collection(),
+        Message::NextOutcome => model.next_outcome(),
+        Message::PreviousOutcome => model.previous_outcome(),
+        Message::OpenOutcome => model.open_outcome(),
+        Message::NextFile => model.next_file(),
+        Message::PreviousFile => model.previous_file(),
+        Message::OpenFile => model.open_selected_file(),
+        Message::Authorize => model.authorize(),
+        Message::CloseAuthorization => model.close_authorization(),
+        Message::SubmitRequest => {
+            model.submit_request();
+        }
+        Message::SaveResponse => model.save_response(),
+        Message::CopyAsCurl => model.copy_as_curl(),
+        Message::TogglePreview => model.toggle_preview(),
+        Message::ToggleDescription => model.toggle_description(),
+        Message::AddAutoHeaders => model.add_auto_headers(),
+        Message::DiffResponse => model.diff_response(),
+        Message::ToggleFormat => model.toggle_format(),
+        Message::OpenOutput => model.open_output(""),
+        Message::EditBody => model.edit_body(),
+        Message::FormatBody => model.format_raw_body(),
+        Message::CloseDiff => model.close_diff(),
+        Message::ToggleMessages => model.toggle_messages(),
+        Message::ImportClipboard => model.import_clipboard(),
+        Message::Record => model.record(),
+        Message::Replay => model.replay(),
+        Message::ChooseRegister(key_event) => model.choose_register(key_event),
+        Message::ConfirmQuit(key_event) => {
+            model.confirm_quit(key_event);
+            // : goes on to open the command line, for :q!.
+            if key_event.code == KeyCode::Char(':') && model.current_mode == Mode::Normal {
+                return Some(Message::ToggleCommand);
+            }
+        }
+        Message::Repeat => model.repeat_change(),
+        Message::CopyJsonPath => model.copy_json_path(),
+        Message::SaveResponseExample => model.save_response_example(),
+        Message::SaveHistoryExample => model.save_history_example(),
+        Message::NextExample => model.next_example(),
+        Message::PreviousExample => model.previous_example(),
+        Message::Suspend => model.suspend = true,
+        Message::Quit => model.quit(),
     };
     None
 }
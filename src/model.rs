@@ -1,32 +1,65 @@
-use std::collections::HashMap;
+use std::env;
 use std::fmt;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::iter::Iterator;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, ExitStatus, Stdio};
 use std::str;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use clippers::Clipboard;
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use enum_iterator::Sequence;
-use http_auth_basic::Credentials;
-use json::JsonValue;
-use log::error;
 use nonempty::{nonempty, NonEmpty};
-use pest::Parser;
-use pest_derive::Parser;
 use ratatui::widgets::ListState;
-use regex::RegexBuilder;
-use reqwest::{blocking::Client, Method, Url};
-use tui_textarea::{CursorMove, TextArea};
+use regex::{Regex, RegexBuilder};
+#[cfg(feature = "grpc")]
+use reqcoon_core::grpc;
+use reqcoon_core::{
+    assertion::{self, Response},
+    audit,
+    cache::{self, Responses},
+    cookies::{self, Cookie},
+    curl,
+    diff::{self, Change},
+    examples::{self, Example, Examples},
+    file::{self, Environments, RequestFile},
+    folder::Folder,
+    history::{self, Entry},
+    json_path,
+    load::{self, Report},
+    markup,
+    mock::{self, Received, Route},
+    oauth::{self, DeviceAuthorization, Token, Tokens},
+    query,
+    request::{self, Auth as RequestAuth, Request},
+    response::{self, format_size},
+    runner::{self, Outcome},
+    secrets::{self, Secret},
+    settings::{self, Settings},
+    snapshot::{self, Snapshot, Snapshots},
+    variables,
+};
+use reqwest::{
+    blocking::{Client, Request as HttpRequest, RequestBuilder, Response as HttpResponse},
+    header::CONTENT_TYPE,
+    Method, StatusCode, Url,
+};
+use tui_textarea::{CursorMove, Scrolling, TextArea};
 
 use crate::tmux::{select_tmux_panel, Direction};
 
+pub use reqcoon_core::request::BodyFormat;
+
 #[derive(Default, PartialEq)]
 pub enum Mode {
     #[default]
     Normal,
     Insert,
-    Visual,
+    Visual(Selection),
 }
 
 impl fmt::Display for Mode {
@@ -34,34 +67,200 @@ impl fmt::Display for Mode {
         match self {
             Mode::Normal => write!(f, "Normal"),
             Mode::Insert => write!(f, "Insert"),
-            Mode::Visual => write!(f, "Visual"),
+            Mode::Visual(Selection::Char) => write!(f, "Visual"),
+            Mode::Visual(Selection::Line) => write!(f, "Visual Line"),
+            Mode::Visual(Selection::Block) => write!(f, "Visual Block"),
         }
     }
 }
 
-#[derive(Default, PartialEq, Sequence)]
+// What Visual mode selects between the cursor and where it started: the characters from one to
+// the other, every line they're on, or the columns between them on each of those lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    Char,
+    Line,
+    Block,
+}
+
+#[derive(Debug, Default, PartialEq, Sequence)]
 pub enum Panel {
     #[default]
     Method,
     Url,
     Input,
     Output,
+    Settings,
+    History,
+    Runner,
+    Files,
+    Authorize,
+    Cookies,
+    Query,
+    Menu,
+    Command,
+    Preview,
+    Description,
+    Diff,
+    Mock,
+    Messages,
 }
 
-#[derive(Default, PartialEq, Sequence)]
+// The most times a count like 5w repeats a motion.
+const MAX_COUNT: usize = 10_000;
+
+// The commands the : line runs, and what each one does.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("w", "Save the request file"),
+    ("q", "Quit"),
+    ("q!", "Quit and undo edits since loading or :w"),
+    ("wq", "Save the request file and quit"),
+    ("send", "Send the request"),
+    ("run", "Run every request in the file"),
+    ("duplicate", "Add a copy of the request after the others"),
+    ("e", "Open another request file, or pick one"),
+    ("import curl", "Replace the request with a curl command"),
+    ("env", "Switch to an environment, or back to none"),
+    ("secret", "Set a variable kept outside the request file"),
+    (
+        "snapshot",
+        "Save the response for snapshot assertions to compare against",
+    ),
+    ("watch", "Send the request whenever its body file changes"),
+    ("poll", "Send the request every few seconds, or stop"),
+    ("audit", "Pause or resume logging requests to the audit log"),
+    ("load", "Send the request many times and report how fast"),
+    ("s/", "Replace a pattern in the field, like s/old/new/g"),
+    ("%s/", "Replace a pattern in every field of the request"),
+    (
+        "open",
+        "Open the response in $PAGER, or $EDITOR with editor",
+    ),
+    ("history", "Show the request history"),
+    ("messages", "Show the messages shown so far"),
+    ("settings", "Show the workspace settings"),
+    ("cookies", "Show the cookies for the URL"),
+    (
+        "mock",
+        "Show the mock routes, serve them on a port, or stop",
+    ),
+    ("catch", "Show every request sent to a port, or stop"),
+    ("oauth", "Sign in with OAuth2"),
+    #[cfg(feature = "grpc")]
+    ("grpc", "List the gRPC methods, or call one"),
+];
+
+// An action in the context menu. Choosing it presses its key in the pane the menu was opened from.
+pub struct MenuItem {
+    pub keys: &'static str,
+    pub key: KeyEvent,
+    pub label: &'static str,
+}
+
+impl MenuItem {
+    fn new(
+        keys: &'static str,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+        label: &'static str,
+    ) -> Self {
+        MenuItem {
+            keys,
+            key: KeyEvent::new(code, modifiers),
+            label,
+        }
+    }
+}
+
+// How the last request went, which the Output panel is colored by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResponseStatus {
+    Code(u16),
+    // The request never got a response, like when the server couldn't be reached.
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Info => write!(f, "INFO"),
+            Level::Warning => write!(f, "WARN"),
+            Level::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+// A message that was shown in the status bar, kept so it can be read after the next one replaces
+// it.
+#[derive(Clone, Debug)]
+pub struct LoggedMessage {
+    pub time: SystemTime,
+    pub level: Level,
+    pub text: String,
+}
+
+impl LoggedMessage {
+    // The time is in UTC, like the history's.
+    pub fn line(&self) -> String {
+        let secs = self
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        format!(
+            "{:02}:{:02}:{:02} {:<5} {}",
+            secs % 86400 / 3600,
+            secs % 3600 / 60,
+            secs % 60,
+            self.level.to_string(),
+            self.text
+        )
+    }
+}
+
+// The most messages :messages keeps, dropping the oldest first.
+const MAX_MESSAGES: usize = 500;
+
+// Sending the request over and over with :poll, to watch something like a job's status change.
+pub struct Polling {
+    pub interval: Duration,
+    pub count: usize,
+    // When a response last came back different from the one before it.
+    pub changed: Option<Instant>,
+    next: Instant,
+}
+
+pub enum Authorization {
+    Device(DeviceAuthorization),
+    // The authorization URL opened in the browser.
+    Browser(String),
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Sequence)]
 pub enum InputType {
     #[default]
     Auth,
+    Path,
     Headers,
     Body,
+    Options,
 }
 
 impl fmt::Display for InputType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             InputType::Auth => write!(f, "Auth"),
+            InputType::Path => write!(f, "Path"),
             InputType::Headers => write!(f, "Headers"),
             InputType::Body => write!(f, "Body"),
+            InputType::Options => write!(f, "Options"),
         }
     }
 }
@@ -84,33 +283,27 @@ impl fmt::Display for AuthFormat {
     }
 }
 
-#[derive(Default, Sequence)]
-pub enum BodyFormat {
-    #[default]
-    Json,
-    Form,
-}
-
-impl fmt::Display for BodyFormat {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            BodyFormat::Json => write!(f, "JSON"),
-            BodyFormat::Form => write!(f, "Form"),
-        }
-    }
-}
-
-#[derive(Default, PartialEq, Sequence)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Sequence)]
 pub enum InputField {
     #[default]
     Key,
     Value,
 }
 
+// Where the cursor was in one of the Input pane's input types.
+#[derive(Clone, Copy)]
+struct InputFocus {
+    input_type: InputType,
+    input_index: usize,
+    input_field: InputField,
+}
+
 #[derive(Default)]
 pub struct InputRow {
     pub key: TextArea<'static>,
     pub value: TextArea<'static>,
+    // Switched off rows stay in the table but aren't sent.
+    pub disabled: bool,
 }
 
 impl InputRow {
@@ -155,12 +348,30 @@ impl Auth {
     }
 }
 
-#[derive(Parser)]
-#[grammar = "http.pest"]
-struct RequestParser;
-
 pub struct Model {
     pub filename: String,
+    pub name: String,
+    // Notes on the request, edited in their own popup.
+    pub description_input: TextArea<'static>,
+    script: Option<String>,
+    standalone: bool,
+    folder: Folder,
+    captures: Vec<(String, String)>,
+    assertions: Vec<String>,
+    pub variables: Vec<(String, String)>,
+    // Variables whose values are kept outside the request file. Their values are among the
+    // variables while reqcoon runs, but aren't saved with them.
+    secrets: Vec<Secret>,
+    // The values of the secrets kept in the secrets file.
+    pub secret_values: secrets::Values,
+    // Values captured from responses this session. They shadow the file's variables but aren't
+    // written back to it, since they're often tokens.
+    pub captured: Vec<(String, String)>,
+    requests: Vec<Request>,
+    pub request_index: usize,
+    // Whether the editor holds a request sent over the control socket instead of the selected one.
+    // It's never saved, and is left behind when another request is selected.
+    pub scratch: bool,
     pub current_mode: Mode,
     pub current_panel: Panel,
     pub list_state: ListState,
@@ -171,19 +382,174 @@ pub struct Model {
     pub current_input_type: InputType,
     pub current_input_field: InputField,
     pub current_body_format: BodyFormat,
+    // A body the Body table can't show, like XML or a JSON array, kept as it was written and
+    // edited in the raw request.
+    text_body: Option<String>,
     pub input_index: usize,
+    // A row for each placeholder in the URL's path, which reqcoon keeps in step with the URL.
+    pub path_input_table: NonEmpty<InputRow>,
     pub headers_input_table: NonEmpty<InputRow>,
     pub body_input_table: NonEmpty<InputRow>,
+    pub options_input_table: NonEmpty<InputRow>,
+    pub settings_input_table: NonEmpty<InputRow>,
+    // Settings from the user's config, under the workspace settings.
+    default_settings: Vec<(String, String)>,
+    // The config's settings for each environment, over its defaults while that one is active.
+    default_environments: Environments,
+    // The cookies for the request's URL while the cookies popup is open, in the order shown.
+    pub cookies_input_table: NonEmpty<InputRow>,
+    cookies_shown: Vec<Cookie>,
+    cookies_url: Option<Url>,
+    // Routes like "GET /users" and the responses the mock server answers them with, like
+    // `200 {"id": 1}`, and the requests it has received.
+    pub mock_input_table: NonEmpty<InputRow>,
+    pub mock_server: Option<mock::Server>,
+    pub mock_log: Vec<Received>,
+    // The server :catch started, which shows every request it receives.
+    pub catcher: Option<mock::Server>,
+    environments: Environments,
+    pub environment: Option<String>,
+    popup_return: Option<(Panel, usize, InputField)>,
+    // Where the cursor last was in each input type, so switching back to one returns to its row.
+    input_focus: Vec<InputFocus>,
+    // The input type and input focus of each request switched away from, by request index.
+    request_focus: Vec<(usize, InputType, Vec<InputFocus>)>,
+    // The row and field the settings popup was closed on.
+    settings_focus: (usize, InputField),
+    pub history: Vec<Entry>,
+    // The directory reqcoon was opened on, whose request files the file picker lists.
+    pub workspace: Option<PathBuf>,
+    pub files: Vec<PathBuf>,
+    // The request file to switch to, which is opened in place of this one once it's been saved.
+    pub open_file: Option<String>,
+    // The last response to each request, kept between sessions.
+    pub cached_responses: Responses,
+    pub outcomes: Vec<Outcome>,
+    pub examples: Examples,
+    // The example shown in the Output panel in place of the live response, which is kept aside.
+    pub example_index: Option<usize>,
+    pub snapshots: Snapshots,
+    live_output: Option<TextArea<'static>>,
+    // An authorization the user hasn't finished yet, and where its token will arrive.
+    pub authorization: Option<Authorization>,
+    pending_token: Option<Receiver<Result<Token, String>>>,
+    // Whether the pending token is a refresh of the current one rather than a new sign in.
+    pub refreshing_token: bool,
+    // Ticks since reqcoon started, which turn the status bar's spinner.
+    pub ticks: usize,
+    pub oauth_tokens: Tokens,
+    pub raw_mode: bool,
+    pub raw_input: TextArea<'static>,
+    pending_motion: Option<char>,
+    // The last f, F, t or T and the character it looked for, for ; and , to look again.
+    last_find: Option<(char, char)>,
+    // The header or body row yy copied, for p to put below the cursor.
+    yanked_row: Option<(String, String, bool)>,
+    // Whether q or @ was pressed and the next key names the register to record to or replay.
+    awaiting_register: Option<char>,
+    // The register being recorded to and the keys pressed since.
+    pub recording: Option<(char, Vec<KeyEvent>)>,
+    pub macros: Vec<(char, Vec<KeyEvent>)>,
+    last_macro: Option<char>,
+    // The keys of the macro @ asked for, for the main loop to replay, and the registers being
+    // replayed, so a macro can't replay itself forever.
+    pub pending_macro: Option<(char, Vec<KeyEvent>)>,
+    pub replaying: Vec<char>,
+    // The count typed before a motion, like the 5 in 5w.
+    count: Option<usize>,
+    // The keys of the command being typed, from its first key until it's back in normal mode with
+    // nothing pending, and those of the last one that changed something, for . to repeat.
+    command_keys: Vec<KeyEvent>,
+    last_change: Vec<KeyEvent>,
+    changed: bool,
+    // Whether the Basic password and Bearer token are shown rather than starred out.
+    pub reveal_secrets: bool,
+    selection_anchor: Option<(usize, usize)>,
     pub output_row: usize,
+    // The top row and left column of the Output panel and its height, as the view last drew it.
+    pub output_scroll: (usize, usize),
+    pub output_height: usize,
     pub output_input: TextArea<'static>,
-    pub message: String,
+    pub query_input: TextArea<'static>,
+    // The Output panel as it was before a query replaced it with the matches.
+    unqueried_output: Option<TextArea<'static>>,
+    // Whether XML and HTML responses are indented, and the response as it came when it has been.
+    pub format_markup: bool,
+    unformatted_output: Option<TextArea<'static>>,
+    pub command_input: TextArea<'static>,
+    // The request as it will be sent, shown by the preview.
+    pub preview_input: TextArea<'static>,
+    pub diff_input: TextArea<'static>,
+    // The latest response to each request and the one before it, by request index.
+    responses: Vec<(usize, String, Option<String>)>,
+    // What was typed before Tab started cycling through its completions, and the one shown.
+    command_completion: Option<(String, usize)>,
+    // A curl command or URL copied outside reqcoon, waiting to be imported with I.
+    pub clipboard_offer: Option<String>,
+    // The body file :watch sends the request again for, and when it was last changed.
+    watched_body: Option<(PathBuf, Option<SystemTime>)>,
+    pub polling: Option<Polling>,
+    // Set with :audit to stop logging requests to the audit-log file for a while.
+    pub audit_paused: bool,
+    // A load test running in the background, and where its report will arrive.
+    load_test: Option<Receiver<Result<Report, String>>>,
+    // How often :poll sends the request without a number of seconds.
+    pub poll_interval: Duration,
+    pub response_body: Option<response::Body>,
+    // The Content-Type of the response in the Output panel, which picks how it's highlighted.
+    pub output_content_type: Option<String>,
+    // The headers of the response in the Output panel, for copying.
+    output_headers: Vec<(String, String)>,
+    // The version and status the last response came back with, like "HTTP/2.0 200 OK".
+    pub status_line: Option<String>,
+    pub response_status: Option<ResponseStatus>,
+    // Every message shown this session, oldest first, and the popup :messages shows them in. The
+    // last is in the status bar until it's cleared.
+    pub messages: Vec<LoggedMessage>,
+    message_cleared: bool,
+    pub messages_input: TextArea<'static>,
+    // The request file as it was last saved or loaded, to tell whether there are edits to save.
+    saved: String,
+    // The file as it was on disk when loaded or last saved with :w, which :q! puts back, since
+    // autosaves don't count. None when there was no file.
+    committed: Option<String>,
+    last_save: Instant,
+    pub suspend: bool,
+    // A program to hand the terminal to and the file to open in it, like $PAGER and the response.
+    pub external: Option<(String, PathBuf)>,
+    // How the body handed to $EDITOR is read back once the editor exits.
+    external_body: Option<BodyEdit>,
+    // Set when quitting has to be confirmed, and the next key answers.
+    pub confirming_quit: bool,
+    // Set by :q!, to put the request file back as it was loaded or last saved with :w.
+    pub discard_edits: bool,
     pub exit: bool,
 }
 
+#[derive(Clone, Copy)]
+enum BodyEdit {
+    Rows,
+    Raw,
+}
+
 impl Model {
     pub fn new(filename: String) -> Model {
         Model {
             filename,
+            name: String::default(),
+            description_input: TextArea::default(),
+            script: None,
+            standalone: false,
+            folder: Folder::default(),
+            captures: vec![],
+            assertions: vec![],
+            variables: vec![],
+            secrets: vec![],
+            secret_values: vec![],
+            captured: vec![],
+            requests: vec![Request::default()],
+            request_index: 0,
+            scratch: false,
             current_mode: Mode::default(),
             current_panel: Panel::default(),
             list_state: ListState::default().with_selected(Some(0)),
@@ -194,12 +560,95 @@ impl Model {
             current_input_field: InputField::default(),
             auth: Auth::default(),
             current_body_format: BodyFormat::default(),
+            text_body: None,
             input_index: 0,
+            path_input_table: nonempty![InputRow::default()],
             headers_input_table: nonempty![InputRow::default()],
             body_input_table: nonempty![InputRow::default()],
+            options_input_table: Self::settings_table(&[]),
+            settings_input_table: Self::settings_table(&[]),
+            default_settings: vec![],
+            default_environments: vec![],
+            cookies_input_table: nonempty![InputRow::default()],
+            cookies_shown: vec![],
+            cookies_url: None,
+            mock_input_table: nonempty![InputRow::default()],
+            mock_server: None,
+            mock_log: vec![],
+            catcher: None,
+            environments: vec![],
+            environment: None,
+            popup_return: None,
+            input_focus: vec![],
+            request_focus: vec![],
+            settings_focus: (0, InputField::default()),
+            history: vec![],
+            workspace: None,
+            files: vec![],
+            open_file: None,
+            cached_responses: vec![],
+            outcomes: vec![],
+            examples: vec![],
+            example_index: None,
+            snapshots: vec![],
+            live_output: None,
+            authorization: None,
+            pending_token: None,
+            refreshing_token: false,
+            ticks: 0,
+            oauth_tokens: vec![],
+            raw_mode: false,
+            raw_input: TextArea::default(),
+            pending_motion: None,
+            last_find: None,
+            yanked_row: None,
+            awaiting_register: None,
+            recording: None,
+            macros: vec![],
+            last_macro: None,
+            pending_macro: None,
+            replaying: vec![],
+            count: None,
+            command_keys: vec![],
+            last_change: vec![],
+            changed: false,
+            reveal_secrets: false,
+            selection_anchor: None,
             output_row: 0,
+            output_scroll: (0, 0),
+            output_height: 0,
             output_input: TextArea::default(),
-            message: String::default(),
+            query_input: TextArea::default(),
+            unqueried_output: None,
+            format_markup: false,
+            unformatted_output: None,
+            command_input: TextArea::default(),
+            preview_input: TextArea::default(),
+            diff_input: TextArea::default(),
+            responses: vec![],
+            command_completion: None,
+            clipboard_offer: None,
+            watched_body: None,
+            polling: None,
+            audit_paused: false,
+            load_test: None,
+            poll_interval: Duration::from_secs(5),
+            response_body: None,
+            output_content_type: None,
+            output_headers: vec![],
+            status_line: None,
+            response_status: None,
+            messages: vec![],
+            message_cleared: false,
+            messages_input: TextArea::default(),
+            saved: String::default(),
+            committed: None,
+            last_save: Instant::now(),
+            suspend: false,
+            external: None,
+            external_body: None,
+            confirming_quit: false,
+            discard_edits: false,
             exit: false,
         }
     }
@@ -209,145 +658,640 @@ impl Model {
         let mut file = File::open(filename.clone())?;
         file.read_to_string(&mut input)?;
 
-        let mut method = Method::GET;
-        let mut uri = "";
-        let mut headers_input = vec![];
-        let mut body_input = vec![];
-
-        let pairs = RequestParser::parse(Rule::request, &input)?;
-        for pair in pairs {
-            match pair.as_rule() {
-                Rule::method => method = Method::from_bytes(pair.as_str().as_bytes())?,
-                Rule::uri => uri = pair.as_str(),
-                Rule::headers => {
-                    for header in pair.into_inner() {
-                        let mut key = "";
-                        let mut value = "";
-                        for inner_rule in header.into_inner() {
-                            match inner_rule.as_rule() {
-                                Rule::header_name => key = inner_rule.as_str(),
-                                Rule::header_value => value = inner_rule.as_str(),
-                                _ => (),
-                            }
-                        }
-                        headers_input.push(InputRow {
-                            key: [key].into(),
-                            value: [value].into(),
-                        });
-                    }
+        let file = RequestFile::parse(&input)?;
+
+        // An empty file starts out as a blank request, like a new one.
+        let mut model = Self::new(filename);
+        model.folder = Folder::for_file(Path::new(&model.filename))?;
+        if let Some(request) = file.requests.first() {
+            model.load_request(request);
+        }
+        model.settings_input_table = Self::settings_table(&file.settings);
+        model.environments = file.environments;
+        model.variables = file.variables;
+        model.secrets = file.secrets;
+        model.requests = file.requests;
+
+        Ok(model)
+    }
+
+    fn load_request(&mut self, request: &Request) {
+        self.apply_request(request);
+        self.name = request.name.clone();
+        self.description_input = TextArea::from(request.description.lines());
+        self.script = request.script.clone();
+        self.captures = request.captures.clone();
+        self.assertions = request.assertions.clone();
+        self.standalone = request.standalone;
+        self.options_input_table = Self::settings_table(&request.options);
+    }
+
+    fn settings_table(settings_input: &[(String, String)]) -> NonEmpty<InputRow> {
+        let value_for = |key: &str| {
+            settings_input
+                .iter()
+                .find(|(input_key, _)| input_key == key)
+                .map_or("", |(_, value)| value.as_str())
+        };
+
+        let mut table = NonEmpty::from_vec(
+            settings::KEYS
+                .iter()
+                .map(|key| (*key, value_for(key)))
+                .chain(
+                    settings_input
+                        .iter()
+                        .filter(|(key, _)| !settings::KEYS.contains(&key.as_str()))
+                        .map(|(key, value)| (key.as_str(), value.as_str())),
+                )
+                .map(|(key, value)| InputRow {
+                    key: [key].into(),
+                    value: [value].into(),
+                    ..InputRow::default()
+                })
+                .collect(),
+        )
+        .unwrap();
+        table.push(InputRow::default());
+        table
+    }
+
+    pub fn to_file(&mut self) -> io::Result<()> {
+        let text = self.to_request_file().to_string();
+        let mut file = File::create(&self.filename)?;
+        file.write_all(text.as_bytes())?;
+
+        self.saved = text;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+
+    // Takes the request file as it is now to be the saved one, once it's been loaded.
+    pub fn mark_saved(&mut self) {
+        self.saved = self.to_request_file().to_string();
+        self.committed = fs::read_to_string(&self.filename).ok();
+    }
+
+    // Puts the request file back the way it was loaded or last saved with :w, undoing autosaves.
+    pub fn restore_committed(&self) -> io::Result<()> {
+        let current = fs::read_to_string(&self.filename).ok();
+        match &self.committed {
+            Some(text) if current.as_ref() != Some(text) => fs::write(&self.filename, text),
+            None if current.is_some() => fs::remove_file(&self.filename),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn modified(&self) -> bool {
+        self.to_request_file().to_string() != self.saved
+    }
+
+    // Saves edits once the interval has passed since the last save, so a crash doesn't lose them.
+    // A raw request that doesn't parse yet is left until it does.
+    pub fn autosave(&mut self, interval: Duration) {
+        if self.last_save.elapsed() < interval {
+            return;
+        }
+        self.save_edits();
+    }
+
+    fn save_edits(&mut self) {
+        if self.raw_mode && self.apply_raw_input().is_err() {
+            return;
+        }
+        if !self.modified() {
+            self.last_save = Instant::now();
+            return;
+        }
+        if let Err(err) = self.to_file() {
+            self.error(format!("Unable to save {}: {}", self.filename, err));
+        }
+    }
+
+    pub fn to_request_file(&self) -> RequestFile {
+        RequestFile {
+            defaults: self.defaults(),
+            settings: input_pairs(&self.settings_input_table)
+                .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            environments: self.environments.clone(),
+            environment: self.environment.clone(),
+            folder: self.folder.clone(),
+            variables: self.variables.clone(),
+            secrets: self.secrets.clone(),
+            requests: self.requests(),
+            snapshots: self.snapshots.clone(),
+        }
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests
+            .iter()
+            .enumerate()
+            .map(|(index, request)| {
+                if index == self.request_index && !self.scratch {
+                    self.to_request()
+                } else {
+                    request.clone()
                 }
-                Rule::body => {
-                    let object = json::parse(pair.as_str())?;
-                    for (key, value) in object.entries() {
-                        body_input.push(InputRow {
-                            key: [key].into(),
-                            value: [value.as_str().unwrap()].into(),
-                        })
+            })
+            .collect()
+    }
+
+    pub fn to_request(&self) -> Request {
+        Request {
+            name: self.name.clone(),
+            description: self.description(),
+            method: self.current_method.clone(),
+            url: self.url().to_string(),
+            auth: match self.auth.format {
+                AuthFormat::None => RequestAuth::None,
+                AuthFormat::Basic => RequestAuth::Basic {
+                    username: self.auth.username(),
+                    password: self.auth.password(),
+                },
+                AuthFormat::Bearer => RequestAuth::Bearer(self.auth.token()),
+            },
+            options: input_pairs(&self.options_input_table)
+                .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            headers: self.header_pairs(),
+            body: self.body_pairs(),
+            body_format: self.current_body_format,
+            raw_body: self.text_body.clone(),
+            script: self.script.clone(),
+            captures: self.captures.clone(),
+            assertions: self.assertions.clone(),
+            standalone: self.standalone,
+            path_params: self.path_pairs(),
+            disabled_headers: disabled_pairs(&self.headers_input_table),
+            disabled_body: disabled_pairs(&self.body_input_table),
+        }
+    }
+
+    // Describes where the user is without including anything that could be a credential, so it
+    // can go in crash reports.
+    pub fn crash_snapshot(&self) -> String {
+        let sensitive = |name: &str| {
+            let name = name.to_lowercase();
+            ["auth", "token", "key", "secret", "cookie", "password"]
+                .iter()
+                .any(|word| name.contains(word))
+        };
+        // URLs, like the request's or a proxy setting, lose their credentials and query string.
+        let mask = |value: &str| match Url::parse(value) {
+            Ok(mut url) if url.has_host() => {
+                let _ = url.set_username("");
+                let _ = url.set_password(None);
+                url.set_query(None);
+                url.to_string()
+            }
+            _ => value.to_string(),
+        };
+        let rows = |table: &NonEmpty<InputRow>| {
+            input_pairs(table)
+                .map(|(key, value)| {
+                    if sensitive(key) || value.is_empty() {
+                        format!("{}: <{} chars>", key, value.len())
+                    } else {
+                        format!("{}: {}", key, mask(value))
                     }
-                }
-                _ => (),
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        // Messages can quote responses and captured values, so only their level is reported.
+        let message = match self.messages.last() {
+            Some(logged) if !self.message().is_empty() => {
+                format!("{} ({} chars)", logged.level, logged.text.len())
             }
+            _ => "none".to_string(),
+        };
+
+        format!(
+            "mode: {}\npanel: {:?}\ninput: {} (row {}, field {:?})\nraw mode: {}\n\
+             request: {}/{}\nmethod: {}\nurl: {}\nauth: {}\nheaders: {}\nbody keys: {}\n\
+             options: {}\nsettings: {}\nvariables: {}\nhistory entries: {}\nmessage: {}",
+            self.current_mode,
+            self.current_panel,
+            self.current_input_type,
+            self.input_index,
+            self.current_input_field,
+            self.raw_mode,
+            self.request_index + 1,
+            self.requests.len(),
+            self.current_method,
+            mask(self.url()),
+            self.auth.format,
+            rows(&self.headers_input_table),
+            self.non_empty_body()
+                .map(|row| row.key.lines()[0].clone())
+                .collect::<Vec<String>>()
+                .join(", "),
+            rows(&self.options_input_table),
+            rows(&self.settings_input_table),
+            self.variables.len(),
+            self.history.len(),
+            message
+        )
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn request_names(&self) -> Vec<String> {
+        self.requests()
+            .into_iter()
+            .map(|request| request.name)
+            .collect()
+    }
+
+    pub fn next_request(&mut self) {
+        let index = (self.request_index + 1) % self.requests.len();
+        if let Err(err) = self.select_request(index) {
+            self.error(err);
         }
+    }
 
-        let (auth, headers) = Self::parse_headers_input(headers_input);
+    pub fn previous_request(&mut self) {
+        let index = (self.request_index + self.requests.len() - 1) % self.requests.len();
+        if let Err(err) = self.select_request(index) {
+            self.error(err);
+        }
+    }
 
-        Ok(Self {
-            filename,
-            current_mode: Mode::default(),
-            current_panel: Panel::default(),
-            list_state: ListState::default().with_selected(Some(0)),
-            current_method: method,
-            dummy_input: TextArea::default(),
-            url_input: TextArea::from([uri]),
-            auth,
-            current_input_type: InputType::default(),
-            current_input_field: InputField::default(),
-            current_body_format: BodyFormat::default(),
-            input_index: 0,
-            headers_input_table: NonEmpty::from_vec(headers)
-                .unwrap_or(nonempty![InputRow::default()]),
-            body_input_table: NonEmpty::from_vec(body_input)
-                .unwrap_or(nonempty![InputRow::default()]),
-            output_row: 0,
-            output_input: TextArea::default(),
-            message: String::default(),
-            exit: false,
-        })
+    pub fn select_request_named(&mut self, name: &str) -> Result<(), String> {
+        let index = self
+            .request_names()
+            .iter()
+            .position(|request_name| request_name == name)
+            .ok_or(format!("No request named {}", name))?;
+        self.select_request(index)
     }
 
-    fn parse_headers_input(mut headers_input: Vec<InputRow>) -> (Auth, Vec<InputRow>) {
-        match headers_input
+    fn select_request(&mut self, index: usize) -> Result<(), String> {
+        if self.raw_mode {
+            self.apply_raw_input()
+                .map_err(|err| format!("Unable to parse raw request: {}", err))?;
+            self.raw_mode = false;
+        }
+
+        self.leave_examples();
+        if !self.scratch {
+            self.requests[self.request_index] = self.to_request();
+            self.remember_input_focus();
+            let input_focus = std::mem::take(&mut self.input_focus);
+            self.request_focus
+                .retain(|(request_index, _, _)| *request_index != self.request_index);
+            self.request_focus
+                .push((self.request_index, self.current_input_type, input_focus));
+        }
+        self.scratch = false;
+
+        let request = self.requests[index].clone();
+        self.load_request(&request);
+        self.request_index = index;
+        if let Some(position) = self
+            .request_focus
             .iter()
-            .position(|input_row| input_row.key.lines()[0] == "Authorization")
+            .position(|(request_index, _, _)| *request_index == index)
         {
-            Some(index) => {
-                let re = RegexBuilder::new(r"(basic|bearer) (.*)")
-                    .case_insensitive(true)
-                    .build()
-                    .unwrap();
-                let auth_header = headers_input.remove(index);
-                match re.captures(&auth_header.value.lines()[0]) {
-                    Some(captures) => match captures[1].to_lowercase().as_str() {
-                        "basic" => {
-                            match Credentials::from_header(auth_header.value.lines()[0].clone()) {
-                                Ok(credentials) => (
-                                    Auth {
-                                        format: AuthFormat::Basic,
-                                        basic_input: InputRow {
-                                            key: TextArea::from(credentials.user_id.lines()),
-                                            value: TextArea::from(credentials.password.lines()),
-                                        },
-                                        bearer_input: TextArea::default(),
-                                    },
-                                    headers_input,
-                                ),
-                                Err(err) => {
-                                    error!("{:?}", err);
-                                    headers_input.insert(index, auth_header);
-                                    (Auth::default(), headers_input)
-                                }
-                            }
-                        }
-                        "bearer" => (
-                            Auth {
-                                format: AuthFormat::Bearer,
-                                basic_input: InputRow::default(),
-                                bearer_input: TextArea::from(captures[2].to_string().lines()),
-                            },
-                            headers_input,
-                        ),
-                        _ => {
-                            headers_input.insert(index, auth_header);
-                            (Auth::default(), headers_input)
-                        }
-                    },
-                    None => {
-                        headers_input.insert(index, auth_header);
-                        (Auth::default(), headers_input)
+            let (_, input_type, input_focus) = self.request_focus.remove(position);
+            self.current_input_type = input_type;
+            self.input_focus = input_focus;
+            self.restore_input_focus();
+        }
+
+        Ok(())
+    }
+
+    pub fn load_request_text(&mut self, input: &str) -> Result<(), String> {
+        let request =
+            Request::parse(input).map_err(|err| format!("Unable to parse request: {}", err))?;
+        self.load_scratch_request(&request)
+    }
+
+    // Shows a request from outside, like an editor, in place of the selected one, which keeps the
+    // edits made to it so far.
+    pub fn load_scratch_request(&mut self, request: &Request) -> Result<(), String> {
+        if self.raw_mode {
+            self.apply_raw_input()
+                .map_err(|err| format!("Unable to parse raw request: {}", err))?;
+            self.raw_mode = false;
+        }
+
+        self.leave_examples();
+        if !self.scratch {
+            self.requests[self.request_index] = self.to_request();
+            self.scratch = true;
+        }
+        self.load_request(request);
+
+        Ok(())
+    }
+
+    // Variables set from outside last for the session, like captured ones, rather than being saved.
+    pub fn set_variable(&mut self, name: &str, value: &str) {
+        set_pair(&mut self.captured, name, value);
+    }
+
+    // Fills in the secrets' values from where they're kept. A secret the file still has a value
+    // for is moved there, so the value is gone from the file the next time it's saved.
+    pub fn load_secrets(&mut self, stored: secrets::Values) -> Result<(), String> {
+        self.secret_values = stored;
+        let mut problems = vec![];
+        for secret in self.secrets.clone() {
+            match secret.value(&self.secret_values, &self.filename) {
+                Some(value) => set_pair(&mut self.variables, &secret.name, &value),
+                None => {
+                    let Some((_, value)) =
+                        self.variables.iter().find(|(name, _)| *name == secret.name)
+                    else {
+                        continue;
+                    };
+                    // Left as a plain variable rather than losing the value when the file is saved.
+                    if let Err(err) = secret.store(value, &mut self.secret_values, &self.filename) {
+                        problems.push(format!(
+                            "Unable to move {} out of the file: {}",
+                            secret.name, err
+                        ));
+                        self.secrets.retain(|existing| existing.name != secret.name);
                     }
                 }
             }
-            None => (Auth::default(), headers_input),
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join(". "))
+        }
+    }
+
+    // :secret name value keeps the value outside the request file, in the secrets file unless the
+    // file declares the secret somewhere else.
+    fn set_secret(&mut self, argument: &str) {
+        let Some((name, value)) = argument
+            .split_once(' ')
+            .map(|(name, value)| (name.trim(), value.trim()))
+        else {
+            self.warn("Usage: :secret <name> <value>");
+            return;
+        };
+
+        let secret = match self.secrets.iter().find(|secret| secret.name == name) {
+            Some(secret) => secret.clone(),
+            None => {
+                let secret = Secret {
+                    name: name.to_string(),
+                    source: secrets::Source::File,
+                };
+                self.secrets.push(secret.clone());
+                secret
+            }
+        };
+        match secret.store(value, &mut self.secret_values, &self.filename) {
+            Ok(()) => {
+                set_pair(&mut self.variables, name, value);
+                self.info(format!("Set the secret {{{{{}}}}}", name));
+            }
+            Err(err) => self.error(err),
         }
     }
 
-    pub fn to_file(&self) -> io::Result<()> {
-        let mut output = format!("{} {}", self.current_method, self.url_input.lines()[0]);
-        if !self.auth_string().is_empty() {
-            output.push_str("\n");
-            output.push_str(&self.auth_string());
+    fn toggle_watch(&mut self) {
+        if self.watched_body.take().is_some() {
+            self.info("Stopped watching the body file");
+            return;
         }
-        if !self.headers_string().is_empty() {
-            output.push_str("\n");
-            output.push_str(&self.headers_string());
+
+        let request = self
+            .folder
+            .apply(&self.to_request())
+            .substituted(&self.all_variables());
+        let Some(path) = request.body_file().map(PathBuf::from) else {
+            self.warn("The body isn't a file, make it a row like @payload.json");
+            return;
+        };
+        self.info(format!("Watching {}", path.display()));
+        let modified = modified(&path);
+        self.watched_body = Some((path, modified));
+    }
+
+    fn toggle_audit(&mut self) {
+        let Some(audit_log) = self.settings().ok().and_then(|settings| settings.audit_log) else {
+            self.warn("Set audit-log to a file to log requests to it");
+            return;
+        };
+        self.audit_paused = !self.audit_paused;
+        self.info(if self.audit_paused {
+            format!("Paused logging requests to {}", audit_log)
+        } else {
+            format!("Logging requests to {}", audit_log)
+        });
+    }
+
+    // Sends the request again once the watched body file has changed since it was last checked.
+    pub fn check_watched_body(&mut self) {
+        let Some((path, last)) = &mut self.watched_body else {
+            return;
+        };
+        let current = modified(path);
+        if current == *last {
+            return;
         }
-        if !self.body_string().is_empty() {
-            output.push_str("\n\n");
-            output.push_str(&self.body_string());
+
+        *last = current;
+        self.submit_request();
+    }
+
+    fn toggle_polling(&mut self, seconds: &str) {
+        if let Some(polling) = self.polling.take() {
+            self.info(format!("Stopped polling after {} requests", polling.count));
+            return;
         }
-        let mut file = File::create(&self.filename)?;
 
-        file.write_all(output.as_bytes())
+        let interval = if seconds.is_empty() {
+            self.poll_interval
+        } else {
+            match seconds.parse::<u64>() {
+                Ok(seconds) if seconds > 0 => Duration::from_secs(seconds),
+                _ => {
+                    self.warn(format!("Usage: :poll [seconds], not {}", seconds));
+                    return;
+                }
+            }
+        };
+        self.info(format!(
+            "Polling every {}s, :poll again to stop",
+            interval.as_secs()
+        ));
+        self.polling = Some(Polling {
+            interval,
+            count: 0,
+            changed: None,
+            next: Instant::now(),
+        });
+    }
+
+    // Sends the request again once the polling interval has passed since the last one came back.
+    // Polling stops when the request can't be sent at all, like when its URL is invalid.
+    pub fn check_polling(&mut self) {
+        match &self.polling {
+            Some(polling) if Instant::now() >= polling.next => (),
+            _ => return,
+        }
+
+        if !self.submit_request() {
+            self.polling = None;
+            return;
+        }
+        let changed = matches!(
+            self.latest_response(),
+            Some((_, latest, Some(previous))) if latest != previous
+        );
+        let Some(polling) = &mut self.polling else {
+            return;
+        };
+        polling.count += 1;
+        if changed {
+            polling.changed = Some(Instant::now());
+        }
+        polling.next = Instant::now() + polling.interval;
+    }
+
+    // Like "poll #3 every 5s · changed 12s ago", for the Output panel's title.
+    pub fn polling_status(&self) -> Option<String> {
+        let polling = self.polling.as_ref()?;
+        let changed = match polling.changed {
+            Some(changed) => format!("changed {}s ago", changed.elapsed().as_secs()),
+            None => "no change yet".to_string(),
+        };
+        Some(format!(
+            "poll #{} every {}s · {}",
+            polling.count,
+            polling.interval.as_secs(),
+            changed
+        ))
+    }
+
+    // Takes the number of requests to send and how many to have in flight at once, like
+    // `:load 200 10`. Responses aren't kept in the history.
+    fn start_load_test(&mut self, argument: &str) {
+        if self.load_test.is_some() {
+            self.warn("A load test is already running");
+            return;
+        }
+        let counts: Vec<usize> = argument
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .unwrap_or_default();
+        let (requests, concurrency) = match counts.as_slice() {
+            [requests] if *requests > 0 => (*requests, 1),
+            [requests, concurrency] if *requests > 0 && *concurrency > 0 => {
+                (*requests, *concurrency)
+            }
+            _ => {
+                self.warn("Usage: :load <requests> [at a time]");
+                return;
+            }
+        };
+        let request_builder = match self.request_builder() {
+            Ok(request_builder) => request_builder,
+            Err(err) => {
+                self.error(err);
+                return;
+            }
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(load::run(&request_builder, requests, concurrency));
+        });
+        self.load_test = Some(receiver);
+        self.info(format!(
+            "Sending {} requests, {} at a time",
+            requests, concurrency
+        ));
+    }
+
+    // What's running in the background, for the status bar to show a spinner beside.
+    pub fn in_flight(&self) -> Option<&'static str> {
+        if self.load_test.is_some() {
+            Some("load test")
+        } else if self.refreshing_token {
+            Some("token refresh")
+        } else if self.pending_token.is_some() {
+            Some("sign-in")
+        } else {
+            None
+        }
+    }
+
+    pub fn check_load_test(&mut self) {
+        let Some(receiver) = &self.load_test else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("The load test stopped".to_string()),
+        };
+
+        self.load_test = None;
+        match result {
+            Ok(report) => {
+                let failed = report.errors.iter().map(|(_, count)| count).sum::<usize>();
+                self.status_line = Some(format!("Load test · {} failed", failed));
+                // Colored by the worst of the responses.
+                self.response_status = if failed > 0 {
+                    Some(ResponseStatus::Failed)
+                } else {
+                    report
+                        .statuses
+                        .last()
+                        .map(|(status, _)| ResponseStatus::Code(*status))
+                };
+                self.output_content_type = None;
+                self.output_headers = vec![];
+                self.response_body = None;
+                self.example_index = None;
+                self.live_output = None;
+                self.unqueried_output = None;
+                self.output_input = TextArea::from(report.to_string().lines());
+                self.info("Load test finished");
+            }
+            Err(err) => self.error(err),
+        }
+    }
+
+    pub fn toggle_raw_input(&mut self) {
+        if self.raw_mode {
+            self.remember_input_focus();
+            match self.apply_raw_input() {
+                Ok(()) => {
+                    self.raw_mode = false;
+                    self.restore_input_focus();
+                }
+                Err(err) => self.error(format!("Unable to parse raw request: {}", err)),
+            }
+        } else {
+            self.raw_input = TextArea::from(self.to_request().raw().lines());
+            self.raw_mode = true;
+        }
+    }
+
+    pub fn editing_raw_input(&self) -> bool {
+        self.current_panel == Panel::Input && self.raw_mode
+    }
+
+    // Whether Enter starts a new line rather than sending the request.
+    pub fn editing_lines(&self) -> bool {
+        self.editing_raw_input() || self.current_panel == Panel::Description
     }
 
     pub fn append(&mut self) {
@@ -360,16 +1304,68 @@ impl Model {
     }
 
     pub fn leave_insert(&mut self) {
+        self.changed = true;
         self.current_input_mut().move_cursor(CursorMove::Back);
     }
 
     pub fn normal(&mut self) {
         self.current_mode = Mode::Normal;
+        self.selection_anchor = None;
+    }
+
+    // Starts a selection, or changes the shape of the one being made. Only character selections
+    // are drawn by the text area, so it only selects for those.
+    pub fn visual(&mut self, shape: Selection) {
+        let cursor = self.current_input().cursor();
+        if !matches!(self.current_mode, Mode::Visual(_)) {
+            self.selection_anchor = Some(cursor);
+        }
+        let anchor = self.selection_anchor.unwrap_or(cursor);
+        self.current_mode = Mode::Visual(shape);
+
+        let input = self.current_input_mut();
+        input.cancel_selection();
+        if shape == Selection::Char {
+            input.move_cursor(CursorMove::Jump(anchor.0 as u16, anchor.1 as u16));
+            input.start_selection();
+            input.move_cursor(CursorMove::Jump(cursor.0 as u16, cursor.1 as u16));
+        }
+    }
+
+    pub fn selection_columns(&self) -> Option<(usize, usize)> {
+        self.selected_columns(self.current_input().cursor().0)
+    }
+
+    // The columns of a line the selection covers, up to but not including the end. Block
+    // selections include the cursor's column so one column can be selected.
+    pub fn selected_columns(&self, row: usize) -> Option<(usize, usize)> {
+        let Mode::Visual(shape) = self.current_mode else {
+            return None;
+        };
+        let anchor = self.selection_anchor?;
+        let cursor = self.current_input().cursor();
+        let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+        if !(start.0..=end.0).contains(&row) {
+            return None;
+        }
+
+        let length = self.current_input().lines().get(row)?.chars().count();
+        let (from, to) = match shape {
+            Selection::Char => (
+                if row == start.0 { start.1 } else { 0 },
+                if row == end.0 { end.1 } else { length },
+            ),
+            Selection::Line => (0, length),
+            Selection::Block => (anchor.1.min(cursor.1), anchor.1.max(cursor.1) + 1),
+        };
+        Some((from.min(length), to.min(length)))
     }
 
-    pub fn visual(&mut self) {
-        self.current_mode = Mode::Visual;
-        self.current_input_mut().start_selection();
+    // The first and last lines of the selection.
+    fn selected_rows(&self) -> Option<(usize, usize)> {
+        let (anchor, _) = self.selection_anchor?;
+        let (cursor, _) = self.current_input().cursor();
+        Some((anchor.min(cursor), anchor.max(cursor)))
     }
 
     pub fn leave_visual(&mut self) {
@@ -381,6 +1377,20 @@ impl Model {
             Panel::Url => {
                 self.current_panel = Panel::Method;
             }
+            Panel::Settings
+            | Panel::History
+            | Panel::Runner
+            | Panel::Files
+            | Panel::Authorize
+            | Panel::Cookies
+            | Panel::Mock
+            | Panel::Query
+            | Panel::Menu
+            | Panel::Command
+            | Panel::Preview
+            | Panel::Description
+            | Panel::Diff
+            | Panel::Messages => (),
             _ => select_tmux_panel(Direction::Left),
         };
     }
@@ -393,6 +1403,20 @@ impl Model {
             Panel::Input => {
                 self.current_panel = Panel::Output;
             }
+            Panel::Settings
+            | Panel::History
+            | Panel::Runner
+            | Panel::Files
+            | Panel::Authorize
+            | Panel::Cookies
+            | Panel::Mock
+            | Panel::Query
+            | Panel::Menu
+            | Panel::Command
+            | Panel::Preview
+            | Panel::Description
+            | Panel::Diff
+            | Panel::Messages => (),
             _ => select_tmux_panel(Direction::Down),
         };
     }
@@ -405,6 +1429,20 @@ impl Model {
             Panel::Input => {
                 self.current_panel = Panel::Url;
             }
+            Panel::Settings
+            | Panel::History
+            | Panel::Runner
+            | Panel::Files
+            | Panel::Authorize
+            | Panel::Cookies
+            | Panel::Mock
+            | Panel::Query
+            | Panel::Menu
+            | Panel::Command
+            | Panel::Preview
+            | Panel::Description
+            | Panel::Diff
+            | Panel::Messages => (),
             _ => select_tmux_panel(Direction::Up),
         };
     }
@@ -414,232 +1452,3164 @@ impl Model {
             Panel::Method => {
                 self.current_panel = Panel::Url;
             }
+            Panel::Settings
+            | Panel::History
+            | Panel::Runner
+            | Panel::Files
+            | Panel::Authorize
+            | Panel::Cookies
+            | Panel::Mock
+            | Panel::Query
+            | Panel::Menu
+            | Panel::Command
+            | Panel::Preview
+            | Panel::Description
+            | Panel::Diff
+            | Panel::Messages => (),
             _ => select_tmux_panel(Direction::Right),
         };
     }
 
-    pub fn method_cursor_position(&self) -> u16 {
-        self.current_method.to_string().len() as u16
-    }
-
-    pub fn next_method(&mut self) {
-        let new_method = match self.current_method {
-            Method::OPTIONS => Method::GET,
-            Method::GET => Method::HEAD,
-            Method::HEAD => Method::POST,
-            Method::POST => Method::PUT,
-            Method::PUT => Method::PATCH,
-            Method::PATCH => Method::DELETE,
-            Method::DELETE => Method::TRACE,
-            Method::TRACE => Method::CONNECT,
-            Method::CONNECT => Method::OPTIONS,
-            _ => return,
+    pub fn toggle_settings(&mut self) {
+        if self.current_panel == Panel::Settings {
+            self.settings_focus = (self.input_index, self.current_input_field);
+            self.toggle_popup(Panel::Settings, 0);
+            return;
+        }
+
+        let (input_index, input_field) = self.settings_focus;
+        self.toggle_popup(
+            Panel::Settings,
+            input_index.min(self.settings_input_table.len() - 1),
+        );
+        if self.current_panel == Panel::Settings {
+            self.current_input_field = input_field;
+        }
+    }
+
+    // Shows the cookies the next request to this URL will send. Edits are applied when the popup
+    // closes, and clearing a cookie's name drops it.
+    pub fn toggle_cookies(&mut self) {
+        if self.current_panel == Panel::Cookies {
+            self.apply_cookies();
+            self.toggle_popup(Panel::Cookies, 0);
+            return;
+        }
+
+        let url = match Url::parse(&self.substitute(self.url())) {
+            Ok(url) => url,
+            Err(err) => {
+                self.error(format!("Invalid URL: {}", err));
+                return;
+            }
         };
+        self.cookies_shown = cookies::jar().matching(&url);
+        let mut table: NonEmpty<InputRow> = NonEmpty::from_vec(
+            self.cookies_shown
+                .iter()
+                .map(|cookie| InputRow {
+                    key: [cookie.name.as_str()].into(),
+                    value: [cookie.value.as_str()].into(),
+                    ..InputRow::default()
+                })
+                .collect(),
+        )
+        .unwrap_or(nonempty![InputRow::default()]);
+        if !table.last().is_empty() {
+            table.push(InputRow::default());
+        }
+        self.cookies_input_table = table;
+        self.cookies_url = Some(url);
+        self.toggle_popup(Panel::Cookies, 0);
+    }
 
-        self.current_method = new_method;
+    fn apply_cookies(&mut self) {
+        let Some(url) = self.cookies_url.take() else {
+            return;
+        };
+        let edited: Vec<Cookie> = self
+            .cookies_input_table
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| !row.key.is_empty())
+            .map(|(index, row)| {
+                let (name, value): (String, String) = row.into();
+                match self.cookies_shown.get(index) {
+                    Some(cookie) => Cookie {
+                        name,
+                        value,
+                        ..cookie.clone()
+                    },
+                    None => Cookie::for_host(&url, &name, &value),
+                }
+            })
+            .collect();
+        cookies::jar().replace_matching(&url, edited);
+        self.cookies_shown.clear();
     }
 
-    pub fn previous_method(&mut self) {
-        let new_method = match self.current_method {
-            Method::OPTIONS => Method::CONNECT,
-            Method::GET => Method::OPTIONS,
-            Method::HEAD => Method::GET,
-            Method::POST => Method::HEAD,
-            Method::PUT => Method::POST,
-            Method::PATCH => Method::PUT,
-            Method::DELETE => Method::PATCH,
-            Method::TRACE => Method::DELETE,
-            Method::CONNECT => Method::TRACE,
-            _ => return,
+    // :mock shows the routes and what the server received, :mock <port> serves the routes on that
+    // port and :mock stop stops serving them.
+    fn mock_command(&mut self, argument: &str) {
+        match argument {
+            "" => self.toggle_mock(),
+            "stop" => match self.mock_server.take() {
+                Some(server) => self.info(format!("Stopped the mock server on {}", server.port)),
+                None => self.warn("The mock server isn't running"),
+            },
+            port => match port.parse::<u16>() {
+                Ok(port) => self.start_mock_server(port),
+                Err(_) => self.warn("Usage: :mock [port|stop]"),
+            },
+        }
+    }
+
+    // Edits to the routes apply when the popup closes, even while the server is running.
+    pub fn toggle_mock(&mut self) {
+        if self.current_panel == Panel::Mock {
+            match (self.mock_routes(), &self.mock_server) {
+                (Ok(routes), Some(server)) => server.set_routes(routes),
+                (Err(err), _) => self.error(err),
+                _ => (),
+            }
+            self.toggle_popup(Panel::Mock, 0);
+            return;
+        }
+
+        if !self.mock_input_table.last().is_empty() {
+            self.mock_input_table.push(InputRow::default());
+        }
+        self.toggle_popup(Panel::Mock, 0);
+    }
+
+    fn start_mock_server(&mut self, port: u16) {
+        let routes = match self.mock_routes() {
+            Ok(routes) => routes,
+            Err(err) => {
+                self.error(err);
+                return;
+            }
         };
+        // The old server lets go of its port before the new one binds it.
+        self.mock_server = None;
+        match mock::start(port, routes) {
+            Ok(server) => {
+                self.info(format!("Mocking on http://127.0.0.1:{}", server.port));
+                self.mock_server = Some(server);
+            }
+            Err(err) => self.error(format!("Unable to listen on port {}: {}", port, err)),
+        }
+    }
 
-        self.current_method = new_method;
+    // The routes with a path, or why one of them is wrong.
+    fn mock_routes(&self) -> Result<Vec<Route>, String> {
+        self.mock_input_table
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| !row.key.is_empty())
+            .map(|(index, row)| {
+                let (route, response): (String, String) = row.into();
+                Route::parse(&route, &response).map_err(|err| format!("Row {}: {}", index + 1, err))
+            })
+            .collect()
     }
 
-    pub fn cursor_col(&self) -> u16 {
-        self.current_input().cursor().1 as u16
+    // Logs what the mock server has received since the last tick.
+    pub fn check_mock_server(&mut self) {
+        let Some(server) = &self.mock_server else {
+            return;
+        };
+        let received: Vec<Received> = server.received.try_iter().collect();
+        if let Some(last) = received.last() {
+            self.info(format!("Mock server: {}", last.summary()));
+        }
+        self.mock_log.extend(received);
     }
 
-    pub fn copy(&mut self) {
-        self.current_input_mut().copy();
-        match Clipboard::get().write_text(self.current_input().yank_text()) {
-            Ok(_) => (),
-            Err(err) => self.message = format!("Unable to save to system clipboard: {:?}", err),
+    // :catch <port> listens on the port and answers everything with a 200, and :catch stop stops.
+    fn catch_command(&mut self, argument: &str) {
+        if argument == "stop" {
+            match self.catcher.take() {
+                Some(catcher) => self.info(format!("Stopped catching on {}", catcher.port)),
+                None => self.warn("Nothing is being caught"),
+            }
+            return;
+        }
+        let Ok(port) = argument.parse::<u16>() else {
+            self.warn("Usage: :catch <port|stop>");
+            return;
+        };
+
+        self.catcher = None;
+        match mock::catch(port) {
+            Ok(catcher) => {
+                self.info(format!(
+                    "Catching requests to http://127.0.0.1:{}",
+                    catcher.port
+                ));
+                self.catcher = Some(catcher);
+            }
+            Err(err) => self.error(format!("Unable to listen on port {}: {}", port, err)),
         }
     }
 
-    pub fn handle_insert_input(&mut self, event: KeyEvent) {
-        if self.current_input_type == InputType::Auth && self.auth.format == AuthFormat::None {
+    // Each request caught since the last tick goes in the history, and the latest is shown in the
+    // Output panel.
+    pub fn check_catcher(&mut self) {
+        let Some(catcher) = &self.catcher else {
+            return;
+        };
+        let port = catcher.port;
+        let caught: Vec<Received> = catcher.received.try_iter().collect();
+        let Some(latest) = caught.last() else {
             return;
+        };
+
+        self.status_line = Some(format!("Caught {} {}", latest.method, latest.target));
+        self.response_status = None;
+        self.output_content_type = None;
+        self.output_headers = latest.headers.clone();
+        self.response_body = None;
+        self.example_index = None;
+        self.live_output = None;
+        self.unqueried_output = None;
+        self.output_input = TextArea::from(latest.text().lines());
+        self.info(format!("Caught {}", latest.summary()));
+        for received in &caught {
+            self.append_history(received.to_entry(port));
         }
+    }
 
-        self.current_input_mut().input(event);
+    pub fn toggle_history(&mut self) {
+        self.toggle_popup(Panel::History, self.history.len().saturating_sub(1));
     }
 
-    pub fn handle_normal_input(&mut self, key_event: KeyEvent) {
-        if self.current_input_type == InputType::Auth && self.auth.format == AuthFormat::None {
+    fn toggle_popup(&mut self, popup: Panel, input_index: usize) {
+        match self.popup_return.take() {
+            Some((panel, input_index, input_field)) => {
+                self.current_panel = panel;
+                self.input_index = input_index;
+                self.current_input_field = input_field;
+            }
+            None => {
+                let panel = std::mem::replace(&mut self.current_panel, popup);
+                let input_field = std::mem::take(&mut self.current_input_field);
+                self.popup_return = Some((panel, self.input_index, input_field));
+                self.input_index = input_index;
+            }
+        }
+    }
+
+    // Runs every request in the file and shows how each one went.
+    pub fn run_collection(&mut self) {
+        if self.raw_mode {
+            if let Err(err) = self.apply_raw_input() {
+                self.error(format!("Unable to parse raw request: {}", err));
+                return;
+            }
+        }
+
+        self.outcomes = runner::run(&self.to_request_file(), &mut self.captured);
+        let failed = self
+            .outcomes
+            .iter()
+            .filter(|outcome| !outcome.passed())
+            .count();
+        self.info(format!(
+            "{} passed, {} failed",
+            self.outcomes.len() - failed,
+            failed
+        ));
+
+        if self.popup_return.is_some() {
+            self.toggle_popup(Panel::Runner, 0);
+        }
+        self.toggle_popup(Panel::Runner, 0);
+    }
+
+    // Signs in with OAuth2, using the device authorization grant if the settings have a device URL
+    // and the authorization code grant in the browser otherwise. The token arrives in the
+    // background.
+    pub fn authorize(&mut self) {
+        let started = self.settings().and_then(|settings| {
+            let client = settings.client().map_err(|err| err.to_string())?;
+            self.start_authorization(client, settings.oauth)
+        });
+        match started {
+            Ok(authorization) => self.authorization = Some(authorization),
+            Err(err) => {
+                self.error(format!("Unable to start authorization: {}", err));
+                return;
+            }
+        }
+
+        if self.popup_return.is_some() {
+            self.toggle_popup(Panel::Authorize, 0);
+        }
+        self.toggle_popup(Panel::Authorize, 0);
+    }
+
+    fn start_authorization(
+        &mut self,
+        client: Client,
+        config: oauth::Config,
+    ) -> Result<Authorization, String> {
+        let (sender, receiver) = mpsc::channel();
+        let authorization = if config.device_url.is_some() {
+            let authorization = oauth::authorize_device(&client, &config)?;
+            let waiting = authorization.clone();
+            thread::spawn(move || {
+                let _ = sender.send(oauth::wait_for_device_token(&client, &config, &waiting));
+            });
+            Authorization::Device(authorization)
+        } else {
+            let authorization = oauth::authorize_code(&config)?;
+            let url = authorization.url.clone();
+            thread::spawn(move || {
+                let _ = sender.send(oauth::wait_for_code_token(&client, &config, authorization));
+            });
+            if open_in_browser(&url).is_err() {
+                self.error("Unable to open a browser, open the URL to sign in");
+            }
+            Authorization::Browser(url)
+        };
+        self.pending_token = Some(receiver);
+
+        Ok(authorization)
+    }
+
+    pub fn close_authorization(&mut self) {
+        self.authorization = None;
+        self.pending_token = None;
+        self.info("Authorization cancelled");
+        self.toggle_popup(Panel::Authorize, 0);
+    }
+
+    // Installs the token once the user approves the authorization.
+    pub fn check_authorization(&mut self) {
+        let Some(receiver) = &self.pending_token else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("Authorization stopped".to_string()),
+        };
+
+        self.pending_token = None;
+        if std::mem::take(&mut self.refreshing_token) {
+            match result {
+                Ok(token) => {
+                    self.install_token(token);
+                    self.info("Refreshed the OAuth2 token");
+                }
+                Err(err) => {
+                    // Stop trying until the user signs in again.
+                    let environment = self.environment.clone().unwrap_or_default();
+                    for (_, token) in self
+                        .oauth_tokens
+                        .iter_mut()
+                        .filter(|(existing, _)| *existing == environment)
+                    {
+                        token.refresh_token = None;
+                    }
+                    self.error(err);
+                }
+            }
             return;
         }
 
-        let cursor_move = match key_event.code {
-            KeyCode::Char('h') | KeyCode::Left => Some(CursorMove::Back),
-            KeyCode::Char('l') | KeyCode::Right => Some(CursorMove::Forward),
-            KeyCode::Char('b') => Some(CursorMove::WordBack),
-            KeyCode::Char('w') => Some(CursorMove::WordForward),
-            KeyCode::Char('^') | KeyCode::Home => Some(CursorMove::Head),
-            KeyCode::Char('$') | KeyCode::End => Some(CursorMove::End),
-            KeyCode::Char('j') | KeyCode::Down if self.current_panel == Panel::Output => {
-                Some(CursorMove::Down)
+        self.authorization = None;
+        if self.current_panel == Panel::Authorize {
+            self.toggle_popup(Panel::Authorize, 0);
+        }
+        match result {
+            Ok(token) => {
+                self.install_token(token);
+                self.info(format!(
+                    "Authorized, the token is available as {{{{{}}}}}",
+                    oauth::TOKEN_VARIABLE
+                ));
             }
-            KeyCode::Char('k') | KeyCode::Up if self.current_panel == Panel::Output => {
-                Some(CursorMove::Up)
+            Err(err) => self.error(format!("Authorization failed: {}", err)),
+        }
+    }
+
+    // Refreshes the token in the background shortly before it expires, so a long session doesn't
+    // start getting 401s.
+    pub fn refresh_expiring_token(&mut self) {
+        if self.pending_token.is_some() {
+            return;
+        }
+        let Some(token) = self.oauth_token() else {
+            return;
+        };
+        let Some(refresh_token) = token.refresh_token.clone() else {
+            return;
+        };
+        if token
+            .remaining()
+            .map_or(true, |remaining| remaining > oauth::REFRESH_AHEAD)
+        {
+            return;
+        }
+        let Ok(settings) = self.settings() else {
+            return;
+        };
+        let Ok(client) = settings.client() else {
+            return;
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let config = settings.oauth;
+        thread::spawn(move || {
+            let _ = sender.send(oauth::refresh(&client, &config, &refresh_token));
+        });
+        self.pending_token = Some(receiver);
+        self.refreshing_token = true;
+    }
+
+    // Tokens are kept per environment, since each usually has its own identity provider.
+    pub fn oauth_token(&self) -> Option<&Token> {
+        let environment = self.environment.as_deref().unwrap_or_default();
+        self.oauth_tokens
+            .iter()
+            .find(|(existing, _)| existing == environment)
+            .map(|(_, token)| token)
+    }
+
+    pub fn load_tokens(&mut self, tokens: Tokens) {
+        self.oauth_tokens = tokens;
+        if let Some(token) = self.oauth_token() {
+            let access_token = token.access_token.clone();
+            set_pair(&mut self.captured, oauth::TOKEN_VARIABLE, &access_token);
+        }
+    }
+
+    fn install_token(&mut self, token: Token) {
+        set_pair(
+            &mut self.captured,
+            oauth::TOKEN_VARIABLE,
+            &token.access_token,
+        );
+        if self.auth.format == AuthFormat::None
+            || (self.auth.format == AuthFormat::Bearer && self.auth.token().is_empty())
+        {
+            self.auth.format = AuthFormat::Bearer;
+            self.auth.bearer_input = TextArea::from([format!("{{{{{}}}}}", oauth::TOKEN_VARIABLE)]);
+        }
+
+        let environment = self.environment.clone().unwrap_or_default();
+        match self
+            .oauth_tokens
+            .iter_mut()
+            .find(|(existing, _)| *existing == environment)
+        {
+            Some((_, existing)) => *existing = token,
+            None => self.oauth_tokens.push((environment, token)),
+        }
+    }
+
+    pub fn toggle_runner(&mut self) {
+        self.toggle_popup(Panel::Runner, 0);
+    }
+
+    pub fn next_outcome(&mut self) {
+        if self.input_index + 1 < self.outcomes.len() {
+            self.input_index += 1;
+        }
+    }
+
+    pub fn previous_outcome(&mut self) {
+        self.input_index = self.input_index.saturating_sub(1);
+    }
+
+    // Lists the request files in the workspace, or next to this one without a workspace.
+    pub fn toggle_files(&mut self) {
+        if self.current_panel == Panel::Files {
+            self.toggle_popup(Panel::Files, 0);
+            return;
+        }
+
+        let directory = self.file_directory();
+        self.files = match runner::collect(&directory) {
+            Ok(files) => files,
+            Err(err) => {
+                self.error(format!("Unable to list {}: {}", directory.display(), err));
+                return;
             }
-            _ => None,
+        };
+        let current = self
+            .files
+            .iter()
+            .position(|file| same_file(file, Path::new(&self.filename)))
+            .unwrap_or_default();
+        self.toggle_popup(Panel::Files, current);
+    }
+
+    pub fn next_file(&mut self) {
+        if self.input_index + 1 < self.files.len() {
+            self.input_index += 1;
+        }
+    }
+
+    pub fn previous_file(&mut self) {
+        self.input_index = self.input_index.saturating_sub(1);
+    }
+
+    pub fn open_selected_file(&mut self) {
+        let file = self.files.get(self.input_index).cloned();
+        self.toggle_files();
+        if let Some(file) = file {
+            self.switch_file(&file);
+        }
+    }
+
+    // :e opens a file by its path in the workspace, which is created if it doesn't exist yet.
+    fn edit_file(&mut self, argument: &str) {
+        if argument.is_empty() {
+            self.toggle_files();
+            return;
+        }
+        let file = self.file_directory().join(argument);
+        self.switch_file(&file);
+    }
+
+    fn switch_file(&mut self, file: &Path) {
+        if !same_file(file, Path::new(&self.filename)) {
+            self.open_file = Some(file.display().to_string());
+        }
+    }
+
+    fn file_directory(&self) -> PathBuf {
+        match &self.workspace {
+            Some(workspace) => workspace.clone(),
+            None => match Path::new(&self.filename).parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            },
+        }
+    }
+
+    pub fn open_outcome(&mut self) {
+        let index = self.input_index;
+        self.toggle_runner();
+        if index < self.requests.len() {
+            if let Err(err) = self.select_request(index) {
+                self.error(err);
+            }
+        }
+    }
+
+    pub fn current_examples(&self) -> &[Example] {
+        let key = examples::key(&self.name, self.request_index);
+        self.examples
+            .iter()
+            .find(|(existing, _)| *existing == key)
+            .map(|(_, examples)| examples.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn save_response_example(&mut self) {
+        let entry = self
+            .history
+            .last()
+            .filter(|entry| entry.status != 0)
+            .filter(|_| self.response_body.is_some() && self.example_index.is_none())
+            .cloned();
+        match entry {
+            Some(entry) => self.add_example(&entry),
+            None => self.warn("No response to save as an example"),
+        }
+    }
+
+    fn save_snapshot(&mut self, name: &str) {
+        if name.is_empty() {
+            self.warn("Usage: :snapshot <name>");
+            return;
+        }
+        let entry = self
+            .history
+            .last()
+            .filter(|entry| entry.status != 0)
+            .filter(|_| self.response_body.is_some() && self.example_index.is_none());
+        let Some(entry) = entry else {
+            self.warn("No response to save as a snapshot");
+            return;
         };
 
-        match cursor_move {
-            Some(request) => self.current_input_mut().move_cursor(request),
-            None => (),
+        let snapshot = Snapshot {
+            status: entry.status,
+            body: entry.response_body.clone(),
         };
+        snapshot::set(&mut self.snapshots, name, snapshot);
+        self.info(format!("Saved snapshot {}", name));
     }
 
-    pub fn next_input_type(&mut self) {
-        self.current_input_type = self.current_input_type.next().unwrap_or_default();
-        self.current_input_field = InputField::default();
-        self.input_index = self.current_input_table().len() - 1;
+    pub fn save_history_example(&mut self) {
+        match self.history.get(self.input_index).cloned() {
+            Some(entry) if entry.status != 0 => self.add_example(&entry),
+            _ => self.warn("This entry has no response to save as an example"),
+        }
     }
 
-    pub fn previous_input_type(&mut self) {
-        self.current_input_type = self
-            .current_input_type
-            .previous()
-            .unwrap_or(InputType::last().unwrap());
-        self.current_input_field = InputField::default();
-        self.input_index = self.current_input_table().len() - 1;
+    fn add_example(&mut self, entry: &Entry) {
+        let example = Example::from_entry(entry);
+        self.info(format!("Saved example {}", example.summary()));
+        let key = examples::key(&self.name, self.request_index);
+        examples::add(&mut self.examples, &key, example);
     }
 
-    pub fn next_input_field(&mut self) {
-        match self.current_input_type {
-            InputType::Auth => match self.auth.format {
-                AuthFormat::None | AuthFormat::Bearer => (),
-                AuthFormat::Basic => {
-                    self.current_input_field = self.current_input_field.next().unwrap_or_default();
+    pub fn next_example(&mut self) {
+        self.cycle_example(true);
+    }
+
+    pub fn previous_example(&mut self) {
+        self.cycle_example(false);
+    }
+
+    // Cycles through the request's examples and back to the live response.
+    fn cycle_example(&mut self, forward: bool) {
+        let count = self.current_examples().len();
+        if count == 0 {
+            self.warn("No examples saved for this request");
+            return;
+        }
+
+        let position = self.example_index.unwrap_or(count);
+        let position = if forward {
+            (position + 1) % (count + 1)
+        } else {
+            (position + count) % (count + 1)
+        };
+        if self.example_index.is_none() {
+            self.live_output = Some(std::mem::take(&mut self.output_input));
+        }
+        if position == count {
+            self.leave_examples();
+        } else {
+            self.example_index = Some(position);
+            self.output_input = TextArea::from(self.current_examples()[position].body.lines());
+        }
+    }
+
+    fn leave_examples(&mut self) {
+        if self.example_index.take().is_some() {
+            self.output_input = self.live_output.take().unwrap_or_default();
+        }
+    }
+
+    pub fn toggle_menu(&mut self) {
+        if self.current_panel != Panel::Menu && self.popup_return.is_some() {
+            return;
+        }
+        self.toggle_popup(Panel::Menu, 0);
+    }
+
+    // The actions for the pane the menu was opened from, followed by the ones available anywhere.
+    pub fn menu_items(&self) -> Vec<MenuItem> {
+        let item = |keys, code, label| MenuItem::new(keys, code, KeyModifiers::NONE, label);
+        let panel = match &self.popup_return {
+            Some((panel, _, _)) if self.current_panel == Panel::Menu => panel,
+            _ => &self.current_panel,
+        };
+
+        let mut items = match panel {
+            Panel::Method => vec![
+                item("j", KeyCode::Char('j'), "Next method"),
+                item("k", KeyCode::Char('k'), "Previous method"),
+            ],
+            Panel::Input if self.raw_mode => vec![
+                item("R", KeyCode::Char('R'), "Stop raw HTTP editing"),
+                item("E", KeyCode::Char('E'), "Edit the body in $EDITOR"),
+                item("=", KeyCode::Char('='), "Format the JSON body"),
+            ],
+            Panel::Input => {
+                let mut items = vec![
+                    MenuItem::new("⇧→", KeyCode::Right, KeyModifiers::SHIFT, "Next input type"),
+                    MenuItem::new(
+                        "⇧←",
+                        KeyCode::Left,
+                        KeyModifiers::SHIFT,
+                        "Previous input type",
+                    ),
+                ];
+                if self.current_input_type != InputType::Auth {
+                    items.push(item("D", KeyCode::Char('D'), "Delete row"));
+                } else if self.auth.format != AuthFormat::None {
+                    items.push(item(
+                        "*",
+                        KeyCode::Char('*'),
+                        if self.reveal_secrets {
+                            "Hide the password or token"
+                        } else {
+                            "Show the password or token"
+                        },
+                    ));
                 }
-            },
-            InputType::Headers | InputType::Body => {
-                if self.current_input_field == InputField::last().unwrap() {
-                    if !self.current_input_table().last().is_empty() {
-                        self.current_input_table_mut().push(InputRow::default());
+                if matches!(
+                    self.current_input_type,
+                    InputType::Headers | InputType::Body
+                ) {
+                    items.push(item("J", KeyCode::Char('J'), "Move row down"));
+                    items.push(item("K", KeyCode::Char('K'), "Move row up"));
+                    items.push(item("␣", KeyCode::Char(' '), "Switch row off or on"));
+                    if self.current_input_type == InputType::Headers {
+                        items.push(item("A", KeyCode::Char('A'), "Add the automatic headers"));
+                    } else {
+                        items.push(item("E", KeyCode::Char('E'), "Edit the body in $EDITOR"));
                     }
-                    if self.input_index < self.current_input_table().len() - 1 {
-                        self.input_index += 1
+                    if self.yanked_row.is_some() {
+                        items.push(item("p", KeyCode::Char('p'), "Put the yanked row below"));
                     }
                 }
-                self.current_input_field = self.current_input_field.next().unwrap_or_default();
+                items.push(item("R", KeyCode::Char('R'), "Raw HTTP editing"));
+                items
+            }
+            Panel::Url => vec![item("P", KeyCode::Char('P'), "Preview the request")],
+            Panel::Output => vec![
+                item("W", KeyCode::Char('W'), "Save the response body"),
+                item("E", KeyCode::Char('E'), "Save the response as an example"),
+                item("/", KeyCode::Char('/'), "Query the response"),
+                item("P", KeyCode::Char('P'), "Copy the value's JSONPath"),
+                item("D", KeyCode::Char('D'), "Compare with the last response"),
+                item("=", KeyCode::Char('='), "Indent XML and HTML"),
+                item("o", KeyCode::Char('o'), "Open in $PAGER or $EDITOR"),
+                item("}", KeyCode::Char('}'), "Next example"),
+                item("{", KeyCode::Char('{'), "Previous example"),
+            ],
+            _ => vec![],
+        };
+        items.extend([
+            item("↵", KeyCode::Enter, "Send the request"),
+            item("Y", KeyCode::Char('Y'), "Copy as curl"),
+            item("I", KeyCode::Char('I'), "Import the copied request"),
+            item("R", KeyCode::Char('R'), "Run every request"),
+            item("]", KeyCode::Char(']'), "Next request"),
+            item("[", KeyCode::Char('['), "Previous request"),
+            item("H", KeyCode::Char('H'), "History"),
+            item("N", KeyCode::Char('N'), "Notes on the request"),
+            item("B", KeyCode::Char('B'), "Request files"),
+            item("S", KeyCode::Char('S'), "Workspace settings"),
+            item("C", KeyCode::Char('C'), "Cookies"),
+            item("O", KeyCode::Char('O'), "Sign in with OAuth2"),
+            item(":", KeyCode::Char(':'), "Run a command"),
+        ]);
+        if *panel == Panel::Input {
+            // R toggles raw editing in the Input pane, so the runner isn't reachable from there.
+            items.retain(|item| item.label != "Run every request");
+        }
+        items
+    }
+
+    pub fn next_menu_item(&mut self) {
+        if self.input_index + 1 < self.menu_items().len() {
+            self.input_index += 1;
+        }
+    }
+
+    pub fn previous_menu_item(&mut self) {
+        self.input_index = self.input_index.saturating_sub(1);
+    }
+
+    // Closes the menu and returns the key of the chosen action.
+    pub fn choose_menu_item(&mut self) -> Option<KeyEvent> {
+        let key = self.menu_items().get(self.input_index).map(|item| item.key);
+        self.toggle_menu();
+        key
+    }
+
+    // Removes the row under the cursor. Settings and options list every setting, so there the
+    // value is cleared instead.
+    pub fn delete_row(&mut self) {
+        let clear_only = match self.current_panel {
+            Panel::Input if !self.raw_mode => match self.current_input_type {
+                InputType::Auth => return,
+                InputType::Headers | InputType::Body => false,
+                InputType::Path | InputType::Options => true,
+            },
+            Panel::Cookies | Panel::Mock => false,
+            Panel::Settings => true,
+            _ => return,
+        };
+
+        let index = self.input_index;
+        let table = self.current_input_table_mut();
+        if clear_only {
+            table[index].value = TextArea::default();
+        } else if table.len() == 1 {
+            table[0] = InputRow::default();
+        } else {
+            let mut rows: Vec<InputRow> =
+                std::mem::replace(table, nonempty![InputRow::default()]).into();
+            rows.remove(index);
+            *table = NonEmpty::from_vec(rows).unwrap_or(nonempty![InputRow::default()]);
+            self.input_index = index.min(self.current_input_table().len() - 1);
+        }
+        self.current_input_field = InputField::Key;
+        self.changed = true;
+    }
+
+    // The {{variable}} the cursor is on and its value, if it has one.
+    pub fn variable_under_cursor(&self) -> Option<(String, Option<String>)> {
+        if matches!(
+            self.current_panel,
+            Panel::Output | Panel::Query | Panel::Command
+        ) {
+            return None;
+        }
+
+        let (row, col) = self.current_input().cursor();
+        let line = self.current_input().lines().get(row)?;
+        let re = Regex::new(r"\{\{\s*([^{}\s]+)\s*\}\}").unwrap();
+        let reference = re.captures_iter(line).find(|captures| {
+            let whole = captures.get(0).unwrap();
+            let start = line[..whole.start()].chars().count();
+            let end = start + whole.as_str().chars().count();
+            (start..end).contains(&col)
+        })?;
+
+        let name = reference[1].to_string();
+        let value = Some(variables::substitute(&reference[0], &self.all_variables()))
+            .filter(|value| *value != reference[0]);
+        Some((name, value))
+    }
+
+    // Copies the JSONPath of the response value under the cursor.
+    pub fn copy_json_path(&mut self) {
+        if let Some((_, path)) = self.json_path_at_cursor() {
+            self.copy_text(path.clone(), &path);
+        }
+    }
+
+    // The JSON in the Output panel and the path of the value under the cursor.
+    fn json_path_at_cursor(&mut self) -> Option<(String, String)> {
+        let text = self.output_input.lines().join("\n");
+        if json::parse(&text).is_err() {
+            self.warn("The response isn't JSON");
+            return None;
+        }
+
+        let (row, col) = self.output_input.cursor();
+        let offset = self.output_input.lines()[..row]
+            .iter()
+            .map(|line| line.chars().count() + 1)
+            .sum::<usize>()
+            + col;
+        let path = json_path::path_at(&text, offset)?;
+        Some((text, path))
+    }
+
+    // The Output keys starting with y: yb copies the body, yh the headers, yj the JSON value under
+    // the cursor and yf the body indented. Returns whether the key was one of them.
+    fn copy_response(&mut self, key: char) -> bool {
+        let body = self
+            .unformatted_output
+            .as_ref()
+            .or(self.unqueried_output.as_ref())
+            .unwrap_or(&self.output_input)
+            .lines()
+            .join("\n");
+        match key {
+            'b' => self.copy_text(body, "the body"),
+            'h' if self.output_headers.is_empty() => {
+                self.warn("The response has no headers to copy");
+            }
+            'h' => {
+                let headers = self
+                    .output_headers
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                self.copy_text(headers, "the headers");
+            }
+            'j' => {
+                let Some((text, path)) = self.json_path_at_cursor() else {
+                    return true;
+                };
+                match query::select(&text, &path) {
+                    Ok(values) => {
+                        self.copy_text(values.join("\n"), &format!("the value at {}", path))
+                    }
+                    Err(err) => self.error(err),
+                }
+            }
+            'f' => {
+                let formatted = match json::parse(&body) {
+                    Ok(value) => Some(value.pretty(2)),
+                    Err(_) => markup::kind(self.output_content_type.as_deref(), &body)
+                        .filter(|_| body.trim_start().starts_with('<'))
+                        .map(|html| markup::pretty(&body, html)),
+                };
+                match formatted {
+                    Some(formatted) => self.copy_text(formatted, "the body indented"),
+                    None => {
+                        self.warn("Only JSON, XML and HTML can be indented");
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn copy_text(&mut self, text: String, what: &str) {
+        match Clipboard::get().write_text(text) {
+            Ok(_) => self.info(format!("Copied {}", what)),
+            Err(err) => self.error(format!("Unable to save to system clipboard: {:?}", err)),
+        }
+    }
+
+    // Headers and body rows can be reordered and switched off. Other tables have a fixed order.
+    pub fn editing_movable_row(&self) -> bool {
+        self.current_panel == Panel::Input
+            && !self.raw_mode
+            && matches!(
+                self.current_input_type,
+                InputType::Headers | InputType::Body
+            )
+    }
+
+    pub fn move_row_down(&mut self) {
+        if self.editing_movable_row() && self.input_index + 1 < self.current_input_table().len() {
+            let index = self.input_index;
+            self.swap_rows(index, index + 1);
+            self.input_index += 1;
+            self.changed = true;
+        }
+    }
+
+    pub fn move_row_up(&mut self) {
+        if self.editing_movable_row() && self.input_index > 0 {
+            let index = self.input_index;
+            self.swap_rows(index - 1, index);
+            self.input_index -= 1;
+            self.changed = true;
+        }
+    }
+
+    fn swap_rows(&mut self, first: usize, second: usize) {
+        let table = self.current_input_table_mut();
+        let row = std::mem::take(&mut table[first]);
+        table[first] = std::mem::replace(&mut table[second], row);
+    }
+
+    fn yank_row(&mut self) {
+        let row = &self.current_input_table()[self.input_index];
+        self.yanked_row = Some((
+            row.key.lines()[0].clone(),
+            row.value.lines()[0].clone(),
+            row.disabled,
+        ));
+        self.info("Yanked the row");
+    }
+
+    fn put_row(&mut self) {
+        let Some((key, value, disabled)) = self.yanked_row.clone() else {
+            return;
+        };
+
+        let index = self.input_index + 1;
+        self.changed = true;
+        let table = self.current_input_table_mut();
+        let mut rows: Vec<InputRow> =
+            std::mem::replace(table, nonempty![InputRow::default()]).into();
+        rows.insert(
+            index,
+            InputRow {
+                key: [key].into(),
+                value: [value].into(),
+                disabled,
+            },
+        );
+        *table = NonEmpty::from_vec(rows).unwrap_or(nonempty![InputRow::default()]);
+        self.input_index = index;
+        self.current_input_field = InputField::Key;
+    }
+
+    // Edits are saved on the way out, but quitting is confirmed first so an edit isn't saved by a
+    // stray Ctrl-c, and so background work like a load test isn't cut short by accident.
+    pub fn quit(&mut self) {
+        let question = match (self.modified(), self.in_flight()) {
+            (false, None) => {
+                self.exit = true;
+                return;
+            }
+            (true, None) => "Save the edits and quit?".to_string(),
+            (modified, Some(work)) => format!(
+                "Quit{} with the {} in flight?",
+                if modified { " and save the edits" } else { "" },
+                work
+            ),
+        };
+        self.warn(format!(
+            "{} y/n, or :q! to quit and undo the edits",
+            question
+        ));
+        self.confirming_quit = true;
+    }
+
+    // y quits, and so does Ctrl-c again, for when quitting was meant. Any other key stays.
+    pub fn confirm_quit(&mut self, key_event: KeyEvent) {
+        self.confirming_quit = false;
+        self.exit = match key_event.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Char('c') => key_event.modifiers == KeyModifiers::CONTROL,
+            _ => false,
+        };
+        if !self.exit {
+            self.clear_message();
+        }
+    }
+
+    pub fn awaiting_register(&self) -> bool {
+        self.awaiting_register.is_some()
+    }
+
+    // Whether f, F, t or T was pressed and the next key is the character to look for.
+    pub fn awaiting_char(&self) -> bool {
+        matches!(self.pending_motion, Some('f' | 'F' | 't' | 'T'))
+    }
+
+    // q starts recording once a register is named, and stops recording when pressed again.
+    pub fn record(&mut self) {
+        match self.recording.take() {
+            Some((register, mut keys)) => {
+                // The q that stopped the recording isn't part of it.
+                keys.pop();
+                self.info(format!("Recorded {} keys to @{}", keys.len(), register));
+                self.macros.retain(|(existing, _)| *existing != register);
+                self.macros.push((register, keys));
+            }
+            None => self.awaiting_register = Some('q'),
+        }
+    }
+
+    pub fn replay(&mut self) {
+        self.awaiting_register = Some('@');
+    }
+
+    // The key after q or @ names a register, a letter or digit, and @@ replays the last macro
+    // again. Any other key cancels.
+    pub fn choose_register(&mut self, key_event: KeyEvent) {
+        let Some(action) = self.awaiting_register.take() else {
+            return;
+        };
+        let register = match key_event.code {
+            KeyCode::Char('@') if action == '@' => match self.last_macro {
+                Some(register) => register,
+                None => {
+                    self.warn("No macro has been replayed yet");
+                    return;
+                }
+            },
+            KeyCode::Char(register) if register.is_ascii_alphanumeric() => register,
+            _ => return,
+        };
+
+        if action == 'q' {
+            self.recording = Some((register, vec![]));
+            return;
+        }
+        if self.replaying.contains(&register) {
+            self.warn(format!("@{} can't replay itself", register));
+            return;
+        }
+        match self
+            .macros
+            .iter()
+            .find(|(existing, _)| *existing == register)
+        {
+            Some((_, keys)) => {
+                self.last_macro = Some(register);
+                self.pending_macro = Some((register, keys.clone()));
+            }
+            None => self.warn(format!("Nothing is recorded to @{}", register)),
+        }
+    }
+
+    // Every key pressed while recording is kept, as it was pressed, so replaying it goes through
+    // the same bindings, and so is every key of the command being typed. Keys a macro or . replays
+    // aren't recorded again.
+    pub fn record_key(&mut self, key_event: KeyEvent) {
+        if !self.replaying.is_empty() {
+            return;
+        }
+        if let Some((_, keys)) = &mut self.recording {
+            keys.push(key_event);
+        }
+        self.command_keys.push(key_event);
+    }
+
+    // Called once a key has been handled. A command is done when it's back in normal mode with no
+    // motion, register or count waiting for another key, and it's kept for . if it changed
+    // something, like an insert from i to ⎋ or dd.
+    pub fn finish_command(&mut self) {
+        if !self.replaying.is_empty() {
+            self.changed = false;
+            return;
+        }
+        if self.current_mode != Mode::Normal
+            || self.pending_motion.is_some()
+            || self.awaiting_register.is_some()
+            || self.count.is_some()
+        {
+            return;
+        }
+
+        let keys = std::mem::take(&mut self.command_keys);
+        if std::mem::take(&mut self.changed) {
+            self.last_change = keys;
+        }
+    }
+
+    // Presses the keys of the last change again, as many times as the count before the . says.
+    pub fn repeat_change(&mut self) {
+        let times = self.count.take().unwrap_or(1);
+        if self.last_change.is_empty() {
+            self.warn("Nothing to repeat");
+            return;
+        }
+        self.pending_macro = Some(('.', self.last_change.repeat(times)));
+    }
+
+    pub fn clear_count(&mut self) {
+        self.count = None;
+    }
+
+    // The copy goes after the last request and is selected, named after the original so
+    // request names stay unique.
+    fn duplicate_request(&mut self) {
+        if self.raw_mode {
+            if let Err(err) = self.apply_raw_input() {
+                self.error(format!("Unable to parse raw request: {}", err));
+                return;
+            }
+        }
+
+        let mut request = self.to_request();
+        if !request.name.is_empty() {
+            let names = self.request_names();
+            let mut copy = 1;
+            request.name = loop {
+                let name = match copy {
+                    1 => format!("{}-copy", self.name),
+                    _ => format!("{}-copy-{}", self.name, copy),
+                };
+                if !names.contains(&name) {
+                    break name;
+                }
+                copy += 1;
+            };
+        }
+
+        self.requests.push(request);
+        match self.select_request(self.requests.len() - 1) {
+            Ok(()) => self.info(format!(
+                "Duplicated as request {} of {}",
+                self.request_index + 1,
+                self.requests.len()
+            )),
+            Err(err) => {
+                self.requests.pop();
+                self.error(err);
+            }
+        }
+    }
+
+    // The headers that will be sent without being in the Headers table.
+    pub fn auto_headers(&self) -> Vec<(String, String)> {
+        let Ok(settings) = self.settings() else {
+            return vec![];
+        };
+        self.folder
+            .apply(&self.to_request())
+            .substituted(&self.all_variables())
+            .auto_headers(&settings)
+    }
+
+    // The form body as it will be sent, with its variables filled in.
+    pub fn form_preview(&self) -> Option<String> {
+        if self.text_body.is_some() && self.non_empty_body().next().is_none() {
+            return Some("Sending a raw body, press R to edit it".to_string());
+        }
+        self.folder
+            .apply(&self.to_request())
+            .substituted(&self.all_variables())
+            .form_preview()
+    }
+
+    // Adds the automatic headers as rows, where they can be changed or switched off. Content-Length
+    // is left out since it has to match the body.
+    pub fn add_auto_headers(&mut self) {
+        if !self.editing_movable_row() || self.current_input_type != InputType::Headers {
+            return;
+        }
+
+        let mut headers = self.auto_headers();
+        headers.retain(|(name, _)| name != "Content-Length");
+        if headers.is_empty() {
+            self.warn("No automatic headers to add");
+            return;
+        }
+        let mut rows: Vec<InputRow> = std::mem::replace(
+            &mut self.headers_input_table,
+            nonempty![InputRow::default()],
+        )
+        .into();
+        // The empty row at the end stays last, to type new headers in.
+        let end = match rows.last() {
+            Some(row) if row.is_empty() => rows.len() - 1,
+            _ => rows.len(),
+        };
+        let count = headers.len();
+        for (offset, (name, value)) in headers.into_iter().enumerate() {
+            rows.insert(
+                end + offset,
+                InputRow {
+                    key: [name].into(),
+                    value: [value].into(),
+                    ..InputRow::default()
+                },
+            );
+        }
+        self.headers_input_table =
+            NonEmpty::from_vec(rows).unwrap_or(nonempty![InputRow::default()]);
+        self.info(format!("Added {} automatic headers", count));
+    }
+
+    pub fn toggle_reveal_secrets(&mut self) {
+        self.reveal_secrets = !self.reveal_secrets;
+        self.info(if self.reveal_secrets {
+            "Showing the password and token".to_string()
+        } else {
+            "Hiding the password and token".to_string()
+        });
+    }
+
+    pub fn toggle_row_disabled(&mut self) {
+        if !self.editing_movable_row() {
+            return;
+        }
+
+        let row = self.current_input_row_mut();
+        row.disabled = !row.disabled;
+        self.changed = true;
+    }
+
+    // Copies the request as it would be sent, without running its pre-request script.
+    pub fn copy_as_curl(&mut self) {
+        if self.raw_mode {
+            if let Err(err) = self.apply_raw_input() {
+                self.error(format!("Unable to parse raw request: {}", err));
+                return;
+            }
+        }
+
+        let curl = self
+            .folder
+            .apply(&self.to_request())
+            .substituted(&self.all_variables())
+            .curl();
+        match Clipboard::get().write_text(curl) {
+            Ok(_) => self.info("Copied the request as curl"),
+            Err(err) => self.error(format!("Unable to save to system clipboard: {:?}", err)),
+        }
+    }
+
+    pub fn toggle_command(&mut self) {
+        if self.current_panel == Panel::Command {
+            self.toggle_popup(Panel::Command, 0);
+            self.current_mode = Mode::Normal;
+            return;
+        }
+        if self.popup_return.is_some() {
+            return;
+        }
+
+        self.command_input = TextArea::default();
+        self.command_completion = None;
+        self.toggle_popup(Panel::Command, 0);
+        self.current_mode = Mode::Insert;
+    }
+
+    // The commands, or environment names after :env, that fuzzily match what has been typed, best
+    // match first.
+    pub fn command_completions(&self) -> Vec<String> {
+        let typed = match &self.command_completion {
+            Some((typed, _)) => typed.clone(),
+            None => self.command_input.lines()[0].clone(),
+        };
+
+        let (prefix, pattern, candidates): (&str, &str, Vec<String>) =
+            match typed.strip_prefix("env ") {
+                Some(name) => (
+                    "env ",
+                    name,
+                    self.environments
+                        .iter()
+                        .map(|(name, _)| name.clone())
+                        .collect(),
+                ),
+                None => (
+                    "",
+                    typed.as_str(),
+                    COMMANDS
+                        .iter()
+                        .map(|(command, _)| command.to_string())
+                        .collect(),
+                ),
+            };
+
+        let mut matches: Vec<(usize, String)> = candidates
+            .into_iter()
+            .filter_map(|candidate| {
+                fuzzy_score(&candidate, pattern.trim())
+                    .map(|score| (score, format!("{}{}", prefix, candidate)))
+            })
+            .collect();
+        matches.sort();
+        matches
+            .into_iter()
+            .map(|(_, candidate)| candidate)
+            .collect()
+    }
+
+    // A URL sent before that starts with what's been typed, offered the way address bars do. The
+    // newest come first, and the host on its own comes before the full URL, so completing goes a
+    // part at a time.
+    pub fn url_suggestion(&self) -> Option<String> {
+        if self.current_panel != Panel::Url || self.current_mode != Mode::Insert {
+            return None;
+        }
+        let typed = &self.url_input.lines()[0];
+        if typed.is_empty() || self.url_input.cursor().1 != typed.chars().count() {
+            return None;
+        }
+
+        let root = |url: &str| {
+            let url = Url::parse(url).ok()?;
+            let host = url.host_str()?;
+            Some(match url.port() {
+                Some(port) => format!("{}://{}:{}/", url.scheme(), host, port),
+                None => format!("{}://{}/", url.scheme(), host),
+            })
+        };
+        self.history
+            .iter()
+            .rev()
+            .flat_map(|entry| [root(&entry.url), Some(entry.url.clone())])
+            .flatten()
+            .find(|url| url.len() > typed.len() && url.starts_with(typed.as_str()))
+    }
+
+    pub fn complete_url(&mut self) {
+        if let Some(suggestion) = self.url_suggestion() {
+            let typed = self.url_input.lines()[0].len();
+            self.url_input.insert_str(&suggestion[typed..]);
+            self.sync_path_params();
+        }
+    }
+
+    // Replaces the command with its next completion.
+    pub fn complete_command(&mut self) {
+        let (typed, index) = match self.command_completion.take() {
+            Some((typed, index)) => (typed, index + 1),
+            None => (self.command_input.lines()[0].clone(), 0),
+        };
+        self.command_completion = Some((typed, index));
+
+        let completions = self.command_completions();
+        if completions.is_empty() {
+            self.command_completion = None;
+            return;
+        }
+        let completion = &completions[index % completions.len()];
+        self.command_input = TextArea::from([completion.as_str()]);
+        self.command_input.move_cursor(CursorMove::End);
+    }
+
+    pub fn run_command(&mut self) {
+        let command = self.command_input.lines()[0].trim().to_string();
+        self.toggle_command();
+        let (name, argument) = command.split_once(' ').unwrap_or((&command, ""));
+        let argument = argument.trim();
+
+        // The pattern and replacement can have spaces in them, so they aren't split off.
+        if let Some(substitution) = command.strip_prefix("%s/") {
+            self.substitute_request(substitution);
+            return;
+        }
+        if let Some(substitution) = command.strip_prefix("s/") {
+            self.substitute_field(substitution);
+            return;
+        }
+
+        match name {
+            "" => (),
+            "w" => self.save(),
+            "q" => self.quit(),
+            "q!" => {
+                self.discard_edits = true;
+                self.exit = true;
+            }
+            "wq" | "x" => {
+                self.save();
+                self.quit();
+            }
+            "send" => {
+                self.submit_request();
+            }
+            "run" => self.run_collection(),
+            "import" => match argument.split_once(' ') {
+                Some(("curl", curl)) => self.import_curl(&format!("curl {}", curl)),
+                _ => self.warn("Usage: :import curl <command>"),
+            },
+            "env" => self.switch_environment(argument),
+            "history" => self.toggle_history(),
+            "messages" => self.toggle_messages(),
+            "settings" => self.toggle_settings(),
+            "cookies" => self.toggle_cookies(),
+            "mock" => self.mock_command(argument),
+            "catch" => self.catch_command(argument),
+            "oauth" => self.authorize(),
+            "duplicate" => self.duplicate_request(),
+            "e" | "edit" => self.edit_file(argument),
+            "secret" => self.set_secret(argument),
+            "snapshot" => self.save_snapshot(argument),
+            "watch" => self.toggle_watch(),
+            "poll" => self.toggle_polling(argument),
+            "audit" => self.toggle_audit(),
+            "load" => self.start_load_test(argument),
+            "open" => self.open_output(argument),
+            #[cfg(feature = "grpc")]
+            "grpc" => self.grpc_command(argument),
+            _ => self.warn(format!("Unknown command: {}", name)),
+        }
+    }
+
+    // :s/pattern/replacement/ in the field that had focus, like vim's :s on a line.
+    fn substitute_field(&mut self, substitution: &str) {
+        let editable = match self.current_panel {
+            Panel::Url | Panel::Description | Panel::Settings | Panel::Cookies | Panel::Mock => {
+                true
+            }
+            Panel::Input => !self.input_disabled(),
+            _ => false,
+        };
+        if !editable {
+            self.warn("There's no field to replace in here");
+            return;
+        }
+        let (regex, replacement, all) = match parse_substitution(substitution) {
+            Ok(substitution) => substitution,
+            Err(err) => {
+                self.error(err);
+                return;
+            }
+        };
+
+        let count = substitute_lines(self.current_input_mut(), &regex, &replacement, all);
+        self.finish_substitution(count, regex.as_str());
+    }
+
+    // :%s/pattern/replacement/ in the URL, every header, body and path row, the auth and the
+    // notes, or in the raw text and the notes while editing raw HTTP.
+    fn substitute_request(&mut self, substitution: &str) {
+        let (regex, replacement, all) = match parse_substitution(substitution) {
+            Ok(substitution) => substitution,
+            Err(err) => {
+                self.error(err);
+                return;
+            }
+        };
+
+        let mut inputs: Vec<&mut TextArea<'static>> = vec![&mut self.description_input];
+        if self.raw_mode {
+            inputs.push(&mut self.raw_input);
+        } else {
+            inputs.push(&mut self.url_input);
+            inputs.push(&mut self.auth.basic_input.key);
+            inputs.push(&mut self.auth.basic_input.value);
+            inputs.push(&mut self.auth.bearer_input);
+            for row in self
+                .headers_input_table
+                .iter_mut()
+                .chain(self.body_input_table.iter_mut())
+            {
+                inputs.push(&mut row.key);
+                inputs.push(&mut row.value);
+            }
+            // Path rows are named by the URL's placeholders, so only their values are replaced.
+            for row in self.path_input_table.iter_mut() {
+                inputs.push(&mut row.value);
+            }
+        }
+        let count = inputs
+            .into_iter()
+            .map(|input| substitute_lines(input, &regex, &replacement, all))
+            .sum();
+        self.finish_substitution(count, regex.as_str());
+    }
+
+    fn finish_substitution(&mut self, count: usize, pattern: &str) {
+        if count == 0 {
+            self.warn(format!("Pattern not found: {}", pattern));
+            return;
+        }
+        self.sync_path_params();
+        self.info(match count {
+            1 => "Replaced 1 match".to_string(),
+            _ => format!("Replaced {} matches", count),
+        });
+    }
+
+    fn save(&mut self) {
+        if self.raw_mode {
+            if let Err(err) = self.apply_raw_input() {
+                self.error(format!("Unable to parse raw request: {}", err));
+                return;
+            }
+        }
+
+        match self.to_file() {
+            Ok(()) => {
+                self.committed = Some(self.saved.clone());
+                self.info(format!("Saved {}", self.filename));
+            }
+            Err(err) => self.error(format!("Unable to save {}: {}", self.filename, err)),
+        }
+    }
+
+    fn import_curl(&mut self, command: &str) {
+        let request = match curl::parse(command) {
+            Ok(request) => request,
+            Err(err) => {
+                self.error(format!("Unable to import the curl command: {}", err));
+                return;
+            }
+        };
+
+        self.apply_request(&request);
+        self.options_input_table = Self::settings_table(&request.options);
+        if self.raw_mode {
+            self.raw_input = TextArea::from(self.to_request().raw().lines());
+        }
+        self.info("Imported the curl command");
+    }
+
+    pub fn offer_clipboard(&mut self, text: String) {
+        self.info(if text.trim_start().starts_with("curl ") {
+            "Copied a curl command, press I to import it".to_string()
+        } else {
+            "Copied a URL, press I to import it".to_string()
+        });
+        self.clipboard_offer = Some(text);
+    }
+
+    // Copied requests are added after the others rather than replacing the one being edited.
+    pub fn import_clipboard(&mut self) {
+        let Some(text) = self.clipboard_offer.take() else {
+            self.warn("Nothing copied to import");
+            return;
+        };
+        let text = text.trim();
+        let request = if text.starts_with("curl ") {
+            match curl::parse(text) {
+                Ok(request) => request,
+                Err(err) => {
+                    self.error(format!("Unable to import the curl command: {}", err));
+                    return;
+                }
+            }
+        } else {
+            Request {
+                url: text.to_string(),
+                ..Request::default()
+            }
+        };
+
+        self.requests.push(request);
+        match self.select_request(self.requests.len() - 1) {
+            Ok(()) => self.info(format!("Imported request {}", self.requests.len())),
+            Err(err) => {
+                self.requests.pop();
+                self.error(err);
+            }
+        }
+    }
+
+    fn switch_environment(&mut self, name: &str) {
+        if name.is_empty() {
+            self.environment = None;
+            self.info("No environment");
+            return;
+        }
+
+        match file::environment(&self.environments, Some(name)) {
+            Ok(_) => {
+                self.environment = Some(name.to_string());
+                self.info(format!("Switched to the {} environment", name));
+            }
+            Err(err) => self.error(err),
+        }
+    }
+
+    // Opens the query bar under the Output panel. Closing it puts the full output back.
+    // Builds the request the way sending it would, pre-request script and all, and shows what
+    // would go over the wire.
+    pub fn toggle_preview(&mut self) {
+        if self.current_panel == Panel::Preview {
+            self.toggle_popup(Panel::Preview, 0);
+            return;
+        }
+
+        // A preview shows what sending would do without keeping what the script sets.
+        let captured = self.captured.clone();
+        let request = self.request_builder().and_then(|request_builder| {
+            request_builder
+                .build()
+                .map_err(|err| format!("Unable to build the request: {}", err))
+        });
+        self.captured = captured;
+        match request {
+            Ok(request) => {
+                self.preview_input = TextArea::from(preview_text(&request).lines());
+                self.toggle_popup(Panel::Preview, 0);
+            }
+            Err(err) => self.error(err),
+        }
+    }
+
+    pub fn toggle_description(&mut self) {
+        if self.current_panel == Panel::Description {
+            self.current_mode = Mode::Normal;
+        }
+        self.toggle_popup(Panel::Description, 0);
+    }
+
+    // The description without the blank lines left at the end of it.
+    pub fn description(&self) -> String {
+        self.description_input
+            .lines()
+            .join("\n")
+            .trim_end()
+            .to_string()
+    }
+
+    // Switches the Output panel between XML and HTML responses as they came and indented, for this
+    // response and the ones after it.
+    pub fn toggle_format(&mut self) {
+        if self.example_index.is_some() {
+            self.warn("Leave the examples to format the response");
+            return;
+        }
+
+        self.format_markup = !self.format_markup;
+        if !self.format_markup {
+            if let Some(output) = self.unformatted_output.take() {
+                self.output_input = output;
+            }
+            self.info("Showing XML and HTML responses as they came");
+        } else if self.format_output() {
+            self.info("Indented the response. Press = to see it as it came.");
+        } else {
+            self.info("XML and HTML responses will be indented");
+        }
+    }
+
+    // Indents the response in the Output panel when formatting is on and it's XML or HTML.
+    fn format_output(&mut self) -> bool {
+        self.unformatted_output = None;
+        let text = self.output_input.lines().join("\n");
+        if !self.format_markup || !text.trim_start().starts_with('<') {
+            return false;
+        }
+        let Some(html) = markup::kind(self.output_content_type.as_deref(), &text) else {
+            return false;
+        };
+
+        let formatted = TextArea::from(markup::pretty(&text, html).lines());
+        self.unformatted_output = Some(std::mem::replace(&mut self.output_input, formatted));
+        true
+    }
+
+    pub fn toggle_query(&mut self) {
+        if self.current_panel == Panel::Query {
+            if let Some(output) = self.unqueried_output.take() {
+                self.output_input = output;
+            }
+            self.toggle_popup(Panel::Query, 0);
+            return;
+        }
+
+        self.toggle_popup(Panel::Query, 0);
+        self.current_mode = Mode::Insert;
+    }
+
+    pub fn run_query(&mut self) {
+        let output = self.unqueried_output.as_ref().unwrap_or(&self.output_input);
+        let body = output.lines().join("\n");
+        let query = self.query_input.lines()[0].clone();
+        if query.trim().is_empty() {
+            return;
+        }
+
+        match query::select(&body, &query) {
+            Ok(matches) => {
+                self.info(match matches.len() {
+                    1 => "1 match".to_string(),
+                    count => format!("{} matches", count),
+                });
+                if self.unqueried_output.is_none() {
+                    self.unqueried_output = Some(self.output_input.clone());
+                }
+                self.output_input = TextArea::from(matches.join("\n").lines());
+            }
+            Err(err) => self.error(err),
+        }
+    }
+
+    pub fn next_history_entry(&mut self) {
+        if self.input_index + 1 < self.history.len() {
+            self.input_index += 1;
+        }
+    }
+
+    pub fn previous_history_entry(&mut self) {
+        self.input_index = self.input_index.saturating_sub(1);
+    }
+
+    pub fn replay_history_entry(&mut self) {
+        let Some(entry) = self.history.get(self.input_index).cloned() else {
+            return;
+        };
+
+        self.toggle_history();
+        self.raw_mode = false;
+        match Request::parse(&entry.request_text()) {
+            Ok(request) => {
+                self.apply_request(&request);
+                self.output_input = TextArea::from(entry.response_body.lines());
+                self.output_content_type = entry_content_type(&entry);
+                self.output_headers = entry.response_headers.clone();
+                self.format_output();
+                self.info(format!("Loaded {}", entry.summary()));
+            }
+            Err(err) => self.error(format!("Unable to load history entry: {}", err)),
+        }
+    }
+
+    pub fn method_cursor_position(&self) -> u16 {
+        self.current_method.to_string().len() as u16
+    }
+
+    pub fn next_method(&mut self) {
+        let new_method = match self.current_method {
+            Method::OPTIONS => Method::GET,
+            Method::GET => Method::HEAD,
+            Method::HEAD => Method::POST,
+            Method::POST => Method::PUT,
+            Method::PUT => Method::PATCH,
+            Method::PATCH => Method::DELETE,
+            Method::DELETE => Method::TRACE,
+            Method::TRACE => Method::CONNECT,
+            Method::CONNECT => grpc_method().unwrap_or(Method::OPTIONS),
+            _ if Some(&self.current_method) == grpc_method().as_ref() => Method::OPTIONS,
+            _ => return,
+        };
+
+        self.current_method = new_method;
+    }
+
+    pub fn previous_method(&mut self) {
+        let new_method = match self.current_method {
+            Method::OPTIONS => grpc_method().unwrap_or(Method::CONNECT),
+            Method::GET => Method::OPTIONS,
+            Method::HEAD => Method::GET,
+            Method::POST => Method::HEAD,
+            Method::PUT => Method::POST,
+            Method::PATCH => Method::PUT,
+            Method::DELETE => Method::PATCH,
+            Method::TRACE => Method::DELETE,
+            Method::CONNECT => Method::TRACE,
+            _ if Some(&self.current_method) == grpc_method().as_ref() => Method::CONNECT,
+            _ => return,
+        };
+
+        self.current_method = new_method;
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url_input.lines()[0]
+    }
+
+    // Checked as the URL is typed, so an empty URL isn't flagged before there's anything in it.
+    pub fn url_error(&self) -> Option<String> {
+        if self.url().trim().is_empty() {
+            return None;
+        }
+        request::parse_url(&self.substitute(self.url())).err()
+    }
+
+    pub fn header_pairs(&self) -> Vec<(String, String)> {
+        self.non_empty_headers().map(|row| row.into()).collect()
+    }
+
+    pub fn body_pairs(&self) -> Vec<(String, String)> {
+        self.non_empty_body().map(|row| row.into()).collect()
+    }
+
+    pub fn cursor_col(&self) -> u16 {
+        self.current_input().cursor().1 as u16
+    }
+
+    pub fn copy(&mut self) {
+        match self.shaped_selection() {
+            Some(text) => self.current_input_mut().set_yank_text(text),
+            None => self.current_input_mut().copy(),
+        }
+        self.write_clipboard();
+    }
+
+    pub fn cut(&mut self) {
+        if self.input_disabled() || self.editing_path_name() {
+            return;
+        }
+
+        match self.shaped_selection() {
+            Some(text) => {
+                self.current_input_mut().set_yank_text(text);
+                self.delete_shaped_selection();
+            }
+            None => self.current_input_mut().cut(),
+        }
+        self.write_clipboard();
+        self.sync_path_params();
+        self.changed = true;
+    }
+
+    // The text of a line or block selection, which the text area can't select itself. Lines end
+    // with a newline so they're put back as lines.
+    fn shaped_selection(&self) -> Option<String> {
+        let Mode::Visual(shape @ (Selection::Line | Selection::Block)) = self.current_mode else {
+            return None;
+        };
+        let (top, bottom) = self.selected_rows()?;
+        let lines = self.current_input().lines();
+        let text = (top..=bottom.min(lines.len() - 1))
+            .map(|row| {
+                let (from, to) = self.selected_columns(row).unwrap_or_default();
+                lines[row]
+                    .chars()
+                    .skip(from)
+                    .take(to - from)
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Some(match shape {
+            Selection::Line => text + "\n",
+            _ => text,
+        })
+    }
+
+    // Line selections take their lines out and block selections their columns, leaving the
+    // cursor where the selection started.
+    fn delete_shaped_selection(&mut self) {
+        let Some((top, bottom)) = self.selected_rows() else {
+            return;
+        };
+        let line_wise = self.current_mode == Mode::Visual(Selection::Line);
+        let columns: Vec<(usize, usize)> = (top..=bottom)
+            .map(|row| self.selected_columns(row).unwrap_or_default())
+            .collect();
+
+        let input = self.current_input_mut();
+        let mut lines = input.lines().to_vec();
+        let cursor = if line_wise {
+            lines.drain(top..=bottom.min(lines.len() - 1));
+            if lines.is_empty() {
+                lines.push(String::new());
+            }
+            (top.min(lines.len() - 1), 0)
+        } else {
+            for (line, &(from, to)) in lines[top..].iter_mut().zip(&columns) {
+                *line = line
+                    .chars()
+                    .enumerate()
+                    .filter(|(index, _)| !(from..to).contains(index))
+                    .map(|(_, c)| c)
+                    .collect();
+            }
+            (top, columns.first().map_or(0, |(from, _)| *from))
+        };
+        *input = TextArea::from(lines);
+        input.move_cursor(CursorMove::Jump(cursor.0 as u16, cursor.1 as u16));
+    }
+
+    fn write_clipboard(&mut self) {
+        match Clipboard::get().write_text(self.current_input().yank_text()) {
+            Ok(_) => (),
+            Err(err) => self.error(format!("Unable to save to system clipboard: {:?}", err)),
+        }
+    }
+
+    pub fn handle_insert_input(&mut self, event: KeyEvent) {
+        if self.input_disabled() {
+            return;
+        }
+        if self.editing_path_name() {
+            self.warn("Path parameters are named by the placeholders in the URL");
+            return;
+        }
+
+        // Typing after Tab keeps the completion and matches from there.
+        self.command_completion = None;
+        self.current_input_mut().input(event);
+        self.sync_path_params();
+    }
+
+    // Gives the Path table a row for each placeholder in the URL, keeping the values of those it
+    // already had a row for.
+    fn sync_path_params(&mut self) {
+        let names = request::path_placeholders(self.url());
+        let current: Vec<&String> = self
+            .path_input_table
+            .iter()
+            .map(|row| &row.key.lines()[0])
+            .filter(|name| !name.is_empty())
+            .collect();
+        if names.iter().eq(current) {
+            return;
+        }
+
+        let rows = names
+            .iter()
+            .map(|name| InputRow {
+                key: [name.as_str()].into(),
+                value: self
+                    .path_input_table
+                    .iter()
+                    .find(|row| row.key.lines()[0] == *name)
+                    .map_or(TextArea::default(), |row| row.value.clone()),
+                ..InputRow::default()
+            })
+            .collect();
+        self.path_input_table = NonEmpty::from_vec(rows).unwrap_or(nonempty![InputRow::default()]);
+        if self.current_input_type == InputType::Path {
+            self.input_index = self.input_index.min(self.path_input_table.len() - 1);
+        }
+    }
+
+    fn path_pairs(&self) -> Vec<(String, String)> {
+        self.path_input_table
+            .iter()
+            .filter(|row| !row.key.is_empty() && !row.value.is_empty())
+            .map(|row| row.into())
+            .collect()
+    }
+
+    fn editing_path_name(&self) -> bool {
+        self.current_panel == Panel::Input
+            && !self.raw_mode
+            && self.current_input_type == InputType::Path
+            && self.current_input_field == InputField::Key
+    }
+
+    // The view draws the Output panel rather than its TextArea, so it's scrolled by the height it
+    // was last drawn at, taking the cursor along the way a TextArea does.
+    fn scroll_output(&mut self, scrolling: Scrolling) {
+        let height = self.output_height as isize;
+        let rows = match scrolling {
+            Scrolling::Delta { rows, .. } => rows as isize,
+            Scrolling::PageDown => height,
+            Scrolling::PageUp => -height,
+            Scrolling::HalfPageDown => height / 2,
+            Scrolling::HalfPageUp => -height / 2,
+        };
+        let last = self.output_input.lines().len().saturating_sub(1);
+        self.output_scroll.0 = self.output_scroll.0.saturating_add_signed(rows).min(last);
+        let cursor_move = if rows < 0 {
+            CursorMove::Up
+        } else {
+            CursorMove::Down
+        };
+        for _ in 0..rows.unsigned_abs() {
+            self.output_input.move_cursor(cursor_move);
+        }
+    }
+
+    pub fn handle_normal_input(&mut self, key_event: KeyEvent) {
+        if self.input_disabled() {
+            return;
+        }
+
+        let scrolling = match (key_event.modifiers, key_event.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('d')) => Some(Scrolling::HalfPageDown),
+            (KeyModifiers::CONTROL, KeyCode::Char('u')) => Some(Scrolling::HalfPageUp),
+            (KeyModifiers::CONTROL, KeyCode::Char('f')) | (_, KeyCode::PageDown) => {
+                Some(Scrolling::PageDown)
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('b')) | (_, KeyCode::PageUp) => {
+                Some(Scrolling::PageUp)
+            }
+            _ => None,
+        };
+        if let Some(scrolling) = scrolling.filter(|_| self.multiline_input()) {
+            if self.current_panel == Panel::Output {
+                self.scroll_output(scrolling);
+            } else {
+                self.current_input_mut().scroll(scrolling);
+            }
+            self.count = None;
+            return;
+        }
+
+        // 0 moves to the start of the line unless it's part of a count. Counts are capped so a
+        // mistyped one can't hang the app repeating a motion.
+        if let KeyCode::Char(digit @ '0'..='9') = key_event.code {
+            if digit != '0' || self.count.is_some() {
+                let digit = digit.to_digit(10).unwrap_or_default() as usize;
+                self.count = Some((self.count.unwrap_or(0) * 10 + digit).min(MAX_COUNT));
+                return;
+            }
+        }
+        let count = self.count.take();
+
+        let pending_motion = self.pending_motion.take();
+        if let Some(kind @ ('f' | 'F' | 't' | 'T')) = pending_motion {
+            if let KeyCode::Char(target) = key_event.code {
+                self.last_find = Some((kind, target));
+                self.find_char(kind, target, count.unwrap_or(1), false);
+            }
+            return;
+        }
+        if self.current_panel == Panel::Output {
+            match (pending_motion, key_event.code) {
+                (Some('y'), KeyCode::Char(key)) if self.copy_response(key) => return,
+                (None, KeyCode::Char('y')) => {
+                    self.pending_motion = Some('y');
+                    return;
+                }
+                _ => (),
+            }
+        }
+        let cursor_move = match key_event.code {
+            KeyCode::Char('h') | KeyCode::Left => Some(CursorMove::Back),
+            KeyCode::Char('l') | KeyCode::Right => Some(CursorMove::Forward),
+            KeyCode::Char('b') => Some(CursorMove::WordBack),
+            KeyCode::Char('w') => Some(CursorMove::WordForward),
+            KeyCode::Char('^') | KeyCode::Char('0') | KeyCode::Home => Some(CursorMove::Head),
+            KeyCode::Char('$') | KeyCode::End => Some(CursorMove::End),
+            KeyCode::Char(kind @ ('f' | 'F' | 't' | 'T')) => {
+                self.pending_motion = Some(kind);
+                self.count = count;
+                None
+            }
+            // ; looks for the last character again the same way, and , the other way.
+            KeyCode::Char(repeat @ (';' | ',')) => {
+                if let Some((kind, target)) = self.last_find {
+                    let kind = match (repeat, kind) {
+                        (';', kind) => kind,
+                        (_, 'f') => 'F',
+                        (_, 'F') => 'f',
+                        (_, 't') => 'T',
+                        _ => 't',
+                    };
+                    self.find_char(kind, target, count.unwrap_or(1), true);
+                }
+                None
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.multiline_input() => Some(CursorMove::Down),
+            KeyCode::Char('k') | KeyCode::Up if self.multiline_input() => Some(CursorMove::Up),
+            // With a count, gg and G go to that line, like 12G.
+            KeyCode::Char('g') if self.multiline_input() => {
+                if pending_motion == Some('g') {
+                    Some(count.map_or(CursorMove::Top, line_jump))
+                } else {
+                    self.pending_motion = Some('g');
+                    self.count = count;
+                    None
+                }
+            }
+            KeyCode::Char('G') if self.multiline_input() => {
+                Some(count.map_or(CursorMove::Bottom, line_jump))
+            }
+            KeyCode::Char('d') if self.editing_table_row() => {
+                if pending_motion == Some('d') {
+                    for _ in 0..count.unwrap_or(1).min(self.current_input_table().len()) {
+                        self.delete_row();
+                    }
+                } else {
+                    self.pending_motion = Some('d');
+                    self.count = count;
+                }
+                None
+            }
+            KeyCode::Char('y') if self.editing_movable_row() => {
+                if pending_motion == Some('y') {
+                    self.yank_row();
+                } else {
+                    self.pending_motion = Some('y');
+                }
+                None
+            }
+            KeyCode::Char('p') if self.editing_movable_row() => {
+                self.put_row();
+                None
+            }
+            _ => None,
+        };
+
+        if let Some(request) = cursor_move {
+            for _ in 0..count.unwrap_or(1) {
+                self.current_input_mut().move_cursor(request);
+            }
+        }
+    }
+
+    // Moves to the count-th target after the cursor on its line for f, or before it for F, and
+    // stops a character short of it for t and T. Repeating t or T skips a target right next to
+    // the cursor, which it would otherwise stop in front of again.
+    fn find_char(&mut self, kind: char, target: char, count: usize, repeat: bool) {
+        let input = self.current_input_mut();
+        let (row, col) = input.cursor();
+        let Some(line) = input.lines().get(row) else {
+            return;
+        };
+        let chars: Vec<char> = line.chars().collect();
+        let skip = usize::from(repeat && matches!(kind, 't' | 'T'));
+        let targets = chars
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c == target)
+            .map(|(index, _)| index);
+        let found = match kind {
+            'f' => targets.filter(|index| *index > col).nth(count - 1),
+            't' => targets
+                .filter(|index| *index > col + skip)
+                .nth(count - 1)
+                .map(|index| index - 1),
+            'F' => targets.rev().filter(|index| *index < col).nth(count - 1),
+            _ => targets
+                .rev()
+                .filter(|index| index + skip < col)
+                .nth(count - 1)
+                .map(|index| index + 1),
+        };
+        if let Some(col) = found {
+            input.move_cursor(CursorMove::Jump(row as u16, col as u16));
+        }
+    }
+
+    pub fn next_input_type(&mut self) {
+        self.remember_input_focus();
+        self.current_input_type = self.current_input_type.next().unwrap_or_default();
+        self.restore_input_focus();
+    }
+
+    pub fn previous_input_type(&mut self) {
+        self.remember_input_focus();
+        self.current_input_type = self
+            .current_input_type
+            .previous()
+            .unwrap_or(InputType::last().unwrap());
+        self.restore_input_focus();
+    }
+
+    fn remember_input_focus(&mut self) {
+        let (input_index, input_field) = match &self.popup_return {
+            Some((_, input_index, input_field)) => (*input_index, *input_field),
+            None => (self.input_index, self.current_input_field),
+        };
+        self.input_focus
+            .retain(|focus| focus.input_type != self.current_input_type);
+        self.input_focus.push(InputFocus {
+            input_type: self.current_input_type,
+            input_index,
+            input_field,
+        });
+    }
+
+    // Puts the cursor back where it was in the current input type, or on its last row if it
+    // hasn't been there yet.
+    fn restore_input_focus(&mut self) {
+        let last_row = match self.current_input_type {
+            InputType::Auth | InputType::Headers => self.headers_input_table.len() - 1,
+            InputType::Path => self.path_input_table.len() - 1,
+            InputType::Body => self.body_input_table.len() - 1,
+            InputType::Options => self.options_input_table.len() - 1,
+        };
+        let (input_index, input_field) = match self
+            .input_focus
+            .iter()
+            .find(|focus| focus.input_type == self.current_input_type)
+        {
+            Some(focus) => (focus.input_index.min(last_row), focus.input_field),
+            // Path parameters are filled in from the top, and only their values can be edited.
+            None if self.current_input_type == InputType::Path => (0, InputField::Value),
+            None => (last_row, InputField::default()),
+        };
+        match &mut self.popup_return {
+            Some((_, popup_index, popup_field)) => {
+                *popup_index = input_index;
+                *popup_field = input_field;
+            }
+            None => {
+                self.input_index = input_index;
+                self.current_input_field = input_field;
+            }
+        }
+    }
+
+    pub fn next_input_field(&mut self) {
+        if matches!(
+            self.current_panel,
+            Panel::Settings | Panel::Cookies | Panel::Mock
+        ) {
+            self.next_table_field();
+            return;
+        }
+
+        match self.current_input_type {
+            InputType::Auth => match self.auth.format {
+                AuthFormat::None | AuthFormat::Bearer => (),
+                AuthFormat::Basic => {
+                    self.current_input_field = self.current_input_field.next().unwrap_or_default();
+                }
+            },
+            InputType::Path | InputType::Headers | InputType::Body | InputType::Options => {
+                self.next_table_field()
+            }
+        }
+    }
+
+    pub fn previous_input_field(&mut self) {
+        if matches!(
+            self.current_panel,
+            Panel::Settings | Panel::Cookies | Panel::Mock
+        ) {
+            self.previous_table_field();
+            return;
+        }
+
+        match self.current_input_type {
+            InputType::Auth => match self.auth.format {
+                AuthFormat::None | AuthFormat::Bearer => (),
+                AuthFormat::Basic => {
+                    self.current_input_field = self
+                        .current_input_field
+                        .previous()
+                        .unwrap_or(InputField::last().unwrap());
+                }
+            },
+            InputType::Path | InputType::Headers | InputType::Body | InputType::Options => {
+                self.previous_table_field()
+            }
+        }
+    }
+
+    fn next_table_field(&mut self) {
+        if self.current_input_field == InputField::last().unwrap() {
+            // The Path table only has the URL's placeholders, so there's no row to add to it.
+            let fixed =
+                self.current_panel == Panel::Input && self.current_input_type == InputType::Path;
+            if !fixed && !self.current_input_table().last().is_empty() {
+                self.current_input_table_mut().push(InputRow::default());
+            }
+            if self.input_index < self.current_input_table().len() - 1 {
+                self.input_index += 1
+            }
+        }
+        self.current_input_field = self.current_input_field.next().unwrap_or_default();
+    }
+
+    fn previous_table_field(&mut self) {
+        if self.current_input_field == InputField::first().unwrap() {
+            if self.input_index == 0 {
+                self.input_index = self.current_input_table().len() - 1;
+            } else {
+                self.input_index -= 1;
+            }
+        }
+        self.current_input_field = self
+            .current_input_field
+            .previous()
+            .unwrap_or(InputField::last().unwrap());
+    }
+
+    pub fn next_input_format(&mut self) {
+        match self.current_input_type {
+            InputType::Auth => {
+                self.auth.format = self.auth.format.next().unwrap_or_default();
+            }
+            InputType::Path | InputType::Headers | InputType::Options => (),
+            InputType::Body => {
+                self.current_body_format = self.current_body_format.next().unwrap_or_default();
+            }
+        }
+    }
+
+    pub fn previous_input_format(&mut self) {
+        match self.current_input_type {
+            InputType::Auth => {
+                self.auth.format = self
+                    .auth
+                    .format
+                    .previous()
+                    .unwrap_or(AuthFormat::last().unwrap());
+            }
+            InputType::Path | InputType::Headers | InputType::Options => (),
+            InputType::Body => {
+                self.current_body_format = self
+                    .current_body_format
+                    .previous()
+                    .unwrap_or(BodyFormat::last().unwrap());
+            }
+        }
+    }
+
+    pub fn current_input_table(&self) -> &NonEmpty<InputRow> {
+        match self.current_panel {
+            Panel::Settings => &self.settings_input_table,
+            Panel::Cookies => &self.cookies_input_table,
+            Panel::Mock => &self.mock_input_table,
+            _ => match self.current_input_type {
+                InputType::Auth | InputType::Headers => &self.headers_input_table,
+                InputType::Path => &self.path_input_table,
+                InputType::Body => &self.body_input_table,
+                InputType::Options => &self.options_input_table,
+            },
+        }
+    }
+
+    pub fn request_builder(&mut self) -> Result<RequestBuilder, String> {
+        if self.raw_mode {
+            self.apply_raw_input()
+                .map_err(|err| format!("Unable to parse raw request: {}", err))?;
+        }
+
+        let settings = self.settings()?;
+        let request = self
+            .folder
+            .apply(&self.to_request())
+            .prepare(&self.variables, &mut self.captured)?;
+        request.builder(&settings, &self.all_variables())
+    }
+
+    pub fn substitute(&self, text: &str) -> String {
+        variables::substitute(text, &self.all_variables())
+    }
+
+    fn all_variables(&self) -> Vec<(String, String)> {
+        self.captured
+            .iter()
+            .chain(&self.variables)
+            .cloned()
+            .collect()
+    }
+
+    fn capture_variables(&mut self, body: &str) {
+        if self.captures.is_empty() {
+            return;
+        }
+
+        match self.to_request().capture(body) {
+            Ok(captured) => {
+                let names: Vec<String> = captured.iter().map(|(name, _)| name.clone()).collect();
+                for (name, value) in captured {
+                    set_pair(&mut self.captured, &name, &value);
+                }
+                self.info(format!("Captured {}", names.join(", ")));
+            }
+            Err(err) => self.error(format!("Unable to capture variables: {}", err)),
+        }
+    }
+
+    fn check_assertions(&mut self, response: &Response) {
+        if self.assertions.is_empty() {
+            return;
+        }
+
+        let failures: Vec<String> = self
+            .assertions
+            .iter()
+            .filter_map(|assertion| assertion::check(assertion, response).err())
+            .collect();
+        if failures.is_empty() {
+            self.info(format!("All {} assertions passed", self.assertions.len()));
+        } else {
+            self.error(format!(
+                "{} of {} assertions failed: {}",
+                failures.len(),
+                self.assertions.len(),
+                failures.join("; ")
+            ));
+        }
+    }
+
+    // Returns whether the request could be built and was sent, even if sending it failed.
+    pub fn submit_request(&mut self) -> bool {
+        self.save_edits();
+        #[cfg(feature = "grpc")]
+        if self.current_method.as_str() == grpc::METHOD {
+            return self.submit_grpc();
+        }
+
+        let request_builder = match self.request_builder() {
+            Ok(request_builder) => request_builder,
+            Err(err) => {
+                self.error(err);
+                return false;
+            }
+        };
+
+        let mut entry = history_entry(&request_builder);
+        let mut started = Instant::now();
+        let (mut result, attempts) =
+            self.send_with_retries(request_builder, &mut entry, &mut started);
+
+        let mut retry_note = None;
+        if self.unauthorized(&result) {
+            let retry = match self.refresh_token() {
+                Some(Ok(source)) => self
+                    .request_builder()
+                    .map(|request_builder| Some((source, request_builder))),
+                Some(Err(err)) => Err(err),
+                None => Ok(None),
+            };
+            match retry {
+                Ok(Some((source, request_builder))) => {
+                    // The rejected attempt stays in the history next to the retry.
+                    if let (Some(mut rejected), Ok(response)) = (entry.take(), result) {
+                        let version = response.version();
+                        let status = response.status();
+                        let headers = response.headers().clone();
+                        let body = response.text().unwrap_or_default();
+                        rejected.set_response(version, status, &headers, &body, started.elapsed());
+                        self.push_history(rejected);
+                    }
+                    entry = history_entry(&request_builder);
+                    started = Instant::now();
+                    result = request_builder.send();
+                    let note = format!("Got 401, retried with a fresh token from {}", source);
+                    self.info(note.clone());
+                    retry_note = Some(note);
+                }
+                Ok(None) => (),
+                Err(err) => self.error(err),
+            }
+        }
+
+        self.status_line = None;
+        self.output_headers = vec![];
+        let output = match result {
+            Ok(mut response) => {
+                let version = response.version();
+                let status = response.status();
+                self.status_line = Some(format!("{:?} {}", version, status));
+                self.response_status = Some(ResponseStatus::Code(status.as_u16()));
+                let headers = response.headers().clone();
+                let content_type = headers
+                    .get(CONTENT_TYPE)
+                    .and_then(|content_type| content_type.to_str().ok());
+                self.output_content_type = content_type.map(str::to_string);
+                self.output_headers = headers
+                    .iter()
+                    .map(|(name, value)| {
+                        (
+                            name.to_string(),
+                            String::from_utf8_lossy(value.as_bytes()).to_string(),
+                        )
+                    })
+                    .collect();
+                let viewer = self.settings().ok().and_then(|settings| {
+                    content_type
+                        .and_then(|content_type| settings.viewer(content_type))
+                        .map(str::to_string)
+                });
+                match response::spool(&mut response, content_type) {
+                    Ok(body) => {
+                        let viewed =
+                            viewer.map(|viewer| (response::view(&body.path, &viewer), viewer));
+                        if let Some((result, viewer)) = &viewed {
+                            match result {
+                                Ok(_) => self.info(format!(
+                                    "Shown with {}. Press W to save the body to a file.",
+                                    viewer
+                                )),
+                                Err(err) => self.error(format!("Unable to view the body: {}", err)),
+                            }
+                        } else if body.binary {
+                            self.info(format!(
+                                "Binary body, {} total, shown as hex. Press W to save it to a file.",
+                                format_size(body.size)
+                            ));
+                        } else if body.truncated {
+                            self.info(format!(
+                                "Body truncated, {} total. Press W to save the full body to a file.",
+                                format_size(body.size)
+                            ));
+                        }
+                        if let Some(entry) = entry.as_mut() {
+                            let text = if body.binary { "" } else { &body.text };
+                            entry.set_response(version, status, &headers, text, started.elapsed());
+                        }
+                        if status.is_success() && !body.binary {
+                            self.capture_variables(&body.text);
+                        }
+                        let header_pairs = self.output_headers.clone();
+                        let snapshots = self.snapshots.clone();
+                        self.check_assertions(&Response {
+                            status: status.as_u16(),
+                            headers: &header_pairs,
+                            body: if body.binary { "" } else { &body.text },
+                            time: started.elapsed(),
+                            snapshots: &snapshots,
+                        });
+                        let text = match viewed {
+                            Some((Ok(text), _)) => text,
+                            _ => body.text.clone(),
+                        };
+                        self.response_body = Some(body);
+                        text
+                    }
+                    Err(err) => format!("Error reading body: {}", err),
+                }
+            }
+            Err(error) => {
+                self.status_line = Some(
+                    if error.is_timeout() {
+                        "Timed out"
+                    } else if error.is_connect() {
+                        "Unable to connect"
+                    } else {
+                        "Request failed"
+                    }
+                    .to_string(),
+                );
+                self.response_status = Some(ResponseStatus::Failed);
+                format!("{:?}", error)
+            }
+        };
+
+        if let Some(entry) = entry {
+            self.push_history(entry);
+        }
+        if let Some(note) = retry_note {
+            if let Some(logged) = self
+                .messages
+                .last_mut()
+                .filter(|logged| logged.text != note)
+            {
+                logged.text = format!("{}. {}", note, logged.text);
+            }
+        }
+        if attempts > 1 {
+            if let Some(status_line) = &mut self.status_line {
+                status_line.push_str(&format!(" · {} attempts", attempts));
+            }
+        }
+        self.example_index = None;
+        self.live_output = None;
+        self.unqueried_output = None;
+        self.output_input = TextArea::from(output.lines());
+        self.format_output();
+        self.record_response(output);
+
+        true
+    }
+
+    #[cfg(feature = "grpc")]
+    fn submit_grpc(&mut self) -> bool {
+        if self.raw_mode {
+            if let Err(err) = self.apply_raw_input() {
+                self.error(format!("Unable to parse raw request: {}", err));
+                return false;
+            }
+        }
+        let settings = match self.settings() {
+            Ok(settings) => settings,
+            Err(err) => {
+                self.error(err);
+                return false;
+            }
+        };
+        let request = match self
+            .folder
+            .apply(&self.to_request())
+            .prepare(&self.variables, &mut self.captured)
+        {
+            Ok(request) => request.substituted(&self.all_variables()),
+            Err(err) => {
+                self.error(err);
+                return false;
+            }
+        };
+
+        let started = Instant::now();
+        // gRPC replies come in HTTP 200 responses, with the call's own status in the trailers.
+        self.output_headers = vec![];
+        let output = match grpc::call(&request, &settings) {
+            Ok(reply) => {
+                self.status_line = Some("gRPC OK".to_string());
+                self.response_status = Some(ResponseStatus::Code(200));
+                self.output_content_type = Some("application/json".to_string());
+                self.info(format!("Took {:.0?}", started.elapsed()));
+                reply
+            }
+            Err(err) => {
+                self.status_line = Some("gRPC call failed".to_string());
+                self.response_status = Some(ResponseStatus::Failed);
+                self.output_content_type = None;
+                err
+            }
+        };
+
+        self.example_index = None;
+        self.live_output = None;
+        self.unqueried_output = None;
+        self.output_input = TextArea::from(output.lines());
+        self.format_output();
+        self.record_response(output);
+
+        true
+    }
+
+    // :grpc lists the server's methods in the Output pane, and :grpc Service/Method switches the
+    // request to calling that method, with a body row for each field of its request message.
+    #[cfg(feature = "grpc")]
+    fn grpc_command(&mut self, method: &str) {
+        let settings = match self.settings() {
+            Ok(settings) => settings,
+            Err(err) => {
+                self.error(err);
+                return;
+            }
+        };
+
+        if method.is_empty() {
+            match grpc::methods(&self.substitute(self.url()), &settings) {
+                Ok(methods) => {
+                    self.info(format!(
+                        "{} methods, call one with :grpc <Service/Method>",
+                        methods.len()
+                    ));
+                    self.output_input = TextArea::from(methods);
+                }
+                Err(err) => self.error(err),
+            }
+            return;
+        }
+
+        let mut request = self.to_request();
+        request.method = grpc_method().unwrap_or_default();
+        request.url = grpc::url_for(self.url(), method);
+        match grpc::fields(&self.substitute(&request.url), &settings) {
+            Ok(fields) => {
+                for field in fields {
+                    if !request.body.iter().any(|(key, _)| *key == field) {
+                        request.body.push((field, String::new()));
+                    }
+                }
+                self.apply_request(&request);
+                self.info(format!("Calling {}", method));
+            }
+            Err(err) => self.error(err),
+        }
+    }
+
+    // Shows the response the request got the last time it was sent, before reqcoon was reopened.
+    pub fn show_cached_response(&mut self) {
+        let key = examples::key(&self.name, self.request_index);
+        let Some(entry) = cache::get(&self.cached_responses, &key).cloned() else {
+            return;
+        };
+
+        self.output_input = TextArea::from(entry.response_body.lines());
+        self.output_content_type = entry_content_type(&entry);
+        self.output_headers = entry.response_headers.clone();
+        self.format_output();
+        self.status_line = Some(format!(
+            "{} {} {} · {}",
+            entry.http_version, entry.status, entry.status_text, entry.started
+        ));
+        self.response_status = Some(ResponseStatus::Code(entry.status));
+        self.record_response(entry.response_body);
+    }
+
+    fn record_response(&mut self, text: String) {
+        // Responses are kept by request, and a scratch request isn't one of the file's.
+        if self.scratch {
+            return;
+        }
+        match self
+            .responses
+            .iter_mut()
+            .find(|(request_index, _, _)| *request_index == self.request_index)
+        {
+            Some((_, latest, previous)) => *previous = Some(std::mem::replace(latest, text)),
+            None => self.responses.push((self.request_index, text, None)),
+        }
+    }
+
+    fn latest_response(&self) -> Option<&(usize, String, Option<String>)> {
+        self.responses
+            .iter()
+            .find(|(request_index, _, _)| *request_index == self.request_index)
+    }
+
+    pub fn diff_response(&mut self) {
+        match self.latest_response() {
+            Some((_, latest, Some(previous))) => {
+                let (previous, latest) = (previous.clone(), latest.clone());
+                self.show_diff(&previous, &latest, "the previous response");
+            }
+            _ => {
+                self.warn("Send the request twice to compare its responses");
+            }
+        }
+    }
+
+    pub fn diff_history_entry(&mut self) {
+        let Some(entry) = self.history.get(self.input_index).cloned() else {
+            return;
+        };
+        let Some((_, latest, _)) = self.latest_response().cloned() else {
+            self.warn("Send the request to compare it with the history");
+            return;
+        };
+
+        self.toggle_history();
+        self.show_diff(&entry.response_body, &latest, &entry.summary());
+    }
+
+    pub fn show_diff(&mut self, old: &str, new: &str, against: &str) {
+        let changes = diff::lines(old, new);
+        let count =
+            |kind: fn(&Change) -> bool| changes.iter().filter(|change| kind(change)).count();
+        let added = count(|change| matches!(change, Change::Added(_)));
+        let removed = count(|change| matches!(change, Change::Removed(_)));
+        self.info(if added == 0 && removed == 0 {
+            format!("Same as {}", against)
+        } else {
+            format!(
+                "{} lines added and {} removed since {}",
+                added, removed, against
+            )
+        });
+        self.diff_input = TextArea::from(changes.iter().map(Change::line));
+        self.toggle_popup(Panel::Diff, 0);
+    }
+
+    pub fn message(&self) -> &str {
+        match self.messages.last() {
+            Some(logged) if !self.message_cleared => &logged.text,
+            _ => "",
+        }
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.log(Level::Info, text.into());
+    }
+
+    pub fn warn(&mut self, text: impl Into<String>) {
+        self.log(Level::Warning, text.into());
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.log(Level::Error, text.into());
+    }
+
+    // Shows the message in the status bar and logs it, unless it's the one already shown.
+    fn log(&mut self, level: Level, text: String) {
+        let repeated = self
+            .messages
+            .last()
+            .is_some_and(|logged| logged.level == level && logged.text == text);
+        self.message_cleared = false;
+        if repeated {
+            return;
+        }
+
+        self.messages.push(LoggedMessage {
+            time: SystemTime::now(),
+            level,
+            text,
+        });
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+    }
+
+    // Leaves the status bar to the key hints. The message stays in the log.
+    pub fn clear_message(&mut self) {
+        self.message_cleared = true;
+    }
+
+    pub fn toggle_messages(&mut self) {
+        if self.current_panel == Panel::Messages {
+            self.toggle_popup(Panel::Messages, 0);
+            return;
+        }
+
+        self.messages_input = TextArea::from(self.messages.iter().map(LoggedMessage::line));
+        // The latest messages are the ones most likely to be wanted.
+        self.messages_input.move_cursor(CursorMove::Bottom);
+        self.toggle_popup(Panel::Messages, 0);
+    }
+
+    pub fn close_diff(&mut self) {
+        if self.current_panel == Panel::Diff {
+            self.toggle_popup(Panel::Diff, 0);
+        }
+    }
+
+    fn push_history(&mut self, entry: Entry) {
+        self.audit(&entry);
+        if entry.status != 0 {
+            let key = examples::key(&self.name, self.request_index);
+            cache::set(&mut self.cached_responses, &key, entry.clone());
+        }
+        self.append_history(entry);
+    }
+
+    fn audit(&mut self, entry: &Entry) {
+        let audit_log = self.settings().ok().and_then(|settings| settings.audit_log);
+        let Some(audit_log) = audit_log.filter(|_| !self.audit_paused) else {
+            return;
+        };
+
+        let secrets = self.secret_texts();
+        let secrets: Vec<&str> = secrets.iter().map(String::as_str).collect();
+        if let Err(err) = audit::append(&audit_log, entry, &secrets) {
+            self.error(format!("Unable to write to {}: {}", audit_log, err));
+        }
+    }
+
+    // Values are read from where each secret is kept, as well as the session's variables, so one
+    // changed outside reqcoon since it was loaded is still left out.
+    pub fn secret_texts(&self) -> Vec<String> {
+        secrets::resolve(&self.secrets, &self.secret_values, &self.filename)
+            .into_iter()
+            .chain(
+                self.variables
+                    .iter()
+                    .filter(|(name, _)| self.secrets.iter().any(|secret| secret.name == *name))
+                    .cloned(),
+            )
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    // Redacts entries the way the audit log does, for the history and responses saved next to the
+    // request file. Secrets are looked up once, however many entries there are.
+    pub fn redactor(&self) -> impl Fn(&Entry) -> Entry {
+        let secrets = self.secret_texts();
+        move |entry| {
+            audit::sanitized(
+                entry,
+                &secrets.iter().map(String::as_str).collect::<Vec<&str>>(),
+            )
+        }
+    }
+
+    fn append_history(&mut self, entry: Entry) {
+        self.history.push(entry);
+        if self.history.len() > history::MAX_ENTRIES {
+            self.history.remove(0);
+        }
+    }
+
+    // A 401 to a bearer-authenticated request is retried once with a fresh token.
+    // Sends the request, then again for as long as the retry settings say the last attempt is worth
+    // retrying. Each attempt that's retried stays in the history. Returns the last attempt and how
+    // many there were.
+    fn send_with_retries(
+        &mut self,
+        mut request_builder: RequestBuilder,
+        entry: &mut Option<Entry>,
+        started: &mut Instant,
+    ) -> (reqwest::Result<HttpResponse>, usize) {
+        let settings = self.settings().unwrap_or_default();
+        let mut attempts = 1;
+        loop {
+            let retry = request_builder
+                .try_clone()
+                .filter(|_| attempts <= settings.retries);
+            let result = request_builder.send();
+            let Some(retry) = retry.filter(|_| settings.retryable(&result)) else {
+                return (result, attempts);
+            };
+
+            if let Some(mut failed) = entry.take() {
+                if let Ok(response) = result {
+                    let version = response.version();
+                    let status = response.status();
+                    let headers = response.headers().clone();
+                    let body = response.text().unwrap_or_default();
+                    failed.set_response(version, status, &headers, &body, started.elapsed());
+                }
+                self.push_history(failed);
             }
+            thread::sleep(settings.backoff(attempts));
+            *entry = history_entry(&retry);
+            *started = Instant::now();
+            request_builder = retry;
+            attempts += 1;
         }
     }
 
-    pub fn previous_input_field(&mut self) {
-        match self.current_input_type {
-            InputType::Auth => match self.auth.format {
-                AuthFormat::None | AuthFormat::Bearer => (),
-                AuthFormat::Basic => {
-                    self.current_input_field = self
-                        .current_input_field
-                        .previous()
-                        .unwrap_or(InputField::last().unwrap());
-                }
-            },
-            InputType::Headers | InputType::Body => {
-                if self.current_input_field == InputField::first().unwrap() {
-                    if self.input_index == 0 {
-                        self.input_index = self.current_input_table().len() - 1;
-                    } else {
-                        self.input_index -= 1;
-                    }
-                }
-                self.current_input_field = self
-                    .current_input_field
-                    .previous()
-                    .unwrap_or(InputField::last().unwrap());
-            }
+    fn unauthorized(&self, result: &reqwest::Result<HttpResponse>) -> bool {
+        self.auth.format == AuthFormat::Bearer
+            && matches!(result, Ok(response) if response.status() == StatusCode::UNAUTHORIZED)
+    }
+
+    // Gets a fresh token by sending the request the refresh setting names, or with the OAuth2
+    // refresh token. Returns where the token came from, or None if there's no way to get one.
+    fn refresh_token(&mut self) -> Option<Result<String, String>> {
+        let settings = self.settings().ok()?;
+        if let Some(name) = settings.refresh {
+            let file = self.to_request_file();
+            return Some(runner::refresh(&file, &name, &mut self.captured).map(|()| name));
         }
+
+        let refresh_token = self.oauth_token()?.refresh_token.clone()?;
+        let refreshed = settings
+            .client()
+            .map_err(|err| err.to_string())
+            .and_then(|client| oauth::refresh(&client, &settings.oauth, &refresh_token));
+        Some(refreshed.map(|token| {
+            self.install_token(token);
+            "the OAuth2 refresh token".to_string()
+        }))
     }
 
-    pub fn next_input_format(&mut self) {
-        match self.current_input_type {
-            InputType::Auth => {
-                self.auth.format = self.auth.format.next().unwrap_or_default();
+    // Writes the response to a file to look through in $PAGER, or in $EDITOR with editor, once the
+    // terminal has been handed over. Without either, $PAGER is used if it's set.
+    pub fn open_output(&mut self, with: &str) {
+        let program = match with {
+            "" => env::var("PAGER").unwrap_or_else(|_| editor()),
+            "pager" => env::var("PAGER").unwrap_or("less".to_string()),
+            "editor" => editor(),
+            _ => {
+                self.warn("Usage: :open [pager|editor]");
+                return;
             }
-            InputType::Headers => (),
-            InputType::Body => {
-                self.current_body_format = self.current_body_format.next().unwrap_or_default();
+        };
+
+        let extension = output_extension(self.output_content_type.as_deref());
+        let path = env::temp_dir().join(format!("reqcoon-output-{}.{}", process::id(), extension));
+        // The Output panel only holds the start of a large body, but the whole of it was spooled.
+        let written = match &self.response_body {
+            Some(body) if body.truncated && !body.binary && self.unqueried_output.is_none() => {
+                fs::copy(&body.path, &path).map(|_| ())
+            }
+            _ => {
+                let output = self
+                    .unformatted_output
+                    .as_ref()
+                    .unwrap_or(&self.output_input);
+                fs::write(&path, output.lines().join("\n"))
             }
+        };
+        match written {
+            Ok(()) => self.external = Some((program, path)),
+            Err(err) => self.error(format!("Unable to write the response: {}", err)),
         }
     }
 
-    pub fn previous_input_format(&mut self) {
-        match self.current_input_type {
-            InputType::Auth => {
-                self.auth.format = self
-                    .auth
-                    .format
-                    .previous()
-                    .unwrap_or(AuthFormat::last().unwrap());
-            }
-            InputType::Headers => (),
-            InputType::Body => {
-                self.current_body_format = self
-                    .current_body_format
-                    .previous()
-                    .unwrap_or(BodyFormat::last().unwrap());
+    // Hands the body to $EDITOR, for payloads too long to write in the Body tab. Rows are edited as
+    // a JSON object and a raw request's body as it is. A body read from a file opens that file.
+    pub fn edit_body(&mut self) {
+        let (text, edit, extension) = if self.raw_mode {
+            let (start, content_type) = self.raw_body();
+            (
+                self.raw_input.lines()[start..].join("\n"),
+                BodyEdit::Raw,
+                output_extension(content_type.as_deref()),
+            )
+        } else if self.current_input_type != InputType::Body {
+            self.warn("Switch to the Body tab to edit the body in $EDITOR");
+            return;
+        } else if let Some(path) = self.to_request().body_file() {
+            self.external = Some((editor(), PathBuf::from(self.substitute(path))));
+            return;
+        } else {
+            let object = json::JsonValue::Object(
+                self.non_empty_body()
+                    .map(Into::<(String, String)>::into)
+                    .collect(),
+            );
+            (object.pretty(2), BodyEdit::Rows, "json".to_string())
+        };
+
+        let path = env::temp_dir().join(format!("reqcoon-body-{}.{}", process::id(), extension));
+        match fs::write(&path, text) {
+            Ok(()) => {
+                self.external = Some((editor(), path));
+                self.external_body = Some(edit);
             }
+            Err(err) => self.error(format!("Unable to write the body: {}", err)),
         }
     }
 
-    pub fn current_input_table(&self) -> &NonEmpty<InputRow> {
-        match self.current_input_type {
-            InputType::Auth | InputType::Headers => &self.headers_input_table,
-            InputType::Body => &self.body_input_table,
+    // Where the body of the raw request starts, after the blank line below the headers, and the
+    // Content-Type the headers give it.
+    fn raw_body(&self) -> (usize, Option<String>) {
+        let lines = self.raw_input.lines();
+        let blank = lines.iter().position(|line| line.trim().is_empty());
+        let content_type = lines[..blank.unwrap_or(lines.len())]
+            .iter()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.trim()
+                    .eq_ignore_ascii_case("content-type")
+                    .then(|| value.trim().to_string())
+            });
+        (blank.map_or(lines.len(), |blank| blank + 1), content_type)
+    }
+
+    // The line of the raw request a JSON body stops making sense on, and why, checked as it's
+    // typed rather than by a 400 from the server. Bodies read from a file aren't checked.
+    pub fn raw_body_error(&self) -> Option<(usize, String)> {
+        if !self.raw_mode {
+            return None;
+        }
+        let (start, content_type) = self.raw_body();
+        let body = self.raw_input.lines()[start..].join("\n");
+        let json = match content_type {
+            Some(content_type) => content_type.to_lowercase().contains("json"),
+            None => body.trim_start().starts_with(['{', '[']),
+        };
+        if !json || body.trim().is_empty() || body.trim_start().starts_with("< ") {
+            return None;
+        }
+
+        match json::parse(&body) {
+            Ok(_) => None,
+            Err(json::Error::UnexpectedCharacter { ch, line, .. }) => {
+                let line = start + line - 1;
+                Some((line, format!("line {}: unexpected {}", line + 1, ch)))
+            }
+            Err(json::Error::UnexpectedEndOfJson) => {
+                let line = self.raw_input.lines().len() - 1;
+                Some((line, format!("line {}: ends too soon", line + 1)))
+            }
+            Err(err) => Some((start, err.to_string())),
         }
     }
 
-    pub fn submit_request(&mut self) {
-        let url = Url::parse(&self.url_input.lines()[0]).expect("Invalid URL");
-        let mut request_builder = Client::new().request(self.current_method.clone(), url);
+    // Pretty-prints the JSON body of the raw request.
+    pub fn format_raw_body(&mut self) {
+        if let Some((_, err)) = self.raw_body_error() {
+            self.error(format!(
+                "Unable to format the body, invalid JSON at {}",
+                err
+            ));
+            return;
+        }
+        let (start, _) = self.raw_body();
+        let mut lines = self.raw_input.lines().to_vec();
+        if lines[start..].iter().all(|line| line.trim().is_empty()) {
+            self.warn("The request has no body to format");
+            return;
+        }
+        let Ok(body) = json::parse(&lines[start..].join("\n")) else {
+            self.warn("The body isn't JSON");
+            return;
+        };
+
+        lines.truncate(start);
+        lines.extend(body.pretty(2).lines().map(str::to_string));
+        let cursor = self.raw_input.cursor();
+        self.raw_input = TextArea::from(lines);
+        self.raw_input
+            .move_cursor(CursorMove::Jump(cursor.0 as u16, cursor.1 as u16));
+        self.info("Formatted the body");
+        self.changed = true;
+    }
 
-        request_builder = match self.auth.format {
-            AuthFormat::None => request_builder,
-            AuthFormat::Basic => {
-                request_builder.basic_auth(self.auth.username(), self.auth.password())
+    // Called once the program handed the terminal has exited, to read back a body it edited.
+    pub fn finish_external(
+        &mut self,
+        program: &str,
+        path: &Path,
+        result: Result<ExitStatus, String>,
+    ) {
+        let edit = self.external_body.take();
+        match result {
+            Ok(status) if status.success() => (),
+            Ok(status) => {
+                self.error(format!("{} exited with {}", program, status));
+                return;
+            }
+            Err(err) => {
+                self.error(err);
+                return;
             }
-            AuthFormat::Bearer => request_builder.bearer_auth(self.auth.token()),
+        }
+        let Some(edit) = edit else {
+            return;
         };
-        request_builder = self
-            .non_empty_headers()
-            .fold(request_builder, |builder, InputRow { key, value }| {
-                builder.header(&key.lines()[0], &value.lines()[0])
-            });
-        request_builder = match self.current_body_format {
-            BodyFormat::Json => request_builder.json(&self.body_hash_map()),
-            BodyFormat::Form => request_builder.form(&self.body_hash_map()),
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                self.error(format!("Unable to read the body back: {}", err));
+                return;
+            }
         };
+        match edit {
+            BodyEdit::Raw => {
+                let mut lines = self.raw_input.lines().to_vec();
+                match lines.iter().position(|line| line.trim().is_empty()) {
+                    Some(blank) => lines.truncate(blank + 1),
+                    None => lines.push(String::new()),
+                }
+                lines.extend(text.trim_end().lines().map(str::to_string));
+                self.raw_input = TextArea::from(lines);
+            }
+            BodyEdit::Rows => match body_rows(&text) {
+                Ok(body) => {
+                    let mut request = self.to_request();
+                    request.body = body;
+                    request.disabled_body = disabled_pairs(&self.body_input_table);
+                    self.apply_request(&request);
+                }
+                Err(err) => {
+                    self.error(format!("The body wasn't changed: {}", err));
+                    return;
+                }
+            },
+        }
+        self.info("Updated the body");
+    }
 
-        let output = match request_builder.send() {
-            Ok(response) => response
-                .text()
-                .unwrap_or("Error unwrapping body".to_string()),
-            Err(error) => format!("{:?}", error),
+    pub fn save_response(&mut self) {
+        let Some(body) = &self.response_body else {
+            self.warn("No response to save");
+            return;
         };
 
-        self.output_input = TextArea::from(output.lines());
+        match response::save(&body.path, &self.substitute(self.url())) {
+            Ok(path) => self.info(format!(
+                "Saved {} to {}",
+                format_size(body.size),
+                path.display()
+            )),
+            Err(err) => self.error(format!("Unable to save response: {}", err)),
+        }
     }
 
     fn current_input(&self) -> &TextArea<'static> {
         match self.current_panel {
             Panel::Method => &self.dummy_input,
             Panel::Url => &self.url_input,
+            Panel::Input if self.raw_mode => &self.raw_input,
             Panel::Input => match self.current_input_type {
                 InputType::Auth => match self.auth.format {
                     AuthFormat::None => &self.dummy_input,
@@ -649,12 +4619,27 @@ impl Model {
                     },
                     AuthFormat::Bearer => &self.auth.bearer_input,
                 },
-                InputType::Headers | InputType::Body => match self.current_input_field {
-                    InputField::Key => &self.current_input_row().key,
-                    InputField::Value => &self.current_input_row().value,
-                },
+                InputType::Path | InputType::Headers | InputType::Body | InputType::Options => {
+                    match self.current_input_field {
+                        InputField::Key => &self.current_input_row().key,
+                        InputField::Value => &self.current_input_row().value,
+                    }
+                }
             },
             Panel::Output => &self.output_input,
+            Panel::Query => &self.query_input,
+            Panel::Command => &self.command_input,
+            Panel::Preview => &self.preview_input,
+            Panel::Description => &self.description_input,
+            Panel::Diff => &self.diff_input,
+            Panel::Messages => &self.messages_input,
+            Panel::History | Panel::Runner | Panel::Files | Panel::Authorize | Panel::Menu => {
+                &self.dummy_input
+            }
+            Panel::Settings | Panel::Cookies | Panel::Mock => match self.current_input_field {
+                InputField::Key => &self.current_input_row().key,
+                InputField::Value => &self.current_input_row().value,
+            },
         }
     }
 
@@ -662,6 +4647,7 @@ impl Model {
         match self.current_panel {
             Panel::Method => &mut self.dummy_input,
             Panel::Url => &mut self.url_input,
+            Panel::Input if self.raw_mode => &mut self.raw_input,
             Panel::Input => match self.current_input_type {
                 InputType::Auth => match self.auth.format {
                     AuthFormat::None => &mut self.dummy_input,
@@ -671,23 +4657,134 @@ impl Model {
                     },
                     AuthFormat::Bearer => &mut self.auth.bearer_input,
                 },
-                InputType::Headers | InputType::Body => match self.current_input_field {
-                    InputField::Key => &mut self.current_input_row_mut().key,
-                    InputField::Value => &mut self.current_input_row_mut().value,
-                },
+                InputType::Path | InputType::Headers | InputType::Body | InputType::Options => {
+                    match self.current_input_field {
+                        InputField::Key => &mut self.current_input_row_mut().key,
+                        InputField::Value => &mut self.current_input_row_mut().value,
+                    }
+                }
             },
             Panel::Output => &mut self.output_input,
+            Panel::Query => &mut self.query_input,
+            Panel::Command => &mut self.command_input,
+            Panel::Preview => &mut self.preview_input,
+            Panel::Description => &mut self.description_input,
+            Panel::Diff => &mut self.diff_input,
+            Panel::Messages => &mut self.messages_input,
+            Panel::History | Panel::Runner | Panel::Files | Panel::Authorize | Panel::Menu => {
+                &mut self.dummy_input
+            }
+            Panel::Settings | Panel::Cookies | Panel::Mock => match self.current_input_field {
+                InputField::Key => &mut self.current_input_row_mut().key,
+                InputField::Value => &mut self.current_input_row_mut().value,
+            },
+        }
+    }
+
+    fn apply_raw_input(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut request = Request::parse(&self.raw_input.lines().join("\n"))?;
+        // The raw text has no directives, so a form body stays in the format it's edited in.
+        if self.current_body_format != BodyFormat::Json {
+            request.body_format = self.current_body_format;
+            if let Some(body) = request.raw_body.take() {
+                request.body = request::form_rows(&body);
+            }
+        }
+        // The raw text only has the rows that are sent, so switched off rows are carried over.
+        request.disabled_headers = disabled_pairs(&self.headers_input_table);
+        request.disabled_body = disabled_pairs(&self.body_input_table);
+        request.path_params = self.path_pairs();
+        self.apply_request(&request);
+
+        Ok(())
+    }
+
+    fn apply_request(&mut self, request: &Request) {
+        let rows = |pairs: &[(String, String)], disabled_pairs: &[(String, String)]| {
+            let row = |(key, value): &(String, String), disabled| InputRow {
+                key: [key.as_str()].into(),
+                value: [value.as_str()].into(),
+                disabled,
+            };
+            NonEmpty::from_vec(
+                pairs
+                    .iter()
+                    .map(|pair| row(pair, false))
+                    .chain(disabled_pairs.iter().map(|pair| row(pair, true)))
+                    .collect(),
+            )
+            .unwrap_or(nonempty![InputRow::default()])
+        };
+
+        self.current_method = request.method.clone();
+        self.url_input = TextArea::from([request.url.as_str()]);
+        self.path_input_table = rows(&request.path_params, &[]);
+        self.sync_path_params();
+        self.auth = match &request.auth {
+            RequestAuth::None => Auth::default(),
+            RequestAuth::Basic { username, password } => Auth {
+                format: AuthFormat::Basic,
+                basic_input: InputRow {
+                    key: [username.as_str()].into(),
+                    value: [password.as_deref().unwrap_or_default()].into(),
+                    ..InputRow::default()
+                },
+                bearer_input: TextArea::default(),
+            },
+            RequestAuth::Bearer(token) => Auth {
+                format: AuthFormat::Bearer,
+                basic_input: InputRow::default(),
+                bearer_input: [token.as_str()].into(),
+            },
+        };
+        self.headers_input_table = rows(&request.headers, &request.disabled_headers);
+        self.body_input_table = rows(&request.body, &request.disabled_body);
+        self.current_body_format = request.body_format;
+        self.text_body = request.raw_body.clone();
+        self.input_index = 0;
+        self.current_input_field = InputField::default();
+    }
+
+    fn editing_table_row(&self) -> bool {
+        match self.current_panel {
+            Panel::Input => {
+                !self.raw_mode
+                    && matches!(
+                        self.current_input_type,
+                        InputType::Path | InputType::Headers | InputType::Body | InputType::Options
+                    )
+            }
+            Panel::Settings | Panel::Cookies | Panel::Mock => true,
+            _ => false,
         }
     }
 
+    pub fn multiline_input(&self) -> bool {
+        self.current_panel == Panel::Output || self.editing_lines()
+    }
+
+    fn input_disabled(&self) -> bool {
+        self.current_panel == Panel::Input
+            && !self.raw_mode
+            && self.current_input_type == InputType::Auth
+            && self.auth.format == AuthFormat::None
+    }
+
     fn current_input_row(&self) -> &InputRow {
         &self.current_input_table()[self.input_index]
     }
 
     fn current_input_table_mut(&mut self) -> &mut NonEmpty<InputRow> {
-        match self.current_input_type {
-            InputType::Auth | InputType::Headers => &mut self.headers_input_table,
-            InputType::Body => &mut self.body_input_table,
+        match self.current_panel {
+            Panel::Settings => &mut self.settings_input_table,
+            Panel::Cookies => &mut self.cookies_input_table,
+            Panel::Mock => &mut self.mock_input_table,
+            _ => match self.current_input_type {
+                InputType::Auth | InputType::Headers => &mut self.headers_input_table,
+                InputType::Path => &mut self.path_input_table,
+                InputType::Body => &mut self.body_input_table,
+                InputType::Options => &mut self.options_input_table,
+            },
         }
     }
 
@@ -696,57 +4793,609 @@ impl Model {
         &mut self.current_input_table_mut()[input_index]
     }
 
-    fn auth_string(&self) -> String {
-        match self.auth.format {
-            AuthFormat::None => String::default(),
-            AuthFormat::Basic => Credentials {
-                user_id: self.auth.username(),
-                password: self.auth.password().unwrap_or(String::default()),
+    pub fn set_defaults(&mut self, settings: Vec<(String, String)>, environments: Environments) {
+        // An environment only the config has can still be switched to, and having no settings it
+        // isn't written to the file.
+        for (name, _) in &environments {
+            if !self
+                .environments
+                .iter()
+                .any(|(existing, _)| existing == name)
+            {
+                self.environments.push((name.clone(), vec![]));
             }
-            .as_http_header(),
-            AuthFormat::Bearer => format!("Bearer {}", self.auth.token()),
         }
+        self.default_settings = settings;
+        self.default_environments = environments;
     }
 
-    fn non_empty_headers(&self) -> impl Iterator<Item = &InputRow> {
-        self.headers_input_table
+    fn defaults(&self) -> Vec<(String, String)> {
+        let mut defaults = self.default_settings.clone();
+        if let Ok(settings) =
+            file::environment(&self.default_environments, self.environment.as_deref())
+        {
+            defaults.extend(settings.iter().cloned());
+        }
+        defaults
+    }
+
+    // The value a setting has without the request's own options, from the environment, the
+    // workspace or the user's defaults. Later rows win within each.
+    pub fn inherited_setting(&self, key: &str) -> Option<String> {
+        let environment =
+            file::environment(&self.environments, self.environment.as_deref()).unwrap_or_default();
+        let workspace: Vec<(&str, &str)> = input_pairs(&self.settings_input_table).collect();
+        let defaults = self.defaults();
+        environment
             .iter()
-            .filter(|header| !header.key.is_empty())
+            .rev()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .chain(workspace.into_iter().rev())
+            .chain(
+                defaults
+                    .iter()
+                    .rev()
+                    .map(|(key, value)| (key.as_str(), value.as_str())),
+            )
+            .find(|(existing, value)| *existing == key && !value.is_empty())
+            .map(|(_, value)| value.to_string())
     }
 
-    fn headers_string(&self) -> String {
-        self.non_empty_headers()
-            .map(|input_row| {
-                format!(
-                    "{}: {}",
-                    input_row.key.lines()[0],
-                    input_row.value.lines()[0]
-                )
-            })
-            .collect()
+    pub fn settings(&self) -> Result<Settings, String> {
+        let environment = file::environment(&self.environments, self.environment.as_deref())?;
+        Settings::default()
+            .overridden(
+                self.defaults()
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str())),
+            )?
+            .overridden(input_pairs(&self.settings_input_table))?
+            .overridden(
+                environment
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str())),
+            )?
+            .overridden(input_pairs(&self.options_input_table))
+    }
+
+    fn non_empty_headers(&self) -> impl Iterator<Item = &InputRow> {
+        self.headers_input_table
+            .iter()
+            .filter(|header| !header.key.is_empty() && !header.disabled)
     }
 
     fn non_empty_body(&self) -> impl Iterator<Item = &InputRow> {
         self.body_input_table
             .iter()
-            .filter(|body_pair| !body_pair.key.is_empty())
+            .filter(|body_pair| !body_pair.key.is_empty() && !body_pair.disabled)
     }
+}
 
-    fn body_string(&self) -> String {
-        match self.current_body_format {
-            BodyFormat::Json => JsonValue::Object(
-                self.non_empty_body()
-                    .map(|InputRow { key, value }| {
-                        (key.lines()[0].clone(), value.lines()[0].clone())
-                    })
-                    .collect(),
+fn line_jump(line: usize) -> CursorMove {
+    CursorMove::Jump(line.saturating_sub(1).min(u16::MAX as usize) as u16, 0)
+}
+
+fn open_in_browser(url: &str) -> io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let mut command = Command::new("xdg-open");
+
+    command
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+fn preview_text(request: &HttpRequest) -> String {
+    let mut text = format!(
+        "{} {} {:?}",
+        request.method(),
+        request.url(),
+        request.version()
+    );
+    for (name, value) in request.headers() {
+        text.push_str(&format!(
+            "\n{}: {}",
+            name,
+            String::from_utf8_lossy(value.as_bytes())
+        ));
+    }
+    match request.body() {
+        Some(body) => match body.as_bytes() {
+            Some(bytes) if !bytes.is_empty() => {
+                text.push_str("\n\n");
+                text.push_str(&String::from_utf8_lossy(bytes));
+            }
+            Some(_) => (),
+            None => text.push_str("\n\n<streamed body>"),
+        },
+        None => (),
+    }
+    text
+}
+
+fn history_entry(request_builder: &RequestBuilder) -> Option<Entry> {
+    request_builder
+        .try_clone()
+        .and_then(|request_builder| request_builder.build().ok())
+        .map(|request| Entry::from_request(&request))
+}
+
+// GRPC is one of the methods only when reqcoon is built with gRPC support.
+fn grpc_method() -> Option<Method> {
+    if cfg!(feature = "grpc") {
+        Method::from_bytes(b"GRPC").ok()
+    } else {
+        None
+    }
+}
+
+// $VISUAL or $EDITOR, or vi when neither is set.
+fn editor() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or("vi".to_string())
+}
+
+// Body rows from a JSON object of strings, as edited in $EDITOR. An empty file clears the body.
+fn body_rows(text: &str) -> Result<Vec<(String, String)>, String> {
+    if text.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let object = json::parse(text).map_err(|err| format!("Invalid JSON: {}", err))?;
+    if !object.is_object() {
+        return Err("The body should be a JSON object".to_string());
+    }
+    object
+        .entries()
+        .map(|(key, value)| {
+            let value = value
+                .as_str()
+                .ok_or(format!("Body value for \"{}\" is not a string", key))?;
+            Ok((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+// The file extension for a response of the content type, so editors know how to highlight it.
+fn output_extension(content_type: Option<&str>) -> String {
+    let mime_type = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    match mime_type.rsplit(['/', '+']).next().unwrap_or_default() {
+        "javascript" | "x-javascript" | "ecmascript" => "js".to_string(),
+        "x-yaml" => "yaml".to_string(),
+        "markdown" => "md".to_string(),
+        subtype if subtype.chars().all(|c| c.is_ascii_alphanumeric()) && !subtype.is_empty() => {
+            match subtype {
+                "plain" | "octet-stream" => "txt".to_string(),
+                _ => subtype.to_string(),
+            }
+        }
+        _ => "txt".to_string(),
+    }
+}
+
+fn entry_content_type(entry: &Entry) -> Option<String> {
+    entry
+        .response_headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.clone())
+}
+
+fn same_file(first: &Path, second: &Path) -> bool {
+    match (first.canonicalize(), second.canonicalize()) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => first == second,
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn set_pair(pairs: &mut Vec<(String, String)>, name: &str, value: &str) {
+    match pairs.iter_mut().find(|(existing, _)| *existing == name) {
+        Some((_, existing)) => *existing = value.to_string(),
+        None => pairs.push((name.to_string(), value.to_string())),
+    }
+}
+
+// Whether the pattern's letters appear in order in the candidate, scored so prefixes come first
+// and then matches with fewer gaps between the letters.
+fn fuzzy_score(candidate: &str, pattern: &str) -> Option<usize> {
+    let candidate = candidate.to_lowercase();
+    if candidate.starts_with(&pattern.to_lowercase()) {
+        return Some(0);
+    }
+
+    let mut gaps = 0;
+    let mut letters = candidate.chars();
+    for wanted in pattern.to_lowercase().chars() {
+        loop {
+            match letters.next() {
+                Some(letter) if letter == wanted => break,
+                Some(_) => gaps += 1,
+                None => return None,
+            }
+        }
+    }
+    Some(1 + gaps)
+}
+
+// Splits `pattern/replacement/flags` at the slashes that aren't escaped, like `\/`. The g flag
+// replaces every match on a line rather than the first, and i ignores case.
+fn parse_substitution(text: &str) -> Result<(Regex, String, bool), String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'/') => {
+                parts.last_mut().unwrap().push(chars.next().unwrap())
+            }
+            '/' => parts.push(String::new()),
+            _ => parts.last_mut().unwrap().push(c),
+        }
+    }
+    let usage = "Usage: :s/pattern/replacement/flags";
+    if parts.len() < 2 || parts.len() > 3 || parts[0].is_empty() {
+        return Err(usage.to_string());
+    }
+
+    let flags = parts.get(2).cloned().unwrap_or_default();
+    if let Some(flag) = flags.chars().find(|flag| !['g', 'i'].contains(flag)) {
+        return Err(format!("Unknown flag {}. {}", flag, usage));
+    }
+    let regex = RegexBuilder::new(&parts[0])
+        .case_insensitive(flags.contains('i'))
+        .build()
+        .map_err(|err| format!("Invalid pattern: {}", err))?;
+    Ok((regex, parts[1].clone(), flags.contains('g')))
+}
+
+// Replaces matches on each line of the input, keeping the cursor where it was, and returns how
+// many were replaced. The replacement can refer to groups in the pattern, like $1.
+fn substitute_lines(
+    input: &mut TextArea<'static>,
+    regex: &Regex,
+    replacement: &str,
+    all: bool,
+) -> usize {
+    let mut count = 0;
+    let lines: Vec<String> = input
+        .lines()
+        .iter()
+        .map(|line| {
+            let matches = regex.find_iter(line).count();
+            if all {
+                count += matches;
+                regex.replace_all(line, replacement).to_string()
+            } else {
+                count += matches.min(1);
+                regex.replace(line, replacement).to_string()
+            }
+        })
+        .collect();
+    if count > 0 {
+        let cursor = input.cursor();
+        *input = TextArea::from(lines);
+        input.move_cursor(CursorMove::Jump(cursor.0 as u16, cursor.1 as u16));
+    }
+    count
+}
+
+fn disabled_pairs(input_table: &NonEmpty<InputRow>) -> Vec<(String, String)> {
+    input_table
+        .iter()
+        .filter(|row| row.disabled && !row.key.is_empty())
+        .map(|row| row.into())
+        .collect()
+}
+
+fn input_pairs(input_table: &NonEmpty<InputRow>) -> impl Iterator<Item = (&str, &str)> {
+    input_table
+        .iter()
+        .map(|row| (row.key.lines()[0].as_str(), row.value.lines()[0].as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use proptest::{collection, prelude::*};
+    use tui_textarea::TextArea;
+
+    use super::*;
+
+    fn editing_key() -> impl Strategy<Value = KeyEvent> {
+        prop_oneof![
+            4 => any::<char>()
+                .prop_filter("printable", |c| !c.is_control())
+                .prop_map(KeyCode::Char),
+            1 => Just(KeyCode::Backspace),
+            1 => Just(KeyCode::Delete),
+            1 => Just(KeyCode::Left),
+            1 => Just(KeyCode::Right),
+            1 => Just(KeyCode::Up),
+            1 => Just(KeyCode::Down),
+            1 => Just(KeyCode::Home),
+            1 => Just(KeyCode::End),
+        ]
+        .prop_map(|code| KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn normal_key() -> impl Strategy<Value = KeyEvent> {
+        (
+            prop::sample::select(vec![
+                'h', 'j', 'k', 'l', 'w', 'b', '^', '$', 'g', 'G', 'd', 'u', '0', '5', 'f', 't',
+                'F', 'T', ';', ',',
+            ]),
+            prop::bool::ANY,
+        )
+            .prop_map(|(c, control)| {
+                let modifiers = if control {
+                    KeyModifiers::CONTROL
+                } else {
+                    KeyModifiers::NONE
+                };
+                KeyEvent::new(KeyCode::Char(c), modifiers)
+            })
+    }
+
+    fn assert_cursor_in_bounds(input: &TextArea) -> Result<(), TestCaseError> {
+        let (row, col) = input.cursor();
+        prop_assert!(row < input.lines().len());
+        prop_assert!(col <= input.lines()[row].chars().count());
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn url_edits_stay_on_one_line(keys in collection::vec(editing_key(), 0..64)) {
+            let mut model = Model::new("test.http".to_string());
+            model.current_panel = Panel::Url;
+            model.insert();
+            for key in keys {
+                model.handle_insert_input(key);
+                assert_cursor_in_bounds(&model.url_input)?;
+            }
+            prop_assert_eq!(model.url_input.lines().len(), 1);
+        }
+
+        #[test]
+        fn raw_edits_keep_cursor_in_bounds(
+            keys in collection::vec(
+                prop_oneof![
+                    9 => editing_key(),
+                    1 => Just(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                ],
+                0..64,
             )
-            .dump(),
-            BodyFormat::Form => "".to_string(),
+        ) {
+            let mut model = Model::new("test.http".to_string());
+            model.current_panel = Panel::Input;
+            model.toggle_raw_input();
+            model.insert();
+            for key in keys {
+                model.handle_insert_input(key);
+                assert_cursor_in_bounds(&model.raw_input)?;
+            }
+        }
+
+        #[test]
+        fn crash_snapshots_leave_out_credentials(
+            username in "[a-z]{6,12}",
+            password in "[a-z]{6,12}",
+            query in "[a-z]{6,12}",
+        ) {
+            let mut model = Model::new("test.http".to_string());
+            model.url_input = TextArea::from([format!(
+                "https://{}:{}@example.com/users?q={}",
+                username, password, query
+            )]);
+            model.settings_input_table = Model::settings_table(&[(
+                "proxy".to_string(),
+                format!("http://{}:{}@proxy:3128", username, password),
+            )]);
+            model.error(format!("Unable to reach {}", password));
+
+            let snapshot = model.crash_snapshot();
+            for secret in [&username, &password, &query] {
+                prop_assert!(!snapshot.contains(secret.as_str()), "{}", snapshot);
+            }
+            prop_assert!(snapshot.contains("url: https://example.com/users\n"), "{}", snapshot);
+        }
+
+        #[test]
+        fn environments_win_over_the_workspace(
+            workspace in "[0-9]{1,3}",
+            staging in "[0-9]{1,3}",
+        ) {
+            let mut model = Model::new("test.http".to_string());
+            model.settings_input_table =
+                Model::settings_table(&[("timeout".to_string(), workspace.clone())]);
+            model.environments = vec![(
+                "staging".to_string(),
+                vec![("timeout".to_string(), staging.clone())],
+            )];
+
+            prop_assert_eq!(model.inherited_setting("timeout"), Some(workspace));
+            model.environment = Some("staging".to_string());
+            prop_assert_eq!(model.inherited_setting("timeout"), Some(staging));
+            prop_assert_eq!(model.inherited_setting("proxy"), None);
+        }
+
+        #[test]
+        fn scratch_requests_are_never_saved(
+            path in "[a-z0-9/._-]{0,30}",
+            name in "[a-z]{1,8}",
+            value in "[A-Za-z0-9 ]{0,16}",
+        ) {
+            let mut model = Model::new("test.http".to_string());
+            model.url_input = TextArea::from(["https://example.com/saved"]);
+            let saved = model.to_request_file().to_string();
+
+            model.set_variable(&name, &value);
+            model
+                .load_request_text(&format!("GET https://example.com/{}", path))
+                .unwrap();
+
+            prop_assert!(model.scratch);
+            prop_assert_eq!(model.to_request_file().to_string(), saved);
+            prop_assert_eq!(model.substitute(&format!("{{{{{}}}}}", name)), value);
+        }
+
+        #[test]
+        fn raw_mode_keeps_form_bodies(
+            body in collection::vec(("[a-z]{1,8}", "\\PC{0,16}"), 1..4),
+            body_format in prop::sample::select(vec![BodyFormat::UrlEncoded, BodyFormat::Multipart]),
+        ) {
+            let mut model = Model::new("test.http".to_string());
+            model.url_input = TextArea::from(["https://example.com/"]);
+            model.current_panel = Panel::Input;
+            model.current_body_format = body_format;
+            for (key, value) in &body {
+                model.body_input_table.push(InputRow {
+                    key: [key.as_str()].into(),
+                    value: [value.as_str()].into(),
+                    ..InputRow::default()
+                });
+            }
+
+            model.toggle_raw_input();
+            model.toggle_raw_input();
+
+            prop_assert!(!model.raw_mode);
+            prop_assert_eq!(model.current_body_format, body_format);
+            prop_assert_eq!(model.body_pairs(), body);
+        }
+
+        #[test]
+        fn output_motions_keep_cursor_in_bounds(
+            lines in collection::vec("\\PC{0,40}", 1..50),
+            keys in collection::vec(normal_key(), 0..64),
+        ) {
+            let mut model = Model::new("test.http".to_string());
+            model.current_panel = Panel::Output;
+            model.output_input = TextArea::from(lines);
+            for key in keys {
+                model.handle_normal_input(key);
+                assert_cursor_in_bounds(&model.output_input)?;
+            }
+        }
+
+        #[test]
+        fn shaped_cuts_keep_cursor_in_bounds(
+            lines in collection::vec("\\PC{0,40}", 1..20),
+            before in collection::vec(normal_key(), 0..16),
+            after in collection::vec(normal_key(), 0..16),
+            shape in prop::sample::select(vec![Selection::Line, Selection::Block]),
+        ) {
+            let mut model = Model::new("test.http".to_string());
+            model.current_panel = Panel::Output;
+            model.output_input = TextArea::from(lines);
+            for key in before {
+                model.handle_normal_input(key);
+            }
+            model.visual(shape);
+            for key in after {
+                model.handle_normal_input(key);
+            }
+            model.cut();
+            assert_cursor_in_bounds(&model.output_input)?;
+        }
+
+        #[test]
+        fn substitutions_keep_cursor_in_bounds(
+            lines in collection::vec("[a-c ]{0,20}", 1..10),
+            pattern in prop::sample::select(vec!["a", "b+", "c$", "^ *", "[abc]{2}"]),
+            replacement in "[a-c$]{0,3}",
+            all in prop::bool::ANY,
+        ) {
+            let mut input = TextArea::from(lines);
+            input.move_cursor(CursorMove::Bottom);
+            input.move_cursor(CursorMove::End);
+            let regex = Regex::new(pattern).unwrap();
+            substitute_lines(&mut input, &regex, &replacement, all);
+            assert_cursor_in_bounds(&input)?;
+        }
+
+        #[test]
+        fn requests_round_trip(
+            path in "[a-z0-9/{}._-]{0,30}",
+            headers in collection::btree_map("X-[A-Za-z0-9-]{1,12}", "[!-~][ -~]{0,30}", 0..4),
+            body in collection::btree_map("[a-z]{1,8}", "\\PC{0,16}", 0..4),
+            body_format in prop::sample::select(enum_iterator::all::<BodyFormat>().collect::<Vec<_>>()),
+        ) {
+            let url = format!("https://example.com/{}", path);
+            let mut model = Model::new("test.http".to_string());
+            model.url_input = TextArea::from([url.clone()]);
+            for (key, value) in &headers {
+                model.headers_input_table.push(InputRow {
+                    key: [key.as_str()].into(),
+                    value: [value.as_str()].into(),
+                    ..InputRow::default()
+                });
+            }
+            for (key, value) in &body {
+                model.body_input_table.push(InputRow {
+                    key: [key.as_str()].into(),
+                    value: [value.as_str()].into(),
+                    ..InputRow::default()
+                });
+            }
+            model.current_body_format = body_format;
+
+            let mut loaded = Model::new("test.http".to_string());
+            loaded.load_request(&Request::parse(&model.to_request().to_string()).unwrap());
+
+            prop_assert_eq!(loaded.url(), url.as_str());
+            prop_assert_eq!(loaded.header_pairs(), headers.into_iter().collect::<Vec<_>>());
+            let mut loaded_body = loaded.body_pairs();
+            loaded_body.sort();
+            prop_assert_eq!(loaded_body, body.into_iter().collect::<Vec<_>>());
+            prop_assert_eq!(loaded.current_body_format, body_format);
         }
     }
 
-    fn body_hash_map(&self) -> HashMap<String, String> {
-        self.non_empty_body().map(|row| row.into()).collect()
+    #[test]
+    fn quitting_without_saving_undoes_autosaves() {
+        let filename = env::temp_dir()
+            .join(format!("reqcoon-quit-{}.http", process::id()))
+            .display()
+            .to_string();
+        let original = "# Kept as written\nGET https://example.com/\n";
+        fs::write(&filename, original).unwrap();
+        let mut model = Model::from_file(filename.clone()).unwrap();
+        model.mark_saved();
+
+        model.url_input = TextArea::from(["https://example.com/autosaved"]);
+        model.to_file().unwrap();
+        model.restore_committed().unwrap();
+        let restored = fs::read_to_string(&filename).unwrap();
+
+        model.url_input = TextArea::from(["https://example.com/written"]);
+        model.save();
+        model.url_input = TextArea::from(["https://example.com/autosaved"]);
+        model.to_file().unwrap();
+        model.restore_committed().unwrap();
+        let written = fs::read_to_string(&filename).unwrap();
+        fs::remove_file(&filename).unwrap();
+
+        assert_eq!(restored, original);
+        assert_eq!(written, "GET https://example.com/written");
     }
 }
@@ -0,0 +1,189 @@
+use std::{str::FromStr, sync::OnceLock};
+
+use ratatui::style::Color;
+use toml::Table;
+
+pub const NAMES: [&str; 4] = ["default", "light", "solarized", "monochrome"];
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub active: Color,
+    pub selection: Color,
+    pub link: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+    // The color of methods without one of their own in methods.
+    pub method: Color,
+    pub methods: Vec<(String, Color)>,
+    pub json_key: Color,
+    pub json_string: Color,
+    pub json_number: Color,
+    pub json_literal: Color,
+    // {{variable}} references in the request.
+    pub variable: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            active: Color::Blue,
+            selection: Color::LightBlue,
+            link: Color::Blue,
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::Red,
+            method: Color::Green,
+            methods: vec![
+                ("POST".to_string(), Color::Yellow),
+                ("PUT".to_string(), Color::Blue),
+                ("PATCH".to_string(), Color::Magenta),
+                ("DELETE".to_string(), Color::Red),
+            ],
+            json_key: Color::Cyan,
+            json_string: Color::Green,
+            json_number: Color::Yellow,
+            json_literal: Color::Magenta,
+            variable: Color::LightMagenta,
+        }
+    }
+}
+
+impl Theme {
+    pub fn named(name: &str) -> Option<Self> {
+        let theme = match name {
+            "default" => Theme::default(),
+            "light" => Theme {
+                selection: Color::Rgb(0xbb, 0xd6, 0xf5),
+                success: Color::Rgb(0x1a, 0x7f, 0x37),
+                warning: Color::Rgb(0x9a, 0x67, 0x00),
+                method: Color::Rgb(0x1a, 0x7f, 0x37),
+                methods: vec![
+                    ("POST".to_string(), Color::Rgb(0x9a, 0x67, 0x00)),
+                    ("PUT".to_string(), Color::Rgb(0x09, 0x69, 0xda)),
+                    ("PATCH".to_string(), Color::Rgb(0x82, 0x50, 0xdf)),
+                    ("DELETE".to_string(), Color::Rgb(0xcf, 0x22, 0x2e)),
+                ],
+                json_key: Color::Rgb(0x09, 0x69, 0xda),
+                json_string: Color::Rgb(0x0a, 0x30, 0x69),
+                json_number: Color::Rgb(0x95, 0x38, 0x00),
+                json_literal: Color::Rgb(0x82, 0x50, 0xdf),
+                variable: Color::Rgb(0xbc, 0x4c, 0x00),
+                ..Theme::default()
+            },
+            "solarized" => Theme {
+                active: Color::Rgb(0x26, 0x8b, 0xd2),
+                selection: Color::Rgb(0x07, 0x36, 0x42),
+                link: Color::Rgb(0x26, 0x8b, 0xd2),
+                success: Color::Rgb(0x85, 0x99, 0x00),
+                warning: Color::Rgb(0xb5, 0x89, 0x00),
+                error: Color::Rgb(0xdc, 0x32, 0x2f),
+                method: Color::Rgb(0x85, 0x99, 0x00),
+                methods: vec![
+                    ("POST".to_string(), Color::Rgb(0xb5, 0x89, 0x00)),
+                    ("PUT".to_string(), Color::Rgb(0x26, 0x8b, 0xd2)),
+                    ("PATCH".to_string(), Color::Rgb(0x6c, 0x71, 0xc4)),
+                    ("DELETE".to_string(), Color::Rgb(0xdc, 0x32, 0x2f)),
+                ],
+                json_key: Color::Rgb(0x26, 0x8b, 0xd2),
+                json_string: Color::Rgb(0x2a, 0xa1, 0x98),
+                json_number: Color::Rgb(0xcb, 0x4b, 0x16),
+                json_literal: Color::Rgb(0xd3, 0x36, 0x82),
+                variable: Color::Rgb(0x6c, 0x71, 0xc4),
+            },
+            "monochrome" => Theme {
+                active: Color::White,
+                selection: Color::DarkGray,
+                link: Color::Reset,
+                success: Color::Reset,
+                warning: Color::Reset,
+                error: Color::Reset,
+                method: Color::Reset,
+                methods: vec![],
+                json_key: Color::Reset,
+                json_string: Color::Reset,
+                json_number: Color::Reset,
+                json_literal: Color::Reset,
+                variable: Color::Reset,
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    pub fn method(&self, method: &str) -> Color {
+        self.methods
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(method))
+            .map_or(self.method, |(_, color)| *color)
+    }
+
+    // A [theme] table picks a built-in theme by name and overrides any of its colors.
+    pub fn parse(table: &Table) -> Result<Self, String> {
+        let mut theme = match table.get("name") {
+            Some(name) => {
+                let name = name.as_str().ok_or("theme.name should be a string")?;
+                Theme::named(name).ok_or(format!(
+                    "Unknown theme {}, expected one of {}",
+                    name,
+                    NAMES.join(", ")
+                ))?
+            }
+            None => Theme::default(),
+        };
+
+        for (key, value) in table {
+            if key == "name" {
+                continue;
+            }
+            if key == "methods" {
+                let methods = value
+                    .as_table()
+                    .ok_or("theme.methods should be a table of methods and colors")?;
+                for (method, value) in methods {
+                    let color = color(&format!("theme.methods.{}", method), value)?;
+                    theme
+                        .methods
+                        .retain(|(name, _)| !name.eq_ignore_ascii_case(method));
+                    theme.methods.push((method.to_uppercase(), color));
+                }
+                continue;
+            }
+
+            let color = color(&format!("theme.{}", key), value)?;
+            match key.as_str() {
+                "active" => theme.active = color,
+                "selection" => theme.selection = color,
+                "link" => theme.link = color,
+                "success" => theme.success = color,
+                "warning" => theme.warning = color,
+                "error" => theme.error = color,
+                "method" => theme.method = color,
+                "json-key" => theme.json_key = color,
+                "json-string" => theme.json_string = color,
+                "json-number" => theme.json_number = color,
+                "json-literal" => theme.json_literal = color,
+                "variable" => theme.variable = color,
+                _ => return Err(format!("Unknown config key: theme.{}", key)),
+            }
+        }
+
+        Ok(theme)
+    }
+}
+
+// Colors are names like "blue" or "lightred", hex like "#268bd2", or a 256-color index.
+fn color(key: &str, value: &toml::Value) -> Result<Color, String> {
+    let text = value.as_str().ok_or(format!("{} should be a color", key))?;
+    Color::from_str(text).map_err(|_| format!("Invalid color for {}: {}", key, text))
+}
+
+pub fn set(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+pub fn current() -> &'static Theme {
+    THEME.get_or_init(Theme::default)
+}
@@ -1,36 +1,154 @@
 use crossterm::{
     cursor::SetCursorStyle,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, Clear, ClearType,
+        EnterAlternateScreen, LeaveAlternateScreen,
+    },
     ExecutableCommand,
 };
 use ratatui::prelude::*;
-use std::{io::stdout, panic};
 
-pub fn init_terminal() -> Terminal<impl Backend> {
-    enable_raw_mode().unwrap();
-    stdout().execute(EnterAlternateScreen).unwrap();
+use crate::crash;
+use std::{
+    env,
+    io::{self, stdout},
+    panic,
+    path::Path,
+    process::{Command, ExitStatus},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+
+pub fn init_terminal(alternate_screen: bool) -> io::Result<Terminal<impl Backend>> {
+    enter_terminal(alternate_screen)?;
+
+    Terminal::new(CrosstermBackend::new(stdout()))
+}
+
+fn enter_terminal(alternate_screen: bool) -> io::Result<()> {
+    enable_raw_mode()?;
+
+    // Fall back to drawing over the main screen if the terminal can't switch screens.
+    if alternate_screen && capable_terminal() && stdout().execute(EnterAlternateScreen).is_ok() {
+        ALTERNATE_SCREEN.store(true, Ordering::Relaxed);
+    } else {
+        stdout().execute(Clear(ClearType::All))?;
+    }
+
+    // Lets terminals that speak the kitty protocol report keys like ctrl+enter and shift+enter.
+    if supports_keyboard_enhancement().unwrap_or(false)
+        && stdout()
+            .execute(PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES,
+            ))
+            .is_ok()
+    {
+        KEYBOARD_ENHANCED.store(true, Ordering::Relaxed);
+    }
+
+    Ok(())
+}
+
+// Raw mode swallows ctrl+z, so hand the terminal back and stop ourselves the way the shell would,
+// then take the terminal over again once we're resumed with fg.
+#[cfg(unix)]
+pub fn suspend<B: Backend>(terminal: &mut Terminal<B>) -> io::Result<()> {
+    let alternate_screen = ALTERNATE_SCREEN.load(Ordering::Relaxed);
+    restore_terminal();
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    enter_terminal(alternate_screen)?;
+    terminal.clear()
+}
+
+#[cfg(not(unix))]
+pub fn suspend<B: Backend>(_terminal: &mut Terminal<B>) -> io::Result<()> {
+    Ok(())
+}
 
-    Terminal::new(CrosstermBackend::new(stdout())).expect("Unable to create terminal")
+// Hands the terminal to a program like less or vim with the file to open, and takes it back once the
+// program exits. The command can have arguments of its own, like `code --wait`. Only failing to take
+// the terminal back is an error here; a program that can't be run is reported in the result.
+pub fn run_external<B: Backend>(
+    terminal: &mut Terminal<B>,
+    command: &str,
+    path: &Path,
+) -> io::Result<Result<ExitStatus, String>> {
+    let alternate_screen = ALTERNATE_SCREEN.load(Ordering::Relaxed);
+    restore_terminal();
+    let mut words = command.split_whitespace();
+    let status = Command::new(words.next().unwrap_or_default())
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|err| format!("Unable to run {}: {}", command, err));
+    enter_terminal(alternate_screen)?;
+    terminal.clear()?;
+    Ok(status)
 }
 
 pub fn restore_terminal() {
-    stdout().execute(LeaveAlternateScreen).unwrap();
-    disable_raw_mode().unwrap();
+    if KEYBOARD_ENHANCED.swap(false, Ordering::Relaxed) {
+        let _ = stdout().execute(PopKeyboardEnhancementFlags);
+    }
+    if ALTERNATE_SCREEN.swap(false, Ordering::Relaxed) {
+        let _ = stdout().execute(LeaveAlternateScreen);
+    }
+    let _ = disable_raw_mode();
 }
 
 pub fn install_panic_hook() {
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
-        stdout().execute(LeaveAlternateScreen).unwrap();
-        disable_raw_mode().unwrap();
+        restore_terminal();
         original_hook(panic_info);
+        match crash::write_report(&panic_info.to_string()) {
+            Ok(path) => eprintln!("A crash report was written to {}", path.display()),
+            Err(err) => eprintln!("Unable to write a crash report: {}", err),
+        }
     }));
 }
 
+fn capable_terminal() -> bool {
+    static CAPABLE: OnceLock<bool> = OnceLock::new();
+    *CAPABLE.get_or_init(|| {
+        let term = env::var("TERM").unwrap_or_default();
+        !term.is_empty() && term != "dumb"
+    })
+}
+
+pub fn supports_hyperlinks() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        let term = env::var("TERM").unwrap_or_default();
+        let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+        env::var_os("KITTY_WINDOW_ID").is_some()
+            || env::var_os("WEZTERM_EXECUTABLE").is_some()
+            || env::var_os("VTE_VERSION").is_some()
+            || term.contains("kitty")
+            || term.contains("wezterm")
+            || matches!(
+                term_program.as_str(),
+                "iTerm.app" | "WezTerm" | "vscode" | "ghostty"
+            )
+    })
+}
+
 pub fn set_cursor_block() {
-    stdout().execute(SetCursorStyle::BlinkingBlock).unwrap();
+    if capable_terminal() {
+        let _ = stdout().execute(SetCursorStyle::BlinkingBlock);
+    }
 }
 
 pub fn set_cursor_bar() {
-    stdout().execute(SetCursorStyle::BlinkingBar).unwrap();
+    if capable_terminal() {
+        let _ = stdout().execute(SetCursorStyle::BlinkingBar);
+    }
 }
@@ -1,16 +1,36 @@
+use std::{sync::OnceLock, time::Instant};
+
+use nonempty::NonEmpty;
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Padding, Paragraph, Row, Table, TableState, Widget},
+    widgets::{
+        block::{Position, Title},
+        Block, Borders, Clear, List, ListState, Padding, Paragraph, Row, Table, TableState, Widget,
+        Wrap,
+    },
     Frame,
 };
+use regex::Regex;
+use reqcoon_core::oauth;
 
+#[cfg(feature = "highlighting")]
+use crate::highlighting;
 use crate::{
-    model::{AuthFormat, BodyFormat, InputField, InputType, Model, Panel},
+    model::{
+        AuthFormat, Authorization, BodyFormat, InputField, InputRow, InputType, Level, Mode, Model,
+        Panel, ResponseStatus, Selection, COMMANDS,
+    },
     text_wrapping::{truncate_ellipse, wrap_string},
+    theme,
+    tui::supports_hyperlinks,
 };
 
+// The [x] in front of header and body rows, and the space after it.
+const CHECKBOX_WIDTH: u16 = 4;
+
 pub fn view(f: &mut Frame, model: &mut Model) {
     // Create the layout sections.
     let [top_section, input_section, output_section, statusbar_section] = Layout::default()
@@ -32,19 +52,215 @@ pub fn view(f: &mut Frame, model: &mut Model) {
 
     f.render_widget(method_block(model), method_section);
     f.render_widget(url_block(model), url_section);
-    f.render_widget(output_block(model), output_section);
-    f.render_widget(mode_block(model), statusbar_section);
+    f.render_widget(output_block(model, output_section), output_section);
+    #[cfg(feature = "highlighting")]
+    let highlighted = highlight_output(
+        f.buffer_mut(),
+        output_section.inner(&Margin::new(1, 1)),
+        model,
+    );
+    #[cfg(not(feature = "highlighting"))]
+    let highlighted = false;
+    if !highlighted && looks_like_json(model) {
+        highlight_json(f.buffer_mut(), output_section.inner(&Margin::new(1, 1)));
+    } else if !highlighted && model.format_markup {
+        highlight_markup(f.buffer_mut(), output_section.inner(&Margin::new(1, 1)));
+    }
+    if supports_hyperlinks() {
+        hyperlink_urls(f.buffer_mut(), output_section.inner(&Margin::new(1, 1)));
+    }
+    let context = context_line(model);
+    let [status_section, context_section] = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(context.width() as u16),
+        ])
+        .areas(statusbar_section);
+    f.render_widget(statusbar_block(model), status_section);
+    f.render_widget(Paragraph::new(context), context_section);
+    highlight_variables(f.buffer_mut(), url_section.inner(&Margin::new(1, 1)));
+    draw_url_suggestion(f.buffer_mut(), url_section.inner(&Margin::new(1, 1)), model);
 
     let mut table_state = TableState::default().with_selected(model.input_index);
-    f.render_stateful_widget(
-        input_block(model, input_field_width as usize),
-        input_section,
-        &mut table_state,
+    if model.raw_mode {
+        f.render_widget(raw_input_block(model), input_section);
+        #[cfg(feature = "highlighting")]
+        highlight_raw_body(
+            f.buffer_mut(),
+            input_section.inner(&Margin::new(1, 1)),
+            model,
+        );
+        highlight_raw_selection(
+            f.buffer_mut(),
+            input_section.inner(&Margin::new(1, 1)),
+            model,
+        );
+    } else {
+        f.render_stateful_widget(
+            input_block(model, input_field_width as usize),
+            input_section,
+            &mut table_state,
+        );
+    }
+    highlight_variables(f.buffer_mut(), input_section.inner(&Margin::new(1, 1)));
+    underline_raw_body_error(
+        f.buffer_mut(),
+        input_section.inner(&Margin::new(1, 1)),
+        model,
     );
 
+    let settings_section = centered_rect(60, 50, f.size());
+    let settings_field_width = (settings_section.width.saturating_sub(6) / 2)
+        .saturating_sub(1)
+        .max(1);
+    let mut settings_state = TableState::default().with_selected(model.input_index);
+    if model.current_panel == Panel::Settings {
+        f.render_widget(Clear, settings_section);
+        f.render_stateful_widget(
+            settings_block(model, settings_field_width as usize),
+            settings_section,
+            &mut settings_state,
+        );
+    }
+
+    let cookies_section = centered_rect(60, 50, f.size());
+    let mut cookies_state = TableState::default().with_selected(model.input_index);
+    if model.current_panel == Panel::Cookies {
+        f.render_widget(Clear, cookies_section);
+        f.render_stateful_widget(
+            cookies_block(model, settings_field_width as usize),
+            cookies_section,
+            &mut cookies_state,
+        );
+    }
+
+    let mock_section = centered_rect(60, 60, f.size());
+    let [mock_routes_section, mock_log_section] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(10)])
+        .areas(mock_section);
+    let mut mock_state = TableState::default().with_selected(model.input_index);
+    if model.current_panel == Panel::Mock {
+        f.render_widget(Clear, mock_section);
+        f.render_stateful_widget(
+            mock_block(model, settings_field_width as usize),
+            mock_routes_section,
+            &mut mock_state,
+        );
+        f.render_widget(mock_log_block(model, mock_log_section), mock_log_section);
+    }
+
+    let history_section = centered_rect(80, 60, f.size());
+    let mut history_state = ListState::default().with_selected(Some(model.input_index));
+    if model.current_panel == Panel::History {
+        f.render_widget(Clear, history_section);
+        f.render_stateful_widget(history_block(model), history_section, &mut history_state);
+    }
+
+    let runner_section = centered_rect(80, 60, f.size());
+    let mut runner_state = ListState::default().with_selected(Some(model.input_index));
+    if model.current_panel == Panel::Runner {
+        f.render_widget(Clear, runner_section);
+        f.render_stateful_widget(runner_block(model), runner_section, &mut runner_state);
+    }
+
+    let files_section = centered_rect(60, 60, f.size());
+    let mut files_state = ListState::default().with_selected(Some(model.input_index));
+    if model.current_panel == Panel::Files {
+        f.render_widget(Clear, files_section);
+        f.render_stateful_widget(files_block(model), files_section, &mut files_state);
+    }
+
+    let authorize_section = centered_rect(60, 40, f.size());
+    if model.current_panel == Panel::Authorize {
+        f.render_widget(Clear, authorize_section);
+        f.render_widget(authorize_block(model), authorize_section);
+    }
+
+    let menu_section = centered_rect(40, 60, f.size());
+    let mut menu_state = ListState::default().with_selected(Some(model.input_index));
+    if model.current_panel == Panel::Menu {
+        f.render_widget(Clear, menu_section);
+        f.render_stateful_widget(menu_block(model), menu_section, &mut menu_state);
+    }
+
+    let preview_section = centered_rect(80, 70, f.size());
+    if model.current_panel == Panel::Preview {
+        f.render_widget(Clear, preview_section);
+        f.render_widget(preview_block(model), preview_section);
+    }
+
+    let description_section = centered_rect(60, 50, f.size());
+    if model.current_panel == Panel::Description {
+        f.render_widget(Clear, description_section);
+        f.render_widget(description_block(model), description_section);
+    }
+
+    let diff_section = centered_rect(80, 70, f.size());
+    if model.current_panel == Panel::Diff {
+        f.render_widget(Clear, diff_section);
+        f.render_widget(diff_block(model), diff_section);
+        highlight_diff(
+            f.buffer_mut(),
+            diff_section.inner(&Margin::new(1, 1)),
+            model,
+        );
+    }
+
+    let messages_section = centered_rect(80, 70, f.size());
+    if model.current_panel == Panel::Messages {
+        f.render_widget(Clear, messages_section);
+        f.render_widget(messages_block(model), messages_section);
+        highlight_messages(
+            f.buffer_mut(),
+            messages_section.inner(&Margin::new(1, 1)),
+            model,
+        );
+    }
+
+    if model.current_panel == Panel::Command {
+        // Completions open upwards from the command line, like a shell's.
+        let completions = model.command_completions();
+        let completions_height = (completions.len() as u16).min(8) + 2;
+        let completions_section = Rect {
+            y: statusbar_section.y.saturating_sub(completions_height),
+            height: completions_height.min(statusbar_section.y),
+            ..statusbar_section
+        };
+        let [prompt_section, command_section] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .areas(statusbar_section);
+        f.render_widget(Clear, statusbar_section);
+        f.render_widget(Paragraph::new(":"), prompt_section);
+        f.render_widget(command_block(model), command_section);
+        if !completions.is_empty() {
+            f.render_widget(Clear, completions_section);
+            f.render_widget(completions_block(&completions), completions_section);
+        }
+    }
+
+    let [_, query_section] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(3)])
+        .areas(output_section.inner(&Margin::new(1, 0)));
+    if model.current_panel == Panel::Query {
+        f.render_widget(Clear, query_section);
+        f.render_widget(query_block(model), query_section);
+    }
+
     let (col, row) = match model.current_panel {
         Panel::Method => (model.method_cursor_position(), 1),
         Panel::Url => (model.cursor_col() + url_section.x + 1, 1),
+        Panel::Input if model.raw_mode => {
+            let (scroll_row, scroll_col) = model.raw_input.viewport.scroll_top();
+            let (row, col) = model.raw_input.cursor();
+            (
+                col as u16 - scroll_col + 1,
+                row as u16 - scroll_row + input_section.y + 1,
+            )
+        }
         Panel::Input => {
             let start_col = match model.current_input_field {
                 InputField::Key => 3,
@@ -69,30 +285,144 @@ pub fn view(f: &mut Frame, model: &mut Model) {
                         input_section.y + 4 + input_row,
                     ),
                 },
-                InputType::Headers | InputType::Body => (
-                    start_col + model.cursor_col() % field_width,
-                    (model.input_index - table_state.offset()) as u16
-                        + input_section.y
-                        + 4
-                        + input_row,
-                ),
+                InputType::Path | InputType::Headers | InputType::Body | InputType::Options => {
+                    table_cursor(model, input_section, field_width, table_state.offset())
+                }
             }
         }
         Panel::Output => {
-            let (scroll_row, scroll_col) = model.output_input.viewport.scroll_top();
+            let (scroll_row, scroll_col) = model.output_scroll;
             let (row, col) = model.output_input.cursor();
             (
-                col as u16 - scroll_col + 1,
-                row as u16 - scroll_row + output_section.y + 1,
+                (col - scroll_col) as u16 + 1,
+                (row - scroll_row) as u16 + output_section.y + 1,
+            )
+        }
+        Panel::Settings => table_cursor(
+            model,
+            settings_section,
+            settings_field_width,
+            settings_state.offset(),
+        ),
+        Panel::Cookies => table_cursor(
+            model,
+            cookies_section,
+            settings_field_width,
+            cookies_state.offset(),
+        ),
+        Panel::Mock => table_cursor(
+            model,
+            mock_routes_section,
+            settings_field_width,
+            mock_state.offset(),
+        ),
+        Panel::History => (
+            history_section.x + 1,
+            history_section.y + 1 + (model.input_index - history_state.offset()) as u16,
+        ),
+        Panel::Runner => (
+            runner_section.x + 1,
+            runner_section.y + 1 + (model.input_index - runner_state.offset()) as u16,
+        ),
+        Panel::Files => (
+            files_section.x + 1,
+            files_section.y + 1 + (model.input_index - files_state.offset()) as u16,
+        ),
+        Panel::Authorize => (authorize_section.x + 1, authorize_section.y + 1),
+        Panel::Query => (
+            query_section.x + 1 + model.cursor_col(),
+            query_section.y + 1,
+        ),
+        Panel::Command => (
+            statusbar_section.x + 1 + model.cursor_col(),
+            statusbar_section.y,
+        ),
+        Panel::Preview => {
+            let (scroll_row, scroll_col) = model.preview_input.viewport.scroll_top();
+            let (row, col) = model.preview_input.cursor();
+            (
+                col as u16 - scroll_col + preview_section.x + 1,
+                row as u16 - scroll_row + preview_section.y + 1,
+            )
+        }
+        Panel::Description => {
+            let (scroll_row, scroll_col) = model.description_input.viewport.scroll_top();
+            let (row, col) = model.description_input.cursor();
+            (
+                col as u16 - scroll_col + description_section.x + 1,
+                row as u16 - scroll_row + description_section.y + 1,
+            )
+        }
+        Panel::Diff => {
+            let (scroll_row, scroll_col) = model.diff_input.viewport.scroll_top();
+            let (row, col) = model.diff_input.cursor();
+            (
+                col as u16 - scroll_col + diff_section.x + 1,
+                row as u16 - scroll_row + diff_section.y + 1,
+            )
+        }
+        Panel::Messages => {
+            let (scroll_row, scroll_col) = model.messages_input.viewport.scroll_top();
+            let (row, col) = model.messages_input.cursor();
+            (
+                col as u16 - scroll_col + messages_section.x + 1,
+                row as u16 - scroll_row + messages_section.y + 1,
             )
         }
+        Panel::Menu => (
+            menu_section.x + 1,
+            menu_section.y + 1 + (model.input_index - menu_state.offset()) as u16,
+        ),
     };
 
     f.set_cursor(col, row);
 }
 
 fn active_style() -> Style {
-    Style::default().fg(Color::Blue)
+    Style::default().fg(theme::current().active)
+}
+
+fn selection_style() -> Style {
+    Style::default().bg(theme::current().selection)
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let [_, middle, _] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .areas(area);
+
+    let [_, center, _] = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .areas(middle);
+
+    center
+}
+
+fn table_cursor(model: &Model, section: Rect, field_width: u16, offset: usize) -> (u16, u16) {
+    let (start_col, field_width) = match model.current_input_field {
+        InputField::Key if model.editing_movable_row() => (
+            section.x + 3 + CHECKBOX_WIDTH,
+            field_width.saturating_sub(CHECKBOX_WIDTH).max(1),
+        ),
+        InputField::Key => (section.x + 3, field_width),
+        InputField::Value => (section.x + section.width / 2 + 1, field_width),
+    };
+    let input_row = model.cursor_col() / field_width;
+
+    (
+        start_col + model.cursor_col() % field_width,
+        (model.input_index - offset) as u16 + section.y + 4 + input_row,
+    )
 }
 
 fn method_block(model: &Model) -> Paragraph {
@@ -109,30 +439,160 @@ fn method_block(model: &Model) -> Paragraph {
 
     Paragraph::new(Text::styled(
         model.current_method.to_string().clone(),
-        Style::default().fg(Color::Green),
+        Style::default().fg(theme::current().method(model.current_method.as_str())),
     ))
     .block(method_block)
 }
 
 fn url_block(model: &mut Model) -> impl Widget + '_ {
-    let style = if model.current_panel == Panel::Url {
+    let url_error = model.url_error();
+    let style = if url_error.is_some() {
+        Style::default().fg(theme::current().error)
+    } else if model.current_panel == Panel::Url {
         active_style()
     } else {
         Style::default()
     };
 
-    let url_block = Block::default()
-        .title("URL")
+    let mut url_block = Block::default()
+        .title(url_title(model))
+        .title(Title::from(override_badges(model)).alignment(Alignment::Right))
         .borders(Borders::ALL)
         .border_style(style);
+    if let Some(url_error) = url_error {
+        url_block = url_block.title(
+            Title::from(url_error)
+                .position(Position::Bottom)
+                .alignment(Alignment::Left),
+        );
+    }
 
     model.url_input.set_cursor_line_style(Style::default());
     model.url_input.set_cursor_style(Style::default());
+    model.url_input.set_selection_style(selection_style());
     model.url_input.set_block(url_block);
 
     model.url_input.widget()
 }
 
+// The rest of the suggested URL is drawn dimmed after the cursor, for ↹ to accept.
+fn draw_url_suggestion(buf: &mut Buffer, area: Rect, model: &Model) {
+    let Some(suggestion) = model.url_suggestion() else {
+        return;
+    };
+    let typed = &model.url_input.lines()[0];
+    let (_, scroll_col) = model.url_input.viewport.scroll_top();
+    let start = typed.chars().count().saturating_sub(scroll_col as usize);
+    if start >= area.width as usize {
+        return;
+    }
+    buf.set_stringn(
+        area.x + start as u16,
+        area.y,
+        &suggestion[typed.len()..],
+        area.width as usize - start,
+        Style::default().dim(),
+    );
+}
+
+fn url_title(model: &Model) -> String {
+    let mut title = "URL".to_string();
+    if !model.name.is_empty() {
+        title.push_str(&format!(" · {}", model.name));
+    }
+    if model.scratch {
+        title.push_str(" · scratch");
+    }
+    if let Some(environment) = &model.environment {
+        title.push_str(&format!(" · env {}", environment));
+    }
+    if model.request_count() > 1 {
+        title.push_str(&format!(
+            " ({}/{})",
+            model.request_index + 1,
+            model.request_count()
+        ));
+    }
+    title
+}
+
+fn output_title(model: &Model) -> Line<'static> {
+    match model.example_index {
+        Some(index) => Line::raw(format!(
+            "Output · Example: {} ({}/{})",
+            model.current_examples()[index].summary(),
+            index + 1,
+            model.current_examples().len()
+        )),
+        None => {
+            let mut spans = vec![Span::raw("Output")];
+            if let Some(status_line) = &model.status_line {
+                spans.push(Span::raw(" · "));
+                spans.push(Span::styled(
+                    status_line.clone(),
+                    status_style(model).unwrap_or_default(),
+                ));
+            }
+            if let Some(polling) = model.polling_status() {
+                spans.push(Span::styled(
+                    format!(" · {}", polling),
+                    Style::default().fg(theme::current().warning),
+                ));
+            }
+            Line::from(spans)
+        }
+    }
+}
+
+// 2xx responses are drawn in the success color, 3xx in the warning color and 4xx and 5xx in the
+// error color. Requests that got no response at all are drawn in the error color in bold.
+fn status_style(model: &Model) -> Option<Style> {
+    if model.example_index.is_some() {
+        return None;
+    }
+
+    let theme = theme::current();
+    match model.response_status? {
+        ResponseStatus::Code(200..=299) => Some(Style::default().fg(theme.success)),
+        ResponseStatus::Code(300..=399) => Some(Style::default().fg(theme.warning)),
+        ResponseStatus::Code(400..) => Some(Style::default().fg(theme.error)),
+        ResponseStatus::Code(_) => None,
+        ResponseStatus::Failed => Some(Style::default().fg(theme.error).bold()),
+    }
+}
+
+fn override_badges(model: &Model) -> Line<'static> {
+    let badges = model
+        .options_input_table
+        .iter()
+        .filter(|row| !row.key.is_empty() && !row.value.is_empty())
+        // An option that only repeats what the workspace or environment already sets changes
+        // nothing, so it isn't flagged.
+        .filter(|row| {
+            model
+                .inherited_setting(&row.key.lines()[0])
+                .as_deref()
+                .map(str::trim)
+                != Some(row.value.lines()[0].trim())
+        })
+        .flat_map(|row| {
+            let key = &row.key.lines()[0];
+            let value = &row.value.lines()[0];
+            let color = match key.as_str() {
+                "verify-tls" | "proxy" => theme::current().error,
+                _ => theme::current().warning,
+            };
+
+            [
+                Span::styled(format!(" {} {} ", key, value), Color::Black).bg(color),
+                Span::raw(" "),
+            ]
+        })
+        .collect::<Vec<Span>>();
+
+    Line::default().spans(badges)
+}
+
 fn input_block(model: &Model, field_width: usize) -> Table {
     let style = if model.current_panel == Panel::Input {
         active_style()
@@ -140,29 +600,40 @@ fn input_block(model: &Model, field_width: usize) -> Table {
         Style::default()
     };
 
-    let input_block = Block::default()
+    let mut input_block = Block::default()
         .title(input_title(model))
         .title_bottom(input_footer(model))
         .borders(Borders::ALL)
         .border_style(style)
         .padding(Padding::proportional(1));
+    // What a form body encodes to, so it's clear what the server gets.
+    if model.current_input_type == InputType::Body {
+        if let Some(preview) = model.form_preview() {
+            input_block = input_block.title_bottom(
+                Line::styled(format!(" {} ", preview), Style::default().dim())
+                    .alignment(Alignment::Right),
+            );
+        }
+    }
 
     match model.current_input_type {
         InputType::Auth => match model.auth.format {
             AuthFormat::None => Table::default().block(input_block),
             AuthFormat::Basic => {
+                let username = &model.auth.basic_input.key.lines()[0];
+                let password = &masked(model, &model.auth.basic_input.value.lines()[0]);
+                let selection = model.selection_columns();
                 let (username, password) = match model.current_input_field {
                     InputField::Key => (
-                        wrap_string(&model.auth.basic_input.key.lines()[0], field_width),
-                        truncate_ellipse(&model.auth.basic_input.value.lines()[0], field_width),
+                        selectable_text(username, field_width, selection),
+                        Text::raw(truncate_ellipse(password, field_width)),
                     ),
                     InputField::Value => (
-                        truncate_ellipse(&model.auth.basic_input.key.lines()[0], field_width),
-                        wrap_string(&model.auth.basic_input.value.lines()[0], field_width),
+                        Text::raw(truncate_ellipse(username, field_width)),
+                        selectable_text(password, field_width, selection),
                     ),
                 };
-                let height =
-                    std::cmp::max(username.lines().count(), password.lines().count()) as u16;
+                let height = std::cmp::max(username.height(), password.height()) as u16;
 
                 Table::new(
                     vec![Row::new(vec![username, password]).height(height)],
@@ -172,8 +643,12 @@ fn input_block(model: &Model, field_width: usize) -> Table {
                 .block(input_block)
             }
             AuthFormat::Bearer => {
-                let token = wrap_string(&model.auth.bearer_input.lines()[0], (field_width + 1) * 2);
-                let height = token.lines().count() as u16;
+                let token = selectable_text(
+                    &masked(model, &model.auth.bearer_input.lines()[0]),
+                    (field_width + 1) * 2,
+                    model.selection_columns(),
+                );
+                let height = token.height() as u16;
 
                 Table::new(
                     vec![Row::new(vec![token]).height(height)],
@@ -183,54 +658,516 @@ fn input_block(model: &Model, field_width: usize) -> Table {
                 .block(input_block)
             }
         },
-        InputType::Headers | InputType::Body => model
-            .current_input_table()
+        InputType::Path | InputType::Headers | InputType::Body | InputType::Options => input_table(
+            model,
+            model.current_input_table(),
+            model.current_panel == Panel::Input,
+            field_width,
+            matches!(
+                model.current_input_type,
+                InputType::Headers | InputType::Body
+            ),
+        )
+        .block(input_block),
+    }
+}
+
+fn raw_input_block(model: &mut Model) -> impl Widget + '_ {
+    let style = if model.current_panel == Panel::Input {
+        active_style()
+    } else {
+        Style::default()
+    };
+
+    let mut title = vec![Span::raw("Raw HTTP")];
+    if let Some((_, err)) = model.raw_body_error() {
+        title.push(Span::styled(
+            format!(" · Invalid JSON at {}", err),
+            Style::default().fg(theme::current().error),
+        ));
+    }
+    let raw_input_block = Block::default()
+        .title(Line::from(title))
+        .borders(Borders::ALL)
+        .border_style(style);
+
+    model.raw_input.set_cursor_line_style(Style::default());
+    model.raw_input.set_cursor_style(Style::default());
+    model.raw_input.set_selection_style(selection_style());
+    model.raw_input.set_block(raw_input_block);
+
+    model.raw_input.widget()
+}
+
+fn settings_block(model: &Model, field_width: usize) -> Table {
+    let settings_block = Block::default()
+        .title("Workspace Settings")
+        .borders(Borders::ALL)
+        .border_style(active_style())
+        .padding(Padding::proportional(1));
+
+    input_table(model, &model.settings_input_table, true, field_width, false).block(settings_block)
+}
+
+fn cookies_block(model: &Model, field_width: usize) -> Table {
+    let cookies_block = Block::default()
+        .title("Cookies")
+        .title_bottom(Line::from(" clear a name to drop it  ⎋ apply ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style())
+        .padding(Padding::proportional(1));
+
+    input_table(model, &model.cookies_input_table, true, field_width, false).block(cookies_block)
+}
+
+fn mock_block(model: &Model, field_width: usize) -> Table {
+    let title = match &model.mock_server {
+        Some(server) => format!("Mock routes · serving on {}", server.port),
+        None => "Mock routes · :mock <port> to serve".to_string(),
+    };
+    let mock_block = Block::default()
+        .title(title)
+        .title_bottom(
+            Line::from(" GET /path  201 {\"json\"}  ⎋ apply ").alignment(Alignment::Right),
+        )
+        .borders(Borders::ALL)
+        .border_style(active_style())
+        .padding(Padding::proportional(1));
+
+    input_table(model, &model.mock_input_table, true, field_width, false)
+        .header(Row::new(vec!["Route", "Response"]).bottom_margin(1))
+        .block(mock_block)
+}
+
+// The latest requests the mock server received that fit, newest last.
+fn mock_log_block(model: &Model, area: Rect) -> List<'static> {
+    let block = Block::default()
+        .title("Received")
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    if model.mock_log.is_empty() {
+        return List::new(["Nothing received yet"]).block(block);
+    }
+
+    let shown = area.height.saturating_sub(2) as usize;
+    let skipped = model.mock_log.len().saturating_sub(shown);
+    List::new(
+        model.mock_log[skipped..]
             .iter()
+            .map(|received| received.summary()),
+    )
+    .block(block)
+}
+
+fn query_block(model: &mut Model) -> impl Widget + '_ {
+    let query_block = Block::default()
+        .title("Query")
+        .title_bottom(
+            Line::from(" $ JSONPath  / XPath  otherwise CSS  ↵ run  ⎋ close ")
+                .alignment(Alignment::Right),
+        )
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    model.query_input.set_cursor_line_style(Style::default());
+    model.query_input.set_cursor_style(Style::default());
+    model.query_input.set_selection_style(selection_style());
+    model.query_input.set_block(query_block);
+
+    model.query_input.widget()
+}
+
+fn preview_block(model: &mut Model) -> impl Widget + '_ {
+    let preview_block = Block::default()
+        .title("Preview")
+        .title_bottom(Line::from(" v select  ⎋ close ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    model.preview_input.set_cursor_line_style(Style::default());
+    model.preview_input.set_cursor_style(Style::default());
+    model.preview_input.set_selection_style(selection_style());
+    model.preview_input.set_block(preview_block);
+
+    model.preview_input.widget()
+}
+
+fn description_block(model: &mut Model) -> impl Widget + '_ {
+    let title = if model.name.is_empty() {
+        "Notes".to_string()
+    } else {
+        format!("Notes on {}", model.name)
+    };
+    let description_block = Block::default()
+        .title(title)
+        .title_bottom(Line::from(" i edit  ⎋ close ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    model
+        .description_input
+        .set_cursor_line_style(Style::default());
+    model.description_input.set_cursor_style(Style::default());
+    model
+        .description_input
+        .set_selection_style(selection_style());
+    model.description_input.set_block(description_block);
+
+    model.description_input.widget()
+}
+
+fn diff_block(model: &mut Model) -> impl Widget + '_ {
+    let diff_block = Block::default()
+        .title("Diff")
+        .title_bottom(Line::from(" v select  ⎋ close ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    model.diff_input.set_cursor_line_style(Style::default());
+    model.diff_input.set_cursor_style(Style::default());
+    model.diff_input.set_selection_style(selection_style());
+    model.diff_input.set_block(diff_block);
+
+    model.diff_input.widget()
+}
+
+// Added lines are drawn in the success color and removed ones in the error color.
+fn highlight_diff(buf: &mut Buffer, area: Rect, model: &Model) {
+    let theme = theme::current();
+    let (scroll_row, _) = model.diff_input.viewport.scroll_top();
+    let lines = model.diff_input.lines();
+    for y in 0..area.height {
+        let Some(line) = lines.get(scroll_row as usize + y as usize) else {
+            break;
+        };
+        let color = match line.chars().next() {
+            Some('+') => theme.success,
+            Some('-') => theme.error,
+            _ => continue,
+        };
+        for x in area.left()..area.right() {
+            buf.get_mut(x, area.top() + y).set_fg(color);
+        }
+    }
+}
+
+fn messages_block(model: &mut Model) -> impl Widget + '_ {
+    let messages_block = Block::default()
+        .title("Messages (UTC)")
+        .title_bottom(Line::from(" v select  ⎋ close ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    model.messages_input.set_cursor_line_style(Style::default());
+    model.messages_input.set_cursor_style(Style::default());
+    model.messages_input.set_selection_style(selection_style());
+    model.messages_input.set_block(messages_block);
+
+    model.messages_input.widget()
+}
+
+// Errors are drawn in the error color and warnings in the warning color.
+fn highlight_messages(buf: &mut Buffer, area: Rect, model: &Model) {
+    let theme = theme::current();
+    let (scroll_row, _) = model.messages_input.viewport.scroll_top();
+    for y in 0..area.height {
+        let Some(logged) = model.messages.get(scroll_row as usize + y as usize) else {
+            break;
+        };
+        let color = match logged.level {
+            Level::Error => theme.error,
+            Level::Warning => theme.warning,
+            Level::Info => continue,
+        };
+        for x in area.left()..area.right() {
+            buf.get_mut(x, area.top() + y).set_fg(color);
+        }
+    }
+}
+
+fn command_block(model: &mut Model) -> impl Widget + '_ {
+    model.command_input.set_cursor_line_style(Style::default());
+    model.command_input.set_cursor_style(Style::default());
+    model.command_input.set_selection_style(selection_style());
+
+    model.command_input.widget()
+}
+
+fn completions_block(completions: &[String]) -> List<'static> {
+    let block = Block::default()
+        .title_bottom(Line::from(" ↹ complete  ↵ run  ⎋ close ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    List::new(completions.iter().map(|completion| {
+        match COMMANDS.iter().find(|(command, _)| command == completion) {
+            Some((_, description)) => format!("{:<12} {}", completion, description),
+            None => completion.clone(),
+        }
+    }))
+    .block(block)
+}
+
+fn history_block(model: &Model) -> List<'static> {
+    let block = Block::default()
+        .title("History")
+        .title_bottom(Line::from(" ↵ load  ⎋ close ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    if model.history.is_empty() {
+        return List::new(["No requests sent yet"]).block(block);
+    }
+
+    List::new(model.history.iter().map(|entry| entry.summary()))
+        .block(block)
+        .highlight_style(Style::default().reversed())
+}
+
+fn runner_block(model: &Model) -> List<'static> {
+    let block = Block::default()
+        .title("Run")
+        .title_bottom(Line::from(" ↵ open  ⎋ close ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    if model.outcomes.is_empty() {
+        return List::new(["No requests to run"]).block(block);
+    }
+
+    List::new(model.outcomes.iter().map(|outcome| {
+        let style = if outcome.passed() {
+            Style::default().fg(theme::current().success)
+        } else {
+            Style::default().fg(theme::current().error)
+        };
+        let mut text = outcome.summary();
+        if !outcome.failures.is_empty() {
+            text.push_str(" — ");
+            text.push_str(&outcome.failures.join("; "));
+        }
+        Line::styled(text, style)
+    }))
+    .block(block)
+    .highlight_style(Style::default().reversed())
+}
+
+// Files are shown by their path in the workspace.
+fn files_block(model: &Model) -> List<'static> {
+    let block = Block::default()
+        .title("Files")
+        .title_bottom(Line::from(" ↵ open  ⎋ close ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    if model.files.is_empty() {
+        return List::new(["No request files, :e <name>.http starts one"]).block(block);
+    }
+
+    let directory = model.workspace.clone().unwrap_or_default();
+    List::new(model.files.iter().map(|file| {
+        file.strip_prefix(&directory)
+            .unwrap_or(file)
+            .display()
+            .to_string()
+    }))
+    .block(block)
+    .highlight_style(Style::default().reversed())
+}
+
+fn menu_block(model: &Model) -> List<'static> {
+    let block = Block::default()
+        .title("Actions")
+        .title_bottom(Line::from(" ↵ run  ⎋ close ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    List::new(
+        model
+            .menu_items()
+            .iter()
+            .map(|item| format!("{:<3} {}", item.keys, item.label)),
+    )
+    .block(block)
+    .highlight_style(Style::default().reversed())
+}
+
+fn authorize_block(model: &Model) -> Paragraph<'static> {
+    let block = Block::default()
+        .title("Authorize")
+        .title_bottom(Line::from(" ⎋ cancel ").alignment(Alignment::Right))
+        .borders(Borders::ALL)
+        .border_style(active_style());
+
+    let authorization = match &model.authorization {
+        Some(Authorization::Device(authorization)) => authorization,
+        Some(Authorization::Browser(url)) => {
+            return Paragraph::new(vec![
+                Line::raw("Finish signing in in your browser."),
+                Line::raw(""),
+                Line::raw("If it didn't open, go to"),
+                Line::styled(url.clone(), theme::current().link),
+            ])
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false })
+            .block(block);
+        }
+        None => return Paragraph::new("No authorization in progress").block(block),
+    };
+
+    let remaining = authorization
+        .expires_at
+        .saturating_duration_since(Instant::now())
+        .as_secs_f64()
+        .ceil() as u64;
+    let mut lines = vec![
+        Line::raw("Open"),
+        Line::raw(""),
+        Line::styled(
+            authorization.verification_uri.clone(),
+            theme::current().link,
+        ),
+        Line::raw(""),
+        Line::raw("and enter the code"),
+        Line::raw(""),
+        Line::styled(authorization.user_code.clone(), Style::default().bold()),
+        Line::raw(""),
+    ];
+    if let Some(complete) = &authorization.verification_uri_complete {
+        lines.push(Line::raw("or open this link, which fills the code in:"));
+        lines.push(Line::styled(complete.clone(), theme::current().link));
+        lines.push(Line::raw(""));
+    }
+    lines.push(Line::raw(format!(
+        "Waiting for approval, the code expires in {}:{:02}",
+        remaining / 60,
+        remaining % 60
+    )));
+
+    Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .block(block)
+}
+
+fn input_table(
+    model: &Model,
+    input_rows: &NonEmpty<InputRow>,
+    active: bool,
+    field_width: usize,
+    checkboxes: bool,
+) -> Table<'static> {
+    // The checkbox takes its room from the key column so values stay where they were.
+    let key_width = if checkboxes {
+        field_width.saturating_sub(CHECKBOX_WIDTH as usize)
+    } else {
+        field_width
+    };
+    let table = input_rows
+        .iter()
+        .enumerate()
+        .map(|(i, input_row)| {
+            let key = &input_row.key.lines()[0];
+            let value = &input_row.value.lines()[0];
+            let (formatted_key, formatted_value) = if active && model.input_index == i {
+                let selection = model.selection_columns();
+                match model.current_input_field {
+                    InputField::Key => (
+                        selectable_text(key, key_width, selection),
+                        Text::raw(truncate_ellipse(value, field_width)),
+                    ),
+                    InputField::Value => (
+                        Text::raw(truncate_ellipse(key, key_width)),
+                        selectable_text(value, field_width, selection),
+                    ),
+                }
+            } else {
+                (
+                    Text::raw(truncate_ellipse(key, key_width)),
+                    Text::raw(truncate_ellipse(value, field_width)),
+                )
+            };
+            let height = std::cmp::max(formatted_key.height(), formatted_value.height()) as u16;
+
+            let row = if checkboxes {
+                let checkbox = if input_row.disabled { "[ ]" } else { "[x]" };
+                Row::new(vec![Text::raw(checkbox), formatted_key, formatted_value])
+            } else {
+                Row::new(vec![formatted_key, formatted_value])
+            }
+            .height(height);
+            if input_row.disabled {
+                row.style(Style::default().dim().crossed_out())
+            } else {
+                row
+            }
+        })
+        .collect::<Table>();
+
+    if checkboxes {
+        table
+            .widths([
+                Constraint::Length(CHECKBOX_WIDTH - 1),
+                Constraint::Length(key_width as u16 + 1),
+                Constraint::Min(0),
+            ])
+            .header(Row::new(vec!["", "Key", "Value"]).bottom_margin(1))
+    } else {
+        table
+            .widths([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .header(Row::new(vec!["Key", "Value"]).bottom_margin(1))
+    }
+}
+
+// A star for each character, so the cursor and selection still line up with the text.
+fn masked(model: &Model, secret: &str) -> String {
+    if model.reveal_secrets {
+        secret.to_string()
+    } else {
+        "*".repeat(secret.chars().count())
+    }
+}
+
+fn selectable_text(text: &str, width: usize, selection: Option<(usize, usize)>) -> Text<'static> {
+    match selection {
+        Some((start, end)) => wrap_string(text, width)
+            .lines()
             .enumerate()
-            .map(|(i, input_row)| {
-                let key = &input_row.key.lines()[0];
-                let value = &input_row.value.lines()[0];
-                let (formatted_key, formatted_value) =
-                    if model.current_panel == Panel::Input && model.input_index == i {
-                        match model.current_input_field {
-                            InputField::Key => (
-                                wrap_string(&key, field_width),
-                                truncate_ellipse(&value, field_width),
-                            ),
-                            InputField::Value => (
-                                truncate_ellipse(&key, field_width),
-                                wrap_string(&value, field_width),
-                            ),
-                        }
-                    } else {
-                        (
-                            truncate_ellipse(&key, field_width),
-                            truncate_ellipse(&value, field_width),
-                        )
-                    };
-                let height = std::cmp::max(
-                    formatted_key.lines().count(),
-                    formatted_value.lines().count(),
-                ) as u16;
-
-                Row::new(vec![formatted_key, formatted_value]).height(height)
+            .map(|(i, line)| {
+                Line::from(
+                    line.chars()
+                        .enumerate()
+                        .map(|(j, c)| {
+                            let index = i * width + j;
+                            if index >= start && index < end {
+                                Span::styled(c.to_string(), selection_style())
+                            } else {
+                                Span::raw(c.to_string())
+                            }
+                        })
+                        .collect::<Vec<Span>>(),
+                )
             })
-            .collect::<Table>()
-            .widths([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .block(input_block)
-            .header(Row::new(vec!["Key", "Value"]).bottom_margin(1)),
+            .collect::<Vec<Line>>()
+            .into(),
+        None => Text::raw(wrap_string(text, width)),
     }
 }
 
 fn input_title(model: &Model) -> Line<'static> {
     let mut auth_title = InputType::Auth.to_string().white();
+    let mut path_title = InputType::Path.to_string().white();
     let mut headers_title = InputType::Headers.to_string().white();
     let mut body_title = InputType::Body.to_string().white();
+    let mut options_title = InputType::Options.to_string().white();
     if model.current_panel == Panel::Input {
         match model.current_input_type {
             InputType::Auth => auth_title = auth_title.blue(),
+            InputType::Path => path_title = path_title.blue(),
             InputType::Headers => headers_title = headers_title.blue(),
             InputType::Body => body_title = body_title.blue(),
+            InputType::Options => options_title = options_title.blue(),
         };
     }
 
@@ -238,9 +1175,13 @@ fn input_title(model: &Model) -> Line<'static> {
         Span::styled("| ", Color::White),
         auth_title,
         Span::styled(" | ", Color::White),
+        path_title,
+        Span::styled(" | ", Color::White),
         headers_title,
         Span::styled(" | ", Color::White),
         body_title,
+        Span::styled(" | ", Color::White),
+        options_title,
         Span::styled(" |", Color::White),
     ])
 }
@@ -271,11 +1212,13 @@ fn input_footer(model: &Model) -> Line<'static> {
         }
         InputType::Body => {
             let mut json_title = BodyFormat::Json.to_string().white();
-            let mut form_title = BodyFormat::Form.to_string().white();
+            let mut form_title = BodyFormat::UrlEncoded.to_string().white();
+            let mut multipart_title = BodyFormat::Multipart.to_string().white();
             if model.current_panel == Panel::Input {
                 match model.current_body_format {
                     BodyFormat::Json => json_title = json_title.blue(),
-                    BodyFormat::Form => form_title = form_title.blue(),
+                    BodyFormat::UrlEncoded => form_title = form_title.blue(),
+                    BodyFormat::Multipart => multipart_title = multipart_title.blue(),
                 };
             }
 
@@ -284,36 +1227,814 @@ fn input_footer(model: &Model) -> Line<'static> {
                 json_title,
                 Span::styled(" | ", Color::White),
                 form_title,
+                Span::styled(" | ", Color::White),
+                multipart_title,
                 Span::styled(" |", Color::White),
             ])
         }
+        // The headers reqcoon adds, by name, so it's clear what's sent besides the table.
+        InputType::Headers => {
+            let names: Vec<String> = model
+                .auto_headers()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect();
+            if names.is_empty() {
+                return Line::default();
+            }
+            Line::styled(
+                format!(" also sent: {} ", names.join(", ")),
+                Style::default().dim(),
+            )
+            .alignment(Alignment::Right)
+        }
+        InputType::Path if model.path_input_table.first().key.is_empty() => Line::styled(
+            " add :name or {name} to the URL's path ",
+            Style::default().dim(),
+        )
+        .alignment(Alignment::Right),
         _ => Line::default(),
     }
 }
 
-fn output_block(model: &mut Model) -> impl Widget + '_ {
+// Only the part of the response on screen is drawn, rather than handing the TextArea to the
+// terminal, which builds every line it shows in full and gets slow on multi-megabyte responses.
+fn output_block(model: &mut Model, area: Rect) -> impl Widget + '_ {
     let style = if model.current_panel == Panel::Output {
         active_style()
     } else {
         Style::default()
     };
 
+    // The border keeps the focus color while the Output panel is focused.
+    let style = match status_style(model) {
+        Some(status_style) if model.current_panel != Panel::Output => status_style,
+        _ => style,
+    };
     let output_block = Block::default()
-        .title("Output")
+        .title(output_title(model))
         .borders(Borders::ALL)
         .border_style(style);
 
-    model.output_input.set_cursor_line_style(Style::default());
-    model.output_input.set_cursor_style(Style::default());
-    model.output_input.set_block(output_block);
+    // The view scrolls the way a TextArea does, just far enough to keep the cursor on screen.
+    let inner = area.inner(&Margin::new(1, 1));
+    let (height, width) = (inner.height as usize, inner.width as usize);
+    let (row, col) = model.output_input.cursor();
+    let (top, left) = model.output_scroll;
+    model.output_scroll = (scroll_to(top, row, height), scroll_to(left, col, width));
+    model.output_height = height;
+
+    let (top, left) = model.output_scroll;
+    let lines = model.output_input.lines();
+    let shown = &lines[top.min(lines.len())..(top + height).min(lines.len())];
+    let text: Vec<Line> = shown
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let selection = Some(top + index)
+                .filter(|_| model.current_panel == Panel::Output)
+                .and_then(|row| model.selected_columns(row));
+            output_line(line, left, width, selection)
+        })
+        .collect();
+
+    Paragraph::new(text).block(output_block)
+}
+
+fn scroll_to(top: usize, cursor: usize, length: usize) -> usize {
+    if cursor < top {
+        cursor
+    } else if top + length <= cursor {
+        cursor + 1 - length
+    } else {
+        top
+    }
+}
+
+// The columns of a line that fit on screen, with the part of the selection on it highlighted.
+// Tabs are drawn as a single space so columns line up with the cursor.
+fn output_line(
+    line: &str,
+    left: usize,
+    width: usize,
+    selection: Option<(usize, usize)>,
+) -> Line<'static> {
+    let chars: Vec<char> = line
+        .chars()
+        .skip(left)
+        .take(width)
+        .map(|c| if c == '\t' { ' ' } else { c })
+        .collect();
+    let Some((start, end)) = selection else {
+        return Line::raw(chars.into_iter().collect::<String>());
+    };
+
+    let start = start.saturating_sub(left).min(chars.len());
+    let end = end.saturating_sub(left).clamp(start, chars.len());
+    Line::from(vec![
+        Span::raw(chars[..start].iter().collect::<String>()),
+        Span::styled(
+            chars[start..end].iter().collect::<String>(),
+            selection_style(),
+        ),
+        Span::raw(chars[end..].iter().collect::<String>()),
+    ])
+}
+
+fn looks_like_json(model: &Model) -> bool {
+    model
+        .output_input
+        .lines()
+        .iter()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| line.trim_start().starts_with(['{', '[']))
+}
+
+#[cfg(feature = "highlighting")]
+fn highlight_output(buf: &mut Buffer, area: Rect, model: &Model) -> bool {
+    let lines = model.output_input.lines();
+    let text = lines.iter().find(|line| !line.trim().is_empty());
+    let Some(syntax) = highlighting::syntax(
+        model.output_content_type.as_deref(),
+        text.map_or("", String::as_str),
+    ) else {
+        return false;
+    };
+
+    let (row, col) = model.output_scroll;
+    highlighting::highlight(buf, area, lines, syntax, row, col);
+    true
+}
+
+// Only the body of a raw request is highlighted, in the language its Content-Type names.
+#[cfg(feature = "highlighting")]
+fn highlight_raw_body(buf: &mut Buffer, area: Rect, model: &Model) {
+    let lines = model.raw_input.lines();
+    let Some(blank) = lines.iter().position(|line| line.trim().is_empty()) else {
+        return;
+    };
+    let content_type = lines[..blank].iter().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("content-type")
+            .then(|| value.trim())
+    });
+    let body = &lines[blank + 1..];
+    let text = body.iter().find(|line| !line.trim().is_empty());
+    let Some(syntax) = highlighting::syntax(content_type, text.map_or("", String::as_str)) else {
+        return;
+    };
+
+    // The body can start partway down the editor, or above its top.
+    let (row, col) = model.raw_input.viewport.scroll_top();
+    let (row, start) = (row as usize, blank + 1);
+    let (area, first) = if row < start {
+        let offset = ((start - row) as u16).min(area.height);
+        (
+            Rect {
+                y: area.y + offset,
+                height: area.height - offset,
+                ..area
+            },
+            0,
+        )
+    } else {
+        (area, row - start)
+    };
+    highlighting::highlight(buf, area, body, syntax, first, col as usize);
+}
+
+// Underlines the line of the raw request where its JSON body stops being valid, if it's on screen.
+// Line and block selections aren't ones the text area can draw, so they're drawn over it.
+fn highlight_raw_selection(buf: &mut Buffer, area: Rect, model: &Model) {
+    if model.current_panel != Panel::Input
+        || !matches!(
+            model.current_mode,
+            Mode::Visual(Selection::Line | Selection::Block)
+        )
+    {
+        return;
+    }
+    let (top, left) = model.raw_input.viewport.scroll_top();
+    for y in area.top()..area.bottom() {
+        let row = (top + y - area.top()) as usize;
+        let Some((start, end)) = model.selected_columns(row) else {
+            continue;
+        };
+        let start =
+            area.left() + start.saturating_sub(left as usize).min(area.width as usize) as u16;
+        let end = area.left() + end.saturating_sub(left as usize).min(area.width as usize) as u16;
+        for x in start..end {
+            buf.get_mut(x, y).set_style(selection_style());
+        }
+    }
+}
+
+fn underline_raw_body_error(buf: &mut Buffer, area: Rect, model: &Model) {
+    let Some((line, _)) = model.raw_body_error() else {
+        return;
+    };
+    let (top, _) = model.raw_input.viewport.scroll_top();
+    let Some(row) = line
+        .checked_sub(top as usize)
+        .filter(|row| *row < area.height as usize)
+    else {
+        return;
+    };
+    let y = area.y + row as u16;
+    for x in area.left()..area.right() {
+        let cell = buf.get_mut(x, y);
+        if cell.symbol() != " " {
+            cell.set_style(Style::default().underlined());
+        }
+    }
+}
+
+// Colors the JSON that is visible on screen a line at a time, which is enough for pretty-printed
+// responses. Strings followed by a colon are keys.
+fn highlight_json(buf: &mut Buffer, area: Rect) {
+    let theme = theme::current();
+    for y in area.top()..area.bottom() {
+        let line: Vec<char> = (area.left()..area.right())
+            .map(|x| match buf.get(x, y).symbol() {
+                symbol if symbol.len() == 1 => symbol.chars().next().unwrap_or(' '),
+                _ => ' ',
+            })
+            .collect();
+
+        let mut index = 0;
+        while index < line.len() {
+            let start = index;
+            let color = match line[index] {
+                '"' => {
+                    index += 1;
+                    while index < line.len() && line[index] != '"' {
+                        index += if line[index] == '\\' { 2 } else { 1 };
+                    }
+                    index = (index + 1).min(line.len());
+                    let key = line[index..]
+                        .iter()
+                        .find(|c| !c.is_whitespace())
+                        .is_some_and(|c| *c == ':');
+                    if key {
+                        theme.json_key
+                    } else {
+                        theme.json_string
+                    }
+                }
+                '-' | '0'..='9' => {
+                    while index < line.len()
+                        && matches!(line[index], '-' | '+' | '.' | 'e' | 'E' | '0'..='9')
+                    {
+                        index += 1;
+                    }
+                    theme.json_number
+                }
+                't' | 'f' | 'n' => {
+                    let word: String = line[index..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_alphabetic())
+                        .collect();
+                    index += word.len();
+                    if !matches!(word.as_str(), "true" | "false" | "null") {
+                        continue;
+                    }
+                    theme.json_literal
+                }
+                _ => {
+                    index += 1;
+                    continue;
+                }
+            };
+
+            for x in start..index.min(line.len()) {
+                buf.get_mut(area.left() + x as u16, y).set_fg(color);
+            }
+        }
+    }
+}
+
+// Colors the tag names and attribute values of the XML or HTML visible on screen, a line at a time
+// like highlight_json, which is enough for indented responses.
+fn highlight_markup(buf: &mut Buffer, area: Rect) {
+    let theme = theme::current();
+    for y in area.top()..area.bottom() {
+        let line: Vec<char> = (area.left()..area.right())
+            .map(|x| match buf.get(x, y).symbol() {
+                symbol if symbol.len() == 1 => symbol.chars().next().unwrap_or(' '),
+                _ => ' ',
+            })
+            .collect();
+
+        let mut colors = vec![];
+        let mut index = 0;
+        while index < line.len() {
+            if line[index] != '<' || matches!(line.get(index + 1), Some('!' | '?')) {
+                index += 1;
+                continue;
+            }
+
+            index += 1;
+            let start = index;
+            while index < line.len() && !matches!(line[index], ' ' | '>') {
+                index += 1;
+            }
+            colors.push((start, index, theme.json_key));
+            while index < line.len() && line[index] != '>' {
+                if matches!(line[index], '"' | '\'') {
+                    let (quote, start) = (line[index], index);
+                    index += 1;
+                    while index < line.len() && line[index] != quote {
+                        index += 1;
+                    }
+                    index = (index + 1).min(line.len());
+                    colors.push((start, index, theme.json_string));
+                } else {
+                    index += 1;
+                }
+            }
+        }
 
-    model.output_input.widget()
+        for (start, end, color) in colors {
+            for x in start..end {
+                buf.get_mut(area.left() + x as u16, y).set_fg(color);
+            }
+        }
+    }
 }
 
-fn mode_block(model: &Model) -> Paragraph {
-    Paragraph::new(format!(
-        "{mode} {message}",
+// Wraps URLs that are visible on screen in OSC 8 escapes. Cells are linked two at a time since
+// ratatui measures the width of each escaped symbol as two columns.
+// Colors the {{variable}} references drawn in the area.
+fn highlight_variables(buf: &mut Buffer, area: Rect) {
+    let color = theme::current().variable;
+    for y in area.top()..area.bottom() {
+        let line: Vec<char> = (area.left()..area.right())
+            .map(|x| match buf.get(x, y).symbol() {
+                symbol if symbol.len() == 1 => symbol.chars().next().unwrap_or(' '),
+                _ => ' ',
+            })
+            .collect();
+
+        let mut start = None;
+        for index in 1..line.len() {
+            match (line[index - 1], line[index]) {
+                ('{', '{') if start.is_none() => start = Some(index - 1),
+                ('}', '}') => {
+                    if let Some(start) = start.take() {
+                        for x in start..=index {
+                            buf.get_mut(area.left() + x as u16, y).set_fg(color);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn hyperlink_urls(buf: &mut Buffer, area: Rect) {
+    static URL_REGEX: OnceLock<Regex> = OnceLock::new();
+    let url_regex =
+        URL_REGEX.get_or_init(|| Regex::new(r#"https?://[^\s"'<>`]+[^\s"'<>`.,;:)\]}]"#).unwrap());
+
+    for y in area.top()..area.bottom() {
+        let line: String = (area.left()..area.right())
+            .map(|x| match buf.get(x, y).symbol() {
+                symbol if symbol.len() == 1 => symbol.chars().next().unwrap_or(' '),
+                _ => ' ',
+            })
+            .collect();
+
+        for url in url_regex.find_iter(&line) {
+            let start = area.left() + url.start() as u16;
+            let end = area.left() + url.end() as u16;
+            for x in (start..end).step_by(2) {
+                let text =
+                    &line[(x - area.left()) as usize..((x + 2).min(end) - area.left()) as usize];
+                let hyperlink = format!("\x1B]8;;{}\x07{}\x1B]8;;\x07", url.as_str(), text);
+                buf.get_mut(x, y).set_symbol(&hyperlink);
+            }
+        }
+    }
+}
+
+const SPINNER: [&str; 4] = ["◐", "◓", "◑", "◒"];
+
+// The mode and what has focus, then the message or, while there isn't one, the keys that matter
+// where the cursor is.
+fn statusbar_block(model: &Model) -> Paragraph {
+    // Unsaved edits and macro recording are marked the way vim marks them.
+    let recording = match &model.recording {
+        Some((register, _)) => format!(" recording @{}", register),
+        None => String::new(),
+    };
+    let mut spans = vec![Span::raw(format!(
+        "{mode}{modified}{recording} · {panel} ",
         mode = model.current_mode.to_string(),
-        message = model.message
+        modified = if model.modified() { " [+]" } else { "" },
+        panel = panel_name(model),
+    ))];
+    if model.message().is_empty() {
+        spans.push(Span::raw(key_hints(model)).dim());
+    } else {
+        spans.push(Span::raw(model.message()));
+    }
+    if let Some((name, value)) = model.variable_under_cursor() {
+        let preview = match value {
+            Some(value) => format!(" · {} = {}", name, value),
+            None => format!(" · {} isn't set", name),
+        };
+        spans.push(Span::styled(preview, theme::current().variable));
+    }
+    Paragraph::new(Line::default().spans(spans))
+}
+
+// What's running in the background, the environment and the time left on the token, on the right
+// of the status bar.
+fn context_line(model: &Model) -> Line<'static> {
+    let mut spans = vec![];
+    if let Some(work) = model.in_flight() {
+        let spinner = SPINNER[model.ticks % SPINNER.len()];
+        spans.push(Span::styled(
+            format!(" {} {}", spinner, work),
+            theme::current().warning,
+        ));
+    }
+    if let Some(environment) = &model.environment {
+        spans.push(Span::styled(
+            format!(" {}", environment),
+            theme::current().active,
+        ));
+    }
+    if let Some(token) = token_status(model) {
+        spans.push(Span::raw(" "));
+        spans.push(token);
+    }
+    if !spans.is_empty() {
+        spans.push(Span::raw(" "));
+    }
+    Line::default().spans(spans)
+}
+
+fn panel_name(model: &Model) -> String {
+    match model.current_panel {
+        Panel::Method => "Method".to_string(),
+        Panel::Url => "URL".to_string(),
+        Panel::Input if model.raw_mode => "Raw".to_string(),
+        Panel::Input => model.current_input_type.to_string(),
+        Panel::Output => "Output".to_string(),
+        Panel::Settings => "Settings".to_string(),
+        Panel::History => "History".to_string(),
+        Panel::Runner => "Runner".to_string(),
+        Panel::Files => "Files".to_string(),
+        Panel::Authorize => "OAuth2".to_string(),
+        Panel::Cookies => "Cookies".to_string(),
+        Panel::Query => "Query".to_string(),
+        Panel::Menu => "Menu".to_string(),
+        Panel::Command => "Command".to_string(),
+        Panel::Preview => "Preview".to_string(),
+        Panel::Description => "Notes".to_string(),
+        Panel::Diff => "Diff".to_string(),
+        Panel::Mock => "Mock".to_string(),
+        Panel::Messages => "Messages".to_string(),
+    }
+}
+
+// Popups list their keys along their bottom border instead.
+fn key_hints(model: &Model) -> &'static str {
+    match (&model.current_mode, &model.current_panel) {
+        (Mode::Insert, Panel::Url) => "↹ complete  ⎋ normal mode",
+        (Mode::Insert, _) => "⎋ normal mode",
+        (Mode::Visual(_), _) => "y yank  d cut  ⎋ cancel",
+        (Mode::Normal, Panel::Method) => "j/k method  ↵ send  m menu",
+        (Mode::Normal, Panel::Url) => "i edit  ↵ send  P preview  m menu",
+        (Mode::Normal, Panel::Input) if model.raw_mode => "R fields  E $EDITOR  ↵ send",
+        (Mode::Normal, Panel::Input) => "↹ next field  S-→ next tab  ↵ send  m menu",
+        (Mode::Normal, Panel::Output) => "/ query  W save  D diff  = format  m menu",
+        _ => "",
+    }
+}
+
+// The time left on the OAuth2 token, which turns yellow shortly before it runs out.
+fn token_status(model: &Model) -> Option<Span<'static>> {
+    let remaining = model.oauth_token()?.remaining()?;
+    if remaining.is_zero() {
+        return Some(Span::styled("token expired", theme::current().error));
+    }
+    let secs = remaining.as_secs();
+    let color = if secs < 2 * oauth::REFRESH_AHEAD.as_secs() {
+        theme::current().warning
+    } else {
+        theme::current().success
+    };
+    Some(Span::styled(
+        format!("token {}:{:02}", secs / 60, secs % 60),
+        color,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::PathBuf,
+        time::{Duration, Instant, UNIX_EPOCH},
+    };
+
+    use nonempty::nonempty;
+    use ratatui::{backend::TestBackend, Terminal};
+    use reqcoon_core::{history::Entry, oauth::DeviceAuthorization, runner::Outcome};
+    use tui_textarea::TextArea;
+
+    use super::view;
+    use crate::model::{
+        AuthFormat, Authorization, InputRow, InputType, Level, LoggedMessage, Model, Panel,
+        ResponseStatus,
+    };
+
+    fn render(model: &mut Model) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|f| view(f, model)).unwrap();
+        let (col, row) = terminal.get_cursor().unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let mut output = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        output.push_str(&format!("\ncursor: {}, {}\n", col, row));
+        output
+    }
+
+    // Compares a render against its snapshot in src/snapshots. UPDATE_SNAPSHOTS=1 writes them,
+    // new ones included; review and commit them with the change. A missing snapshot fails, so a
+    // checkout without them can't pass by writing its own.
+    fn assert_snapshot(name: &str, actual: String) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/snapshots")
+            .join(format!("{}.snap", name));
+
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+
+        let Ok(expected) = fs::read_to_string(&path) else {
+            panic!(
+                "{} has no snapshot at {}.\n\nActual:\n{}\n\
+                 Run with UPDATE_SNAPSHOTS=1 to write it, then commit it.",
+                name,
+                path.display(),
+                actual
+            );
+        };
+        assert!(
+            expected == actual,
+            "{} doesn't match its snapshot.\n\nExpected:\n{}\nActual:\n{}\n\
+             Run with UPDATE_SNAPSHOTS=1 to accept the change.",
+            name,
+            expected,
+            actual
+        );
+    }
+
+    fn request() -> Model {
+        let mut model = Model::new("test.http".to_string());
+        model.name = "list-users".to_string();
+        model.url_input = TextArea::from(["https://api.example.com/users"]);
+        model.headers_input_table = nonempty![
+            InputRow {
+                key: ["Accept"].into(),
+                value: ["application/json"].into(),
+                ..InputRow::default()
+            },
+            InputRow::default()
+        ];
+        model
+    }
+
+    #[test]
+    fn empty_request() {
+        assert_snapshot(
+            "empty_request",
+            render(&mut Model::new("test.http".to_string())),
+        );
+    }
+
+    #[test]
+    fn headers() {
+        let mut model = request();
+        model.current_panel = Panel::Input;
+        model.current_input_type = InputType::Headers;
+        assert_snapshot("headers", render(&mut model));
+    }
+
+    #[test]
+    fn bearer_auth() {
+        let mut model = request();
+        model.current_panel = Panel::Input;
+        model.auth.format = AuthFormat::Bearer;
+        model.auth.bearer_input = TextArea::from(["abc123"]);
+        assert_snapshot("bearer_auth", render(&mut model));
+    }
+
+    #[test]
+    fn raw_input() {
+        let mut model = request();
+        model.current_panel = Panel::Input;
+        model.toggle_raw_input();
+        assert_snapshot("raw_input", render(&mut model));
+    }
+
+    #[test]
+    fn output() {
+        let mut model = request();
+        model.current_panel = Panel::Output;
+        model.output_input = TextArea::from(["{", "  \"id\": 1,", "  \"name\": \"Ada\"", "}"]);
+        model.status_line = Some("HTTP/2.0 200 OK".to_string());
+        model.response_status = Some(ResponseStatus::Code(200));
+        assert_snapshot("output", render(&mut model));
+    }
+
+    #[test]
+    fn settings_popup() {
+        let mut model = request();
+        model.toggle_settings();
+        assert_snapshot("settings_popup", render(&mut model));
+    }
+
+    #[test]
+    fn cookies_popup() {
+        let mut model = request();
+        model.cookies_input_table = nonempty![
+            InputRow {
+                key: TextArea::from(["session"]),
+                value: TextArea::from(["3f9a1c"]),
+                ..InputRow::default()
+            },
+            InputRow::default(),
+        ];
+        model.current_panel = Panel::Cookies;
+        assert_snapshot("cookies_popup", render(&mut model));
+    }
+
+    #[test]
+    fn mock_popup() {
+        let mut model = request();
+        model.mock_input_table = nonempty![
+            InputRow {
+                key: TextArea::from(["POST /users"]),
+                value: TextArea::from(["201 {\"id\": 1}"]),
+                ..InputRow::default()
+            },
+            InputRow::default(),
+        ];
+        model.current_panel = Panel::Mock;
+        assert_snapshot("mock_popup", render(&mut model));
+    }
+
+    #[test]
+    fn query_popup() {
+        let mut model = request();
+        model.output_input = TextArea::from(["Ada", "Grace"]);
+        model.toggle_query();
+        model.query_input = TextArea::from(["$.users[*].name"]);
+        assert_snapshot("query_popup", render(&mut model));
+    }
+
+    #[test]
+    fn menu_popup() {
+        let mut model = request();
+        model.current_panel = Panel::Output;
+        model.toggle_menu();
+        model.next_menu_item();
+        assert_snapshot("menu_popup", render(&mut model));
+    }
+
+    #[test]
+    fn command_popup() {
+        let mut model = request();
+        model.toggle_command();
+        model.command_input = TextArea::from(["se"]);
+        assert_snapshot("command_popup", render(&mut model));
+    }
+
+    #[test]
+    fn preview_popup() {
+        let mut model = request();
+        model.current_panel = Panel::Url;
+        model.toggle_preview();
+        assert_snapshot("preview_popup", render(&mut model));
+    }
+
+    #[test]
+    fn description_popup() {
+        let mut model = request();
+        model.description_input =
+            TextArea::from(["Lists every user.", "", "- Paginated by `page`"]);
+        model.toggle_description();
+        assert_snapshot("description_popup", render(&mut model));
+    }
+
+    #[test]
+    fn diff_popup() {
+        let mut model = request();
+        model.current_panel = Panel::Output;
+        model.show_diff(
+            "{\n  \"name\": \"Ada\"\n}",
+            "{\n  \"name\": \"Grace\"\n}",
+            "the previous response",
+        );
+        assert_snapshot("diff_popup", render(&mut model));
+    }
+
+    #[test]
+    fn messages_popup() {
+        let mut model = request();
+        for (secs, level, text) in [
+            (0, Level::Info, "Opened test.http"),
+            (5, Level::Error, "Unable to connect"),
+            (9, Level::Warning, "Usage: :snapshot <name>"),
+        ] {
+            model.messages.push(LoggedMessage {
+                time: UNIX_EPOCH + Duration::from_secs(secs),
+                level,
+                text: text.to_string(),
+            });
+        }
+        model.toggle_messages();
+        assert_snapshot("messages_popup", render(&mut model));
+    }
+
+    #[test]
+    fn history_popup() {
+        let mut model = request();
+        model.history.push(Entry {
+            method: "GET".to_string(),
+            url: "https://api.example.com/users".to_string(),
+            status: 200,
+            ..Default::default()
+        });
+        model.toggle_history();
+        assert_snapshot("history_popup", render(&mut model));
+    }
+
+    #[test]
+    fn runner_popup() {
+        let mut model = request();
+        model.outcomes = vec![
+            Outcome {
+                name: "login".to_string(),
+                status: Some(200),
+                time: Duration::from_millis(42),
+                failures: vec![],
+                retried: false,
+                attempts: 1,
+            },
+            Outcome {
+                name: "list-users".to_string(),
+                status: Some(404),
+                time: Duration::from_millis(7),
+                failures: vec!["status == 200 (got 404)".to_string()],
+                retried: false,
+                attempts: 1,
+            },
+        ];
+        model.toggle_runner();
+        assert_snapshot("runner_popup", render(&mut model));
+    }
+
+    #[test]
+    fn files_popup() {
+        let directory = env::temp_dir().join("reqcoon-files-popup");
+        fs::create_dir_all(directory.join("admin")).unwrap();
+        for file in ["users.http", "admin/roles.http"] {
+            fs::write(directory.join(file), "GET https://api.example.com\n").unwrap();
+        }
+        let mut model = request();
+        model.workspace = Some(directory);
+        model.toggle_files();
+        assert_snapshot("files_popup", render(&mut model));
+    }
+
+    #[test]
+    fn authorize_popup() {
+        let mut model = request();
+        model.authorization = Some(Authorization::Device(DeviceAuthorization {
+            device_code: "device".to_string(),
+            user_code: "WDJB-MJHT".to_string(),
+            verification_uri: "https://example.com/device".to_string(),
+            verification_uri_complete: None,
+            interval: Duration::from_secs(5),
+            expires_at: Instant::now() + Duration::from_secs(600),
+        }));
+        model.current_panel = Panel::Authorize;
+        assert_snapshot("authorize_popup", render(&mut model));
+    }
+}